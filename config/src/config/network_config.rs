@@ -41,6 +41,14 @@ pub const MAX_CONCURRENT_NETWORK_REQS: usize = 100;
 pub const MAX_CONNECTION_DELAY_MS: u64 = 60_000; /* 1 minute */
 pub const MAX_FULLNODE_OUTBOUND_CONNECTIONS: usize = 3;
 pub const MAX_INBOUND_CONNECTIONS: usize = 100;
+pub const DIAL_TIMEOUT_MS: u64 = 30_000;
+pub const INBOUND_UPGRADE_TIMEOUT_MS: u64 = 30_000;
+pub const MAX_CONCURRENT_INBOUND_UPGRADES: usize = 100;
+pub const MAX_CONCURRENT_OUTBOUND_UPGRADES: usize = 100;
+pub const TRANSPORT_NOTIFICATION_CHANNEL_SIZE: usize = 1024;
+pub const UPGRADE_LATENCY_LOG_INTERVAL_MS: u64 = 60_000; /* 1 minute */
+pub const DIAL_RETRY_MAX_ATTEMPTS: usize = 1;
+pub const DIAL_RETRY_BACKOFF_MS: u64 = 100;
 pub const MAX_FRAME_SIZE: usize = 8 * 1024 * 1024; /* 8 MiB */
 pub const CONNECTION_BACKOFF_BASE: u64 = 2;
 pub const IP_BYTE_BUCKET_RATE: usize = 102400 /* 100 KiB */;
@@ -94,6 +102,28 @@ pub struct NetworkConfig {
     pub max_outbound_connections: usize,
     // Maximum number of outbound connections, limited by PeerManager
     pub max_inbound_connections: usize,
+    // Timeout until an outbound connection has to be upgraded, after which the dial fails
+    pub dial_timeout_ms: u64,
+    // Timeout until an inbound connection has to be upgraded, after which it is dropped
+    pub inbound_upgrade_timeout_ms: u64,
+    // Maximum number of inbound connections being upgraded at once, beyond which new inbound
+    // connections are dropped
+    pub max_concurrent_inbound_upgrades: usize,
+    // Maximum number of outbound connections being upgraded at once, beyond which further dial
+    // requests wait until pending dials complete
+    pub max_concurrent_outbound_upgrades: usize,
+    // Capacity of the channel on which upgraded connections are handed to PeerManager. A full
+    // channel blocks the transport until PeerManager catches up, while a large one holds more
+    // upgraded connections in memory
+    pub transport_notification_channel_size: usize,
+    // Interval at which the percentiles of the latencies of the connection upgrades completed
+    // since the last interval are logged
+    pub upgrade_latency_log_interval_ms: u64,
+    // Maximum number of attempts of a dial which fails with a transient error, e.g. a refused
+    // connection, before the failure is reported to PeerManager. A single attempt disables retries
+    pub dial_retry_max_attempts: usize,
+    // Delay before the first retry of a failed dial, which doubles with every further retry
+    pub dial_retry_backoff_ms: u64,
     // Inbound rate limiting configuration, if not specified, no rate limiting
     pub inbound_rate_limit_config: Option<RateLimitConfig>,
     // Outbound rate limiting configuration, if not specified, no rate limiting
@@ -130,6 +160,14 @@ impl NetworkConfig {
             ping_failures_tolerated: PING_FAILURES_TOLERATED,
             max_outbound_connections: MAX_FULLNODE_OUTBOUND_CONNECTIONS,
             max_inbound_connections: MAX_INBOUND_CONNECTIONS,
+            dial_timeout_ms: DIAL_TIMEOUT_MS,
+            inbound_upgrade_timeout_ms: INBOUND_UPGRADE_TIMEOUT_MS,
+            max_concurrent_inbound_upgrades: MAX_CONCURRENT_INBOUND_UPGRADES,
+            max_concurrent_outbound_upgrades: MAX_CONCURRENT_OUTBOUND_UPGRADES,
+            transport_notification_channel_size: TRANSPORT_NOTIFICATION_CHANNEL_SIZE,
+            upgrade_latency_log_interval_ms: UPGRADE_LATENCY_LOG_INTERVAL_MS,
+            dial_retry_max_attempts: DIAL_RETRY_MAX_ATTEMPTS,
+            dial_retry_backoff_ms: DIAL_RETRY_BACKOFF_MS,
             inbound_rate_limit_config: None,
             outbound_rate_limit_config: None,
         };
@@ -906,7 +906,7 @@ impl AdminTest for PreburnAndBurnEvents {
         );
         let burn_txn = ctx
             .chain_info()
-            .treasury_compliance_account()
+            .treasury_compliance_account()?
             .sign_with_transaction_builder(factory.payload(script));
         let result = env.submit_and_wait(&burn_txn);
         let version = result["version"].as_u64().unwrap();
@@ -971,7 +971,7 @@ impl AdminTest for CancleBurnEvent {
 
         let cancel_burn_txn = ctx
             .chain_info()
-            .treasury_compliance_account()
+            .treasury_compliance_account()?
             .sign_with_transaction_builder(factory.cancel_burn_with_amount(
                 Currency::XUS,
                 dd.address(),
@@ -1047,7 +1047,7 @@ impl AdminTest for UpdateExchangeRateEvent {
         let script = stdlib::encode_update_exchange_rate_script(xus_tag(), 0, 1, 4);
         let txn = ctx
             .chain_info()
-            .treasury_compliance_account()
+            .treasury_compliance_account()?
             .sign_with_transaction_builder(factory.script(script.clone()));
 
         let result = env.submit_and_wait(&txn);
@@ -1088,7 +1088,7 @@ impl AdminTest for UpdateExchangeRateEvent {
         // Reset exchange rate
         let txn = ctx
             .chain_info()
-            .treasury_compliance_account()
+            .treasury_compliance_account()?
             .sign_with_transaction_builder(factory.update_exchange_rate(Currency::XUS, 0, 1, 1));
 
         env.submit_and_wait(&txn);
@@ -1112,13 +1112,13 @@ impl AdminTest for MintAndReceivedMintEvents {
         let script = stdlib::encode_tiered_mint_script(
             xus_tag(),
             0,
-            ctx.chain_info().designated_dealer_account().address(),
+            ctx.chain_info().designated_dealer_account()?.address(),
             1_000_000,
             1,
         );
         let txn = ctx
             .chain_info()
-            .treasury_compliance_account()
+            .treasury_compliance_account()?
             .sign_with_transaction_builder(factory.script(script.clone()));
 
         let result = env.submit_and_wait(&txn);
@@ -1515,7 +1515,7 @@ impl AdminTest for AddAndRemoveVaspDomain {
 
         let txn = ctx
             .chain_info()
-            .treasury_compliance_account()
+            .treasury_compliance_account()?
             .sign_with_transaction_builder(factory.add_vasp_domain(vasp.address(), domain.clone()));
 
         let result = env.submit_and_wait(&txn);
@@ -1530,7 +1530,7 @@ impl AdminTest for AddAndRemoveVaspDomain {
         // remove domain
         let txn = ctx
             .chain_info()
-            .treasury_compliance_account()
+            .treasury_compliance_account()?
             .sign_with_transaction_builder(factory.remove_vasp_domain(vasp.address(), domain));
 
         let result = env.submit_and_wait(&txn);
@@ -1869,7 +1869,7 @@ impl AdminTest for MultiAgentRotateAuthenticationKeyAdminScriptFunction {
 
         let new_key = AccountKey::generate(ctx.rng());
         let txn = env.create_multi_agent_txn(
-            ctx.chain_info().root_account(),
+            ctx.chain_info().root_account()?,
             &[&mut vasp],
             stdlib::encode_rotate_authentication_key_with_nonce_admin_script_function(
                 0,
@@ -1888,15 +1888,15 @@ impl AdminTest for MultiAgentRotateAuthenticationKeyAdminScriptFunction {
             result["transaction"],
             json!({
                 "type": "user",
-                "sender": format!("{:x}", ctx.chain_info().root_account().address()),
+                "sender": format!("{:x}", ctx.chain_info().root_account()?.address()),
                 "signature_scheme": "Scheme::Ed25519",
                 "signature": hex::encode(txn.authenticator().sender().signature_bytes()),
-                "public_key": ctx.chain_info().root_account().public_key(),
+                "public_key": ctx.chain_info().root_account()?.public_key(),
                 "secondary_signers": [ format!("{:x}", vasp.address()) ],
                 "secondary_signature_schemes": [ "Scheme::Ed25519" ],
                 "secondary_signatures": [ hex::encode(txn.authenticator().secondary_signers()[0].signature_bytes())],
                 "secondary_public_keys": [ vasp.public_key().to_string() ],
-                "sequence_number": ctx.chain_info().root_account().sequence_number() - 1,
+                "sequence_number": ctx.chain_info().root_account()?.sequence_number() - 1,
                 "chain_id": ctx.chain_info().chain_id(),
                 "max_gas_amount": 1000000,
                 "gas_unit_price": 0,
@@ -1938,7 +1938,7 @@ impl AdminTest for MultiAgentRotateAuthenticationKeyAdminScript {
             .create_parent_vasp_account(Currency::XUS, vasp.authentication_key())?;
         let new_key = AccountKey::generate(ctx.rng());
         let txn = env.create_multi_agent_txn(
-            ctx.chain_info().root_account(),
+            ctx.chain_info().root_account()?,
             &[&mut vasp],
             TransactionPayload::Script(
                 stdlib::encode_rotate_authentication_key_with_nonce_admin_script(
@@ -1960,15 +1960,15 @@ impl AdminTest for MultiAgentRotateAuthenticationKeyAdminScript {
             result["transaction"],
             json!({
                 "type": "user",
-                "sender": format!("{:x}", ctx.chain_info().root_account().address()),
+                "sender": format!("{:x}", ctx.chain_info().root_account()?.address()),
                 "signature_scheme": "Scheme::Ed25519",
                 "signature": hex::encode(txn.authenticator().sender().signature_bytes()),
-                "public_key": ctx.chain_info().root_account().public_key(),
+                "public_key": ctx.chain_info().root_account()?.public_key(),
                 "secondary_signers": [ format!("{:x}", vasp.address()) ],
                 "secondary_signature_schemes": [ "Scheme::Ed25519" ],
                 "secondary_signatures": [ hex::encode(txn.authenticator().secondary_signers()[0].signature_bytes())],
                 "secondary_public_keys": [ vasp.public_key().to_string() ],
-                "sequence_number": ctx.chain_info().root_account().sequence_number() - 1,
+                "sequence_number": ctx.chain_info().root_account()?.sequence_number() - 1,
                 "chain_id": ctx.chain_info().chain_id(),
                 "max_gas_amount": 1000000,
                 "gas_unit_price": 0,
@@ -2074,7 +2074,7 @@ impl AdminTest for UpgradeEventAndNewEpoch {
         let write_set = ChangeSet::new(create_common_write_set(), vec![]);
         let txn = ctx
             .chain_info()
-            .root_account()
+            .root_account()?
             .sign_with_transaction_builder(factory.payload(TransactionPayload::WriteSet(
                 WriteSetPayload::Direct(write_set),
             )));
@@ -2143,7 +2143,7 @@ impl AdminTest for UpgradeDiemVersion {
         let factory = ctx.chain_info().transaction_factory();
         let txn = ctx
             .chain_info()
-            .root_account()
+            .root_account()?
             .sign_with_transaction_builder(
                 factory.update_diem_version(0, DIEM_MAX_KNOWN_VERSION.major + 1),
             );
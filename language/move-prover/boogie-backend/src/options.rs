@@ -275,7 +275,14 @@ impl BoogieOptions {
         Ok(())
     }
 
-    fn get_version(tool: &str, prog: &str, args: &[&str], regex: &str) -> anyhow::Result<String> {
+    /// Obtains the version of a tool by running `prog` with the given arguments and extracting
+    /// the first capture group of `regex` from its output.
+    pub fn get_version(
+        tool: &str,
+        prog: &str,
+        args: &[&str],
+        regex: &str,
+    ) -> anyhow::Result<String> {
         let out = match Command::new(prog).args(args).output() {
             Ok(out) => String::from_utf8_lossy(&out.stdout).to_string(),
             Err(msg) => {
@@ -13,53 +13,276 @@ use crate::{
     function_target::FunctionData,
     function_target_pipeline::{FunctionTargetProcessor, FunctionTargetsHolder},
     options::ProverOptions,
-    stackless_bytecode::{Bytecode, Operation},
+    stackless_bytecode::{AttrId, Bytecode, Constant, Operation},
+    stackless_bytecode_generator::StacklessBytecodeGenerator,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    rc::Rc,
 };
 
 use move_model::{
+    ast::TempIndex,
     exp_generator::ExpGenerator,
-    model::{FunctionEnv, GlobalEnv},
+    model::{FunId, FunctionEnv, GlobalEnv, Loc, QualifiedId},
+    ty::{PrimitiveType, Type},
 };
 
 pub struct MutationTester {}
 
+/// The state of the mutation pass. Each counter belongs to one mutation operator and counts
+/// down the candidate instructions of that operator; the instruction at which the counter
+/// reaches one is mutated.
+#[derive(Default)]
 pub struct MutationManager {
     pub mutated: bool,
     pub add_sub: usize,
     pub sub_add: usize,
     pub mul_div: usize,
     pub div_mul: usize,
+    pub lt_le: usize,
+    pub le_lt: usize,
+    pub gt_ge: usize,
+    pub ge_gt: usize,
+    pub eq_neq: usize,
+    pub neq_eq: usize,
+    /// Counts down pairs of a loaded integer constant and one of its replacements as returned
+    /// by `constant_replacements`.
+    pub constant: usize,
+    /// Counts down the conditional branches of the source code, which are negated by swapping
+    /// their targets.
+    pub negate_branch: usize,
+    /// Counts down the calls of functions with two arguments of the same type, of which the
+    /// first such pair as returned by `swapped_arguments` is swapped.
+    pub swap_args: usize,
+    /// Counts down pairs of a returned boolean or integer value and one of its transformations
+    /// as returned by `return_mutations`.
+    pub return_value: usize,
+    /// If set, only this function is mutated, in each of its variants, so the mutation is also
+    /// seen by the verified functions calling it. Otherwise, the verification variants of all
+    /// functions are mutated.
+    pub function: Option<QualifiedId<FunId>>,
+}
+
+/// The location of the instruction the last applied mutation has been applied to.
+pub struct MutatedLocation {
+    pub loc: Loc,
+    /// The constant loaded instead of the original one, if a constant has been replaced.
+    pub replacement: Option<Constant>,
 }
 
+/// A mutation of the code of verified functions, applied after the mutations of the counters
+/// of the mutation manager. Clients install it as an extension of the environment to apply
+/// mutations of their own kind.
+#[derive(Clone)]
+pub struct CustomMutation(pub Rc<dyn Fn(&FunctionEnv<'_>, FunctionData) -> FunctionData>);
+
 impl MutationTester {
     pub fn new() -> Box<Self> {
         Box::new(Self {})
     }
 }
 
-fn mutate_arith(
+/// Returns the operation the given operation is replaced by when mutated, together with the
+/// counter of the mutation operator performing this replacement.
+fn mutation_counter<'a>(
+    mutation_manager: &'a mut MutationManager,
+    operation: &Operation,
+) -> Option<(Operation, &'a mut usize)> {
+    use Operation::*;
+    match operation {
+        Add => Some((Sub, &mut mutation_manager.add_sub)),
+        Sub => Some((Add, &mut mutation_manager.sub_add)),
+        Mul => Some((Div, &mut mutation_manager.mul_div)),
+        Div => Some((Mul, &mut mutation_manager.div_mul)),
+        Lt => Some((Le, &mut mutation_manager.lt_le)),
+        Le => Some((Lt, &mut mutation_manager.le_lt)),
+        Gt => Some((Ge, &mut mutation_manager.gt_ge)),
+        Ge => Some((Gt, &mut mutation_manager.ge_gt)),
+        Eq => Some((Neq, &mut mutation_manager.eq_neq)),
+        Neq => Some((Eq, &mut mutation_manager.neq_eq)),
+        _ => None,
+    }
+}
+
+/// Returns the boundary values an integer constant is replaced by when mutated: zero, one, the
+/// constant plus one, and the maximal value of its type, each only once and excluding the
+/// constant itself. Returns no values for constants which are not integers.
+pub fn constant_replacements(constant: &Constant) -> Vec<Constant> {
+    let (value, max, make): (u128, u128, fn(u128) -> Constant) = match constant {
+        Constant::U8(v) => (*v as u128, u8::MAX as u128, |v| Constant::U8(v as u8)),
+        Constant::U64(v) => (*v as u128, u64::MAX as u128, |v| Constant::U64(v as u64)),
+        Constant::U128(v) => (*v, u128::MAX, Constant::U128),
+        _ => return vec![],
+    };
+    let mut candidates = vec![0, 1];
+    if value < max {
+        candidates.push(value + 1);
+    }
+    candidates.push(max);
+    let mut replacements = vec![];
+    for candidate in candidates {
+        if candidate != value && !replacements.contains(&candidate) {
+            replacements.push(candidate);
+        }
+    }
+    replacements.into_iter().map(make).collect()
+}
+
+/// Returns true if the location lies within one of the inline spec blocks of the function.
+pub fn is_in_spec_block(fun_env: &FunctionEnv<'_>, loc: &Loc) -> bool {
+    fun_env
+        .get_spec()
+        .on_impl
+        .values()
+        .filter_map(|spec| spec.loc.as_ref())
+        .any(|spec_loc| {
+            spec_loc.file_id() == loc.file_id()
+                && spec_loc.span().start() <= loc.span().start()
+                && loc.span().end() <= spec_loc.span().end()
+        })
+}
+
+/// Returns the attribute ids of the conditional branches in the code generated for the function,
+/// excluding those within inline spec blocks. Branches added later in the pipeline, e.g. for
+/// checking abort conditions, are not part of the source code and therefore not mutated.
+pub fn source_branches(fun_env: &FunctionEnv<'_>) -> BTreeSet<AttrId> {
+    let data = StacklessBytecodeGenerator::new(fun_env).generate_function();
+    data.code
+        .iter()
+        .filter_map(|bc| match bc {
+            Bytecode::Branch(attr_id, ..) => Some(*attr_id),
+            _ => None,
+        })
+        .filter(|attr_id| match data.locations.get(attr_id) {
+            Some(loc) => !is_in_spec_block(fun_env, loc),
+            None => true,
+        })
+        .collect()
+}
+
+/// Returns the positions of the first two arguments of a call with the given arguments which
+/// have the same type but are different temporaries, so swapping them changes the call but
+/// still type checks. Returns `None` if there are no such arguments.
+///
+/// The arguments must be those of the code generated for the function, as used by
+/// `source_swaps`, since later in the pipeline immutable references are replaced by the values
+/// they refer to, and copies of arguments are propagated.
+pub fn swapped_arguments(local_types: &[Type], srcs: &[TempIndex]) -> Option<(usize, usize)> {
+    (0..srcs.len())
+        .flat_map(|i| (i + 1..srcs.len()).map(move |j| (i, j)))
+        .find(|(i, j)| {
+            srcs[*i] != srcs[*j]
+                && local_types.get(srcs[*i]).is_some()
+                && local_types.get(srcs[*i]) == local_types.get(srcs[*j])
+        })
+}
+
+/// Returns the positions of the swapped arguments of the calls in the code generated for the
+/// function, by the attribute ids of the calls. The positions are swapped even if copy
+/// propagation has made both arguments the same temporary, so such a mutation does not change
+/// the program, and it is recognized as equivalent.
+pub fn source_swaps(fun_env: &FunctionEnv<'_>) -> BTreeMap<AttrId, (usize, usize)> {
+    let data = StacklessBytecodeGenerator::new(fun_env).generate_function();
+    data.code
+        .iter()
+        .filter_map(|bc| match bc {
+            Bytecode::Call(attr_id, _, Operation::Function(..), srcs, _) => {
+                swapped_arguments(&data.local_types, srcs).map(|positions| (*attr_id, positions))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// A transformation of the value returned by a function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReturnMutation {
+    /// The returned boolean is negated.
+    Negate,
+    /// The returned integer is replaced by zero.
+    Zero,
+    /// The returned integer is incremented by one.
+    Increment,
+}
+
+/// Returns the transformations of the value returned with the given values: a boolean is
+/// negated, and an integer replaced by zero or incremented by one. Returns none if not exactly
+/// one value of these types is returned.
+pub fn return_mutations(local_types: &[Type], srcs: &[TempIndex]) -> Vec<ReturnMutation> {
+    match srcs {
+        [src] => match &local_types[*src] {
+            Type::Primitive(PrimitiveType::Bool) => vec![ReturnMutation::Negate],
+            Type::Primitive(PrimitiveType::U8)
+            | Type::Primitive(PrimitiveType::U64)
+            | Type::Primitive(PrimitiveType::U128) => {
+                vec![ReturnMutation::Zero, ReturnMutation::Increment]
+            }
+            _ => vec![],
+        },
+        _ => vec![],
+    }
+}
+
+/// Returns the given value as a constant of the given integer type, or `None` if the type is
+/// not an integer type.
+pub fn integer_constant(ty: &Type, value: u8) -> Option<Constant> {
+    match ty {
+        Type::Primitive(PrimitiveType::U8) => Some(Constant::U8(value)),
+        Type::Primitive(PrimitiveType::U64) => Some(Constant::U64(value as u64)),
+        Type::Primitive(PrimitiveType::U128) => Some(Constant::U128(value as u128)),
+        _ => None,
+    }
+}
+
+/// Emits the code computing the transformation of the returned value, and returns the
+/// temporary holding the transformed value.
+fn emit_return_mutation(
+    builder: &mut FunctionDataBuilder<'_>,
+    mutation: ReturnMutation,
+    src: TempIndex,
+) -> TempIndex {
+    let ty = builder.data.local_types[src].clone();
+    let dest = builder.new_temp(ty.clone());
+    match mutation {
+        ReturnMutation::Negate => {
+            builder.emit_with(|id| Bytecode::Call(id, vec![dest], Operation::Not, vec![src], None));
+        }
+        ReturnMutation::Zero => {
+            let zero = integer_constant(&ty, 0).expect("integer type");
+            builder.emit_with(|id| Bytecode::Load(id, dest, zero));
+        }
+        ReturnMutation::Increment => {
+            let one = builder.new_temp(ty.clone());
+            let constant = integer_constant(&ty, 1).expect("integer type");
+            builder.emit_with(|id| Bytecode::Load(id, one, constant));
+            builder.emit_with(|id| {
+                Bytecode::Call(id, vec![dest], Operation::Add, vec![src, one], None)
+            });
+        }
+    }
+    dest
+}
+
+fn mutate_operation(
     call: Bytecode,
     mutation_value: usize,
     global_env: &GlobalEnv,
     mutation_manager: MutationManager,
     bc: Bytecode,
+    loc: Loc,
 ) -> Bytecode {
     if mutation_value > 1 {
-        global_env.set_extension(MutationManager {
-            mutated: mutation_manager.mutated,
-            add_sub: mutation_manager.add_sub,
-            sub_add: mutation_manager.sub_add,
-            mul_div: mutation_manager.mul_div,
-            div_mul: mutation_manager.div_mul,
-        });
-    }
-    if mutation_value == 1 {
+        global_env.set_extension(mutation_manager);
+        bc
+    } else if mutation_value == 1 {
         global_env.set_extension(MutationManager {
             mutated: true,
-            add_sub: mutation_manager.add_sub,
-            sub_add: mutation_manager.sub_add,
-            mul_div: mutation_manager.mul_div,
-            div_mul: mutation_manager.div_mul,
+            ..mutation_manager
+        });
+        global_env.set_extension(MutatedLocation {
+            loc,
+            replacement: None,
         });
         call
     } else {
@@ -79,6 +302,7 @@ impl FunctionTargetProcessor for MutationTester {
                 sub_add: options.mutation_sub_add,
                 mul_div: options.mutation_mul_div,
                 div_mul: options.mutation_div_mul,
+                ..MutationManager::default()
             }),
         };
     }
@@ -96,103 +320,188 @@ impl FunctionTargetProcessor for MutationTester {
             return data;
         }
 
-        if !data.variant.is_verified() {
+        let global_env = fun_env.module_env.env;
+        let initial = global_env
+            .get_extension::<MutationManager>()
+            .map(|m| MutationManager { ..*m });
+        match initial.as_ref().and_then(|m| m.function) {
+            Some(function) if function != fun_env.get_qualified_id() => return data,
+            Some(_) => {}
             // Only need to instrument if this is a verification variant
-            return data;
+            None if !data.variant.is_verified() => return data,
+            None => {}
         }
 
         let mut builder = FunctionDataBuilder::new(fun_env, data);
         let code = std::mem::take(&mut builder.data.code);
 
         builder.set_loc(builder.fun_env.get_loc().at_start());
-        let global_env = fun_env.module_env.env;
-        let m = global_env.get_extension::<MutationManager>().unwrap();
+        let branches = if global_env
+            .get_extension::<MutationManager>()
+            .map_or(false, |m| m.negate_branch > 0)
+        {
+            source_branches(fun_env)
+        } else {
+            BTreeSet::new()
+        };
+        let swaps = if global_env
+            .get_extension::<MutationManager>()
+            .map_or(false, |m| m.swap_args > 0)
+        {
+            source_swaps(fun_env)
+        } else {
+            BTreeMap::new()
+        };
 
         for bc in code {
+            // Read the state for each instruction, as mutating an instruction updates it.
+            let mut m = {
+                let m = global_env.get_extension::<MutationManager>().unwrap();
+                MutationManager { ..*m }
+            };
             match bc {
-                Call(ref attrid, ref indices, Operation::Add, ref srcs, ref dests) => {
-                    let call = Call(
-                        *attrid,
-                        (*indices).clone(),
-                        Operation::Sub,
-                        (*srcs).clone(),
-                        (*dests).clone(),
-                    );
-                    let mv = m.add_sub;
-                    let result: usize;
-                    if mv > 0 {
-                        result = mv - 1;
-                    } else {
-                        result = mv;
+                Call(
+                    attrid,
+                    ref dests,
+                    ref operation @ Operation::Function(..),
+                    ref srcs,
+                    ref aa,
+                ) if m.swap_args > 0 => match swaps.get(&attrid) {
+                    Some(&(i, j)) => {
+                        let mv = m.swap_args;
+                        if mv > 1 {
+                            global_env.set_extension(MutationManager {
+                                swap_args: mv - 1,
+                                ..m
+                            });
+                            builder.emit(bc);
+                        } else {
+                            let mut swapped = srcs.clone();
+                            swapped.swap(i, j);
+                            let call = Call(
+                                attrid,
+                                dests.clone(),
+                                operation.clone(),
+                                swapped,
+                                aa.clone(),
+                            );
+                            global_env.set_extension(MutationManager {
+                                mutated: true,
+                                swap_args: 0,
+                                ..m
+                            });
+                            global_env.set_extension(MutatedLocation {
+                                loc: builder.get_loc(attrid),
+                                replacement: None,
+                            });
+                            builder.emit(call);
+                        }
+                    }
+                    None => builder.emit(bc),
+                },
+                Call(ref attrid, ref indices, ref operation, ref srcs, ref dests) => {
+                    match mutation_counter(&mut m, operation) {
+                        Some((replacement, counter)) => {
+                            let mv = *counter;
+                            if mv > 0 {
+                                *counter = mv - 1;
+                            }
+                            let call = Call(
+                                *attrid,
+                                (*indices).clone(),
+                                replacement,
+                                (*srcs).clone(),
+                                (*dests).clone(),
+                            );
+                            let loc = builder.get_loc(*attrid);
+                            builder.emit(mutate_operation(call, mv, global_env, m, bc, loc));
+                        }
+                        None => builder.emit(bc),
                     }
-                    let mm = MutationManager {
-                        add_sub: result,
-                        ..*m
-                    };
-                    builder.emit(mutate_arith(call, mv, global_env, mm, bc));
                 }
-                Call(ref attrid, ref indices, Operation::Sub, ref srcs, ref dests) => {
-                    let call = Call(
-                        *attrid,
-                        (*indices).clone(),
-                        Operation::Add,
-                        (*srcs).clone(),
-                        (*dests).clone(),
-                    );
-                    let mv = m.sub_add;
-                    let result: usize;
-                    if mv > 0 {
-                        result = mv - 1;
+                Load(attrid, dest, ref constant) => {
+                    let loc = builder.get_loc(attrid);
+                    let replacements = if is_in_spec_block(fun_env, &loc) {
+                        vec![]
                     } else {
-                        result = mv;
-                    }
-                    let mm = MutationManager {
-                        sub_add: result,
-                        ..*m
+                        constant_replacements(constant)
                     };
-                    builder.emit(mutate_arith(call, mv, global_env, mm, bc));
+                    let mv = m.constant;
+                    if mv > replacements.len() {
+                        global_env.set_extension(MutationManager {
+                            constant: mv - replacements.len(),
+                            ..m
+                        });
+                        builder.emit(bc);
+                    } else if mv > 0 {
+                        let replacement = replacements[mv - 1].clone();
+                        global_env.set_extension(MutationManager {
+                            mutated: true,
+                            constant: 0,
+                            ..m
+                        });
+                        global_env.set_extension(MutatedLocation {
+                            loc,
+                            replacement: Some(replacement.clone()),
+                        });
+                        builder.emit(Load(attrid, dest, replacement));
+                    } else {
+                        builder.emit(bc);
+                    }
                 }
-                Call(ref attrid, ref indices, Operation::Mul, ref srcs, ref dests) => {
-                    let call = Call(
-                        *attrid,
-                        (*indices).clone(),
-                        Operation::Div,
-                        (*srcs).clone(),
-                        (*dests).clone(),
-                    );
-                    let mv = m.mul_div;
-                    let result: usize;
-                    if mv > 0 {
-                        result = mv - 1;
+                Ret(attrid, ref srcs) if m.return_value > 0 => {
+                    let mutations = return_mutations(&builder.data.local_types, srcs);
+                    let mv = m.return_value;
+                    if mv > mutations.len() {
+                        global_env.set_extension(MutationManager {
+                            return_value: mv - mutations.len(),
+                            ..m
+                        });
+                        builder.emit(bc);
                     } else {
-                        result = mv;
+                        let mutation = mutations[mv - 1];
+                        let loc = builder.get_loc(attrid);
+                        builder.set_loc(loc.clone());
+                        let result = emit_return_mutation(&mut builder, mutation, srcs[0]);
+                        // The replacing constant is reported, so the zero can be told apart from
+                        // the increment at the same site.
+                        let replacement = match mutation {
+                            ReturnMutation::Zero => {
+                                integer_constant(&builder.data.local_types[result], 0)
+                            }
+                            _ => None,
+                        };
+                        global_env.set_extension(MutationManager {
+                            mutated: true,
+                            return_value: 0,
+                            ..m
+                        });
+                        global_env.set_extension(MutatedLocation { loc, replacement });
+                        builder.emit(Ret(attrid, vec![result]));
                     }
-                    let mm = MutationManager {
-                        mul_div: result,
-                        ..*m
-                    };
-                    builder.emit(mutate_arith(call, mv, global_env, mm, bc));
                 }
-                Call(ref attrid, ref indices, Operation::Div, ref srcs, ref dests) => {
-                    let call = Call(
-                        *attrid,
-                        (*indices).clone(),
-                        Operation::Mul,
-                        (*srcs).clone(),
-                        (*dests).clone(),
-                    );
-                    let mv = m.div_mul;
-                    let result: usize;
-                    if mv > 0 {
-                        result = mv - 1;
+                Branch(attrid, then_label, else_label, cond) if branches.contains(&attrid) => {
+                    let mv = m.negate_branch;
+                    if mv > 1 {
+                        global_env.set_extension(MutationManager {
+                            negate_branch: mv - 1,
+                            ..m
+                        });
+                        builder.emit(bc);
+                    } else if mv == 1 {
+                        global_env.set_extension(MutationManager {
+                            mutated: true,
+                            negate_branch: 0,
+                            ..m
+                        });
+                        global_env.set_extension(MutatedLocation {
+                            loc: builder.get_loc(attrid),
+                            replacement: None,
+                        });
+                        builder.emit(Branch(attrid, else_label, then_label, cond));
                     } else {
-                        result = mv;
+                        builder.emit(bc);
                     }
-                    let mm = MutationManager {
-                        div_mul: result,
-                        ..*m
-                    };
-                    builder.emit(mutate_arith(call, mv, global_env, mm, bc));
                 }
                 _ => {
                     builder.emit(bc);
@@ -200,7 +509,20 @@ impl FunctionTargetProcessor for MutationTester {
             }
         }
 
-        builder.data
+        let data = match global_env.get_extension::<CustomMutation>() {
+            Some(custom) => (custom.0)(fun_env, builder.data),
+            None => builder.data,
+        };
+
+        // Each variant of a single mutated function starts from the same counters, so the same
+        // instruction is mutated in all of them.
+        if let Some(initial) = initial.filter(|m| m.function.is_some()) {
+            let mutated = global_env
+                .get_extension::<MutationManager>()
+                .map_or(false, |m| m.mutated);
+            global_env.set_extension(MutationManager { mutated, ..initial });
+        }
+        data
     }
 
     fn name(&self) -> String {
@@ -0,0 +1,211 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// Determination of the functions changed since a git revision, to only mutate those.
+
+use anyhow::{anyhow, bail};
+use codespan::Span;
+use move_model::model::{FunctionEnv, GlobalEnv, Loc};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Ranges of changed lines by file, each given by its first and last line, starting at 1.
+pub type ChangedLines = BTreeMap<PathBuf, Vec<(usize, usize)>>;
+
+/// Returns the lines of the given source files and directories which changed since the given
+/// git revision, as determined by `git diff`. The files are given by their canonical paths.
+pub fn changed_lines(rev: &str, sources: &[String]) -> anyhow::Result<ChangedLines> {
+    let source = Path::new(
+        sources
+            .first()
+            .ok_or_else(|| anyhow!("no sources to compare to `{}`", rev))?,
+    );
+    let dir = if source.is_dir() {
+        source
+    } else {
+        source.parent().unwrap_or_else(|| Path::new(""))
+    };
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let git = |args: &[&str], paths: &[PathBuf]| -> anyhow::Result<String> {
+        let output = Command::new("git")
+            .args(args)
+            .args(paths)
+            .current_dir(dir)
+            .output()?;
+        if !output.status.success() {
+            bail!(
+                "`git {}` failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    };
+    let root = git(&["rev-parse", "--show-toplevel"], &[])
+        .map_err(|e| anyhow!("cannot determine the git repository of the sources: {}", e))?;
+    let root = PathBuf::from(
+        root.lines()
+            .next()
+            .ok_or_else(|| anyhow!("cannot determine the git repository of the sources"))?,
+    );
+    let paths = sources
+        .iter()
+        .map(|s| std::fs::canonicalize(s).unwrap_or_else(|_| s.into()))
+        .collect::<Vec<_>>();
+    let diff = git(
+        &[
+            "diff",
+            "--unified=0",
+            "--no-color",
+            "--no-ext-diff",
+            rev,
+            "--",
+        ],
+        &paths,
+    )?;
+    Ok(parse_diff(&diff)
+        .into_iter()
+        .map(|(file, ranges)| {
+            let path = root.join(file);
+            (std::fs::canonicalize(&path).unwrap_or(path), ranges)
+        })
+        .collect())
+}
+
+/// Parses the output of `git diff --unified=0` into the changed lines of the new version of
+/// each file, by the path of the file relative to the root of the repository. Lines which
+/// have only been removed are represented by the lines before and after them.
+pub fn parse_diff(diff: &str) -> BTreeMap<String, Vec<(usize, usize)>> {
+    let mut changes: BTreeMap<String, Vec<(usize, usize)>> = BTreeMap::new();
+    let mut file = None;
+    for line in diff.lines() {
+        if let Some(path) = line.strip_prefix("+++ ") {
+            // Files which have been deleted have no lines left to mutate.
+            file = path.strip_prefix("b/").map(|p| p.to_string());
+        } else if let (Some(file), Some(hunk)) = (&file, line.strip_prefix("@@ ")) {
+            // The hunk header has the form `@@ -START[,COUNT] +START[,COUNT] @@`.
+            let range = match hunk.split_whitespace().find_map(|s| s.strip_prefix('+')) {
+                Some(range) => range,
+                None => continue,
+            };
+            let mut parts = range.splitn(2, ',');
+            let start = parts.next().and_then(|s| s.parse::<usize>().ok());
+            let count = parts.next().map_or(Some(1), |s| s.parse::<usize>().ok());
+            if let (Some(start), Some(count)) = (start, count) {
+                let range = if count == 0 {
+                    (start.max(1), start + 1)
+                } else {
+                    (start, start + count - 1)
+                };
+                changes.entry(file.clone()).or_default().push(range);
+            }
+        }
+    }
+    changes
+}
+
+/// Returns the names of the functions of the target modules, qualified by the full name of
+/// their module, which overlap the changed lines and are accepted by `is_selected`, and if
+/// `callers` is set, the functions of target modules directly calling them as well.
+pub fn changed_functions(
+    env: &GlobalEnv,
+    changes: &ChangedLines,
+    callers: bool,
+    is_selected: impl Fn(&FunctionEnv<'_>) -> bool,
+) -> BTreeSet<String> {
+    let qualified_name = |fun: &FunctionEnv<'_>| {
+        format!(
+            "{}::{}",
+            fun.module_env.get_full_name_str(),
+            fun.get_simple_name_string()
+        )
+    };
+    let mut functions = BTreeSet::new();
+    for module in env.get_modules().filter(|m| m.is_target()) {
+        for fun in module.get_functions() {
+            if fun.is_native() || !is_selected(&fun) || !is_changed(env, &fun.get_loc(), changes) {
+                continue;
+            }
+            functions.insert(qualified_name(&fun));
+            if callers {
+                for caller in fun.get_calling_functions() {
+                    let caller = env.get_function(caller);
+                    if caller.module_env.is_target() && is_selected(&caller) {
+                        functions.insert(qualified_name(&caller));
+                    }
+                }
+            }
+        }
+    }
+    functions
+}
+
+/// Returns true if any line of the given location changed.
+fn is_changed(env: &GlobalEnv, loc: &Loc, changes: &ChangedLines) -> bool {
+    let (file, start) = match env.get_file_and_location(loc) {
+        Some(file_and_start) => file_and_start,
+        None => return false,
+    };
+    let file = PathBuf::from(file);
+    let file = std::fs::canonicalize(&file).unwrap_or(file);
+    let ranges = match changes.get(&file) {
+        Some(ranges) => ranges,
+        None => return false,
+    };
+    let end = Loc::new(loc.file_id(), Span::new(loc.span().end(), loc.span().end()));
+    match env.get_location(&end) {
+        Some(end) => {
+            let (start, end) = (start.line.0 as usize + 1, end.line.0 as usize + 1);
+            ranges
+                .iter()
+                .any(|(first, last)| *first <= end && start <= *last)
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_is_parsed() {
+        let diff = "\
+diff --git a/sources/arith.move b/sources/arith.move
+index 1234567..89abcde 100644
+--- a/sources/arith.move
++++ b/sources/arith.move
+@@ -4 +4 @@ module 0x42::Arith {
+-        x + y
++        y + x
+@@ -10,2 +9,0 @@ module 0x42::Arith {
+-    // gone
+-    // too
+@@ -20,0 +19,3 @@ module 0x42::Arith {
++    fun mul(x: u64, y: u64): u64 {
++        x * y
++    }
+diff --git a/sources/old.move b/sources/old.move
+deleted file mode 100644
+--- a/sources/old.move
++++ /dev/null
+@@ -1,3 +0,0 @@
+-module 0x42::Old {
+-}
+-
+";
+        let changes = parse_diff(diff);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            changes["sources/arith.move"],
+            vec![(4, 4), (9, 10), (19, 21)]
+        );
+    }
+}
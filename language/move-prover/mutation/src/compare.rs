@@ -0,0 +1,489 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// Comparison of the results of two mutation runs, e.g. before and after a change of the specs.
+
+use crate::{
+    database::ResultDatabase,
+    error::MutationError,
+    report::{MutationRecord, MutationReport, MutationSite, MutationStatus},
+};
+use anyhow::anyhow;
+use clap::{App, Arg};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The differences between the results of an old and a new mutation run.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Comparison {
+    /// Mutants which survive in the new run, but were killed in the old one or did not exist.
+    pub newly_surviving: Vec<MutantChange>,
+    /// Mutants which are killed in the new run, but survived in the old one.
+    pub newly_caught: Vec<MutantChange>,
+    /// Mutants which time out in the new run, but did not in the old one or did not exist.
+    pub new_timeouts: Vec<MutantChange>,
+    /// Modules whose total verification time increased by more than the allowed percentage.
+    pub slower_modules: Vec<ModuleTimeChange>,
+    /// Tools whose versions differ between the runs, which makes the results incomparable.
+    pub tool_version_changes: Vec<ToolVersionChange>,
+}
+
+impl Comparison {
+    /// Returns the number of changes which indicate that the new run is worse than the old one,
+    /// i.e. all but the newly caught mutants.
+    pub fn regressions(&self) -> usize {
+        self.newly_surviving.len() + self.new_timeouts.len() + self.slower_modules.len()
+    }
+}
+
+/// The change of the verification status of a mutant between two runs.
+#[derive(Clone, Debug, Serialize)]
+pub struct MutantChange {
+    /// The id of the mutant, or if the result file does not contain it, its function, operator,
+    /// and location.
+    pub mutant: String,
+    /// The location of the mutation, if known.
+    pub location: Option<String>,
+    /// The status in the old run, if the mutant existed.
+    pub old_status: Option<MutationStatus>,
+    /// The status in the new run.
+    pub new_status: MutationStatus,
+}
+
+/// The change of the total verification time of the mutants of a module between two runs.
+#[derive(Clone, Debug, Serialize)]
+pub struct ModuleTimeChange {
+    /// The full name of the module.
+    pub module: String,
+    /// The total verification time in the old run, in milliseconds.
+    pub old_ms: u64,
+    /// The total verification time in the new run, in milliseconds.
+    pub new_ms: u64,
+    /// The increase of the verification time in percent.
+    pub increase_pct: f64,
+}
+
+/// The change of the version of a tool between two runs.
+#[derive(Clone, Debug, Serialize)]
+pub struct ToolVersionChange {
+    /// The name of the tool.
+    pub tool: String,
+    /// The version in the old run, if recorded.
+    pub old_version: Option<String>,
+    /// The version in the new run, if recorded.
+    pub new_version: Option<String>,
+}
+
+/// Compares the results of the old and the new run. Mutants are matched by their id, and
+/// modules are reported as slower if the total time of their records increased by more than
+/// `max_time_increase_pct` percent.
+pub fn compare_reports(
+    old: &MutationReport,
+    new: &MutationReport,
+    max_time_increase_pct: f64,
+) -> Comparison {
+    let old_records = old
+        .records
+        .iter()
+        .filter(|r| !r.function.is_empty())
+        .map(|r| (mutant_key(r), r))
+        .collect::<BTreeMap<_, _>>();
+    let mut comparison = Comparison::default();
+    for record in new.records.iter().filter(|r| !r.function.is_empty()) {
+        let key = mutant_key(record);
+        // Runs are compared by their first attempts, as timeouts may have been retried in only
+        // one of them.
+        let old_status = old_records.get(&key).map(|r| r.first_attempt_status());
+        let change = MutantChange {
+            mutant: key,
+            location: record.site.as_ref().map(MutationSite::location),
+            old_status,
+            new_status: record.first_attempt_status(),
+        };
+        match (old_status, record.first_attempt_status()) {
+            // A mutant which was skipped, hung, or otherwise not killed in the old run does not
+            // newly survive.
+            (None, MutationStatus::Ok)
+            | (Some(MutationStatus::Errors), MutationStatus::Ok)
+            | (Some(MutationStatus::CaughtDownstream), MutationStatus::Ok) => {
+                comparison.newly_surviving.push(change)
+            }
+            (Some(MutationStatus::Ok), MutationStatus::Errors)
+            | (Some(MutationStatus::Ok), MutationStatus::CaughtDownstream) => {
+                comparison.newly_caught.push(change)
+            }
+            (Some(MutationStatus::Timeout), MutationStatus::Timeout) => {}
+            (_, MutationStatus::Timeout) => comparison.new_timeouts.push(change),
+            _ => {}
+        }
+    }
+
+    // Reports written before tool versions were recorded cannot be checked.
+    if !old.tool_versions.is_empty() && !new.tool_versions.is_empty() {
+        let tools = old
+            .tool_versions
+            .keys()
+            .chain(new.tool_versions.keys())
+            .collect::<BTreeSet<_>>();
+        for tool in tools {
+            let (old_version, new_version) =
+                (old.tool_versions.get(tool), new.tool_versions.get(tool));
+            if old_version != new_version {
+                comparison.tool_version_changes.push(ToolVersionChange {
+                    tool: tool.clone(),
+                    old_version: old_version.cloned(),
+                    new_version: new_version.cloned(),
+                });
+            }
+        }
+    }
+
+    let old_times = module_times(old);
+    for (module, new_ms) in module_times(new) {
+        let old_ms = match old_times.get(&module) {
+            Some(old_ms) if *old_ms > 0 => *old_ms,
+            _ => continue,
+        };
+        let increase_pct = (new_ms as f64 - old_ms as f64) * 100.0 / old_ms as f64;
+        if increase_pct > max_time_increase_pct {
+            comparison.slower_modules.push(ModuleTimeChange {
+                module,
+                old_ms,
+                new_ms,
+                increase_pct,
+            });
+        }
+    }
+    comparison
+}
+
+/// Returns the key a mutation record is matched by between runs.
+fn mutant_key(record: &MutationRecord) -> String {
+    if !record.mutant_id.is_empty() {
+        return record.mutant_id.clone();
+    }
+    format!(
+        "{}::{}@{}",
+        record.function,
+        record.operator,
+        record
+            .site
+            .as_ref()
+            .map_or_else(|| "?".to_string(), MutationSite::location)
+    )
+}
+
+/// Returns the total verification time of the records of each module, in milliseconds.
+fn module_times(report: &MutationReport) -> BTreeMap<String, u64> {
+    let mut times = BTreeMap::new();
+    for record in &report.records {
+        *times.entry(record.module.clone()).or_default() += record.duration_ms;
+    }
+    times
+}
+
+/// Reads the result file of a mutation run at the given path.
+pub fn read_report(path: &str) -> Result<MutationReport, MutationError> {
+    std::fs::read_to_string(path)
+        .map_err(anyhow::Error::from)
+        .and_then(|content| MutationReport::parse(&content))
+        .map_err(|e| MutationError::ReportReadFailed(path.to_string(), e))
+}
+
+/// Prints the comparison as a table of the changed mutants and modules.
+fn print_comparison(comparison: &Comparison) {
+    if !comparison.tool_version_changes.is_empty() {
+        println!(
+            "WARNING: the runs used different tool versions, so their results are not comparable:"
+        );
+        for change in &comparison.tool_version_changes {
+            println!(
+                "  {:<10} {} -> {}",
+                change.tool,
+                change.old_version.as_deref().unwrap_or("unknown"),
+                change.new_version.as_deref().unwrap_or("unknown")
+            );
+        }
+    }
+    let print_changes = |title: &str, changes: &[MutantChange]| {
+        if changes.is_empty() {
+            return;
+        }
+        println!("{} ({}):", title, changes.len());
+        for change in changes {
+            println!(
+                "  {:<50} {:<40} {:>10} -> {}",
+                change.mutant,
+                change.location.as_deref().unwrap_or("-"),
+                change.old_status.map_or("new", MutationStatus::as_str),
+                change.new_status
+            );
+        }
+    };
+    print_changes("Newly surviving mutants", &comparison.newly_surviving);
+    print_changes("Newly caught mutants", &comparison.newly_caught);
+    print_changes("New timeouts", &comparison.new_timeouts);
+    if !comparison.slower_modules.is_empty() {
+        println!("Slower modules ({}):", comparison.slower_modules.len());
+        for change in &comparison.slower_modules {
+            println!(
+                "  {:<50} {:>10}ms -> {:>10}ms  +{:.1}%",
+                change.module, change.old_ms, change.new_ms, change.increase_pct
+            );
+        }
+    }
+    println!(
+        "{} newly surviving, {} newly caught, {} new timeouts, {} slower modules",
+        comparison.newly_surviving.len(),
+        comparison.newly_caught.len(),
+        comparison.new_timeouts.len(),
+        comparison.slower_modules.len()
+    );
+}
+
+/// Parses the id of a campaign in a results database.
+fn parse_campaign_id(id: &str) -> Result<i64, MutationError> {
+    id.parse().map_err(|_| {
+        MutationError::InvalidConfig(anyhow!("campaign ids must be integers, found `{}`", id))
+    })
+}
+
+/// Runs the `compare` subcommand with the given arguments, the first of which is the name of
+/// the subcommand.
+pub fn compare(args: &[String]) -> anyhow::Result<()> {
+    let cmd_line_parser = App::new("compare")
+        .about(
+            "Compares the result files of two mutation runs and reports the mutants which newly \
+            survive, are newly caught, or newly time out, as well as modules whose verification \
+            time regressed. Terminates with a non-zero exit code if there are regressions",
+        )
+        .arg(Arg::with_name("allow-partial").long("allow-partial").help(
+            "compares result files which are truncated, e.g. because the run writing \
+                    them died, instead of failing",
+        ))
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .takes_value(true)
+                .value_name("PATH")
+                .help("additionally writes the comparison as JSON to the given file"),
+        )
+        .arg(
+            Arg::with_name("max-time-increase")
+                .long("max-time-increase")
+                .takes_value(true)
+                .default_value("20")
+                .value_name("PERCENT")
+                .help(
+                    "the percentage by which the total verification time of a module may \
+                    increase before it is reported as regressed",
+                ),
+        )
+        .arg(
+            Arg::with_name("sqlite")
+                .long("sqlite")
+                .takes_value(true)
+                .value_name("PATH")
+                .help(
+                    "reads the runs from the SQLite database at the given path, as written by \
+                    `--sqlite` of a mutation run. OLD and NEW are then ids of campaigns in the \
+                    database instead of result files",
+                ),
+        )
+        .arg(
+            Arg::with_name("old")
+                .required(true)
+                .value_name("OLD")
+                .help("the result file, or campaign id, of the old run"),
+        )
+        .arg(
+            Arg::with_name("new")
+                .required(true)
+                .value_name("NEW")
+                .help("the result file, or campaign id, of the new run"),
+        );
+    let matches = cmd_line_parser.get_matches_from(args);
+    let value = matches.value_of("max-time-increase").unwrap();
+    let max_time_increase_pct = match value.parse::<f64>() {
+        Ok(pct) if pct >= 0.0 => pct,
+        _ => {
+            return Err(MutationError::InvalidConfig(anyhow!(
+                "`--max-time-increase` must be a non-negative number, found `{}`",
+                value
+            ))
+            .into())
+        }
+    };
+    let allow_partial = matches.is_present("allow-partial");
+    let database = matches
+        .value_of("sqlite")
+        .map(ResultDatabase::open)
+        .transpose()?;
+    let read = |arg: &str| {
+        let path = matches.value_of(arg).unwrap();
+        let report = match &database {
+            Some(database) => database.read_campaign(parse_campaign_id(path)?)?,
+            None => read_report(path)?,
+        };
+        if report.is_truncated() && !allow_partial {
+            return Err(MutationError::TruncatedReport(path.to_string()));
+        }
+        Ok(report)
+    };
+    let old = read("old")?;
+    let new = read("new")?;
+    let comparison = compare_reports(&old, &new, max_time_increase_pct);
+    print_comparison(&comparison);
+    if let Some(path) = matches.value_of("json") {
+        let json = serde_json::to_string_pretty(&comparison)
+            .map_err(|e| MutationError::OutputWriteFailed(e.into()))?;
+        std::fs::write(path, json + "\n")
+            .map_err(|e| MutationError::OutputPathFailed(path.to_string(), e))?;
+    }
+    match comparison.regressions() {
+        0 => Ok(()),
+        n => Err(MutationError::Regressions(n).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(
+        function: &str,
+        id: &str,
+        status: MutationStatus,
+        duration_ms: u64,
+    ) -> MutationRecord {
+        MutationRecord {
+            function: function.to_string(),
+            mutant_id: id.to_string(),
+            timeout_secs: 100,
+            duration_ms,
+            ..MutationRecord::test_record(status)
+        }
+    }
+
+    fn report(records: Vec<MutationRecord>) -> MutationReport {
+        MutationReport {
+            records,
+            ..MutationReport::default()
+        }
+    }
+
+    #[test]
+    fn changed_mutants_are_reported() {
+        let old = report(vec![
+            record(
+                "Arith::add",
+                "0x42::Arith::add::add-sub::1",
+                MutationStatus::Errors,
+                100,
+            ),
+            record(
+                "Arith::sub",
+                "0x42::Arith::sub::sub-add::1",
+                MutationStatus::Ok,
+                100,
+            ),
+            record(
+                "Arith::mul",
+                "0x42::Arith::mul::mul-div::1",
+                MutationStatus::Errors,
+                100,
+            ),
+            record(
+                "Arith::rem",
+                "0x42::Arith::rem::rem-mul::1",
+                MutationStatus::SkippedSlow,
+                100,
+            ),
+        ]);
+        let new = report(vec![
+            record(
+                "Arith::add",
+                "0x42::Arith::add::add-sub::1",
+                MutationStatus::Ok,
+                100,
+            ),
+            record(
+                "Arith::sub",
+                "0x42::Arith::sub::sub-add::1",
+                MutationStatus::Errors,
+                100,
+            ),
+            record(
+                "Arith::mul",
+                "0x42::Arith::mul::mul-div::1",
+                MutationStatus::Timeout,
+                100,
+            ),
+            record(
+                "Arith::div",
+                "0x42::Arith::div::div-mul::1",
+                MutationStatus::Ok,
+                100,
+            ),
+            record(
+                "Arith::rem",
+                "0x42::Arith::rem::rem-mul::1",
+                MutationStatus::Ok,
+                100,
+            ),
+        ]);
+        let comparison = compare_reports(&old, &new, 20.0);
+        let mutants =
+            |changes: &[MutantChange]| changes.iter().map(|c| c.mutant.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            mutants(&comparison.newly_surviving),
+            vec![
+                "0x42::Arith::add::add-sub::1",
+                "0x42::Arith::div::div-mul::1"
+            ]
+        );
+        assert_eq!(
+            mutants(&comparison.newly_caught),
+            vec!["0x42::Arith::sub::sub-add::1"]
+        );
+        assert_eq!(
+            mutants(&comparison.new_timeouts),
+            vec!["0x42::Arith::mul::mul-div::1"]
+        );
+        assert_eq!(comparison.newly_surviving[1].old_status, None);
+        assert!(comparison.slower_modules.is_empty());
+        assert_eq!(comparison.regressions(), 3);
+    }
+
+    #[test]
+    fn slower_modules_are_reported() {
+        let old = report(vec![record("Arith::add", "a", MutationStatus::Errors, 100)]);
+        let new = report(vec![record("Arith::add", "a", MutationStatus::Errors, 150)]);
+        let comparison = compare_reports(&old, &new, 20.0);
+        assert_eq!(comparison.slower_modules.len(), 1);
+        assert!((comparison.slower_modules[0].increase_pct - 50.0).abs() < f64::EPSILON);
+        assert!(compare_reports(&old, &new, 60.0).slower_modules.is_empty());
+    }
+
+    #[test]
+    fn tool_version_changes_are_reported() {
+        let versions = |z3: &str| {
+            vec![("boogie", "2.9.0"), ("z3", z3)]
+                .into_iter()
+                .map(|(tool, version)| (tool.to_string(), version.to_string()))
+                .collect()
+        };
+        let mut old = report(vec![]);
+        let mut new = report(vec![]);
+        assert!(compare_reports(&old, &new, 20.0)
+            .tool_version_changes
+            .is_empty());
+        old.tool_versions = versions("4.8.9");
+        new.tool_versions = versions("4.8.12");
+        let changes = compare_reports(&old, &new, 20.0).tool_version_changes;
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].tool, "z3");
+        assert_eq!(changes[0].old_version.as_deref(), Some("4.8.9"));
+        assert_eq!(changes[0].new_version.as_deref(), Some("4.8.12"));
+    }
+}
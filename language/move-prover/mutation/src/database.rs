@@ -0,0 +1,316 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// Storage of the results of mutation runs in a SQLite database, so the quality of the specs can
+// be tracked across many runs.
+
+use crate::{
+    error::MutationError,
+    report::{MutationRecord, MutationReport},
+};
+use anyhow::anyhow;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+
+/// The statements migrating the schema of the database from each version to the next, where
+/// the statements at index `i` migrate from version `i` to `i + 1`. Changes of the schema must
+/// be added as a new migration, so existing databases are upgraded when they are opened.
+const MIGRATIONS: &[&str] = &["
+CREATE TABLE campaigns (
+    id INTEGER PRIMARY KEY,
+    time TEXT NOT NULL,
+    config TEXT NOT NULL,
+    git_rev TEXT,
+    options_hash TEXT NOT NULL,
+    tool_versions TEXT NOT NULL,
+    kill_rate REAL NOT NULL,
+    report TEXT NOT NULL
+);
+CREATE TABLE mutants (
+    id TEXT PRIMARY KEY,
+    module TEXT NOT NULL,
+    function TEXT NOT NULL,
+    operator TEXT NOT NULL,
+    file TEXT,
+    line INTEGER,
+    column INTEGER,
+    before TEXT,
+    after TEXT
+);
+CREATE TABLE results (
+    campaign_id INTEGER NOT NULL REFERENCES campaigns(id),
+    mutant_id TEXT REFERENCES mutants(id),
+    module TEXT NOT NULL,
+    status TEXT NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    peak_mem_mb INTEGER NOT NULL,
+    record TEXT NOT NULL
+);
+CREATE INDEX results_by_campaign ON results(campaign_id);
+CREATE INDEX results_by_mutant ON results(mutant_id);
+"];
+
+/// A database of the results of mutation runs. Each run is stored as a campaign, with one
+/// result for each of its records, which refers to the mutant the record is of, if any.
+pub struct ResultDatabase {
+    path: String,
+    connection: Connection,
+}
+
+impl ResultDatabase {
+    /// Opens the database at the given path, creating it if it does not exist, and migrates its
+    /// schema to the current version. Fails if the schema is of a newer version than supported.
+    pub fn open(path: &str) -> Result<Self, MutationError> {
+        let mut db = Self {
+            path: path.to_string(),
+            connection: Connection::open(Path::new(path))
+                .map_err(|e| MutationError::DatabaseFailed(path.to_string(), e.into()))?,
+        };
+        db.migrate()?;
+        Ok(db)
+    }
+
+    /// Returns the version of the schema of the database, where 0 denotes an empty database.
+    pub fn schema_version(&self) -> Result<usize, MutationError> {
+        let version: i64 = self
+            .connection
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(|e| self.error(e.into()))?;
+        Ok(version as usize)
+    }
+
+    fn migrate(&mut self) -> Result<(), MutationError> {
+        let version = self.schema_version()?;
+        if version > MIGRATIONS.len() {
+            return Err(self.error(anyhow!(
+                "schema version {} is newer than the supported version {}",
+                version,
+                MIGRATIONS.len()
+            )));
+        }
+        let path = self.path.clone();
+        let error = |e: rusqlite::Error| MutationError::DatabaseFailed(path.clone(), e.into());
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = self.connection.transaction().map_err(error)?;
+            tx.execute_batch(migration).map_err(error)?;
+            tx.pragma_update(None, "user_version", &(i as i64 + 1))
+                .map_err(error)?;
+            tx.commit().map_err(error)?;
+        }
+        Ok(())
+    }
+
+    /// Stores the report as a new campaign and returns the id of the campaign.
+    pub fn insert_campaign(&mut self, report: &MutationReport) -> Result<i64, MutationError> {
+        let path = self.path.clone();
+        let error = |e: anyhow::Error| MutationError::DatabaseFailed(path.clone(), e);
+        // The records are stored as results, so they are not repeated in the campaign.
+        let header = MutationReport {
+            records: vec![],
+            ..report.clone()
+        };
+        let tx = self.connection.transaction().map_err(|e| error(e.into()))?;
+        tx.execute(
+            "INSERT INTO campaigns
+            (time, config, git_rev, options_hash, tool_versions, kill_rate, report)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                report.time,
+                report.config,
+                report.git_rev,
+                report.options_hash,
+                serde_json::to_string(&report.tool_versions).map_err(|e| error(e.into()))?,
+                report.summary.kill_rate,
+                serde_json::to_string(&header).map_err(|e| error(e.into()))?,
+            ],
+        )
+        .map_err(|e| error(e.into()))?;
+        let campaign_id = tx.last_insert_rowid();
+        for record in &report.records {
+            insert_result(&tx, campaign_id, record).map_err(error)?;
+        }
+        tx.commit().map_err(|e| error(e.into()))?;
+        Ok(campaign_id)
+    }
+
+    /// Reads the report of the campaign with the given id.
+    pub fn read_campaign(&self, campaign_id: i64) -> Result<MutationReport, MutationError> {
+        self.query_campaign(campaign_id)
+            .map_err(|e| self.error(e))?
+            .ok_or_else(|| self.error(anyhow!("no campaign with id {}", campaign_id)))
+    }
+
+    fn query_campaign(&self, campaign_id: i64) -> anyhow::Result<Option<MutationReport>> {
+        let header = self
+            .connection
+            .query_row(
+                "SELECT report FROM campaigns WHERE id = ?1",
+                params![campaign_id],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+        let mut report: MutationReport = match header {
+            Some(header) => serde_json::from_str(&header)?,
+            None => return Ok(None),
+        };
+        let mut statement = self
+            .connection
+            .prepare("SELECT record FROM results WHERE campaign_id = ?1 ORDER BY rowid")?;
+        let records = statement
+            .query_map(params![campaign_id], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        for record in records {
+            report.records.push(serde_json::from_str(&record)?);
+        }
+        Ok(Some(report))
+    }
+
+    fn error(&self, error: anyhow::Error) -> MutationError {
+        MutationError::DatabaseFailed(self.path.clone(), error)
+    }
+}
+
+/// Stores the record as a result of the campaign, together with its mutant, if any.
+fn insert_result(
+    tx: &rusqlite::Transaction<'_>,
+    campaign_id: i64,
+    record: &MutationRecord,
+) -> anyhow::Result<()> {
+    let mutant_id = if record.mutant_id.is_empty() {
+        None
+    } else {
+        let site = record.site.as_ref();
+        // The latest location of a mutant is kept, as its id does not change when the
+        // function is moved.
+        tx.execute(
+            "INSERT OR REPLACE INTO mutants
+            (id, module, function, operator, file, line, column, before, after)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                record.mutant_id,
+                record.module,
+                record.function,
+                record.operator,
+                site.map(|s| s.file.clone()),
+                site.map(|s| s.line as i64),
+                site.map(|s| s.column as i64),
+                site.map(|s| s.before.clone()),
+                site.map(|s| s.after.clone()),
+            ],
+        )?;
+        Some(record.mutant_id.as_str())
+    };
+    tx.execute(
+        "INSERT INTO results
+        (campaign_id, mutant_id, module, status, duration_ms, peak_mem_mb, record)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            campaign_id,
+            mutant_id,
+            record.module,
+            record.status.to_string(),
+            record.duration_ms as i64,
+            record.peak_mem_mb as i64,
+            serde_json::to_string(record)?,
+        ],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::{MutationSite, MutationStatus};
+
+    fn record(module: &str, mutant_id: &str, status: MutationStatus) -> MutationRecord {
+        MutationRecord {
+            module: module.to_string(),
+            function: if mutant_id.is_empty() {
+                String::new()
+            } else {
+                "Arith::add".to_string()
+            },
+            mutant_id: mutant_id.to_string(),
+            site: Some(MutationSite {
+                file: "arith.move".to_string(),
+                line: 4,
+                column: 9,
+                before: "x + y".to_string(),
+                after: "x - y".to_string(),
+                description: String::new(),
+            }),
+            duration_ms: 120,
+            ..MutationRecord::test_record(status)
+        }
+    }
+
+    #[test]
+    fn campaigns_are_stored_and_read_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("results.db").to_string_lossy().to_string();
+        let mut report = MutationReport {
+            config: "default".to_string(),
+            git_rev: Some("abc123".to_string()),
+            seed: 7,
+            records: vec![
+                record("0x42::Slow", "", MutationStatus::SkippedSlow),
+                record(
+                    "0x42::Arith",
+                    "0x42::Arith::add::add-sub::1@12345678",
+                    MutationStatus::Errors,
+                ),
+            ],
+            ..MutationReport::default()
+        };
+        report.summarize();
+        report.conclude();
+
+        let mut db = ResultDatabase::open(&path).unwrap();
+        let first = db.insert_campaign(&report).unwrap();
+        report.records[1].status = MutationStatus::Ok;
+        let second = db.insert_campaign(&report).unwrap();
+        assert_ne!(first, second);
+        drop(db);
+
+        // The database is reused when opened again.
+        let db = ResultDatabase::open(&path).unwrap();
+        assert_eq!(db.schema_version().unwrap(), MIGRATIONS.len());
+        let read = db.read_campaign(first).unwrap();
+        assert_eq!(read.git_rev.as_deref(), Some("abc123"));
+        assert_eq!(read.seed, 7);
+        assert!(!read.is_truncated());
+        let statuses = read.records.iter().map(|r| r.status).collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![MutationStatus::SkippedSlow, MutationStatus::Errors]
+        );
+        assert_eq!(
+            db.read_campaign(second).unwrap().records[1].status,
+            MutationStatus::Ok
+        );
+        let mutants: i64 = db
+            .connection
+            .query_row("SELECT COUNT(*) FROM mutants", params![], |row| row.get(0))
+            .unwrap();
+        assert_eq!(mutants, 1);
+        assert!(db.read_campaign(second + 1).is_err());
+    }
+
+    #[test]
+    fn newer_schema_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("results.db").to_string_lossy().to_string();
+        let connection = Connection::open(&path).unwrap();
+        connection
+            .pragma_update(None, "user_version", &(MIGRATIONS.len() as i64 + 1))
+            .unwrap();
+        drop(connection);
+        match ResultDatabase::open(&path) {
+            Err(MutationError::DatabaseFailed(_, e)) => {
+                assert!(e.to_string().contains("newer than the supported version"))
+            }
+            Err(e) => panic!("expected the schema to be rejected, found {}", e),
+            Ok(_) => panic!("expected the schema to be rejected"),
+        }
+    }
+}
@@ -0,0 +1,79 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// Errors reported by the mutation tool.
+
+use thiserror::Error;
+
+/// Defines all errors in this crate.
+#[derive(Debug, Error)]
+pub enum MutationError {
+    #[error("invalid configuration: {0}")]
+    InvalidConfig(anyhow::Error),
+    #[error(
+        "function `{0}` not found in target modules. Available functions: {}",
+        .1.join(", ")
+    )]
+    UnknownFunction(String, Vec<String>),
+    #[error("failed to build the model: {0}")]
+    ModelBuildFailed(anyhow::Error),
+    #[error("verification of the unmutated program failed: {0}")]
+    BaselineFailed(String),
+    #[error("prover backend not found: {0}")]
+    BackendMissing(String),
+    #[error("preflight check of the toolchain failed: {0}")]
+    PreflightFailed(String),
+    #[error("version `{2}` of {0} found, but version `{1}` is required")]
+    ToolVersionMismatch(String, String, String),
+    #[error("failed to translate mutated program: {0}")]
+    TranslationFailed(anyhow::Error),
+    #[error("failed to run prover backend: {0}")]
+    VerificationFailed(anyhow::Error),
+    #[error("failed to write output: {0}")]
+    OutputWriteFailed(#[from] std::io::Error),
+    #[error("cannot write output to `{0}`: {1}")]
+    OutputPathFailed(String, std::io::Error),
+    #[error("mutation score {0:.2} is below the required minimum of {1:.2}")]
+    ScoreBelowThreshold(f64, f64),
+    #[error("{0} mutations survived")]
+    MutationsSurvived(usize),
+    #[error("the specs of {0} are vacuous")]
+    VacuousSpecs(String),
+    #[error("cannot read results from `{0}`: {1}")]
+    ReportReadFailed(String, anyhow::Error),
+    #[error("results in `{0}` are truncated. Pass `--allow-partial` to compare them anyway")]
+    TruncatedReport(String),
+    #[error("cannot access the results database `{0}`: {1}")]
+    DatabaseFailed(String, anyhow::Error),
+    #[error("{0} regressions found")]
+    Regressions(usize),
+    #[error("interrupted, the results are incomplete")]
+    Interrupted,
+}
+
+impl MutationError {
+    /// Returns the exit code the mutation tool terminates with for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            MutationError::InvalidConfig(_) => 2,
+            MutationError::UnknownFunction(..) => 2,
+            MutationError::ModelBuildFailed(_) => 3,
+            MutationError::BaselineFailed(_) => 4,
+            MutationError::BackendMissing(_) => 5,
+            MutationError::ToolVersionMismatch(..) => 5,
+            MutationError::PreflightFailed(_) => 5,
+            MutationError::TranslationFailed(_) => 6,
+            MutationError::VerificationFailed(_) => 7,
+            MutationError::OutputWriteFailed(_) => 8,
+            MutationError::OutputPathFailed(..) => 8,
+            MutationError::ScoreBelowThreshold(..) => 9,
+            MutationError::MutationsSurvived(_) => 10,
+            MutationError::ReportReadFailed(..) => 2,
+            MutationError::TruncatedReport(_) => 2,
+            MutationError::DatabaseFailed(..) => 8,
+            MutationError::Regressions(_) => 11,
+            MutationError::VacuousSpecs(_) => 12,
+            MutationError::Interrupted => 130,
+        }
+    }
+}
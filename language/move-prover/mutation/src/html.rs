@@ -0,0 +1,233 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// Rendering of the results of a mutation run as a standalone HTML page.
+
+use crate::report::{FlakyPolicy, MutationRecord, MutationReport, MutationStatus, MutationSummary};
+use std::{collections::BTreeMap, fmt::Write};
+
+/// The styles of the page, which are inlined so the page does not depend on external assets.
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.8em; text-align: right; }
+th:first-child, td:first-child { text-align: left; }
+th { background: #eee; }
+tr.total td { font-weight: bold; }
+details { margin: 0.3em 0; }
+summary { cursor: pointer; font-family: monospace; }
+pre { margin: 0.3em 0 0.3em 1.5em; padding: 0.3em; background: #f6f6f6; }
+.before { color: #a00; }
+.after { color: #070; }
+";
+
+/// Renders the report as a standalone HTML page. The page contains a table with the summary
+/// and the verification time of each module, followed by the surviving mutations, each with its
+/// location, the source text before and after the mutation, and a link to its kept artifacts.
+pub fn render_html(report: &MutationReport) -> String {
+    let mut html = String::new();
+    // Writing to a string cannot fail.
+    let _ = write_html(&mut html, report);
+    html
+}
+
+fn write_html(html: &mut String, report: &MutationReport) -> std::fmt::Result {
+    writeln!(html, "<!DOCTYPE html>")?;
+    writeln!(html, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(
+        html,
+        "<title>Mutation results: {}</title>",
+        escape(&report.config)
+    )?;
+    writeln!(html, "<style>\n{}</style>\n</head>\n<body>", STYLE)?;
+    writeln!(
+        html,
+        "<h1>Mutation results for config <code>{}</code></h1>",
+        escape(&report.config)
+    )?;
+    writeln!(
+        html,
+        "<p>Run at {}{}{}.</p>",
+        escape(&report.time),
+        report
+            .git_rev
+            .as_ref()
+            .map_or_else(String::new, |rev| format!(
+                " on revision <code>{}</code>",
+                escape(rev)
+            )),
+        if report.interrupted {
+            ", interrupted before all mutations were verified"
+        } else {
+            ""
+        }
+    )?;
+    writeln!(html, "<p>{}</p>", escape(&report.summary.to_string()))?;
+
+    writeln!(html, "<h2>Modules</h2>\n<table>")?;
+    writeln!(
+        html,
+        "<tr><th>module</th><th>total</th><th>killed</th><th>survived</th><th>timeout</th>\
+        <th>kill rate</th><th>time</th></tr>"
+    )?;
+    let mut times: BTreeMap<&str, u64> = BTreeMap::new();
+    for record in &report.records {
+        *times.entry(record.module.as_str()).or_default() += record.duration_ms;
+    }
+    let row = |html: &mut String, class: &str, name: &str, summary: &MutationSummary, ms: u64| {
+        writeln!(
+            html,
+            "<tr class=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td>\
+            <td>{:.2}%</td><td>{:.3}s</td></tr>",
+            class,
+            escape(name),
+            summary.total,
+            summary.errors,
+            summary.survived(report.flaky_policy),
+            summary.timeout,
+            summary.kill_rate,
+            ms as f64 / 1000.0
+        )
+    };
+    for (module, summary) in MutationSummary::per_module(&report.records, report.flaky_policy) {
+        let ms = times.get(module.as_str()).copied().unwrap_or_default();
+        row(html, "module", &module, &summary, ms)?;
+    }
+    row(
+        html,
+        "total",
+        "total",
+        &report.summary,
+        times.values().sum(),
+    )?;
+    writeln!(html, "</table>")?;
+
+    let survivors = report
+        .records
+        .iter()
+        .filter(|r| is_survivor(r, report.flaky_policy))
+        .collect::<Vec<_>>();
+    writeln!(html, "<h2>Surviving mutations ({})</h2>", survivors.len())?;
+    for record in survivors {
+        write_survivor(html, record)?;
+    }
+    writeln!(html, "</body>\n</html>")
+}
+
+/// Returns true if the record is of a mutation which counts as surviving under the policy.
+fn is_survivor(record: &MutationRecord, flaky_policy: FlakyPolicy) -> bool {
+    !record.function.is_empty()
+        && (record.status == MutationStatus::Ok
+            || (record.status == MutationStatus::Flaky && flaky_policy == FlakyPolicy::Survived))
+}
+
+/// Writes an expandable entry for the surviving mutation.
+fn write_survivor(html: &mut String, record: &MutationRecord) -> std::fmt::Result {
+    let title = if record.mutant_id.is_empty() {
+        format!("{} {}", record.function, record.operator)
+    } else {
+        record.mutant_id.clone()
+    };
+    writeln!(html, "<details>\n<summary>{}", escape(&title))?;
+    if let Some(site) = &record.site {
+        write!(html, " at {}", escape(&site.location()))?;
+    }
+    writeln!(html, " ({})</summary>", escape(record.status.as_str()))?;
+    if let Some(site) = &record.site {
+        if !site.before.is_empty() {
+            writeln!(
+                html,
+                "<pre class=\"before\">- {}</pre>",
+                escape(&site.before)
+            )?;
+        }
+        if !site.after.is_empty() {
+            writeln!(html, "<pre class=\"after\">+ {}</pre>", escape(&site.after))?;
+        }
+    }
+    if let Some(artifacts) = &record.artifacts {
+        writeln!(
+            html,
+            "<p>Artifacts: <a href=\"{0}/mutant.bpl\">mutant.bpl</a>, \
+            <a href=\"{0}/prover.log\">prover.log</a>, \
+            <a href=\"{0}/mutant.json\">mutant.json</a></p>",
+            escape(artifacts)
+        )?;
+    }
+    writeln!(html, "</details>")
+}
+
+/// Escapes the characters of the text which have a meaning in HTML.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::MutationSite;
+
+    fn record(status: MutationStatus, before: &str, after: &str) -> MutationRecord {
+        MutationRecord {
+            module: "0x42::Relational".to_string(),
+            function: "Relational::lt".to_string(),
+            mutant_id: "0x42::Relational::lt::lt-le::1".to_string(),
+            operator: "lt-le".to_string(),
+            site: Some(MutationSite {
+                file: "relational.move".to_string(),
+                line: 3,
+                column: 9,
+                before: before.to_string(),
+                after: after.to_string(),
+                description: String::new(),
+            }),
+            duration_ms: 1500,
+            artifacts: Some("artifacts/0x42.Relational/0x42.Relational.lt.lt-le.1".to_string()),
+            ..MutationRecord::test_record(status)
+        }
+    }
+
+    #[test]
+    fn survivors_are_rendered_escaped() {
+        let records = vec![
+            record(MutationStatus::Ok, "x < y", "x <= y"),
+            record(MutationStatus::Errors, "a", "b"),
+        ];
+        let report = MutationReport {
+            config: "default".to_string(),
+            summary: MutationSummary::from_records(&records, FlakyPolicy::Exclude),
+            records,
+            ..MutationReport::default()
+        };
+        let html = render_html(&report);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<td>0x42::Relational</td><td>2</td><td>1</td><td>1</td>"));
+        assert!(html.contains("<td>3.000s</td>"));
+        assert!(html.contains("<h2>Surviving mutations (1)</h2>"));
+        assert!(html.contains("<pre class=\"before\">- x &lt; y</pre>"));
+        assert!(html.contains("<pre class=\"after\">+ x &lt;= y</pre>"));
+        assert!(html.contains(
+            "<a href=\"artifacts/0x42.Relational/0x42.Relational.lt.lt-le.1/mutant.bpl\">"
+        ));
+        assert!(!html.contains("<link") && !html.contains("<script"));
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        assert_eq!(
+            escape("<a href=\"x\">&'"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&#39;"
+        );
+    }
+}
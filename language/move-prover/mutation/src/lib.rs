@@ -3,4 +3,13 @@
 
 #![forbid(unsafe_code)]
 
+pub mod changes;
+pub mod compare;
+pub mod database;
+pub mod error;
+pub mod html;
+pub mod memory;
 pub mod mutator;
+pub mod operators;
+pub mod options;
+pub mod report;
@@ -4,9 +4,23 @@
 #![forbid(unsafe_code)]
 
 use itertools::Itertools;
-use prover_mutation::mutator;
+use prover_mutation::{compare, error::MutationError, mutator};
 
 fn main() {
     let args = std::env::args().collect_vec();
-    mutator::mutate(&args[1..]);
+    // On Ctrl-C or termination, stop after the verification in progress and report the results
+    // so far.
+    if let Err(e) = ctrlc::set_handler(mutator::interrupt) {
+        eprintln!("cannot install interrupt handler: {}", e);
+    }
+    let result = match args.get(1).map(String::as_str) {
+        Some("compare") => compare::compare(&args[1..]),
+        _ => mutator::mutate(&args[1..]),
+    };
+    if let Err(e) = result {
+        let code = e
+            .downcast_ref::<MutationError>()
+            .map_or(1, MutationError::exit_code);
+        std::process::exit(code);
+    }
 }
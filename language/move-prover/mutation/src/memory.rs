@@ -0,0 +1,250 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// Monitoring of the memory and the run time of the prover backend while it verifies a mutation.
+
+use std::{
+    collections::BTreeMap,
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+/// The interval in which the memory of the processes is sampled and the deadline checked.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The memory used by the processes of a verification, and whether they have been killed, as
+/// determined by `MemoryMonitor`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// The peak of the resident memory of all processes, in MiB.
+    pub peak_mb: u64,
+    /// Whether the processes have been killed because they exceeded the memory limit.
+    pub exceeded: bool,
+    /// Whether the processes have been killed because they were still running at the deadline.
+    pub hung: bool,
+}
+
+/// Samples the resident memory of the processes of the prover backend verifying a Boogie file,
+/// together with all processes they started, until stopped. Also kills these processes once a
+/// deadline has passed, so a wedged solver cannot stall the run even if the backend fails to
+/// terminate it. The processes are determined from `/proc`, so they are only monitored on
+/// Linux; elsewhere, the usage is reported as zero and processes are never killed.
+pub struct MemoryMonitor {
+    stopped: Arc<AtomicBool>,
+    peak_kb: Arc<AtomicU64>,
+    exceeded: Arc<AtomicBool>,
+    hung: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl MemoryMonitor {
+    /// Starts monitoring the child processes of this process which are given the Boogie file as
+    /// an argument. If a limit in MiB is given, these processes are killed once their resident
+    /// memory exceeds it, and if a deadline is given, once it has passed. Processes of other
+    /// workers verifying other files are left alone.
+    pub fn start(limit_mb: Option<u64>, deadline: Option<Duration>, boogie_file: &str) -> Self {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let peak_kb = Arc::new(AtomicU64::new(0));
+        let exceeded = Arc::new(AtomicBool::new(false));
+        let hung = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let (stopped, peak_kb) = (stopped.clone(), peak_kb.clone());
+            let (exceeded, hung) = (exceeded.clone(), hung.clone());
+            let boogie_file = boogie_file.to_string();
+            let start = Instant::now();
+            std::thread::spawn(move || {
+                while !stopped.load(Ordering::SeqCst) {
+                    let processes = backend_processes(&boogie_file);
+                    let rss_kb = processes.iter().filter_map(|pid| resident_kb(*pid)).sum();
+                    peak_kb.fetch_max(rss_kb, Ordering::SeqCst);
+                    if matches!(limit_mb, Some(limit) if rss_kb > limit * 1024) {
+                        exceeded.store(true, Ordering::SeqCst);
+                        kill(&processes);
+                    } else if !processes.is_empty()
+                        && matches!(deadline, Some(deadline) if start.elapsed() >= deadline)
+                    {
+                        // Keep killing until stopped, in case the backend starts new processes.
+                        hung.store(true, Ordering::SeqCst);
+                        kill(&processes);
+                    }
+                    std::thread::sleep(SAMPLE_INTERVAL);
+                }
+            })
+        };
+        Self {
+            stopped,
+            peak_kb,
+            exceeded,
+            hung,
+            handle,
+        }
+    }
+
+    /// Stops monitoring and returns the memory used since the start.
+    pub fn stop(self) -> MemoryUsage {
+        self.stopped.store(true, Ordering::SeqCst);
+        let _ = self.handle.join();
+        MemoryUsage {
+            peak_mb: self.peak_kb.load(Ordering::SeqCst) / 1024,
+            exceeded: self.exceeded.load(Ordering::SeqCst),
+            hung: self.hung.load(Ordering::SeqCst),
+        }
+    }
+}
+
+/// Returns the ids of all transitive child processes of the given process.
+fn descendants(root: u32) -> Vec<u32> {
+    let mut children: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+    let entries = match std::fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    for entry in entries.flatten() {
+        let pid = match entry
+            .file_name()
+            .to_str()
+            .and_then(|s| s.parse::<u32>().ok())
+        {
+            Some(pid) => pid,
+            None => continue,
+        };
+        if let Some(ppid) = std::fs::read_to_string(entry.path().join("stat"))
+            .ok()
+            .and_then(|stat| parent_of(&stat))
+        {
+            children.entry(ppid).or_default().push(pid);
+        }
+    }
+    let mut result = vec![];
+    let mut todo = vec![root];
+    while let Some(pid) = todo.pop() {
+        for child in children.remove(&pid).unwrap_or_default() {
+            result.push(child);
+            todo.push(child);
+        }
+    }
+    result
+}
+
+/// Returns the ids of the child processes of this process which are given the argument, and of
+/// all processes they started.
+fn backend_processes(arg: &str) -> Vec<u32> {
+    let mut result = vec![];
+    for pid in descendants(std::process::id()) {
+        if !result.contains(&pid) && arguments(pid).iter().any(|a| a == arg) {
+            result.push(pid);
+            result.extend(descendants(pid));
+        }
+    }
+    result
+}
+
+/// Returns the command line of the given process, from `/proc/PID/cmdline`.
+fn arguments(pid: u32) -> Vec<String> {
+    std::fs::read(format!("/proc/{}/cmdline", pid))
+        .map(|cmdline| {
+            cmdline
+                .split(|b| *b == 0)
+                .filter(|arg| !arg.is_empty())
+                .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the id of the parent process from the content of `/proc/PID/stat`. The name of the
+/// process is in parentheses and may contain spaces, so the fields are counted from its end.
+fn parent_of(stat: &str) -> Option<u32> {
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Returns the resident memory of the given process in KiB, from `/proc/PID/status`.
+fn resident_kb(pid: u32) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|value| value.trim().trim_end_matches("kB").trim().parse().ok())
+}
+
+/// Kills the given processes. Processes which already terminated are ignored.
+fn kill(pids: &[u32]) {
+    if pids.is_empty() {
+        return;
+    }
+    let _ = Command::new("kill")
+        .arg("-KILL")
+        .args(pids.iter().map(|pid| pid.to_string()))
+        .output();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn only_processes_of_the_monitored_file_are_killed() {
+        let marker = format!("memory-test-{}", std::process::id());
+        let other_marker = format!("{}-other", marker);
+        let spawn = |marker: &str| {
+            Command::new("sh")
+                .args(&["-c", "sleep 1000; true", marker])
+                .spawn()
+                .unwrap()
+        };
+        let mut child = spawn(&marker);
+        let mut other_child = spawn(&other_marker);
+        // Any process exceeds a limit of zero.
+        let monitor = MemoryMonitor::start(Some(0), None, &marker);
+        child.wait().unwrap();
+        let usage = monitor.stop();
+        assert!(usage.exceeded);
+        assert!(!usage.hung);
+        assert!(backend_processes(&marker).is_empty());
+        assert!(!backend_processes(&other_marker).is_empty());
+        kill(&backend_processes(&other_marker));
+        other_child.wait().unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn hung_process_tree_is_killed() {
+        let marker = format!("deadline-test-{}", std::process::id());
+        // The shell waits for the sleep it started, as a backend waits for its solver.
+        let mut child = Command::new("sh")
+            .args(&["-c", "sleep 1000; true", &marker])
+            .spawn()
+            .unwrap();
+        let monitor = MemoryMonitor::start(None, Some(Duration::from_millis(100)), &marker);
+        let status = child.wait().unwrap();
+        let usage = monitor.stop();
+        assert!(usage.hung);
+        assert!(!usage.exceeded);
+        assert!(!status.success());
+        assert!(backend_processes(&marker).is_empty());
+    }
+
+    #[test]
+    fn nothing_is_killed_before_deadline() {
+        let monitor = MemoryMonitor::start(
+            None,
+            Some(Duration::from_secs(1000)),
+            "no-such-boogie-file.bpl",
+        );
+        assert!(!monitor.stop().hung);
+    }
+
+    #[test]
+    fn parent_is_parsed_from_stat() {
+        assert_eq!(parent_of("42 (z3) S 7 42 42 0 -1"), Some(7));
+        assert_eq!(parent_of("42 (a (b) c) R 9 42 42 0 -1"), Some(9));
+        assert_eq!(parent_of("garbage"), None);
+    }
+}
@@ -3,34 +3,179 @@
 
 // Functions for running move programs with mutations and reporting errors if found
 
-use bytecode::{mutation_tester::MutationManager, options::ProverOptions};
+use crate::{
+    changes::{changed_functions, changed_lines},
+    compare::read_report,
+    database::ResultDatabase,
+    error::MutationError,
+    html::render_html,
+    memory::MemoryMonitor,
+    operators::{install_mutation, MutationOperator, OperatorRegistry},
+    options::{
+        function_matches, Granularity, ModulePattern, MutationOptions, Sample, SampleSize, Shard,
+        TimeoutEscalation, VerifyScope,
+    },
+    report::{
+        FlakyPolicy, Mutant, MutationRecord, MutationReport, MutationSite, MutationStatus,
+        MutationSummary, SpecMutationRecord, TimeoutRetry, TimingRecord, VerificationRun,
+    },
+};
+use anyhow::anyhow;
+use boogie_backend::options::BoogieOptions;
+use bytecode::{
+    function_data_builder::FunctionDataBuilder,
+    function_target::FunctionData,
+    function_target_pipeline::FunctionTargetsHolder,
+    mutation_tester::{CustomMutation, MutatedLocation, MutationManager},
+    options::ProverOptions,
+    stackless_bytecode::{Bytecode, Constant, PropKind},
+    stackless_bytecode_generator::StacklessBytecodeGenerator,
+};
 use clap::{App, Arg};
-use codespan_reporting::term::termcolor::{ColorChoice, StandardStream};
+use codespan_reporting::{
+    diagnostic::Severity,
+    term::termcolor::{Buffer, ColorChoice, StandardStream},
+};
 use itertools::Itertools;
 use log::LevelFilter;
+use move_command_line_common::files::find_move_filenames;
+use move_lang::Flags;
 use move_model::{
-    model::{FunctionEnv, GlobalEnv, VerificationScope},
+    ast::ConditionKind,
+    code_writer::CodeWriter,
+    exp_generator::ExpGenerator,
+    model::{FunId, FunctionEnv, GlobalEnv, Loc, ModuleEnv, QualifiedId, VerificationScope},
     options::ModelBuilderOptions,
     parse_addresses_from_options, run_model_builder_with_options,
+    run_model_builder_with_options_and_compilation_flags,
 };
 use move_prover::{
     check_errors, cli::Options, create_and_process_bytecode, generate_boogie, verify_boogie,
 };
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
 use std::{
-    io::Write,
-    path::PathBuf,
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    io::{BufRead, LineWriter, Write},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
     time::{Duration, Instant},
 };
 
+/// Set when the run is to be stopped, e.g. on Ctrl-C. It is checked between mutations, so the
+/// verification in progress can finish and the results so far are reported as usual.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Requests the run to stop after the verification in progress. When requested a second time,
+/// terminates the process right away.
+pub fn interrupt() {
+    if INTERRUPTED.swap(true, Ordering::SeqCst) {
+        std::process::exit(MutationError::Interrupted.exit_code());
+    }
+    println!("\nInterrupted, finishing the current verification ..");
+}
+
+/// Returns true if the run has been requested to stop.
+fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
 // ============================================================================================
 // Command line interface for running a mutation
 
-struct Runner {
-    options: Options,
-    error_writer: StandardStream,
+/// How the second backend for cross-checking mutation results is obtained.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum CrossCheck {
+    /// No cross-checking.
+    Disabled,
+    /// The backend of the configuration with the other solver, i.e. CVC4 instead of Z3 and
+    /// vice versa.
+    OtherSolver,
+    /// The backend of the given prover toml configuration file.
+    Config(String),
+}
+
+/// The format in which mutation results are written to the output file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    /// A header of `#` comment lines followed by one fixed-width line per record.
+    Text,
+    /// A JSON serialization of the `MutationReport`.
+    Json,
+}
+
+/// Settings from the command line which apply to the runs of all configurations.
+struct RunSettings {
+    addresses: Vec<String>,
+    sources: Vec<String>,
+    dependencies: Vec<String>,
+    include_test_code: bool,
+    timeout_secs: u64,
+    seed: Option<u64>,
+    /// The backend options given by `--backend-opt`, applied in order onto the backend of each
+    /// configuration.
+    backend_opts: Vec<(String, String)>,
+    jobs: usize,
+    mutation_options: MutationOptions,
+    cross_check: CrossCheck,
+    format: OutputFormat,
+    /// The minimal fraction of mutations which must be killed for the run to succeed.
+    min_score: Option<f64>,
+    /// Whether the run fails if any mutation survives.
+    fail_on_survivor: bool,
+    /// Whether the run fails if the specs of any function are vacuous.
+    fail_on_vacuous: bool,
+    /// Whether to print the surviving mutations as diffs at the end of the run.
+    show_diffs: bool,
+    /// Whether to print the surviving mutations as GitHub annotations at the end of the run.
+    gh_annotations: bool,
+    /// The directory the files of GitHub annotations are given relative to, if not the current
+    /// directory.
+    repo_root: Option<PathBuf>,
+    /// The path to render the results to as an HTML page, if any.
+    html: Option<String>,
+    /// The path of the SQLite database to append the results to as a campaign, if any.
+    sqlite: Option<String>,
+    /// Whether to only count the mutations instead of verifying them.
+    dry_run: bool,
+    /// Whether a dry run only prints the id of each mutant.
+    ids_only: bool,
+    /// Whether to mutate the specs instead of the code.
+    mutate_specs: bool,
+    /// The surviving mutations of a previous run, if only these are verified again.
+    rerun_survivors: Option<Vec<MutationRecord>>,
+    /// The versions the tools must have by name of the tool, as given by `--require-versions`.
+    required_versions: BTreeMap<String, String>,
+    /// The git revision to restrict mutation to the functions changed since, if any.
+    changed_since: Option<String>,
+    /// Whether the direct callers of the changed functions are mutated as well.
+    callers: bool,
+    /// Whether to check that the toolchain works before starting the mutations.
+    preflight: bool,
+}
+
+pub fn mutate(args: &[String]) -> anyhow::Result<()> {
+    mutate_with_operators(args, OperatorRegistry::default())
 }
 
-pub fn mutate(args: &[String]) {
+/// Runs the mutation tool with the given command line arguments, selecting the mutation
+/// operators to apply from the given registry. This allows tools built on the mutation tool to
+/// add operators of their own.
+pub fn mutate_with_operators(args: &[String], registry: OperatorRegistry) -> anyhow::Result<()> {
+    let operators_help = format!(
+        "comma-separated list of the mutation operators to apply; by default all are applied. \
+        Supported are the operators {} and the groups {}",
+        registry.names().join(", "),
+        registry
+            .groups()
+            .map(|(group, operators)| format!("{} ({})", group, operators.join(", ")))
+            .join(", ")
+    );
     let cmd_line_parser = App::new("mutation")
         .version("0.1.0")
         .about("Mutation tool for the move prover")
@@ -38,12 +183,16 @@ pub fn mutate(args: &[String]) {
         .arg(
             Arg::with_name("addresses")
                 .long("address")
+                .alias("named-addresses")
                 .short("a")
                 .multiple(true)
                 .number_of_values(1)
                 .takes_value(true)
-                .value_name("ADDRESS")
-                .help("Address specified for the move prover"),
+                .value_name("NAME=ADDRESS")
+                .help(
+                    "the value of a named address used in the Move files, e.g. \
+                    `DiemFramework=0x1`. Can be given multiple times",
+                ),
         )
         .arg(
             Arg::with_name("config")
@@ -59,6 +208,21 @@ pub fn mutate(args: &[String]) {
                           configurations can be checked against the same set of input modules.",
                 ),
         )
+        .arg(Arg::with_name("cross-check").long("cross-check").help(
+            "verify each mutation also with a second backend and flag mutations for \
+                    which the results disagree. Unless `--cross-check-config` is given, the \
+                    second backend uses the other solver, i.e. CVC4 instead of Z3 and vice versa",
+        ))
+        .arg(
+            Arg::with_name("cross-check-config")
+                .long("cross-check-config")
+                .takes_value(true)
+                .value_name("CONFIG_PATH")
+                .help(
+                    "path to a prover toml configuration file whose backend settings are used \
+                    for cross-checking. Implies `--cross-check`",
+                ),
+        )
         .arg(
             Arg::with_name("dependencies")
                 .long("dependency")
@@ -72,12 +236,560 @@ pub fn mutate(args: &[String]) {
                     Move files, containing dependencies which will not be verified",
                 ),
         )
+        .arg(Arg::with_name("dry-run").long("dry-run").help(
+            "only lists the mutations which would be verified, with their id, location, and \
+                    description, and counts them per target module and operator, without \
+                    verifying anything. The list is written to the output file as well",
+        ))
+        .arg(
+            Arg::with_name("dump-boogie")
+                .long("dump-boogie")
+                .takes_value(true)
+                .value_name("DIR")
+                .help(
+                    "writes the Boogie program of each mutation whose verification reports \
+                    errors or times out to `DIR/ADDRESS_MODULE_FUNCTION_OPERATOR_INDEX.bpl`, \
+                    for inspecting why verification behaved as it did",
+                ),
+        )
+        .arg(
+            Arg::with_name("excluded-modules")
+                .long("exclude-module")
+                .multiple(true)
+                .number_of_values(1)
+                .takes_value(true)
+                .value_name("MODULE")
+                .help(
+                    "excludes the given target module from mutation, specified by simple name \
+                    or with its address as in `0x1::M`. Excluded modules are neither mutated \
+                    nor verified. This is a shorthand for `--exclude-modules` with the exact \
+                    name, and can be repeated to exclude multiple modules",
+                ),
+        )
+        .arg(
+            Arg::with_name("exclude-modules")
+                .long("exclude-modules")
+                .multiple(true)
+                .number_of_values(1)
+                .takes_value(true)
+                .value_name("PATTERN")
+                .help(
+                    "excludes the target modules whose full name, as in `0x1::M`, matches the \
+                    given glob, or the regular expression if enclosed in slashes as in `/^0x1::/`. \
+                    Takes precedence over `--include-modules`. This can be repeated",
+                ),
+        )
+        .arg(
+            Arg::with_name("fail-on-survivor")
+                .long("fail-on-survivor")
+                .help(
+                    "terminates with a non-zero exit code if any mutation survives, i.e. \
+                    verifies. The results are written nevertheless",
+                ),
+        )
+        .arg(
+            Arg::with_name("fail-on-vacuous")
+                .long("fail-on-vacuous")
+                .help(
+                    "terminates with a non-zero exit code if the specs of any function are \
+                    vacuous, i.e. the function verifies with `ensures false` added. The \
+                    results are written nevertheless",
+                ),
+        )
+        .arg(
+            Arg::with_name("flaky-policy")
+                .long("flaky-policy")
+                .takes_value(true)
+                .possible_values(&["exclude", "survived"])
+                .default_value("exclude")
+                .value_name("POLICY")
+                .help(
+                    "how flaky mutations, for which repeated verifications disagree, count \
+                    towards the kill rate: `exclude` leaves them out, `survived` counts them as \
+                    surviving",
+                ),
+        )
+        .arg(
+            Arg::with_name("functions")
+                .long("function")
+                .multiple(true)
+                .number_of_values(1)
+                .takes_value(true)
+                .value_name("FUNCTION")
+                .help(
+                    "restricts mutation to the given function, specified by simple name or as \
+                    `MODULE::FUN`, where MODULE may include the address as in `0x1::M::f`. \
+                    Only this function is verified for each mutant. This can be repeated to \
+                    select multiple functions",
+                ),
+        )
+        .arg(
+            Arg::with_name("gh-annotations")
+                .long("gh-annotations")
+                .help(
+                    "prints a GitHub annotation for each surviving mutation at the end of the \
+                    run, located at the mutated expression, or at the file of its module if its \
+                    site is unknown",
+                ),
+        )
+        .arg(
+            Arg::with_name("granularity")
+                .long("granularity")
+                .takes_value(true)
+                .possible_values(&["module", "function"])
+                .default_value("module")
+                .value_name("UNIT")
+                .help(
+                    "the units for which the time verification of the unmutated code takes is \
+                    recorded. With `function`, each selected function is verified on its own \
+                    in addition to its module, and the slowest functions are reported",
+                ),
+        )
+        .arg(
+            Arg::with_name("html")
+                .long("html")
+                .takes_value(true)
+                .value_name("PATH")
+                .help(
+                    "also renders the results into a standalone HTML page at the given path, \
+                    with the summary of each module and the surviving mutations. With multiple \
+                    configs, the name of the config is appended to the file name",
+                ),
+        )
+        .arg(
+            Arg::with_name("sqlite")
+                .long("sqlite")
+                .takes_value(true)
+                .value_name("PATH")
+                .help(
+                    "also appends the results as a new campaign to the SQLite database at the \
+                    given path, which is created if it does not exist, so results can be \
+                    tracked across runs. Campaigns are compared with `compare --sqlite`",
+                ),
+        )
+        .arg(
+            Arg::with_name("include-test-code")
+                .long("include-test-code")
+                .help(
+                    "whether to include `#[test_only]` modules and functions in mutation; \
+                    by default test code is excluded",
+                ),
+        )
+        .arg(
+            Arg::with_name("include-modules")
+                .long("include-modules")
+                .multiple(true)
+                .number_of_values(1)
+                .takes_value(true)
+                .value_name("PATTERN")
+                .help(
+                    "restricts mutation to the target modules whose full name, as in `0x1::M`, \
+                    matches the given glob, or the regular expression if enclosed in slashes as \
+                    in `/^0x1::/`. This can be repeated to include modules matching any pattern",
+                ),
+        )
+        .arg(
+            Arg::with_name("jobs")
+                .long("jobs")
+                .short("j")
+                .takes_value(true)
+                .default_value("1")
+                .value_name("N")
+                .help(
+                    "the number of target modules to mutate in parallel. Each job runs its own \
+                    prover backend, which in turn uses `proc_cores` cores of the boogie \
+                    options, so jobs times proc_cores should not exceed the available cores",
+                ),
+        )
+        .arg(
+            Arg::with_name("keep-all")
+                .long("keep-all")
+                .requires("keep-artifacts")
+                .help("keeps the artifacts of all mutations, not only of the surviving ones"),
+        )
+        .arg(
+            Arg::with_name("keep-artifacts")
+                .long("keep-artifacts")
+                .takes_value(true)
+                .value_name("DIR")
+                .help(
+                    "writes the Boogie program, the prover diagnostics, the translation log, and \
+                    the record of each surviving mutation to `DIR/MODULE/MUTANT`, and records \
+                    the directory in the results. Artifacts of killed mutations are removed",
+                ),
+        )
+        .arg(Arg::with_name("list-mutants").long("list-mutants").help(
+            "only prints the id of each mutation which would be verified, i.e. of the \
+                    mutations of the shard given by `--shard`, without verifying anything. \
+                    Short for `--dry-run --output-format ids`",
+        ))
+        .arg(
+            Arg::with_name("max-log-bytes")
+                .long("max-log-bytes")
+                .takes_value(true)
+                .default_value("16384")
+                .value_name("BYTES")
+                .help(
+                    "the number of bytes the output of translating a mutation, such as warnings \
+                    of the prover, is truncated to when kept in its record",
+                ),
+        )
+        .arg(
+            Arg::with_name("max-mutants")
+                .long("max-mutants")
+                .takes_value(true)
+                .conflicts_with("sample")
+                .requires("seed")
+                .value_name("N")
+                .help(
+                    "verifies a random sample of the given number of mutations, selected \
+                    reproducibly by the seed given by `--seed`, before sharding",
+                ),
+        )
+        .arg(
+            Arg::with_name("max-mutants-per-function")
+                .long("max-mutants-per-function")
+                .takes_value(true)
+                .value_name("N")
+                .help(
+                    "the maximal number of mutations generated per function, over all \
+                    operators in the order they are applied. Bounds the run time for functions \
+                    with many candidate instructions",
+                ),
+        )
+        .arg(
+            Arg::with_name("min-score")
+                .long("min-score")
+                .takes_value(true)
+                .value_name("SCORE")
+                .help(
+                    "the minimal fraction of mutations which must be killed, e.g. 0.8. If the \
+                    score of a run is lower, the tool terminates with a non-zero exit code",
+                ),
+        )
+        .arg(
+            Arg::with_name("mem-limit")
+                .long("mem-limit")
+                .takes_value(true)
+                .value_name("MB")
+                .help(
+                    "aborts verification of a mutation once the prover backend uses more than \
+                    the given number of MiB of resident memory, and records it with status \
+                    `oom`. Memory is only monitored on Linux. With `--jobs`, the limit applies \
+                    to each parallel verification separately",
+                ),
+        )
+        .arg(
+            Arg::with_name("module-budget")
+                .long("module-budget")
+                .takes_value(true)
+                .value_name("SECONDS")
+                .help(
+                    "once the mutants of a module took longer than the given number of seconds \
+                    in total, records its remaining mutants with status `skipped_budget` \
+                    without verifying them. The budget is checked between mutants, so a \
+                    running verification is never interrupted",
+                ),
+        )
+        .arg(Arg::with_name("mutate-specs").long("mutate-specs").help(
+            "instead of the code, mutates the specs of the target functions by dropping each \
+                    `ensures` and `aborts_if` condition in turn and verifying the function and \
+                    its callers. Conditions whose removal causes no verification errors are \
+                    reported as redundant. Runs sequentially",
+        ))
+        .arg(
+            Arg::with_name("no-preflight")
+                .long("no-preflight")
+                .overrides_with("preflight")
+                .help("skips the preflight check of the toolchain before starting the mutations"),
+        )
+        .arg(
+            Arg::with_name("no-vacuity-check")
+                .long("no-vacuity-check")
+                .help(
+                    "skips checking the specs of the selected functions for vacuity during the \
+                    baseline. Otherwise, each function with an `ensures` condition is verified \
+                    with `ensures false` added, and if it verifies, its specs are reported as \
+                    vacuous and its mutants recorded with status `vacuous_spec`",
+                ),
+        )
+        .arg(
+            Arg::with_name("num-shards")
+                .long("num-shards")
+                .takes_value(true)
+                .requires("shard")
+                .value_name("N")
+                .help("the number of shards mutations are partitioned into by `--shard`"),
+        )
+        .arg(
+            Arg::with_name("operator-timeouts")
+                .long("operator-timeout")
+                .multiple(true)
+                .number_of_values(1)
+                .takes_value(true)
+                .value_name("OPERATOR=SECONDS")
+                .help(
+                    "overrides the hard timeout for mutations produced by the given operator, \
+                    one of the operators supported by `--operators`. This can be repeated",
+                ),
+        )
+        .arg(
+            Arg::with_name("backend-opts")
+                .long("backend-opt")
+                .multiple(true)
+                .number_of_values(1)
+                .takes_value(true)
+                .value_name("KEY=VALUE")
+                .help(
+                    "sets an option of the prover backend, overriding the prover configuration. \
+                    Supported are the keys random_seed, vc_timeout, proc_cores, \
+                    eager_threshold, lazy_threshold, stratification_depth, num_instances, \
+                    use_cvc4, and boogie_flag, which adds a flag passed on to boogie. This can \
+                    be repeated",
+                ),
+        )
+        .arg(
+            Arg::with_name("operators")
+                .long("operators")
+                .takes_value(true)
+                .use_delimiter(true)
+                .value_name("OPERATORS")
+                .help(&operators_help),
+        )
+        .arg(
+            Arg::with_name("out")
+                .long("out")
+                .takes_value(true)
+                .value_name("PATH")
+                .help(
+                    "the file the results are written to if no configuration is given, by \
+                    default `mutation.data`. With configurations, the directory the \
+                    `.mod_data` files are written to instead of next to the configurations. \
+                    Missing parent directories are created",
+                ),
+        )
+        .arg(
+            Arg::with_name("output-format")
+                .long("output-format")
+                .alias("format")
+                .takes_value(true)
+                .possible_values(&["text", "json", "ids"])
+                .default_value("text")
+                .value_name("FORMAT")
+                .help(
+                    "the format of the mutation output file. With `--dry-run`, `ids` only \
+                    prints the id of each mutation which would be verified, i.e. of the \
+                    mutations of the shard given by `--shard`, as for scripts distributing \
+                    the mutations, and writes no output file",
+                ),
+        )
+        .arg(
+            Arg::with_name("preflight")
+                .long("preflight")
+                .overrides_with("no-preflight")
+                .help(
+                    "checks that the toolchain works before starting the mutations, failing the \
+                    run upfront if the model does not build, the backend tools do not respond to \
+                    `--version`, or a trivial function does not verify. This is the default",
+                ),
+        )
+        .arg(
+            Arg::with_name("sample")
+                .long("sample")
+                .takes_value(true)
+                .requires("seed")
+                .value_name("FRACTION")
+                .help(
+                    "verifies a random sample of the given fraction of the mutations, between 0 \
+                    and 1, selected reproducibly by the seed given by `--seed`, before sharding",
+                ),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .takes_value(true)
+                .value_name("SEED")
+                .help(
+                    "the seed for randomness in the mutation run, including the solver. Two \
+                    runs with the same seed and inputs produce the same mutations in the same \
+                    order. Defaults to the random seed of the prover configuration",
+                ),
+        )
+        .arg(
+            Arg::with_name("timeout-retry")
+                .long("timeout-retry")
+                .takes_value(true)
+                .value_name("FACTOR[,MAX]")
+                .help(
+                    "verifies each mutation which times out once more, with the timeout \
+                    multiplied by FACTOR and capped at MAX seconds. The status of such a \
+                    mutation is `timeout_then_caught`, `timeout_then_survived`, or \
+                    `timeout_final`, and it counts as a timeout towards the kill rate",
+                ),
+        )
+        .arg(
+            Arg::with_name("require-versions")
+                .long("require-versions")
+                .takes_value(true)
+                .value_name("TOOL=VERSION,..")
+                .help(
+                    "fails before mutating if the versions of the tools differ from the given \
+                    ones, e.g. `boogie=2.9.0,z3=4.8.9`. The tools are `boogie`, `z3`, `cvc4`, \
+                    and `mutation`",
+                ),
+        )
+        .arg(
+            Arg::with_name("repeat")
+                .long("repeat")
+                .takes_value(true)
+                .default_value("1")
+                .value_name("N")
+                .help(
+                    "verifies each mutation up to the given number of times, as results near the \
+                    timeout may vary between runs. Mutations for which the results disagree are \
+                    recorded with status `flaky`, together with the result of each run",
+                ),
+        )
+        .arg(
+            Arg::with_name("repo-root")
+                .long("repo-root")
+                .takes_value(true)
+                .value_name("DIR")
+                .help(
+                    "the root of the repository the files of GitHub annotations are given \
+                    relative to. Defaults to the current directory",
+                ),
+        )
+        .arg(Arg::with_name("quiet").long("quiet").short("q").help(
+            "does not print the progress of verifying each mutation, only the summary at the end",
+        ))
+        .arg(
+            Arg::with_name("rerun-survivors")
+                .long("rerun-survivors")
+                .takes_value(true)
+                .value_name("PATH")
+                .conflicts_with("mutate-specs")
+                .help(
+                    "only verifies the mutations which survived in the result file at the given \
+                    path, and reports which of them are now caught. Mutations which cannot be \
+                    reconstructed from the current sources are recorded with status `stale`",
+                ),
+        )
+        .arg(
+            Arg::with_name("changed-since")
+                .long("changed-since")
+                .takes_value(true)
+                .value_name("REV")
+                .help(
+                    "restricts mutation to the functions of the target modules with lines \
+                    changed since the given git revision, as determined by `git diff` over the \
+                    sources. If no function changed, the run succeeds with a report recording \
+                    that there was nothing to mutate",
+                ),
+        )
+        .arg(
+            Arg::with_name("callers")
+                .long("callers")
+                .requires("changed-since")
+                .help("mutates the direct callers of the changed functions as well"),
+        )
+        .arg(
+            Arg::with_name("shard")
+                .long("shard")
+                .takes_value(true)
+                .requires("num-shards")
+                .value_name("INDEX")
+                .help(
+                    "only verifies the mutations of the given shard, starting at 0, out of the \
+                    number of shards given by `--num-shards`. Mutations are assigned to shards \
+                    by a hash of their module, function, operator, and index, so the shards of \
+                    separate runs are disjoint and together cover all mutations",
+                ),
+        )
+        .arg(Arg::with_name("show-diffs").long("show-diffs").help(
+            "prints each surviving mutation as a unified diff of the mutated \
+                    expression at the end of the run",
+        ))
+        .arg(
+            Arg::with_name("skip-slow")
+                .long("skip-slow")
+                .takes_value(true)
+                .value_name("SECONDS")
+                .help(
+                    "skips mutation of any module whose unmutated code takes longer than the \
+                    given number of seconds to verify. Skipped modules are recorded with status \
+                    `skipped_slow`",
+                ),
+        )
+        .arg(
+            Arg::with_name("stub-backend")
+                .long("stub-backend")
+                .hidden(true)
+                .help(
+                    "replaces verification by a stub which kills each mutation unless its line \
+                    is marked with `stub: survives`, for testing without boogie and a solver. \
+                    Also enabled by setting `MUTATION_STUB_BACKEND`",
+                ),
+        )
+        .arg(
+            Arg::with_name("timeout-secs")
+                .long("timeout-secs")
+                .alias("timeout")
+                .takes_value(true)
+                .default_value("100")
+                .value_name("SECONDS")
+                .help(
+                    "the hard timeout for verifying a single mutation, in seconds. Must be \
+                    positive. Mutations exceeding it are recorded with status `timeout`",
+                ),
+        )
+        .arg(Arg::with_name("verbose").long("verbose").short("v").help(
+            "prints the output of translating each mutation and the log messages of the prover \
+            to stderr. Otherwise, this output is only kept in the record of the mutation",
+        ))
+        .arg(
+            Arg::with_name("verify-scope")
+                .long("verify-scope")
+                .takes_value(true)
+                .possible_values(&["function", "module", "package", "callers"])
+                .default_value("function")
+                .help(
+                    "what is verified for each mutation: only the mutated function, all \
+                    functions of its module, all target modules, or the module of the function \
+                    and each target module calling it, directly or transitively. Kill rates are \
+                    only comparable between runs with the same scope",
+                ),
+        )
+        .arg(
+            Arg::with_name("watchdog-grace")
+                .long("watchdog-grace")
+                .takes_value(true)
+                .value_name("SECONDS")
+                .help(
+                    "how long verification of a mutation may take beyond the hard timeout \
+                    before the processes of the prover backend are killed and the mutation is \
+                    recorded with status `hung`. Defaults to 30",
+                ),
+        )
+        .arg(
+            Arg::with_name("with-dependents")
+                .long("with-dependents")
+                .help(
+                    "also verifies each mutation in each target module importing the module of \
+                    the mutated function, after the scope given by `--verify-scope`. Mutations \
+                    only killed in one of these modules are recorded with status \
+                    `caught_downstream`. Modules which fail to verify unmutated are left out",
+                ),
+        )
         .arg(
             Arg::with_name("sources")
                 .multiple(true)
                 .value_name("PATH_TO_SOURCE_FILE")
                 .min_values(1)
-                .help("the source files to verify"),
+                .help(
+                    "the source files to verify. A single `-` reads newline-separated paths \
+                    from stdin",
+                ),
         );
     let matches = cmd_line_parser.get_matches_from(args);
     let get_vec = |s: &str| -> Vec<String> {
@@ -87,219 +799,4290 @@ pub fn mutate(args: &[String]) {
         }
     };
     let addresses = get_vec("addresses");
-    let sources = get_vec("sources");
+    let sources = expand_sources(get_vec("sources"), std::io::stdin().lock())?;
     let deps = get_vec("dependencies");
     let configs: Vec<Option<String>> = if matches.is_present("config") {
         get_vec("config").into_iter().map(Some).collect_vec()
     } else {
         vec![None]
     };
-
-    for config_spec in configs {
-        let (config, out) = if let Some(config_file) = &config_spec {
+    let include_test_code = matches.is_present("include-test-code");
+    let timeout_secs = parse_positive("timeout-secs", matches.value_of("timeout-secs").unwrap())?;
+    let jobs = parse_positive("jobs", matches.value_of("jobs").unwrap())? as usize;
+    let mut operator_timeouts = BTreeMap::new();
+    for spec in get_vec("operator-timeouts") {
+        let (operator, secs) = parse_operator_timeout(&spec, &registry)?;
+        operator_timeouts.insert(operator, secs);
+    }
+    let seed = matches
+        .value_of("seed")
+        .map(|v| {
+            v.parse::<u64>().map_err(|_| {
+                MutationError::InvalidConfig(anyhow!("`--seed` must be a number, found `{}`", v))
+            })
+        })
+        .transpose()?;
+    let backend_opts = get_vec("backend-opts")
+        .iter()
+        .map(|spec| parse_backend_opt(spec))
+        .collect::<Result<Vec<_>, _>>()?;
+    let max_mutants_per_function = matches
+        .value_of("max-mutants-per-function")
+        .map(|v| parse_positive("max-mutants-per-function", v).map(|n| n as usize))
+        .transpose()?;
+    let max_log_bytes = matches
+        .value_of("max-log-bytes")
+        .map(|v| parse_positive("max-log-bytes", v).map(|n| n as usize))
+        .transpose()?;
+    let sample_size = match (matches.value_of("sample"), matches.value_of("max-mutants")) {
+        (Some(fraction), _) => Some(SampleSize::Fraction(parse_fraction("sample", fraction)?)),
+        (None, Some(count)) => Some(SampleSize::Count(
+            parse_positive("max-mutants", count)? as usize
+        )),
+        (None, None) => None,
+    };
+    // Both sample flags require the seed.
+    let sample = sample_size.map(|size| Sample {
+        size,
+        seed: seed.unwrap_or_default(),
+    });
+    let shard = match (matches.value_of("shard"), matches.value_of("num-shards")) {
+        (Some(index), Some(count)) => Some(parse_shard(index, count)?),
+        _ => None,
+    };
+    let repeat = parse_positive("repeat", matches.value_of("repeat").unwrap())? as usize;
+    let mem_limit_mb = matches
+        .value_of("mem-limit")
+        .map(|v| parse_positive("mem-limit", v))
+        .transpose()?;
+    let flaky_policy = matches
+        .value_of("flaky-policy")
+        .unwrap()
+        .parse::<FlakyPolicy>()
+        .map_err(MutationError::InvalidConfig)?;
+    let skip_slow_secs = matches
+        .value_of("skip-slow")
+        .map(|v| parse_positive("skip-slow", v))
+        .transpose()?;
+    let module_budget_secs = matches
+        .value_of("module-budget")
+        .map(|v| parse_positive("module-budget", v))
+        .transpose()?;
+    let watchdog_grace_secs = matches
+        .value_of("watchdog-grace")
+        .map(|v| parse_positive("watchdog-grace", v))
+        .transpose()?;
+    let operators = registry.resolve(&get_vec("operators")).map_err(|name| {
+        MutationError::InvalidConfig(anyhow!(
+            "unknown mutation operator `{}`. Available operators: {}",
+            name,
+            registry
+                .names()
+                .into_iter()
+                .chain(registry.groups().map(|(group, _)| group))
+                .join(", ")
+        ))
+    })?;
+    let parse_patterns = |flag: &str| {
+        get_vec(flag)
+            .iter()
+            .map(|p| {
+                ModulePattern::new(p).map_err(|e| {
+                    MutationError::InvalidConfig(anyhow!(
+                        "invalid pattern `{}` for `--{}`: {}",
+                        p,
+                        flag,
+                        e
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+    };
+    let mut exclude_modules = get_vec("excluded-modules")
+        .iter()
+        .map(|name| ModulePattern::exact(name))
+        .collect_vec();
+    exclude_modules.extend(parse_patterns("exclude-modules")?);
+    let rerun_survivors = matches
+        .value_of("rerun-survivors")
+        .map(|path| read_report(path).map(|report| surviving_records(&report)))
+        .transpose()?;
+    let mutation_options = MutationOptions {
+        registry,
+        modules: rerun_survivors
+            .iter()
+            .flatten()
+            .map(|r| r.module.clone())
+            .unique()
+            .collect(),
+        include_modules: parse_patterns("include-modules")?,
+        exclude_modules,
+        functions: get_vec("functions"),
+        operators,
+        operator_timeouts,
+        skip_slow_secs,
+        module_budget_secs,
+        watchdog_grace_secs,
+        max_mutants_per_function,
+        dump_boogie_dir: matches.value_of("dump-boogie").map(PathBuf::from),
+        artifacts_dir: matches.value_of("keep-artifacts").map(PathBuf::from),
+        keep_all_artifacts: matches.is_present("keep-all"),
+        sample,
+        shard,
+        mutant_ids: rerun_survivors.as_ref().map(|survivors| {
+            survivors
+                .iter()
+                .map(|r| r.mutant_id.clone())
+                .filter(|id| !id.is_empty())
+                .collect()
+        }),
+        repeat,
+        flaky_policy,
+        mem_limit_mb,
+        granularity: match matches.value_of("granularity") {
+            Some("function") => Granularity::Function,
+            _ => Granularity::Module,
+        },
+        verify_scope: match matches.value_of("verify-scope") {
+            Some("module") => VerifyScope::Module,
+            Some("package") => VerifyScope::Package,
+            Some("callers") => VerifyScope::Callers,
+            _ => VerifyScope::Function,
+        },
+        with_dependents: matches.is_present("with-dependents"),
+        quiet: matches.is_present("quiet"),
+        verbose: matches.is_present("verbose"),
+        max_log_bytes,
+        stub_backend: matches.is_present("stub-backend")
+            || std::env::var_os(STUB_BACKEND_ENV).is_some(),
+        check_vacuity: !matches.is_present("no-vacuity-check"),
+        timeout_retry: matches
+            .value_of("timeout-retry")
+            .map(parse_timeout_retry)
+            .transpose()?,
+        ..MutationOptions::default()
+    };
+    let cross_check = match matches.value_of("cross-check-config") {
+        Some(config) => CrossCheck::Config(config.to_string()),
+        None if matches.is_present("cross-check") => CrossCheck::OtherSolver,
+        None => CrossCheck::Disabled,
+    };
+    let format = match matches.value_of("output-format") {
+        Some("json") => OutputFormat::Json,
+        _ => OutputFormat::Text,
+    };
+    let ids_only =
+        matches.is_present("list-mutants") || matches.value_of("output-format") == Some("ids");
+    let dry_run = matches.is_present("dry-run") || matches.is_present("list-mutants");
+    if ids_only && !dry_run {
+        return Err(MutationError::InvalidConfig(anyhow!(
+            "`--output-format ids` requires `--dry-run`"
+        ))
+        .into());
+    }
+    let min_score = matches
+        .value_of("min-score")
+        .map(parse_min_score)
+        .transpose()?;
+    let required_versions = matches
+        .value_of("require-versions")
+        .map(parse_required_versions)
+        .transpose()?
+        .unwrap_or_default();
+    let settings = RunSettings {
+        addresses,
+        sources,
+        dependencies: deps,
+        include_test_code,
+        timeout_secs,
+        seed,
+        backend_opts,
+        jobs,
+        mutation_options,
+        cross_check,
+        format,
+        min_score,
+        fail_on_survivor: matches.is_present("fail-on-survivor"),
+        fail_on_vacuous: matches.is_present("fail-on-vacuous"),
+        show_diffs: matches.is_present("show-diffs"),
+        gh_annotations: matches.is_present("gh-annotations"),
+        repo_root: matches.value_of("repo-root").map(PathBuf::from),
+        html: matches.value_of("html").map(str::to_string),
+        sqlite: matches.value_of("sqlite").map(str::to_string),
+        dry_run,
+        ids_only,
+        mutate_specs: matches.is_present("mutate-specs"),
+        rerun_survivors,
+        required_versions,
+        changed_since: matches.value_of("changed-since").map(str::to_string),
+        callers: matches.is_present("callers"),
+        preflight: !matches.is_present("no-preflight"),
+    };
+
+    let out_path = matches.value_of("out");
+    let mut failure = None;
+    let multiple_configs = configs.len() > 1;
+    for config_spec in configs {
+        let (config, out) = if let Some(config_file) = &config_spec {
             let extension = "mod_data";
-            let out = PathBuf::from(config_file)
-                .with_extension(extension)
-                .to_string_lossy()
-                .to_string();
-            (config_spec, out)
+            let mut out = PathBuf::from(config_file).with_extension(extension);
+            if let (Some(dir), Some(file_name)) = (out_path, out.file_name()) {
+                out = PathBuf::from(dir).join(file_name);
+            }
+            (config_spec, out.to_string_lossy().to_string())
         } else {
-            (None, "mutation.data".to_string())
+            (None, out_path.unwrap_or("mutation.data").to_string())
         };
-        if let Err(s) = apply_mutation(config.as_ref(), &addresses, &sources, &deps) {
+        let html = settings.html.as_ref().map(|path| match &config {
+            Some(config_file) if multiple_configs => html_path(path, config_file),
+            _ => path.clone(),
+        });
+        if let Err(s) = apply_mutation(&out, html.as_deref(), config.as_ref(), &settings) {
             println!("ERROR: execution failed: {}", s);
-        } else {
+            failure.get_or_insert(s);
+        } else if !settings.ids_only {
             println!("results stored at `{}`", out);
         }
+        if is_interrupted() {
+            break;
+        }
+    }
+    match failure {
+        Some(error) => Err(error),
+        None => Ok(()),
     }
 }
 
 fn apply_mutation(
+    out: &str,
+    html: Option<&str>,
     config_file_opt: Option<&String>,
-    addresses: &[String],
-    modules: &[String],
-    dep_dirs: &[String],
+    settings: &RunSettings,
 ) -> anyhow::Result<()> {
-    println!("building model");
-    let env = run_model_builder_with_options(
-        modules,
-        dep_dirs,
-        ModelBuilderOptions::default(),
-        parse_addresses_from_options(addresses.to_owned())?,
-    )?;
-    let mut error_writer = StandardStream::stderr(ColorChoice::Auto);
+    let modules = &settings.sources;
+    let dep_dirs = &settings.dependencies;
+    let include_test_code = settings.include_test_code;
+    let quiet = settings.mutation_options.quiet;
+    if !quiet {
+        println!("building model");
+    }
+    let flags = if include_test_code {
+        Flags::testing()
+    } else {
+        Flags::empty()
+    };
+    let named_addresses = parse_addresses_from_options(settings.addresses.clone())
+        .map_err(MutationError::InvalidConfig)?;
+    let build_env = || {
+        run_model_builder_with_options_and_compilation_flags(
+            modules,
+            dep_dirs,
+            ModelBuilderOptions::default(),
+            flags.clone(),
+            named_addresses.clone(),
+        )
+        .map_err(MutationError::ModelBuildFailed)
+    };
+    let env = build_env()?;
+
+    let mut mutation_options = settings.mutation_options.clone();
+    if let Some(rev) = &settings.changed_since {
+        let changes = changed_lines(rev, modules).map_err(MutationError::InvalidConfig)?;
+        let functions = changed_functions(&env, &changes, settings.callers, |fun| {
+            mutation_options.is_module_selected(&fun.module_env)
+                && mutation_options.is_function_selected(fun)
+        });
+        if functions.is_empty() {
+            println!(
+                "No target function changed since `{}`, nothing to mutate.",
+                rev
+            );
+            let mut report = MutationReport {
+                config: config_file_opt.map_or_else(|| "default".to_string(), String::clone),
+                time: chrono::Utc::now().to_string(),
+                git_rev: git_revision(modules),
+                test_code: include_test_code,
+                changed_since: Some(rev.clone()),
+                nothing_to_mutate: true,
+                ..MutationReport::default()
+            };
+            report.conclude();
+            write_report(out, &report, settings.format)?;
+            return Ok(());
+        }
+        if !quiet {
+            println!(
+                "Mutating the {} functions changed since `{}`.",
+                functions.len(),
+                rev
+            );
+        }
+        mutation_options.functions = functions.into_iter().collect();
+    }
+
+    if settings.dry_run {
+        let mut error_writer = StandardStream::stderr(ColorChoice::Auto);
+        check_errors(
+            &env,
+            &Options::default(),
+            &mut error_writer,
+            "unexpected build errors",
+        )
+        .map_err(MutationError::ModelBuildFailed)?;
+        check_functions(&env, &mutation_options)?;
+        warn_unmatched_module_patterns(&env, &mutation_options);
+        if settings.ids_only {
+            for mutant in list_mutants(&env, &mutation_options) {
+                println!("{}", mutant.id());
+            }
+        } else {
+            let mutants = list_mutants(&env, &mutation_options);
+            print_mutants(&mutants, &mutation_options.registry);
+            print_mutation_counts(&count_mutations(&env, &mutation_options));
+            write_mutants(out, &mutants, &mutation_options.registry, settings.format)?;
+        }
+        return Ok(());
+    }
+
+    // If test code is included, build the model a second time without it, so we can tell which
+    // functions only exist in test mode.
+    let production_funs = if include_test_code {
+        let production_env = run_model_builder_with_options(
+            modules,
+            dep_dirs,
+            ModelBuilderOptions::default(),
+            named_addresses.clone(),
+        )
+        .map_err(MutationError::ModelBuildFailed)?;
+        Some(
+            production_env
+                .get_modules()
+                .flat_map(|m| m.into_functions().map(|f| f.get_full_name_str()))
+                .collect::<BTreeSet<_>>(),
+        )
+    } else {
+        None
+    };
     let mut options = if let Some(config_file) = config_file_opt {
-        Options::create_from_toml_file(config_file)?
+        Options::create_from_toml_file(config_file).map_err(MutationError::InvalidConfig)?
     } else {
         Options::default()
     };
+    for (key, value) in &settings.backend_opts {
+        apply_backend_opt(&mut options.backend, key, value)?;
+    }
 
-    // Do not allow any mutation to run longer than 100 seconds to avoid extremely long use times
-    options.backend.hard_timeout_secs = 100;
+    // Do not allow any mutation to run longer than the given timeout to avoid extremely long
+    // use times
+    options.backend.hard_timeout_secs = settings.timeout_secs;
 
-    options.verbosity_level = LevelFilter::Error;
+    if let Some(seed) = settings.seed {
+        options.backend.random_seed = seed as usize;
+    }
+    if !quiet {
+        println!("Using seed {}.", options.backend.random_seed);
+    }
 
-    options.prover.mutation = true;
-    options.backend.derive_options();
+    options.verbosity_level = if settings.mutation_options.verbose {
+        LevelFilter::Info
+    } else {
+        LevelFilter::Error
+    };
     options.setup_logging();
-    check_errors(&env, &options, &mut error_writer, "unexpected build errors")?;
 
-    let config_descr = "default".to_string();
+    if settings.preflight && !settings.mutation_options.stub_backend {
+        if !quiet {
+            println!("Checking the toolchain.");
+        }
+        preflight(&env, &options)?;
+    }
+    let tool_versions = tool_versions(&options.backend);
+    check_required_versions(&tool_versions, &settings.required_versions)?;
 
-    println!("Starting mutations with config `{}`.", config_descr);
+    mutation_options.cross_check = match &settings.cross_check {
+        CrossCheck::Disabled => None,
+        CrossCheck::OtherSolver => {
+            let mut backend = options.backend.clone();
+            backend.use_cvc4 = !backend.use_cvc4;
+            Some(backend)
+        }
+        CrossCheck::Config(config_file) => Some(
+            Options::create_from_toml_file(config_file)
+                .map_err(MutationError::InvalidConfig)?
+                .backend,
+        ),
+    };
 
-    let mut i = 0;
-    let mut mutation_applied = true;
+    let config_descr = if let Some(config) = config_file_opt {
+        config.clone()
+    } else {
+        "default".to_string()
+    };
+    if !quiet {
+        println!("Starting mutations with config `{}`.", config_descr);
+    }
 
-    let mut runner = Runner {
-        options,
-        error_writer,
+    // The records are written to the output file as they are produced, so they are not lost if
+    // the process dies. The file is replaced by the concluded report once the run ends.
+    let git_rev = git_revision(modules);
+    let header = MutationReport {
+        config: config_descr.clone(),
+        time: chrono::Utc::now().to_string(),
+        git_rev: git_rev.clone(),
+        changed_since: settings.changed_since.clone(),
+        tool_versions: tool_versions.clone(),
+        seed: options.backend.random_seed as u64,
+        test_code: include_test_code,
+        flaky_policy: mutation_options.flaky_policy,
+        ..MutationReport::default()
     };
+    mutation_options.record_stream = Some(RecordStream::create(out, &header)?);
 
-    while mutation_applied {
-        i += 1;
-        println!("Applying add-sub mutation {}", i);
-        runner.options.prover.mutation_add_sub = i;
-        env.set_extension(MutationManager {
-            mutated: false,
-            add_sub: i,
-            sub_add: 0,
-            mul_div: 0,
-            div_mul: 0,
-        });
-        mutation_applied = runner.mutate(&env)?;
-        if !mutation_applied {
-            println!("No mutations applied");
+    let mut report = if settings.mutate_specs {
+        let build_mutated_env = |file: &str, mutated_file: &str| {
+            let mut sources = find_move_filenames(&modules[..], true)?;
+            let source = sources
+                .iter_mut()
+                .find(|s| s.as_str() == file)
+                .ok_or_else(|| anyhow!("source file `{}` not found among the sources", file))?;
+            *source = mutated_file.to_string();
+            run_model_builder_with_options_and_compilation_flags(
+                &sources,
+                dep_dirs,
+                ModelBuilderOptions::default(),
+                flags.clone(),
+                named_addresses.clone(),
+            )
+        };
+        run_spec_mutation(&env, build_mutated_env, &options, &mutation_options)?
+    } else if settings.jobs > 1 {
+        run_mutation_parallel(
+            &env,
+            || build_env().map_err(Into::into),
+            &options,
+            &mutation_options,
+            settings.jobs,
+        )?
+    } else {
+        run_mutation(&env, &options, &mutation_options)?
+    };
+    if let Some(survivors) = &settings.rerun_survivors {
+        if !is_interrupted() {
+            report
+                .records
+                .extend(stale_records(&env, &mutation_options, survivors));
+            report.summarize();
         }
     }
-    i = 0;
-    mutation_applied = true;
-    while mutation_applied {
-        i += 1;
-        println!("Applying sub-add mutation {}", i);
-        runner.options.prover.mutation_sub_add = i;
-        env.set_extension(MutationManager {
-            mutated: false,
-            add_sub: 0,
-            sub_add: i,
-            mul_div: 0,
-            div_mul: 0,
-        });
-        mutation_applied = runner.mutate(&env)?;
-        if !mutation_applied {
-            println!("No mutations applied");
+    report.config = config_descr;
+    report.git_rev = git_rev;
+    report.changed_since = settings.changed_since.clone();
+    report.tool_versions = tool_versions;
+    report.test_code = include_test_code;
+    if let Some(production_funs) = &production_funs {
+        for record in &mut report.records {
+            record.test_code = !production_funs.contains(&record.function);
         }
     }
-    i = 0;
-    mutation_applied = true;
-    while mutation_applied {
-        i += 1;
-        println!("Applying mul-div mutation {}", i);
-        runner.options.prover.mutation_mul_div = i;
-        env.set_extension(MutationManager {
-            mutated: false,
-            add_sub: 0,
-            sub_add: 0,
-            mul_div: i,
-            div_mul: 0,
-        });
-        mutation_applied = runner.mutate(&env)?;
-        if !mutation_applied {
-            println!("No mutations applied");
-        }
+    print_summary_table(&report, &mutation_options.registry);
+    print_operator_table(&report);
+    print_redundant_conditions(&report);
+    print_slowest_functions(&report);
+    if let Some(survivors) = &settings.rerun_survivors {
+        print_rerun_delta(survivors, &report);
     }
-    i = 0;
-    mutation_applied = true;
-    while mutation_applied {
-        i += 1;
-        println!("Applying div-mul mutation {}", i);
-        runner.options.prover.mutation_div_mul = i;
-        env.set_extension(MutationManager {
-            mutated: false,
-            add_sub: 0,
-            sub_add: 0,
-            mul_div: 0,
-            div_mul: i,
-        });
-        mutation_applied = runner.mutate(&env)?;
-        if !mutation_applied {
-            println!("No mutations applied");
+    if settings.show_diffs {
+        print_survivor_diffs(&report);
+    }
+    if settings.gh_annotations {
+        let repo_root = match &settings.repo_root {
+            Some(dir) => dir.clone(),
+            None => std::env::current_dir()?,
+        };
+        print_gh_annotations(&env, &report, &mutation_options.registry, &repo_root);
+    }
+    println!("Summary: {}", report.summary);
+
+    report.interrupted = is_interrupted();
+    report.conclude();
+    write_report(out, &report, settings.format)?;
+    if let Some(html) = html {
+        create_output(html)?.write_all(render_html(&report).as_bytes())?;
+        println!("HTML report stored at `{}`", html);
+    }
+    if let Some(path) = &settings.sqlite {
+        let campaign_id = ResultDatabase::open(path)?.insert_campaign(&report)?;
+        println!("results stored as campaign {} in `{}`", campaign_id, path);
+    }
+    if report.interrupted {
+        println!("partial results stored at `{}`", out);
+        return Err(MutationError::Interrupted.into());
+    }
+
+    let baseline_failed = report
+        .records
+        .iter()
+        .filter(|r| r.status == MutationStatus::BaselineFailed)
+        .map(|r| r.module.as_str())
+        .collect_vec();
+    if !baseline_failed.is_empty() {
+        return Err(MutationError::BaselineFailed(baseline_failed.join(", ")).into());
+    }
+    if settings.fail_on_vacuous && !report.vacuous_specs.is_empty() {
+        return Err(MutationError::VacuousSpecs(report.vacuous_specs.join(", ")).into());
+    }
+    if let Some(min_score) = settings.min_score {
+        let score = report.summary.kill_rate / 100.0;
+        if score < min_score {
+            return Err(MutationError::ScoreBelowThreshold(score, min_score).into());
         }
     }
+    let survived = report.summary.survived(report.flaky_policy);
+    if settings.fail_on_survivor && survived > 0 {
+        return Err(MutationError::MutationsSurvived(survived).into());
+    }
     Ok(())
 }
 
-impl Runner {
-    fn mutate(&mut self, env: &GlobalEnv) -> anyhow::Result<bool> {
-        let mut mutated = false;
-        for module in env.get_modules() {
-            if module.is_target() {
-                for fun in module.get_functions() {
-                    mutated = self.mutate_function(fun)?;
-                    if mutated {
-                        break;
-                    }
-                }
+/// Prints the number of killed, surviving, and timed out mutations per module and in total,
+/// followed by the surviving mutations, which indicate where specs should be strengthened, and
+/// the flaky ones.
+fn print_summary_table(report: &MutationReport, registry: &OperatorRegistry) {
+    let row = |name: &str, summary: &MutationSummary| {
+        println!(
+            "{:<40} {:>8} {:>8} {:>8} {:>8} {:>10}",
+            name,
+            summary.errors + summary.caught_downstream,
+            summary.ok,
+            summary.timeout,
+            format!("{:.2}%", summary.kill_rate),
+            summary.peak_mem_mb
+        )
+    };
+    println!(
+        "{:<40} {:>8} {:>8} {:>8} {:>8} {:>10}",
+        "module", "caught", "survived", "timeout", "score", "peak MiB"
+    );
+    for (module, summary) in MutationSummary::per_module(&report.records, report.flaky_policy) {
+        row(&module, &summary);
+    }
+    row("total", &report.summary);
+
+    let print_records = |title: &str, status: MutationStatus| {
+        let records = report
+            .records
+            .iter()
+            .filter(|r| r.status == status)
+            .collect_vec();
+        if records.is_empty() {
+            return;
+        }
+        println!("{}:", title);
+        for record in records {
+            let runs = if record.runs.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", record.runs.iter().map(|run| run.status).join(", "))
+            };
+            match &record.site {
+                Some(site) => println!(
+                    "  {} {} at {}: {}{}",
+                    record.module,
+                    record.function,
+                    site.location(),
+                    mutation_description(registry, &record.operator, Some(site)),
+                    runs
+                ),
+                None => println!(
+                    "  {} {}: {}{}",
+                    record.module,
+                    record.function,
+                    registry.description(&record.operator),
+                    runs
+                ),
             }
         }
-        Ok(mutated)
+    };
+    print_records("Surviving mutations", MutationStatus::Ok);
+    print_records("Flaky mutations", MutationStatus::Flaky);
+    print_records(
+        "Mutations caught downstream",
+        MutationStatus::CaughtDownstream,
+    );
+
+    let mut over_budget: BTreeMap<&str, usize> = BTreeMap::new();
+    for record in report
+        .records
+        .iter()
+        .filter(|r| r.status == MutationStatus::SkippedBudget)
+    {
+        *over_budget.entry(record.module.as_str()).or_default() += 1;
+    }
+    if !over_budget.is_empty() {
+        println!("Modules over budget:");
+        for (module, skipped) in over_budget {
+            println!("  {}: {} mutants skipped", module, skipped);
+        }
     }
+    if !report.vacuous_specs.is_empty() {
+        println!("Functions with vacuous specs:");
+        for function in &report.vacuous_specs {
+            println!("  {}", function);
+        }
+    }
+}
 
-    fn mutate_function(&mut self, fun: FunctionEnv<'_>) -> anyhow::Result<bool> {
-        // Scope verification to the given function
-        let env = fun.module_env.env;
-        self.options.prover.verify_scope = VerificationScope::Only(fun.get_full_name_str());
-        ProverOptions::set(env, self.options.prover.clone());
-        let (duration, status) = self.run_mutated_function(fun.module_env.env)?;
+/// Prints a table of the results of each operator, and in how many modules only the mutations
+/// of the operator survive, i.e. how many modules would not show holes in their specs without
+/// it.
+fn print_operator_table(report: &MutationReport) {
+    if report.operators.is_empty() {
+        return;
+    }
+    println!(
+        "{:<20} {:>8} {:>8} {:>8} {:>8} {:>12}",
+        "operator", "caught", "survived", "timeout", "score", "sole modules"
+    );
+    for (operator, operator_summary) in &report.operators {
+        let summary = &operator_summary.summary;
+        println!(
+            "{:<20} {:>8} {:>8} {:>8} {:>8} {:>12}",
+            operator,
+            summary.errors + summary.caught_downstream,
+            summary.survived(report.flaky_policy),
+            summary.timeout,
+            format!("{:.2}%", summary.kill_rate),
+            operator_summary.sole_survivor_modules
+        );
+    }
+}
 
-        let mutated = env
-            .get_extension::<MutationManager>()
-            .map(|e| e.mutated)
-            .unwrap_or(false);
-        if mutated {
-            print!("mutated function {} ..", fun.get_full_name_str());
-            std::io::stdout().flush()?;
-            println!("\x08\x08{:.3}s {}.", duration.as_secs_f64(), status);
+/// Returns the records of the mutations which survived in the given report.
+fn surviving_records(report: &MutationReport) -> Vec<MutationRecord> {
+    report
+        .records
+        .iter()
+        .filter(|r| !r.function.is_empty() && r.status == MutationStatus::Ok)
+        .cloned()
+        .collect()
+}
+
+/// Returns records with status `stale` for the surviving mutations of a previous run which are
+/// not among the mutants of the current sources anymore, or have no id to reconstruct them by.
+fn stale_records(
+    env: &GlobalEnv,
+    mutation_options: &MutationOptions,
+    survivors: &[MutationRecord],
+) -> Vec<MutationRecord> {
+    let ids = list_mutants(env, mutation_options)
+        .iter()
+        .map(Mutant::id)
+        .collect::<BTreeSet<_>>();
+    survivors
+        .iter()
+        .filter(|r| !ids.contains(&r.mutant_id))
+        .map(|r| MutationRecord {
+            function: r.function.clone(),
+            mutant_id: r.mutant_id.clone(),
+            operator: r.operator.clone(),
+            site: r.site.clone(),
+            test_code: r.test_code,
+            ..MutationRecord::new(r.module.clone(), r.timeout_secs, 0, MutationStatus::Stale)
+        })
+        .collect()
+}
+
+/// Prints which of the surviving mutations of a previous run are now caught, which still
+/// survive, and which are stale.
+fn print_rerun_delta(survivors: &[MutationRecord], report: &MutationReport) {
+    let ids = survivors
+        .iter()
+        .map(|r| r.mutant_id.as_str())
+        .collect::<BTreeSet<_>>();
+    let rerun = report
+        .records
+        .iter()
+        .filter(|r| !r.function.is_empty() && ids.contains(r.mutant_id.as_str()))
+        .collect_vec();
+    let (still_surviving, rest): (Vec<_>, Vec<_>) = rerun
+        .into_iter()
+        .partition(|r| r.status == MutationStatus::Ok || r.status == MutationStatus::Flaky);
+    let (stale, now_caught): (Vec<_>, Vec<_>) = rest
+        .into_iter()
+        .partition(|r| r.status == MutationStatus::Stale);
+    println!(
+        "Rerun of {} surviving mutations: {} now caught, {} still surviving, {} stale",
+        survivors.len(),
+        now_caught.len(),
+        still_surviving.len(),
+        stale.len()
+    );
+    for (title, records) in [
+        ("Now caught", now_caught),
+        ("Still surviving", still_surviving),
+        ("Stale", stale),
+    ] {
+        if records.is_empty() {
+            continue;
+        }
+        println!("{}:", title);
+        for record in records {
+            println!("  {:<60} {}", record.mutant_id, record.status);
         }
-        Ok(mutated)
     }
+}
 
-    fn run_mutated_function(&mut self, env: &GlobalEnv) -> anyhow::Result<(Duration, String)> {
-        // Create and process bytecode.
-        let targets = create_and_process_bytecode(&self.options, env);
+/// The number of functions listed by `print_slowest_functions`.
+const SLOWEST_FUNCTIONS: usize = 10;
 
-        check_errors(
-            env,
-            &self.options,
-            &mut self.error_writer,
-            "unexpected transformation errors",
-        )?;
+/// Prints the functions whose unmutated code took longest to verify, if timed by function.
+fn print_slowest_functions(report: &MutationReport) {
+    let slowest = report
+        .timings
+        .iter()
+        .filter(|t| !t.function.is_empty())
+        .sorted_by_key(|t| std::cmp::Reverse(t.duration_ms))
+        .take(SLOWEST_FUNCTIONS)
+        .collect_vec();
+    if slowest.is_empty() {
+        return;
+    }
+    println!("Slowest functions:");
+    for timing in slowest {
+        println!(
+            "  {:<60} {:>10.3}s {}",
+            timing.function,
+            timing.duration_ms as f64 / 1000.0,
+            timing.status
+        );
+    }
+}
 
-        // Generate boogie code.
-        let code_writer = generate_boogie(env, &self.options, &targets)?;
-        check_errors(
-            env,
-            &self.options,
-            &mut self.error_writer,
-            "unexpected boogie generation errors",
-        )?;
+/// Prints the spec conditions which turned out to be redundant when mutating specs.
+fn print_redundant_conditions(report: &MutationReport) {
+    if report.spec_records.is_empty() {
+        return;
+    }
+    let redundant = report
+        .spec_records
+        .iter()
+        .filter(|r| r.status == "redundant")
+        .collect_vec();
+    println!(
+        "{} of {} spec conditions are redundant",
+        redundant.len(),
+        report.spec_records.len()
+    );
+    for record in redundant {
+        println!(
+            "  {} {}: {}",
+            record.function,
+            record.site.location(),
+            record.site.before
+        );
+    }
+}
 
-        // Verify boogie, measuring duration.
-        let now = Instant::now();
-        verify_boogie(env, &self.options, &targets, code_writer)?;
+/// Prints one line per mutant with its id, location, and description, as determined by a dry
+/// run.
+fn print_mutants(mutants: &[Mutant], registry: &OperatorRegistry) {
+    for mutant in mutants {
+        println!("{}", mutant_line(mutant, registry));
+    }
+}
 
-        // Determine result status.
-        let status = if env.error_count() > 0 {
-            if env.has_diag("timeout") {
-                "timeout"
-            } else {
-                "errors"
+/// Formats a mutant as a line of the id, the location, and a description of the mutation.
+fn mutant_line(mutant: &Mutant, registry: &OperatorRegistry) -> String {
+    let description = mutation_description(registry, &mutant.operator, mutant.site.as_ref());
+    match &mutant.site {
+        Some(site) if !site.after.is_empty() => format!(
+            "{:<50} {:<40} {}: `{}` -> `{}`",
+            mutant.id(),
+            site.location(),
+            description,
+            site.before,
+            site.after
+        ),
+        Some(site) => format!(
+            "{:<50} {:<40} {}",
+            mutant.id(),
+            site.location(),
+            description
+        ),
+        None => format!("{:<50} {:<40} {}", mutant.id(), "-", description),
+    }
+}
+
+/// Prints the number of mutations per module and operator, as determined by a dry run.
+fn print_mutation_counts(counts: &BTreeMap<String, BTreeMap<String, usize>>) {
+    let operators = counts
+        .values()
+        .next()
+        .map(|c| c.keys().cloned().collect_vec())
+        .unwrap_or_default();
+    println!(
+        "{:<40} {} {:>8}",
+        "module",
+        operators.iter().map(|o| format!("{:>8}", o)).join(" "),
+        "total"
+    );
+    let mut totals: BTreeMap<&str, usize> = BTreeMap::new();
+    for (module, module_counts) in counts {
+        println!(
+            "{:<40} {} {:>8}",
+            module,
+            module_counts
+                .values()
+                .map(|n| format!("{:>8}", n))
+                .join(" "),
+            module_counts.values().sum::<usize>()
+        );
+        for (operator, n) in module_counts {
+            *totals.entry(operator.as_str()).or_default() += n;
+        }
+    }
+    println!(
+        "{:<40} {} {:>8}",
+        "total",
+        totals.values().map(|n| format!("{:>8}", n)).join(" "),
+        totals.values().sum::<usize>()
+    );
+}
+
+/// Prints a unified diff of the mutated expression for each surviving mutation whose site is
+/// known.
+fn print_survivor_diffs(report: &MutationReport) {
+    for record in report
+        .records
+        .iter()
+        .filter(|r| r.status == MutationStatus::Ok)
+    {
+        if let Some(site) = record.site.as_ref().filter(|s| !s.after.is_empty()) {
+            println!("--- a/{}", site.file);
+            println!("+++ b/{}", site.file);
+            println!(
+                "@@ -{},{} +{},{} @@ {} ({})",
+                site.line,
+                site.before.lines().count(),
+                site.line,
+                site.after.lines().count(),
+                record.function,
+                record.operator
+            );
+            for line in site.before.lines() {
+                println!("-{}", line);
+            }
+            for line in site.after.lines() {
+                println!("+{}", line);
             }
+        }
+    }
+}
+
+/// Prints a GitHub annotation for each surviving mutation. A mutation whose site is unknown is
+/// annotated at the file of its module instead, or without a file if that is unknown as well.
+fn print_gh_annotations(
+    env: &GlobalEnv,
+    report: &MutationReport,
+    registry: &OperatorRegistry,
+    repo_root: &Path,
+) {
+    let survivors = report.records.iter().filter(|r| {
+        !r.function.is_empty()
+            && (r.status == MutationStatus::Ok
+                || (r.status == MutationStatus::Flaky
+                    && report.flaky_policy == FlakyPolicy::Survived))
+    });
+    for record in survivors {
+        let name = if record.mutant_id.is_empty() {
+            format!("{} {}", record.function, record.operator)
         } else {
-            "ok"
+            record.mutant_id.clone()
         };
-        env.clear_diag();
+        let message = format!(
+            "mutation {} survived: {}",
+            name,
+            mutation_description(registry, &record.operator, record.site.as_ref())
+        );
+        let (file, position) = match &record.site {
+            Some(site) => (Some(site.file.clone()), Some((site.line, site.column))),
+            None => {
+                let file = env
+                    .get_modules()
+                    .find(|m| m.get_full_name_str() == record.module)
+                    .and_then(|m| env.get_file_and_location(&m.get_loc()))
+                    .map(|(file, _)| file);
+                (file, None)
+            }
+        };
+        let file = file.map(|f| relative_to(&f, repo_root));
+        println!("{}", gh_annotation(file.as_deref(), position, &message));
+    }
+}
+
+/// Formats a GitHub warning annotation with the message, located at the file, and at the line
+/// and column in it if given.
+fn gh_annotation(file: Option<&str>, position: Option<(usize, usize)>, message: &str) -> String {
+    // Values and the message of workflow commands are percent-encoded where they would end
+    // them.
+    let escape = |text: &str, property: bool| {
+        let mut escaped = text
+            .replace('%', "%25")
+            .replace('\r', "%0D")
+            .replace('\n', "%0A");
+        if property {
+            escaped = escaped.replace(':', "%3A").replace(',', "%2C");
+        }
+        escaped
+    };
+    let mut properties = vec![];
+    if let Some(file) = file {
+        properties.push(format!("file={}", escape(file, true)));
+        if let Some((line, column)) = position {
+            properties.push(format!("line={}", line));
+            properties.push(format!("col={}", column));
+        }
+    }
+    format!(
+        "::warning{}{}::{}",
+        if properties.is_empty() { "" } else { " " },
+        properties.join(","),
+        escape(message, false)
+    )
+}
+
+/// Returns the path of the file relative to the directory, or the path as given if the file is
+/// not below it.
+fn relative_to(file: &str, dir: &Path) -> String {
+    let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    canonical(Path::new(file))
+        .strip_prefix(canonical(dir))
+        .map_or_else(|_| file.to_string(), |p| p.display().to_string())
+}
+
+/// Determines the git revision of the source tree containing the first of the given sources,
+/// if it is part of a git repository.
+fn git_revision(sources: &[String]) -> Option<String> {
+    let source = PathBuf::from(sources.first()?);
+    let dir = if source.is_dir() {
+        source
+    } else {
+        source.parent()?.to_path_buf()
+    };
+    let output = Command::new("git")
+        .arg("rev-parse")
+        .arg("HEAD")
+        .current_dir(if dir.as_os_str().is_empty() {
+            PathBuf::from(".")
+        } else {
+            dir
+        })
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let rev = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if rev.is_empty() {
+        None
+    } else {
+        Some(rev)
+    }
+}
+
+/// Installs a mutation which asserts `false` before each return of the verified variants of the
+/// target function, which is equivalent to adding `ensures false` to its spec.
+fn install_ensures_false(env: &GlobalEnv, target: QualifiedId<FunId>) {
+    install_mutation(env, move |fun, data| {
+        if fun.get_qualified_id() != target || !data.variant.is_verified() {
+            return data;
+        }
+        let mut builder = FunctionDataBuilder::new(fun, data);
+        let code = std::mem::take(&mut builder.data.code);
+        for bc in code {
+            if matches!(bc, Bytecode::Ret(..)) {
+                builder.set_loc_and_vc_info(fun.get_spec_loc(), "ensures false does not hold");
+                let exp = builder.mk_bool_const(false);
+                builder.emit_with(|id| Bytecode::Prop(id, PropKind::Assert, exp));
+            }
+            builder.emit(bc);
+        }
+        builder.data
+    });
+}
+
+/// Returns the status the stub backend verifies the current program with. A mutation is killed
+/// unless the source line it has been applied to contains `STUB_SURVIVES_MARKER`, and unmutated
+/// code verifies. Likewise, a dropped spec condition is needed unless its line has the marker.
+/// A custom mutation which does not mark the code as mutated is the `ensures false` of the
+/// vacuity check, which fails, so specs are never vacuous.
+fn stub_status(env: &GlobalEnv) -> MutationStatus {
+    let mutated = env
+        .get_extension::<MutationManager>()
+        .map_or(false, |m| m.mutated);
+    if !mutated {
+        return if env.get_extension::<CustomMutation>().is_some() {
+            MutationStatus::Errors
+        } else {
+            MutationStatus::Ok
+        };
+    }
+    let survives = env
+        .get_extension::<MutatedLocation>()
+        .and_then(|mutated| env.get_file_and_location(&mutated.loc))
+        .and_then(|(file, pos)| {
+            let content = std::fs::read_to_string(file).ok()?;
+            let line = content.lines().nth(pos.line.0 as usize)?;
+            Some(line.contains(STUB_SURVIVES_MARKER))
+        })
+        .unwrap_or(false);
+    if survives {
+        MutationStatus::Ok
+    } else {
+        MutationStatus::Errors
+    }
+}
+
+/// Truncates the log to at most the given number of bytes, noting how much has been dropped.
+fn truncate_log(mut log: String, max_bytes: usize) -> String {
+    if log.len() <= max_bytes {
+        return log;
+    }
+    let mut end = max_bytes;
+    while !log.is_char_boundary(end) {
+        end -= 1;
+    }
+    let dropped = log.len() - end;
+    log.truncate(end);
+    log.push_str(&format!("\n[{} more bytes truncated]\n", dropped));
+    log
+}
 
-        Ok((now.elapsed(), status.to_string()))
+/// Returns the names of the target modules importing the module, whose verification may rely
+/// on its specs.
+fn dependent_modules(module: &ModuleEnv<'_>) -> Vec<String> {
+    let env = module.env;
+    module
+        .get_using_modules(true)
+        .into_iter()
+        .map(|id| env.get_module(id))
+        .filter(|m| m.is_target() && m.get_id() != module.get_id())
+        .map(|m| m.get_full_name_str())
+        .collect()
+}
+
+/// Returns true if the location `inner` lies within the location `outer`.
+fn encloses(outer: &Loc, inner: &Loc) -> bool {
+    outer.file_id() == inner.file_id()
+        && outer.span().start() <= inner.span().start()
+        && inner.span().end() <= outer.span().end()
+}
+
+/// Replaces a `-` among the given sources by the newline-separated paths read from the input,
+/// keeping their order and skipping blank lines.
+fn expand_sources(sources: Vec<String>, input: impl BufRead) -> Result<Vec<String>, MutationError> {
+    if !sources.iter().any(|s| s == "-") {
+        return Ok(sources);
+    }
+    let mut stdin_sources = vec![];
+    for line in input.lines() {
+        let line = line.map_err(|e| MutationError::InvalidConfig(e.into()))?;
+        let path = line.trim();
+        if !path.is_empty() {
+            stdin_sources.push(path.to_string());
+        }
+    }
+    let mut expanded = vec![];
+    for source in sources {
+        if source == "-" {
+            expanded.append(&mut stdin_sources);
+        } else {
+            expanded.push(source);
+        }
+    }
+    Ok(expanded)
+}
+
+/// Parses the value of the `--min-score` flag, a fraction between 0 and 1.
+fn parse_min_score(value: &str) -> Result<f64, MutationError> {
+    match value.parse::<f64>() {
+        Ok(score) if (0.0..=1.0).contains(&score) => Ok(score),
+        _ => Err(MutationError::InvalidConfig(anyhow!(
+            "`--min-score` must be a number between 0 and 1, found `{}`",
+            value
+        ))),
+    }
+}
+
+/// Parses the value of the given flag, rejecting anything but a number greater than 0 and at most
+/// 1.
+fn parse_fraction(flag: &str, value: &str) -> Result<f64, MutationError> {
+    match value.parse::<f64>() {
+        Ok(fraction) if fraction > 0.0 && fraction <= 1.0 => Ok(fraction),
+        _ => Err(MutationError::InvalidConfig(anyhow!(
+            "`--{}` must be a number greater than 0 and at most 1, found `{}`",
+            flag,
+            value
+        ))),
+    }
+}
+
+/// Parses the value of the given numeric flag, rejecting anything but a positive number.
+fn parse_positive(flag: &str, value: &str) -> Result<u64, MutationError> {
+    match value.parse::<u64>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(MutationError::InvalidConfig(anyhow!(
+            "`--{}` must be a positive number, found `{}`",
+            flag,
+            value
+        ))),
+    }
+}
+
+/// Parses the values of the `--shard` and `--num-shards` flags.
+fn parse_shard(index: &str, count: &str) -> Result<Shard, MutationError> {
+    let count = parse_positive("num-shards", count)? as usize;
+    match index.parse::<usize>() {
+        Ok(index) if index < count => Ok(Shard { index, count }),
+        _ => Err(MutationError::InvalidConfig(anyhow!(
+            "`--shard` must be a number below the number of shards {}, found `{}`",
+            count,
+            index
+        ))),
+    }
+}
+
+/// Parses a value of the `--operator-timeout` flag of the form `OPERATOR=SECONDS`.
+fn parse_operator_timeout(
+    spec: &str,
+    registry: &OperatorRegistry,
+) -> Result<(String, u64), MutationError> {
+    let (operator, secs) = spec.split_once('=').ok_or_else(|| {
+        MutationError::InvalidConfig(anyhow!(
+            "`--operator-timeout` expects `OPERATOR=SECONDS`, found `{}`",
+            spec
+        ))
+    })?;
+    if registry.get(operator).is_none() {
+        return Err(MutationError::InvalidConfig(anyhow!(
+            "unknown mutation operator `{}`. Available operators: {}",
+            operator,
+            registry.names().join(", ")
+        )));
+    }
+    Ok((
+        operator.to_string(),
+        parse_positive("operator-timeout", secs)?,
+    ))
+}
+
+/// Parses a value of the `--timeout-retry` flag of the form `FACTOR[,MAX]`.
+fn parse_timeout_retry(spec: &str) -> Result<TimeoutEscalation, MutationError> {
+    let (factor, max) = match spec.split_once(',') {
+        Some((factor, max)) => (factor, Some(max)),
+        None => (spec, None),
+    };
+    let factor = match factor.parse::<f64>() {
+        Ok(factor) if factor > 1.0 => factor,
+        _ => {
+            return Err(MutationError::InvalidConfig(anyhow!(
+                "`--timeout-retry` expects a factor greater than 1, found `{}`",
+                factor
+            )))
+        }
+    };
+    Ok(TimeoutEscalation {
+        factor,
+        max_secs: max
+            .map(|max| parse_positive("timeout-retry", max))
+            .transpose()?,
+    })
+}
+
+/// Parses a value of the `--require-versions` flag of the form `TOOL=VERSION,..`.
+fn parse_required_versions(spec: &str) -> Result<BTreeMap<String, String>, MutationError> {
+    spec.split(',')
+        .map(|version| match version.split_once('=') {
+            Some((tool, version)) if TOOLS.contains(&tool) && !version.is_empty() => {
+                Ok((tool.to_string(), version.to_string()))
+            }
+            Some((tool, _)) if !TOOLS.contains(&tool) => {
+                Err(MutationError::InvalidConfig(anyhow!(
+                    "unknown tool `{}`. Available tools: {}",
+                    tool,
+                    TOOLS.join(", ")
+                )))
+            }
+            _ => Err(MutationError::InvalidConfig(anyhow!(
+                "`--require-versions` expects `TOOL=VERSION,..`, found `{}`",
+                spec
+            ))),
+        })
+        .collect()
+}
+
+/// The keys of the backend options which can be set by `--backend-opt`.
+const BACKEND_OPTS: &[&str] = &[
+    "random_seed",
+    "vc_timeout",
+    "proc_cores",
+    "eager_threshold",
+    "lazy_threshold",
+    "stratification_depth",
+    "num_instances",
+    "use_cvc4",
+    "boogie_flag",
+];
+
+/// Parses a value of the `--backend-opt` flag of the form `KEY=VALUE`, checking that the key is
+/// supported and the value valid for it.
+fn parse_backend_opt(spec: &str) -> Result<(String, String), MutationError> {
+    let (key, value) = spec.split_once('=').ok_or_else(|| {
+        MutationError::InvalidConfig(anyhow!(
+            "`--backend-opt` expects `KEY=VALUE`, found `{}`",
+            spec
+        ))
+    })?;
+    apply_backend_opt(&mut BoogieOptions::default(), key, value)?;
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// Sets the backend option with the given key, one of `BACKEND_OPTS`, to the value.
+fn apply_backend_opt(
+    backend: &mut BoogieOptions,
+    key: &str,
+    value: &str,
+) -> Result<(), MutationError> {
+    let number = || {
+        value.parse::<usize>().map_err(|_| {
+            MutationError::InvalidConfig(anyhow!(
+                "backend option `{}` must be a number, found `{}`",
+                key,
+                value
+            ))
+        })
+    };
+    match key {
+        "random_seed" => backend.random_seed = number()?,
+        "vc_timeout" => backend.vc_timeout = number()?,
+        "proc_cores" => backend.proc_cores = number()?,
+        "eager_threshold" => backend.eager_threshold = number()?,
+        "lazy_threshold" => backend.lazy_threshold = number()?,
+        "stratification_depth" => backend.stratification_depth = number()?,
+        "num_instances" => backend.num_instances = number()?,
+        "use_cvc4" => {
+            backend.use_cvc4 = value.parse::<bool>().map_err(|_| {
+                MutationError::InvalidConfig(anyhow!(
+                    "backend option `use_cvc4` must be `true` or `false`, found `{}`",
+                    value
+                ))
+            })?
+        }
+        "boogie_flag" => backend.boogie_flags.push(value.to_string()),
+        _ => {
+            return Err(MutationError::InvalidConfig(anyhow!(
+                "unknown backend option `{}`. Supported options: {}",
+                key,
+                BACKEND_OPTS.join(", ")
+            )))
+        }
+    }
+    Ok(())
+}
+
+/// The tools whose versions are recorded in the report.
+const TOOLS: &[&str] = &["boogie", "z3", "cvc4", "mutation"];
+
+/// Determines the versions of the tools used with the given backend options: of boogie, of the
+/// solver, and of the mutation tool itself. Tools whose version cannot be determined are left
+/// out, as verification will report them as missing.
+fn tool_versions(backend: &BoogieOptions) -> BTreeMap<String, String> {
+    let mut versions = BTreeMap::new();
+    versions.insert(
+        "mutation".to_string(),
+        env!("CARGO_PKG_VERSION").to_string(),
+    );
+    for (tool, exe, args, regex) in backend_tools(backend) {
+        if exe.is_empty() {
+            continue;
+        }
+        if let Ok(version) = BoogieOptions::get_version(tool, exe, &args, regex) {
+            versions.insert(tool.to_string(), version);
+        }
+    }
+    versions
+}
+
+/// Returns the backend tools used with the given backend options, i.e. boogie and the solver,
+/// each with its executable and the arguments and regex to determine its version.
+fn backend_tools(backend: &BoogieOptions) -> [(&str, &str, [&str; 1], &str); 2] {
+    let (solver, solver_exe, solver_regex) = if backend.use_cvc4 {
+        ("cvc4", backend.cvc4_exe.as_str(), r"git master ([0-9a-f]*)")
+    } else {
+        ("z3", backend.z3_exe.as_str(), r"version ([0-9.]*)")
+    };
+    [
+        (
+            "boogie",
+            backend.boogie_exe.as_str(),
+            ["-version"],
+            r"version ([0-9.]*)",
+        ),
+        (solver, solver_exe, ["--version"], solver_regex),
+    ]
+}
+
+/// The module verified by the preflight check, whose only function trivially verifies.
+const PREFLIGHT_SOURCE: &str = "\
+module 0x1::MutationPreflight {
+    fun id(x: u64): u64 { x }
+    spec id { ensures result == x; }
+}
+";
+
+/// Checks that the toolchain works before starting a campaign, as otherwise every module fails
+/// and the cause is buried in the results of each: that the model of the sources builds without
+/// errors, that the backend tools exist and respond to `--version`, and that a trivial
+/// synthetic function verifies with the given options.
+fn preflight(env: &GlobalEnv, options: &Options) -> Result<(), MutationError> {
+    let mut options = options.clone();
+    options.backend.derive_options();
+    let mut error_writer = StandardStream::stderr(ColorChoice::Auto);
+    check_errors(env, &options, &mut error_writer, "unexpected build errors")
+        .map_err(MutationError::ModelBuildFailed)?;
+    check_backend(&options.backend)?;
+    for (tool, exe, args, regex) in backend_tools(&options.backend) {
+        // The executable of experimental boogie is taken from the environment instead.
+        if exe.is_empty() {
+            continue;
+        }
+        BoogieOptions::get_version(tool, exe, &args, regex).map_err(|e| {
+            MutationError::BackendMissing(format!(
+                "`{}` does not respond to `{}`: {}",
+                exe, args[0], e
+            ))
+        })?;
+    }
+
+    let preflight_error = |e: anyhow::Error| MutationError::PreflightFailed(e.to_string());
+    let dir = tempfile::tempdir().map_err(|e| preflight_error(e.into()))?;
+    let source = dir.path().join("MutationPreflight.move");
+    std::fs::write(&source, PREFLIGHT_SOURCE).map_err(|e| preflight_error(e.into()))?;
+    let probe_env = move_model::run_model_builder(&[source.to_string_lossy().to_string()], &[])
+        .map_err(preflight_error)?;
+    check_errors(
+        &probe_env,
+        &options,
+        &mut error_writer,
+        "unexpected build errors",
+    )
+    .map_err(preflight_error)?;
+    ProverOptions::set(&probe_env, options.prover.clone());
+    let targets = create_and_process_bytecode(&options, &probe_env);
+    let code_writer = generate_boogie(&probe_env, &options, &targets).map_err(preflight_error)?;
+    verify_boogie(&probe_env, &options, &targets, code_writer).map_err(preflight_error)?;
+    if probe_env.error_count() > 0 {
+        let mut buffer = Buffer::no_color();
+        probe_env.report_diag(&mut buffer, Severity::Warning);
+        return Err(MutationError::PreflightFailed(format!(
+            "a trivial function does not verify:\n{}",
+            String::from_utf8_lossy(&buffer.into_inner()).trim()
+        )));
+    }
+    Ok(())
+}
+
+/// Checks that the tools have the required versions.
+fn check_required_versions(
+    versions: &BTreeMap<String, String>,
+    required: &BTreeMap<String, String>,
+) -> Result<(), MutationError> {
+    for (tool, version) in required {
+        let found = versions.get(tool).map_or("unknown", String::as_str);
+        if found != version {
+            return Err(MutationError::ToolVersionMismatch(
+                tool.clone(),
+                version.clone(),
+                found.to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Write the report to the output file in the given format, replacing the records streamed to it
+/// while the run was in progress.
+fn write_report(
+    out: &str,
+    report: &MutationReport,
+    format: OutputFormat,
+) -> Result<(), MutationError> {
+    let mut out = create_output(out)?;
+    match format {
+        OutputFormat::Text => {
+            write_header(&mut out, report)?;
+            for record in &report.records {
+                write_record(&mut out, record)?;
+            }
+            for timing in &report.timings {
+                writeln!(out, "# timing   : {}", timing.to_line())?;
+            }
+            for record in &report.spec_records {
+                write_spec_record(&mut out, record)?;
+            }
+            writeln!(out, "# summary  : {}", report.summary)?;
+            if let Some(trailer) = &report.trailer {
+                writeln!(out, "# trailer  : {}", trailer.to_line())?;
+            }
+        }
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut out, report)
+                .map_err(|e| MutationError::OutputWriteFailed(e.into()))?;
+            writeln!(out)?;
+        }
+    }
+    sync_output(&mut out)
+}
+
+/// Writes the header of the report in the text output format.
+fn write_header(out: &mut impl Write, report: &MutationReport) -> Result<(), MutationError> {
+    writeln!(out, "# config   : {}", report.config)?;
+    writeln!(out, "# options  : {}", report.options_hash)?;
+    writeln!(out, "# time     : {}", report.time)?;
+    writeln!(
+        out,
+        "# git rev  : {}",
+        report.git_rev.as_deref().unwrap_or("unknown")
+    )?;
+    if !report.tool_versions.is_empty() {
+        writeln!(
+            out,
+            "# versions : {}",
+            report
+                .tool_versions
+                .iter()
+                .map(|(tool, version)| format!("{}={}", tool, version))
+                .join(" ")
+        )?;
+    }
+    writeln!(out, "# seed     : {}", report.seed)?;
+    if report.flaky_policy != FlakyPolicy::default() {
+        writeln!(out, "# flaky    : {}", report.flaky_policy.as_str())?;
+    }
+    if let Some(rev) = &report.changed_since {
+        writeln!(out, "# changed  : {}", rev)?;
+    }
+    if !report.vacuous_specs.is_empty() {
+        writeln!(out, "# vacuous  : {}", report.vacuous_specs.join(" "))?;
+    }
+    if report.interrupted {
+        writeln!(out, "# status   : interrupted")?;
+    } else if report.nothing_to_mutate {
+        writeln!(out, "# status   : nothing to mutate")?;
+    }
+    writeln!(
+        out,
+        "# test code: {}",
+        if report.test_code {
+            "included"
+        } else {
+            "excluded"
+        }
+    )?;
+    Ok(())
+}
+
+/// Writes the mutation record as a line of the text output format.
+fn write_record(out: &mut impl Write, record: &MutationRecord) -> Result<(), MutationError> {
+    writeln!(
+        out,
+        "{:<40} {:>8} {:>8} {:>12} {:>12} {:>8}  {} {} {}",
+        if record.function.is_empty() {
+            &record.module
+        } else {
+            &record.function
+        },
+        record.operator,
+        record.timeout_secs,
+        record.duration_ms,
+        if record.is_unstable() {
+            "unstable"
+        } else {
+            record.status.as_str()
+        },
+        if record.test_code { "test" } else { "source" },
+        record
+            .site
+            .as_ref()
+            .map_or_else(|| "-".to_string(), MutationSite::location),
+        if record.mutant_id.is_empty() {
+            "-"
+        } else {
+            record.mutant_id.as_str()
+        },
+        record.peak_mem_mb
+    )?;
+    Ok(())
+}
+
+/// Writes the spec mutation record as a line of the text output format.
+fn write_spec_record(
+    out: &mut impl Write,
+    record: &SpecMutationRecord,
+) -> Result<(), MutationError> {
+    writeln!(
+        out,
+        "{:<40} {:>10} {:>12} {:>12}  {} {}",
+        record.function,
+        record.kind,
+        record.duration_ms,
+        record.status,
+        record.site.location(),
+        record.site.before
+    )?;
+    Ok(())
+}
+
+/// Flushes the output and waits until its content has been written to disk.
+fn sync_output(out: &mut LineWriter<File>) -> Result<(), MutationError> {
+    out.flush()?;
+    out.get_ref().sync_data()?;
+    Ok(())
+}
+
+/// A result file in the text output format to which each record is written as soon as it is
+/// produced, so the records are not lost if the process dies. The file lacks the trailer of the
+/// report until it is replaced by the result file of the concluded run, so a file left behind
+/// by a run which died is recognized as truncated. Clones write to the same file.
+#[derive(Clone, Debug)]
+pub struct RecordStream {
+    out: Arc<Mutex<LineWriter<File>>>,
+}
+
+impl RecordStream {
+    /// Creates the result file at the given path, starting it with the header of the report.
+    pub fn create(path: &str, report: &MutationReport) -> Result<Self, MutationError> {
+        let mut out = create_output(path)?;
+        write_header(&mut out, report)?;
+        sync_output(&mut out)?;
+        Ok(Self {
+            out: Arc::new(Mutex::new(out)),
+        })
+    }
+
+    /// Appends the mutation record to the result file.
+    pub fn append(&self, record: &MutationRecord) -> Result<(), MutationError> {
+        let mut out = self.out.lock().unwrap();
+        write_record(&mut *out, record)?;
+        sync_output(&mut out)
+    }
+
+    /// Appends the spec mutation record to the result file.
+    pub fn append_spec(&self, record: &SpecMutationRecord) -> Result<(), MutationError> {
+        let mut out = self.out.lock().unwrap();
+        write_spec_record(&mut *out, record)?;
+        sync_output(&mut out)
+    }
+}
+
+/// Writes the mutants listed by a dry run to the given file in the given format.
+fn write_mutants(
+    out: &str,
+    mutants: &[Mutant],
+    registry: &OperatorRegistry,
+    format: OutputFormat,
+) -> Result<(), MutationError> {
+    let mut out = create_output(out)?;
+    match format {
+        OutputFormat::Text => {
+            writeln!(out, "# mutants  : {}", mutants.len())?;
+            for mutant in mutants {
+                writeln!(out, "{}", mutant_line(mutant, registry))?;
+            }
+        }
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut out, mutants)
+                .map_err(|e| MutationError::OutputWriteFailed(e.into()))?;
+            writeln!(out)?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Returns the path of the HTML page for one of multiple configs, with the name of the config
+/// appended to the file name of the given path, e.g. `report.html` becomes `report.cvc4.html`.
+fn html_path(path: &str, config_file: &str) -> String {
+    let path = Path::new(path);
+    let config = Path::new(config_file)
+        .file_stem()
+        .map_or_else(String::new, |s| s.to_string_lossy().to_string());
+    let stem = path
+        .file_stem()
+        .map_or_else(String::new, |s| s.to_string_lossy().to_string());
+    path.with_file_name(format!("{}.{}.html", stem, config))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Creates the given output file, including missing parent directories.
+fn create_output(out: &str) -> Result<LineWriter<File>, MutationError> {
+    let path_error = |e| MutationError::OutputPathFailed(out.to_string(), e);
+    if let Some(parent) = Path::new(out).parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent).map_err(path_error)?;
+        }
+    }
+    Ok(LineWriter::new(File::create(out).map_err(path_error)?))
+}
+
+// ============================================================================================
+// Library interface for running a mutation
+
+/// Runs all mutations on the target modules of the given environment, verifying each mutated
+/// function with the given options, and returns the results.
+pub fn run_mutation(
+    env: &GlobalEnv,
+    options: &Options,
+    mutation_options: &MutationOptions,
+) -> anyhow::Result<MutationReport> {
+    let options = prepare_options(env, options, mutation_options)?;
+    let report = new_report(&options, mutation_options)?;
+    let mut runner = Runner::new(options, mutation_options, report, None);
+    runner.run(env)?;
+    runner.report.summarize();
+    Ok(runner.report)
+}
+
+/// Like `run_mutation`, but instead of the code, mutates the specs of the selected functions,
+/// dropping each `ensures` and `aborts_if` condition in turn. For each condition, the source
+/// file containing it is rewritten into a temporary directory with the condition dropped, and
+/// `build_env`, given the original and the rewritten file, builds the environment with the
+/// original file replaced. The function and all of its callers in target modules are then
+/// verified, and the condition recorded as `needed` if this yields errors, or `redundant`
+/// otherwise.
+pub fn run_spec_mutation<F>(
+    env: &GlobalEnv,
+    build_env: F,
+    options: &Options,
+    mutation_options: &MutationOptions,
+) -> anyhow::Result<MutationReport>
+where
+    F: Fn(&str, &str) -> anyhow::Result<GlobalEnv>,
+{
+    let options = prepare_options(env, options, mutation_options)?;
+    let report = new_report(&options, mutation_options)?;
+    let mut runner = Runner::new(options, mutation_options, report, None);
+    runner.run_baselines(env)?;
+    runner.run_spec_mutations(env, &build_env)?;
+    runner.report.summarize();
+    Ok(runner.report)
+}
+
+/// Like `run_mutation`, but mutates the selected target modules of the given environment in
+/// parallel, using at most `jobs` worker threads. The results are merged in the order of the
+/// modules in the environment, so the report does not depend on scheduling. Progress output of
+/// a worker is buffered and printed once the module is done.
+///
+/// A `GlobalEnv` cannot be shared between threads, and the prover stores its options in the
+/// environment, so every worker builds its own environment via `build_env`, which must yield
+/// the same modules as `env`. The hard timeout is applied to each backend invocation, i.e. per
+/// mutation. Each worker runs its own backend process, which itself uses up to `proc_cores`
+/// cores as configured in the backend options; callers should choose `jobs` accordingly.
+pub fn run_mutation_parallel<F>(
+    env: &GlobalEnv,
+    build_env: F,
+    options: &Options,
+    mutation_options: &MutationOptions,
+    jobs: usize,
+) -> anyhow::Result<MutationReport>
+where
+    F: Fn() -> anyhow::Result<GlobalEnv> + Sync,
+{
+    let checked_options = prepare_options(env, options, mutation_options)?;
+    let header = new_report(&checked_options, mutation_options)?;
+    let modules = env
+        .get_modules()
+        .filter(|m| mutation_options.is_module_selected(m))
+        .map(|m| m.get_full_name_str())
+        .collect_vec();
+    // Each worker only enumerates the mutants of its module, so the bound of the sample is
+    // determined from the mutants of all modules, to select the same ones as a single run.
+    let sample = mutation_options.sample.map(|sample| {
+        let unsampled = MutationOptions {
+            sample: None,
+            ..mutation_options.clone()
+        };
+        let mutants = collect_mutants(env, &unsampled);
+        Sample {
+            size: SampleSize::Fraction(sample.bound(mutants.iter().map(|(_, m)| m))),
+            ..sample
+        }
+    });
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .build()
+        .map_err(|e| MutationError::InvalidConfig(e.into()))?;
+    let module_reports: Vec<anyhow::Result<MutationReport>> = pool.install(|| {
+        modules
+            .par_iter()
+            .map(|module| {
+                // Each worker owns its environment, so setting the prover options in it while
+                // mutating does not affect other workers. It also writes its own Boogie file, by
+                // which the memory monitor tells its backend processes apart from those of others.
+                let env = build_env()?;
+                let worker_options = Options {
+                    output_path: Path::new(&checked_options.output_path)
+                        .with_extension(format!("{}.bpl", module.replace("::", ".")))
+                        .to_string_lossy()
+                        .into_owned(),
+                    ..checked_options.clone()
+                };
+                let module_options = MutationOptions {
+                    modules: vec![module.clone()],
+                    sample,
+                    ..mutation_options.clone()
+                };
+                // The options are prepared and the report created once for the whole run.
+                let mut runner = Runner::new(
+                    worker_options,
+                    &module_options,
+                    header.clone(),
+                    Some(vec![]),
+                );
+                runner.run(&env)?;
+                if !mutation_options.quiet {
+                    let stdout = std::io::stdout();
+                    let mut stdout = stdout.lock();
+                    writeln!(stdout, "Mutations of module `{}`:", module)?;
+                    stdout.write_all(&runner.progress.unwrap_or_default())?;
+                    stdout.flush()?;
+                }
+                Ok(runner.report)
+            })
+            .collect()
+    });
+
+    let mut report = header;
+    for module_report in module_reports {
+        report.merge(module_report?);
+    }
+    report.summarize();
+    Ok(report)
+}
+
+/// Counts the mutations each selected operator produces in each selected target module, by
+/// module and operator, without running the prover. Only the mutants listed by `list_mutants`
+/// are counted.
+pub fn count_mutations(
+    env: &GlobalEnv,
+    mutation_options: &MutationOptions,
+) -> BTreeMap<String, BTreeMap<String, usize>> {
+    let mut counts: BTreeMap<String, BTreeMap<String, usize>> = env
+        .get_modules()
+        .filter(|m| mutation_options.is_module_selected(m))
+        .map(|m| {
+            let module_counts = mutation_options
+                .registry
+                .names()
+                .into_iter()
+                .filter(|o| mutation_options.is_operator_selected(o))
+                .map(|o| (o.to_string(), 0))
+                .collect();
+            (m.get_full_name_str(), module_counts)
+        })
+        .collect();
+    for mutant in list_mutants(env, mutation_options) {
+        *counts
+            .get_mut(&mutant.module)
+            .and_then(|c| c.get_mut(&mutant.operator))
+            .unwrap() += 1;
+    }
+    counts
+}
+
+/// Lists the mutations the selected operators produce in the selected target modules, without
+/// running the prover. An operator produces one mutation for each instruction of a selected
+/// function it applies to, or for a constant, one for each of its replacements. Operators are
+/// applied in order until a function has as many mutations as allowed by the mutation options.
+/// If the mutation options select a sample, only its mutants are listed, and if they select a
+/// shard, only the mutants of the sample which belong to the shard.
+pub fn list_mutants(env: &GlobalEnv, mutation_options: &MutationOptions) -> Vec<Mutant> {
+    collect_mutants(env, mutation_options)
+        .into_iter()
+        .map(|(_, mutant)| mutant)
+        .filter(|mutant| mutation_options.is_mutant_selected(mutant))
+        .collect()
+}
+
+/// Collects the mutants of all shards as described by `list_mutants`, together with the id of
+/// the function each applies to.
+fn collect_mutants(
+    env: &GlobalEnv,
+    mutation_options: &MutationOptions,
+) -> Vec<(QualifiedId<FunId>, Mutant)> {
+    let operators = mutation_options
+        .registry
+        .operators()
+        .filter(|o| mutation_options.is_operator_selected(o.name()))
+        .collect_vec();
+    let mut mutants = vec![];
+    for module in env.get_modules() {
+        if !mutation_options.is_module_selected(&module) {
+            continue;
+        }
+        for fun in module.get_functions() {
+            if fun.is_native() || !mutation_options.is_function_selected(&fun) {
+                continue;
+            }
+            let data = StacklessBytecodeGenerator::new(&fun).generate_function();
+            let mut budget = mutation_options
+                .max_mutants_per_function
+                .unwrap_or(usize::MAX);
+            for operator in &operators {
+                let mutations = operator.sites(&fun, &data);
+                let count = mutations.len().min(budget);
+                budget -= count;
+                mutants.extend(mutations.into_iter().take(count).enumerate().map(
+                    |(i, (loc, replacement))| {
+                        let mutant = Mutant {
+                            module: module.get_full_name_str(),
+                            function: fun.get_full_name_str(),
+                            operator: operator.name().to_string(),
+                            index: i + 1,
+                            site: mutation_site(
+                                env,
+                                *operator,
+                                &fun,
+                                &data,
+                                &loc,
+                                replacement.as_ref(),
+                            ),
+                        };
+                        (fun.get_qualified_id(), mutant)
+                    },
+                ));
+            }
+        }
+    }
+    if let Some(sample) = &mutation_options.sample {
+        let bound = sample.bound(mutants.iter().map(|(_, m)| m));
+        mutants.retain(|(_, m)| sample.key(m) < bound);
+    }
+    mutants
+}
+
+/// Returns the description of the mutation at the site, if the operator describes its sites, or
+/// otherwise the one of the operator.
+fn mutation_description<'a>(
+    registry: &'a OperatorRegistry,
+    operator: &str,
+    site: Option<&'a MutationSite>,
+) -> &'a str {
+    match site {
+        Some(site) if !site.description.is_empty() => &site.description,
+        _ => registry.description(operator),
+    }
+}
+
+/// Determines the site of a mutation by the given operator of the instruction at the given
+/// location in the code generated for the function, including the source text of the
+/// expression before and after the mutation.
+fn mutation_site(
+    env: &GlobalEnv,
+    operator: &dyn MutationOperator,
+    fun: &FunctionEnv<'_>,
+    data: &FunctionData,
+    loc: &Loc,
+    replacement: Option<&Constant>,
+) -> Option<MutationSite> {
+    let (file, pos) = env.get_file_and_location(loc)?;
+    let before = env
+        .get_source(loc)
+        .map(|s| s.to_string())
+        .unwrap_or_default();
+    let after = operator.mutated_text(&before, replacement);
+    Some(MutationSite {
+        file,
+        line: pos.line.0 as usize + 1,
+        column: pos.column.0 as usize + 1,
+        before,
+        after,
+        description: operator.site_description(fun, data, loc, replacement),
+    })
+}
+
+/// Creates an empty report for a run with the given options.
+fn new_report(
+    options: &Options,
+    mutation_options: &MutationOptions,
+) -> Result<MutationReport, MutationError> {
+    Ok(MutationReport {
+        config: "default".to_string(),
+        options_hash: hash_options(options)?,
+        time: chrono::Utc::now().to_string(),
+        interrupted: false,
+        git_rev: None,
+        changed_since: None,
+        nothing_to_mutate: false,
+        tool_versions: BTreeMap::new(),
+        seed: options.backend.random_seed as u64,
+        test_code: false,
+        flaky_policy: mutation_options.flaky_policy,
+        records: vec![],
+        spec_records: vec![],
+        vacuous_specs: vec![],
+        timings: vec![],
+        summary: MutationSummary::default(),
+        operators: BTreeMap::new(),
+        trailer: None,
+    })
+}
+
+/// Derives the options used for mutation from the given ones, and checks that the environment
+/// and backend are ready for running mutations.
+fn prepare_options(
+    env: &GlobalEnv,
+    options: &Options,
+    mutation_options: &MutationOptions,
+) -> Result<Options, MutationError> {
+    let mut options = options.clone();
+    options.prover.mutation = true;
+    options.backend.derive_options();
+
+    let mut error_writer = StandardStream::stderr(ColorChoice::Auto);
+    check_errors(env, &options, &mut error_writer, "unexpected build errors")
+        .map_err(MutationError::ModelBuildFailed)?;
+    check_functions(env, mutation_options)?;
+    warn_unmatched_module_patterns(env, mutation_options);
+    if !mutation_options.stub_backend {
+        check_backend(&options.backend)?;
+        if let Some(backend) = &mutation_options.cross_check {
+            check_backend(backend)?;
+        }
+    }
+    Ok(options)
+}
+
+/// Checks whether all functions named in the mutation options exist in the target modules.
+fn check_functions(
+    env: &GlobalEnv,
+    mutation_options: &MutationOptions,
+) -> Result<(), MutationError> {
+    let target_funs = || {
+        env.get_modules()
+            .filter(|m| m.is_target())
+            .flat_map(|m| m.into_functions())
+    };
+    for name in &mutation_options.functions {
+        if !target_funs().any(|f| function_matches(&f, name)) {
+            let available = target_funs().map(|f| f.get_full_name_str()).collect_vec();
+            return Err(MutationError::UnknownFunction(name.clone(), available));
+        }
+    }
+    Ok(())
+}
+
+/// Prints a warning for each module pattern of the mutation options which matches no target
+/// module, as this most likely is a typo.
+fn warn_unmatched_module_patterns(env: &GlobalEnv, mutation_options: &MutationOptions) {
+    let unmatched = unmatched_module_patterns(env, mutation_options);
+    if unmatched.is_empty() {
+        return;
+    }
+    let available = env
+        .get_modules()
+        .filter(|m| m.is_target())
+        .map(|m| m.get_full_name_str())
+        .join(", ");
+    for pattern in unmatched {
+        println!(
+            "WARNING: module pattern `{}` matches no target module. Available modules: {}",
+            pattern, available
+        );
+    }
+}
+
+/// Returns the include and exclude module patterns of the mutation options which match no
+/// target module.
+fn unmatched_module_patterns<'a>(
+    env: &GlobalEnv,
+    mutation_options: &'a MutationOptions,
+) -> Vec<&'a str> {
+    mutation_options
+        .include_modules
+        .iter()
+        .chain(mutation_options.exclude_modules.iter())
+        .filter(|p| !env.get_modules().any(|m| m.is_target() && p.matches(&m)))
+        .map(ModulePattern::as_str)
+        .collect()
+}
+
+/// Checks whether the executables needed by the prover backend are configured.
+fn check_backend(backend: &BoogieOptions) -> Result<(), MutationError> {
+    if !backend.use_exp_boogie && backend.boogie_exe.is_empty() {
+        return Err(MutationError::BackendMissing(
+            "no boogie executable set. Please set BOOGIE_EXE".to_string(),
+        ));
+    }
+    if backend.use_cvc4 && backend.cvc4_exe.is_empty() {
+        return Err(MutationError::BackendMissing(
+            "no cvc4 executable set. Please set CVC4_EXE".to_string(),
+        ));
+    }
+    if !backend.use_cvc4 && backend.z3_exe.is_empty() {
+        return Err(MutationError::BackendMissing(
+            "no z3 executable set. Please set Z3_EXE".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the name of the file the Boogie program of the mutant is dumped to, which is unique
+/// within a run, e.g. `0x42_Arith_add_add-sub_1.bpl`.
+fn dump_boogie_file(mutant: &Mutant) -> String {
+    format!("{}.bpl", mutant.id().replace("::", "_"))
+}
+
+/// Writes the Boogie program, the prover diagnostics, the translation log if not empty, and the
+/// record of a mutation into the directory `dir/MODULE/MUTANT`, and returns that directory.
+/// Unless `keep_all` is set, this is only done for surviving mutations, and the directory of a
+/// killed one is removed, so artifacts of earlier runs do not linger.
+fn write_artifacts(
+    dir: &Path,
+    record: &MutationRecord,
+    boogie_source: Option<&str>,
+    keep_all: bool,
+) -> Result<Option<String>, MutationError> {
+    let mutant_dir = artifacts_dir(dir, record);
+    if record.status != MutationStatus::Ok && !keep_all {
+        if mutant_dir.exists() {
+            std::fs::remove_dir_all(&mutant_dir)?;
+        }
+        return Ok(None);
+    }
+    std::fs::create_dir_all(&mutant_dir)
+        .map_err(|e| MutationError::OutputPathFailed(mutant_dir.display().to_string(), e))?;
+    if let Some(source) = boogie_source {
+        std::fs::write(mutant_dir.join("mutant.bpl"), source)?;
+    }
+    std::fs::write(
+        mutant_dir.join("prover.log"),
+        record.diagnostics.join("\n\n"),
+    )?;
+    if !record.log.is_empty() {
+        std::fs::write(mutant_dir.join("mutant.log"), &record.log)?;
+    }
+    let metadata = serde_json::to_string_pretty(record)
+        .map_err(|e| MutationError::OutputWriteFailed(e.into()))?;
+    std::fs::write(mutant_dir.join("mutant.json"), metadata + "\n")?;
+    Ok(Some(mutant_dir.display().to_string()))
+}
+
+/// Returns the directory below `dir` the artifacts of the mutation of the record are kept in.
+/// The `::` separators of module and id are replaced, as they are not valid in Windows paths.
+fn artifacts_dir(dir: &Path, record: &MutationRecord) -> PathBuf {
+    dir.join(record.module.replace("::", "."))
+        .join(record.mutant_id.replace("::", "."))
+}
+
+/// Compute a hash of the given options, identifying the configuration a report was produced
+/// with.
+fn hash_options(options: &Options) -> Result<String, MutationError> {
+    let serialized =
+        serde_json::to_string(options).map_err(|e| MutationError::InvalidConfig(e.into()))?;
+    Ok(hex::encode(Sha256::digest(serialized.as_bytes())))
+}
+
+/// The number of seconds verification may take beyond the hard timeout before the memory
+/// monitor kills the prover backend, unless given by the mutation options.
+const DEFAULT_WATCHDOG_GRACE_SECS: u64 = 30;
+
+/// The environment variable which, if set, replaces verification by the stub backend.
+const STUB_BACKEND_ENV: &str = "MUTATION_STUB_BACKEND";
+
+/// The marker which makes the stub backend verify a mutation on the marked source line.
+const STUB_SURVIVES_MARKER: &str = "stub: survives";
+
+struct Runner {
+    options: Options,
+    mutation_options: MutationOptions,
+    /// The hard timeout used for operators without a timeout of their own.
+    default_timeout_secs: u64,
+    /// The name of the operator currently being applied.
+    operator: String,
+    /// The modules which are not mutated because their unmutated code fails to verify or
+    /// verifies too slowly.
+    skipped_modules: BTreeSet<String>,
+    report: MutationReport,
+    /// The output of translating the current mutation, which is kept in its record.
+    log: Vec<u8>,
+    /// A buffer for progress output if the runner is a worker of a parallel run, so output of
+    /// different workers does not interleave.
+    progress: Option<Vec<u8>>,
+    /// The Boogie program of the last verification, if it is to be dumped or kept.
+    boogie_source: Option<String>,
+    /// The hashes of the Boogie programs generated so far, to recognize equivalent mutations.
+    boogie_hashes: BoogieHashes,
+    /// How many of the mutants of the run have been verified.
+    campaign: CampaignProgress,
+    /// Whether the last output is a progress line which is refreshed in place on a terminal.
+    status_line: bool,
+    /// The peak memory of the prover backend during the last verification, in MiB.
+    peak_mem_mb: u64,
+    /// The time spent on the mutants of each module so far, by module name.
+    module_time: BTreeMap<String, Duration>,
+    /// The scopes the current mutation is verified in, one after the other.
+    scopes: Vec<VerificationScope>,
+    /// How many of the scopes of the current mutation are its own, with the remaining ones
+    /// verifying the modules depending on the mutated one.
+    local_scopes: usize,
+    /// The modules depending on selected modules which are not selected themselves and fail to
+    /// verify unmutated, so they are not verified for mutations.
+    failing_dependents: BTreeSet<String>,
+}
+
+/// The hashes of the Boogie programs generated for each verified function, unmutated and with
+/// each of its mutations verified so far. A mutation whose program has been generated before
+/// does not change what is verified, so it is equivalent to the unmutated function or to an
+/// earlier mutation.
+#[derive(Debug, Default)]
+struct BoogieHashes(BTreeMap<String, BTreeSet<Vec<u8>>>);
+
+impl BoogieHashes {
+    /// Returns true if the unmutated program of the function has been recorded.
+    fn contains_function(&self, fun: &str) -> bool {
+        self.0.contains_key(fun)
+    }
+
+    /// Records the program generated for the function. Returns false if the same program has
+    /// been recorded for it before.
+    fn insert(&mut self, fun: &str, source: &str) -> bool {
+        self.0
+            .entry(fun.to_string())
+            .or_default()
+            .insert(Sha256::digest(source.as_bytes()).to_vec())
+    }
+}
+
+/// Tracks how many of the mutants of a run have been verified, to estimate the remaining time
+/// from the average time per mutant so far.
+#[derive(Debug)]
+struct CampaignProgress {
+    total: usize,
+    done: usize,
+    start: Instant,
+}
+
+impl CampaignProgress {
+    fn new(total: usize) -> Self {
+        Self {
+            total,
+            done: 0,
+            start: Instant::now(),
+        }
+    }
+
+    /// Counts the given mutant as verified and returns the progress line for it.
+    fn advance(&mut self, mutant: &Mutant) -> String {
+        self.done += 1;
+        self.line(mutant, self.start.elapsed())
+    }
+
+    /// Returns the progress line for the given mutant after the given time has elapsed.
+    fn line(&self, mutant: &Mutant, elapsed: Duration) -> String {
+        let remaining = self.total.saturating_sub(self.done) as f64 / self.done.max(1) as f64;
+        format!(
+            "[{}/{}] {} mutant {} #{} \u{2014} {} elapsed, ~{} remaining",
+            self.done,
+            self.total,
+            mutant.function,
+            mutant.operator,
+            mutant.index,
+            format_duration(elapsed),
+            format_duration(elapsed.mul_f64(remaining))
+        )
+    }
+}
+
+/// Formats the duration as `MM:SS`, or `HH:MM:SS` if it is an hour or longer.
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+impl Runner {
+    /// Creates a runner with the given prepared options, which adds its results to the given
+    /// report, and reports progress into the given buffer if one is provided.
+    fn new(
+        options: Options,
+        mutation_options: &MutationOptions,
+        report: MutationReport,
+        progress: Option<Vec<u8>>,
+    ) -> Self {
+        Self {
+            default_timeout_secs: options.backend.hard_timeout_secs,
+            options,
+            mutation_options: mutation_options.clone(),
+            operator: String::new(),
+            skipped_modules: BTreeSet::new(),
+            report,
+            log: vec![],
+            progress,
+            boogie_source: None,
+            boogie_hashes: BoogieHashes::default(),
+            campaign: CampaignProgress::new(0),
+            status_line: false,
+            peak_mem_mb: 0,
+            module_time: BTreeMap::new(),
+            scopes: vec![],
+            local_scopes: 0,
+            failing_dependents: BTreeSet::new(),
+        }
+    }
+
+    /// Reports progress, either directly to stdout or, for a worker of a parallel run, into a
+    /// buffer which is printed as a whole once the worker is done. Nothing is reported if the
+    /// run is quiet.
+    fn progress(&mut self, args: std::fmt::Arguments<'_>) -> Result<(), MutationError> {
+        if self.mutation_options.quiet {
+            return Ok(());
+        }
+        match &mut self.progress {
+            Some(buffer) => buffer.write_fmt(args)?,
+            None => {
+                let mut stdout = std::io::stdout();
+                if self.status_line {
+                    // Clear the progress line, which is printed again after the next mutant.
+                    stdout.write_all(b"\r\x1b[K")?;
+                    self.status_line = false;
+                }
+                stdout.write_fmt(args)?;
+                stdout.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts the mutant as verified and reports the progress of the run. On a terminal, the
+    /// progress line is refreshed in place instead of printed for each mutant.
+    fn advance_campaign(&mut self, mutant: &Mutant) -> Result<(), MutationError> {
+        let line = self.campaign.advance(mutant);
+        if self.progress.is_none() && !self.mutation_options.quiet && atty::is(atty::Stream::Stdout)
+        {
+            let mut stdout = std::io::stdout();
+            write!(stdout, "\r\x1b[K{}", line)?;
+            stdout.flush()?;
+            self.status_line = true;
+            Ok(())
+        } else {
+            self.progress(format_args!("{}\n", line))
+        }
+    }
+
+    fn run(&mut self, env: &GlobalEnv) -> Result<(), MutationError> {
+        self.run_baselines(env)?;
+        let mutants = collect_mutants(env, &self.mutation_options)
+            .into_iter()
+            .filter(|(_, mutant)| {
+                !self.skipped_modules.contains(&mutant.module)
+                    && self.mutation_options.is_mutant_selected(mutant)
+            })
+            .collect_vec();
+        self.campaign = CampaignProgress::new(mutants.len());
+        let operators = self
+            .mutation_options
+            .registry
+            .names()
+            .into_iter()
+            .filter(|o| self.mutation_options.is_operator_selected(o))
+            .map(|o| o.to_string())
+            .collect_vec();
+        for operator in &operators {
+            if is_interrupted() {
+                break;
+            }
+            self.run_operator(env, operator, &mutants)?;
+        }
+        if self.status_line {
+            println!();
+            self.status_line = false;
+        }
+        Ok(())
+    }
+
+    /// Verifies the unmutated code of each selected module and excludes modules from mutation
+    /// which already fail to verify, as every mutation would trivially be killed, or which
+    /// verify slower than allowed by the mutation options.
+    fn run_baselines(&mut self, env: &GlobalEnv) -> Result<(), MutationError> {
+        self.options.backend.hard_timeout_secs = self.default_timeout_secs;
+        for module in env.get_modules() {
+            if !self.mutation_options.is_module_selected(&module) {
+                continue;
+            }
+            if is_interrupted() {
+                break;
+            }
+            let name = module.get_full_name_str();
+            self.progress(format_args!("verifying unmutated module {} ..", name))?;
+            env.set_extension(MutationManager::default());
+            self.options.prover.verify_scope = VerificationScope::OnlyModule(name.clone());
+            ProverOptions::set(env, self.options.prover.clone());
+            self.log.clear();
+            let (duration, status, diagnostics) = self.run_mutated_function(env)?;
+            let peak_mem_mb = self.peak_mem_mb;
+            let log = self.take_log();
+            if is_interrupted() {
+                // The prover may have been interrupted as well, so its result is not reliable.
+                self.progress(format_args!("\x08\x08 interrupted.\n"))?;
+                break;
+            }
+            self.progress(format_args!(
+                "\x08\x08{:.3}s {}.\n",
+                duration.as_secs_f64(),
+                status
+            ))?;
+            self.report.timings.push(TimingRecord {
+                module: name.clone(),
+                function: String::new(),
+                duration_ms: duration.as_millis() as u64,
+                status,
+            });
+            if self.mutation_options.granularity == Granularity::Function {
+                self.time_functions(env, &module)?;
+                if is_interrupted() {
+                    break;
+                }
+            }
+            let skip_status = if status != MutationStatus::Ok {
+                self.progress(format_args!("skipping failing module {}\n", name))?;
+                MutationStatus::BaselineFailed
+            } else if matches!(self.mutation_options.skip_slow_secs,
+                Some(secs) if duration.as_secs_f64() > secs as f64)
+            {
+                self.progress(format_args!("skipping slow module {}\n", name))?;
+                MutationStatus::SkippedSlow
+            } else {
+                if self.mutation_options.check_vacuity {
+                    self.check_vacuity(env, &module)?;
+                    if is_interrupted() {
+                        break;
+                    }
+                }
+                continue;
+            };
+            self.push_record(MutationRecord {
+                peak_mem_mb,
+                diagnostics,
+                log,
+                ..MutationRecord::new(
+                    name.clone(),
+                    self.default_timeout_secs,
+                    duration.as_millis() as u64,
+                    skip_status,
+                )
+            })?;
+            self.skipped_modules.insert(name);
+        }
+        if self.mutation_options.with_dependents {
+            self.run_dependent_baselines(env)?;
+        }
+        Ok(())
+    }
+
+    /// Verifies the unmutated code of each target module which is not selected but depends on
+    /// a selected module, so failures which it already has are not attributed to mutations.
+    fn run_dependent_baselines(&mut self, env: &GlobalEnv) -> Result<(), MutationError> {
+        for module in env.get_modules() {
+            if !module.is_target() || self.mutation_options.is_module_selected(&module) {
+                continue;
+            }
+            let depends_on_selected = module.get_used_modules(true).into_iter().any(|id| {
+                self.mutation_options
+                    .is_module_selected(&env.get_module(id))
+            });
+            if !depends_on_selected {
+                continue;
+            }
+            if is_interrupted() {
+                break;
+            }
+            let name = module.get_full_name_str();
+            self.progress(format_args!(
+                "verifying unmutated dependent module {} ..",
+                name
+            ))?;
+            env.set_extension(MutationManager::default());
+            self.options.prover.verify_scope = VerificationScope::OnlyModule(name.clone());
+            ProverOptions::set(env, self.options.prover.clone());
+            self.log.clear();
+            let (duration, status, _) = self.run_mutated_function(env)?;
+            self.log.clear();
+            if is_interrupted() {
+                self.progress(format_args!("\x08\x08 interrupted.\n"))?;
+                break;
+            }
+            self.progress(format_args!(
+                "\x08\x08{:.3}s {}.\n",
+                duration.as_secs_f64(),
+                status
+            ))?;
+            if status != MutationStatus::Ok {
+                self.progress(format_args!(
+                    "not verifying mutations in failing dependent module {}\n",
+                    name
+                ))?;
+                self.failing_dependents.insert(name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the scopes verifying the modules depending on the module, leaving out those
+    /// which fail to verify unmutated or verify too slowly.
+    fn dependent_scopes(&self, module: &ModuleEnv<'_>) -> Vec<VerificationScope> {
+        dependent_modules(module)
+            .into_iter()
+            .filter(|name| {
+                !self.skipped_modules.contains(name) && !self.failing_dependents.contains(name)
+            })
+            .map(VerificationScope::OnlyModule)
+            .collect()
+    }
+
+    /// Verifies each selected function of the module on its own, recording the time it takes.
+    fn time_functions(
+        &mut self,
+        env: &GlobalEnv,
+        module: &ModuleEnv<'_>,
+    ) -> Result<(), MutationError> {
+        for fun in module.get_functions() {
+            if fun.is_native() || !self.mutation_options.is_function_selected(&fun) {
+                continue;
+            }
+            if is_interrupted() {
+                break;
+            }
+            let name = fun.get_full_name_str();
+            self.progress(format_args!("  verifying unmutated function {} ..", name))?;
+            env.set_extension(MutationManager::default());
+            self.options.prover.verify_scope = VerificationScope::Only(name.clone());
+            ProverOptions::set(env, self.options.prover.clone());
+            let (duration, status, _) = self.run_mutated_function(env)?;
+            if is_interrupted() {
+                self.progress(format_args!("\x08\x08 interrupted.\n"))?;
+                break;
+            }
+            self.progress(format_args!(
+                "\x08\x08{:.3}s {}.\n",
+                duration.as_secs_f64(),
+                status
+            ))?;
+            self.report.timings.push(TimingRecord {
+                module: module.get_full_name_str(),
+                function: name,
+                duration_ms: duration.as_millis() as u64,
+                status,
+            });
+        }
+        Ok(())
+    }
+
+    /// Verifies each selected function of the module which has an `ensures` condition of its own
+    /// with `ensures false` added. A function which still verifies has vacuous specs, e.g. as its
+    /// `aborts_if` conditions cover all inputs, so every mutation of it would survive.
+    fn check_vacuity(
+        &mut self,
+        env: &GlobalEnv,
+        module: &ModuleEnv<'_>,
+    ) -> Result<(), MutationError> {
+        for fun in module.get_functions() {
+            if fun.is_native() || !self.mutation_options.is_function_selected(&fun) {
+                continue;
+            }
+            let spec = fun.get_spec();
+            if !spec.conditions.iter().any(|cond| {
+                cond.kind == ConditionKind::Ensures
+                    && matches!(&spec.loc, Some(loc) if encloses(loc, &cond.loc))
+            }) {
+                continue;
+            }
+            if is_interrupted() {
+                break;
+            }
+            let name = fun.get_full_name_str();
+            self.progress(format_args!(
+                "  checking spec of function {} for vacuity ..",
+                name
+            ))?;
+            env.set_extension(MutationManager::default());
+            install_ensures_false(env, fun.get_qualified_id());
+            self.options.prover.verify_scope = VerificationScope::Only(name.clone());
+            ProverOptions::set(env, self.options.prover.clone());
+            let result = self.run_mutated_function(env);
+            env.clear_extension::<CustomMutation>();
+            let (duration, status, _) = result?;
+            if is_interrupted() {
+                self.progress(format_args!("\x08\x08 interrupted.\n"))?;
+                break;
+            }
+            if status == MutationStatus::Ok {
+                self.progress(format_args!(
+                    "\x08\x08{:.3}s vacuous.\n",
+                    duration.as_secs_f64()
+                ))?;
+                println!(
+                    "WARNING: the specs of function {} are vacuous, as it verifies with \
+                    `ensures false`. Its mutants are skipped",
+                    name
+                );
+                self.report.vacuous_specs.push(name);
+            } else {
+                self.progress(format_args!(
+                    "\x08\x08{:.3}s {}.\n",
+                    duration.as_secs_f64(),
+                    status
+                ))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Drops each `ensures` and `aborts_if` condition of the selected functions in turn, and
+    /// verifies the function together with its callers without it.
+    fn run_spec_mutations(
+        &mut self,
+        env: &GlobalEnv,
+        build_env: &dyn Fn(&str, &str) -> anyhow::Result<GlobalEnv>,
+    ) -> Result<(), MutationError> {
+        self.options.backend.hard_timeout_secs = self.default_timeout_secs;
+        for module in env.get_modules() {
+            if !self.mutation_options.is_module_selected(&module)
+                || self.skipped_modules.contains(&module.get_full_name_str())
+            {
+                continue;
+            }
+            for fun in module.get_functions() {
+                if !self.mutation_options.is_function_selected(&fun) {
+                    continue;
+                }
+                let spec = fun.get_spec();
+                for (index, cond) in spec.conditions.iter().enumerate() {
+                    if is_interrupted() {
+                        return Ok(());
+                    }
+                    let (kind, replacement) = match cond.kind {
+                        ConditionKind::Ensures => ("ensures", "true"),
+                        ConditionKind::AbortsIf => ("aborts_if", "false"),
+                        _ => continue,
+                    };
+                    // Skip conditions included from schemas, which are not specific to the
+                    // function.
+                    if !matches!(&spec.loc, Some(loc) if encloses(loc, &cond.loc)) {
+                        continue;
+                    }
+                    self.mutate_condition(&fun, index, kind, replacement, build_env)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Verifies the function and its callers with the `index`-th condition of its spec replaced
+    /// by `replacement`, and records the result.
+    fn mutate_condition(
+        &mut self,
+        fun: &FunctionEnv<'_>,
+        index: usize,
+        kind: &str,
+        replacement: &str,
+        build_env: &dyn Fn(&str, &str) -> anyhow::Result<GlobalEnv>,
+    ) -> Result<(), MutationError> {
+        let env = fun.module_env.env;
+        let loc = &fun.get_spec().conditions[index].loc;
+        let (file, pos) = match env.get_file_and_location(loc) {
+            Some(file_and_pos) => file_and_pos,
+            None => return Ok(()),
+        };
+        let before = env
+            .get_source(loc)
+            .map_err(|e| MutationError::InvalidConfig(e.into()))?
+            .to_string();
+        let after = match before.strip_prefix(kind) {
+            Some(_) => format!("{} {}", kind, replacement),
+            None => replacement.to_string(),
+        };
+        let content = std::fs::read_to_string(&file)?;
+        let span = loc.span();
+        let mutated_content = format!(
+            "{}{}{}",
+            &content[..span.start().to_usize()],
+            after,
+            &content[span.end().to_usize()..]
+        );
+        let dir = tempfile::tempdir()?;
+        let mutated_file = dir.path().join(
+            PathBuf::from(&file)
+                .file_name()
+                .unwrap_or_else(|| "mutated.move".as_ref()),
+        );
+        std::fs::write(&mutated_file, mutated_content)?;
+        let mutated_env = build_env(&file, &mutated_file.to_string_lossy())
+            .map_err(MutationError::ModelBuildFailed)?;
+        let name = fun.get_full_name_str();
+        let mutated_loc = mutated_env
+            .get_modules()
+            .find(|m| m.get_full_name_str() == fun.module_env.get_full_name_str())
+            .and_then(|m| m.into_functions().find(|f| f.get_full_name_str() == name))
+            .and_then(|f| f.get_spec().conditions.get(index).map(|c| c.loc.clone()));
+
+        // Verify the module of the function as well as the target modules calling it.
+        let mut modules = BTreeSet::new();
+        modules.insert(fun.module_env.get_full_name_str());
+        for caller in fun.get_calling_functions() {
+            let caller_module = env.get_module(caller.module_id);
+            if caller_module.is_target() {
+                modules.insert(caller_module.get_full_name_str());
+            }
+        }
+        self.progress(format_args!(
+            "dropping {} of function {} at line {} ..",
+            kind,
+            name,
+            pos.line.0 + 1
+        ))?;
+        let mut duration = Duration::default();
+        let mut status = "redundant";
+        let mut diagnostics = vec![];
+        self.log.clear();
+        for module in modules {
+            // Mark the program as mutated at the condition, so the stub backend verifies it as
+            // it does mutated code.
+            mutated_env.set_extension(MutationManager {
+                mutated: true,
+                ..MutationManager::default()
+            });
+            if let Some(loc) = &mutated_loc {
+                mutated_env.set_extension(MutatedLocation {
+                    loc: loc.clone(),
+                    replacement: None,
+                });
+            }
+            self.options.prover.verify_scope = VerificationScope::OnlyModule(module);
+            ProverOptions::set(&mutated_env, self.options.prover.clone());
+            let (module_duration, module_status, module_diagnostics) =
+                self.run_mutated_function(&mutated_env)?;
+            duration += module_duration;
+            diagnostics.extend(module_diagnostics);
+            match module_status {
+                MutationStatus::Errors => status = "needed",
+                MutationStatus::Timeout if status == "redundant" => status = "timeout",
+                _ => {}
+            }
+        }
+        if is_interrupted() {
+            self.progress(format_args!("\x08\x08 interrupted.\n"))?;
+            return Ok(());
+        }
+        self.progress(format_args!(
+            "\x08\x08{:.3}s {}.\n",
+            duration.as_secs_f64(),
+            status
+        ))?;
+        self.push_spec_record(SpecMutationRecord {
+            module: fun.module_env.get_full_name_str(),
+            function: name,
+            kind: kind.to_string(),
+            site: MutationSite {
+                file,
+                line: pos.line.0 as usize + 1,
+                column: pos.column.0 as usize + 1,
+                before,
+                after,
+                description: String::new(),
+            },
+            duration_ms: duration.as_millis() as u64,
+            status: status.to_string(),
+            diagnostics,
+        })
+    }
+
+    /// Applies the given mutation operator to produce each of the given mutants in turn.
+    fn run_operator(
+        &mut self,
+        env: &GlobalEnv,
+        operator: &str,
+        mutants: &[(QualifiedId<FunId>, Mutant)],
+    ) -> Result<(), MutationError> {
+        let timeout_secs = self
+            .mutation_options
+            .operator_timeouts
+            .get(operator)
+            .copied()
+            .unwrap_or(self.default_timeout_secs);
+        self.options.backend.hard_timeout_secs = timeout_secs;
+        self.operator = operator.to_string();
+        self.progress(format_args!("Applying {} mutations\n", operator))?;
+        let mut applied = 0;
+        for (fun_id, mutant) in mutants {
+            if is_interrupted() {
+                return Ok(());
+            }
+            if mutant.operator != operator {
+                continue;
+            }
+            let fun = env.get_function(*fun_id);
+            if self.is_over_budget(&mutant.module) {
+                self.record_skipped(
+                    &fun,
+                    mutant,
+                    MutationStatus::SkippedBudget,
+                    "module over budget",
+                )?;
+                self.advance_campaign(mutant)?;
+                continue;
+            }
+            if self.report.vacuous_specs.contains(&mutant.function) {
+                self.record_skipped(&fun, mutant, MutationStatus::VacuousSpec, "vacuous spec")?;
+                self.advance_campaign(mutant)?;
+                continue;
+            }
+            let start = Instant::now();
+            self.select_mutation(env, mutant.index);
+            if self.mutate_function(&fun, mutant)? {
+                applied += 1;
+            }
+            *self.module_time.entry(mutant.module.clone()).or_default() += start.elapsed();
+            if !is_interrupted() {
+                self.advance_campaign(mutant)?;
+            }
+        }
+        if applied == 0 {
+            self.progress(format_args!("No mutations applied\n"))?;
+        }
+        Ok(())
+    }
+
+    /// Resets any previous mutation and sets up the current operator to be applied to its
+    /// `i`-th site in the verified function, starting at 1.
+    fn select_mutation(&mut self, env: &GlobalEnv, i: usize) {
+        self.log.clear();
+        env.clear_extension::<CustomMutation>();
+        env.set_extension(MutationManager::default());
+        match self.mutation_options.registry.get(&self.operator) {
+            Some(operator) => operator.select(env, i),
+            None => unreachable!("unknown mutation operator `{}`", self.operator),
+        }
+    }
+
+    fn mutate_function(
+        &mut self,
+        fun: &FunctionEnv<'_>,
+        mutant: &Mutant,
+    ) -> Result<bool, MutationError> {
+        // Scope verification to the first of the scopes of the given function
+        let env = fun.module_env.env;
+        self.scopes = self.mutation_options.verify_scope.verification_scopes(fun);
+        self.local_scopes = self.scopes.len();
+        let with_dependents = self.mutation_options.with_dependents
+            && self.mutation_options.verify_scope != VerifyScope::Package;
+        if with_dependents {
+            for scope in self.dependent_scopes(&fun.module_env) {
+                if !self.scopes.contains(&scope) {
+                    self.scopes.push(scope);
+                }
+            }
+        }
+        self.options.prover.verify_scope = self.scopes[0].clone();
+        ProverOptions::set(env, self.options.prover.clone());
+        // Beyond the function itself, the mutation must be seen by the verified functions
+        // calling it, so it is applied to each variant of the function, and only to it.
+        if self.mutation_options.verify_scope != VerifyScope::Function || with_dependents {
+            if let Some(manager) = env.get_extension::<MutationManager>() {
+                env.set_extension(MutationManager {
+                    function: Some(fun.get_qualified_id()),
+                    ..*manager
+                });
+            }
+        }
+        // Remember the state of the mutation manager, so the same mutation can be applied again
+        // when cross-checking.
+        let manager_state = env
+            .get_extension::<MutationManager>()
+            .map(|m| MutationManager { ..*m });
+        let name = fun.get_full_name_str();
+        if !self.boogie_hashes.contains_function(&name) {
+            let source = self.unmutated_boogie(env, &manager_state)?;
+            self.boogie_hashes.insert(&name, &source);
+        }
+        let (targets, code_writer) = self.translate(env)?;
+        let mutated = env
+            .get_extension::<MutationManager>()
+            .map(|e| e.mutated)
+            .unwrap_or(false);
+        // A mutation which does not change the program cannot be killed, so it is not verified.
+        if mutated
+            && !self
+                .boogie_hashes
+                .insert(&name, &code_writer.process_result(|s| s.to_string()))
+        {
+            self.record_equivalent(env, fun, mutant)?;
+            self.boogie_source = None;
+            return Ok(true);
+        }
+        let result = self.verify(env, &targets, code_writer)?;
+        let boogie_source = self.boogie_source.take();
+        let (duration, status, diagnostics) = if mutated {
+            self.verify_remaining_scopes(env, &manager_state, result)?
+        } else {
+            result
+        };
+        let peak_mem_mb = self.peak_mem_mb;
+        if is_interrupted() {
+            // The prover may have been interrupted as well, so its result is not reliable.
+            return Ok(false);
+        }
+
+        if mutated {
+            let site = self.mutation_site(env, fun);
+            let runs = self.repeat_verification(env, &manager_state, duration, status)?;
+            if is_interrupted() {
+                return Ok(false);
+            }
+            let status = if runs.iter().any(|run| run.status != status) {
+                MutationStatus::Flaky
+            } else {
+                status
+            };
+            let timeout_retry = if status == MutationStatus::Timeout {
+                self.retry_timeout(env, &manager_state)?
+            } else {
+                None
+            };
+            if is_interrupted() {
+                return Ok(false);
+            }
+            let status = match &timeout_retry {
+                Some(retry) => match retry.status {
+                    MutationStatus::Errors | MutationStatus::CaughtDownstream => {
+                        MutationStatus::TimeoutThenCaught
+                    }
+                    MutationStatus::Ok => MutationStatus::TimeoutThenSurvived,
+                    _ => MutationStatus::TimeoutFinal,
+                },
+                None => status,
+            };
+            match &site {
+                Some(site) => self.progress(format_args!(
+                    "mutated function {} at {} ..",
+                    name,
+                    site.location()
+                ))?,
+                None => self.progress(format_args!("mutated function {} ..", name))?,
+            }
+            self.progress(format_args!(
+                "\x08\x08{:.3}s {}.\n",
+                duration.as_secs_f64(),
+                status
+            ))?;
+            // A retried timeout is dumped as well, as the program is the one of the first attempt.
+            if matches!(status, MutationStatus::Errors | MutationStatus::Timeout)
+                || timeout_retry.is_some()
+            {
+                if let (Some(dir), Some(source)) =
+                    (&self.mutation_options.dump_boogie_dir, &boogie_source)
+                {
+                    std::fs::create_dir_all(dir)?;
+                    std::fs::write(dir.join(dump_boogie_file(mutant)), source)?;
+                }
+            }
+            let cross_check_status = match self.mutation_options.cross_check.clone() {
+                Some(backend) => {
+                    let cross_check_status = self.cross_check(env, backend, manager_state)?;
+                    self.progress(format_args!("  cross-check: {}.\n", cross_check_status))?;
+                    Some(cross_check_status)
+                }
+                None => None,
+            };
+
+            // Keep data record of mutation result
+            let mut record = MutationRecord {
+                site,
+                peak_mem_mb,
+                cross_check_status,
+                diagnostics,
+                runs,
+                timeout_retry,
+                verify_scope: Some(self.mutation_options.verify_scope.to_string()),
+                log: self.take_log(),
+                ..MutationRecord::of_mutant(
+                    mutant,
+                    self.options.backend.hard_timeout_secs,
+                    duration.as_millis() as u64,
+                    status,
+                )
+            };
+            if let Some(dir) = &self.mutation_options.artifacts_dir {
+                record.artifacts = write_artifacts(
+                    dir,
+                    &record,
+                    boogie_source.as_deref(),
+                    self.mutation_options.keep_all_artifacts,
+                )?;
+            }
+            self.push_record(record)?;
+        }
+        Ok(mutated)
+    }
+
+    /// Records the mutation last applied to the function as `equivalent`, without verifying it.
+    fn record_equivalent(
+        &mut self,
+        env: &GlobalEnv,
+        fun: &FunctionEnv<'_>,
+        mutant: &Mutant,
+    ) -> Result<(), MutationError> {
+        let site = self.mutation_site(env, fun);
+        let name = fun.get_full_name_str();
+        self.progress(format_args!(
+            "mutated function {} .. equivalent, skipped.\n",
+            name
+        ))?;
+        let timeout_secs = self.options.backend.hard_timeout_secs;
+        self.push_record(MutationRecord {
+            site,
+            log: self.take_log(),
+            ..MutationRecord::of_mutant(mutant, timeout_secs, 0, MutationStatus::Equivalent)
+        })
+    }
+
+    /// Returns true if the time spent on the mutants of the module exceeds its budget.
+    fn is_over_budget(&self, module: &str) -> bool {
+        match (
+            self.mutation_options.module_budget_secs,
+            self.module_time.get(module),
+        ) {
+            (Some(budget_secs), Some(time)) => time.as_secs() >= budget_secs,
+            _ => false,
+        }
+    }
+
+    /// Records the mutant with the given status, without applying or verifying it, reporting
+    /// the reason it is skipped.
+    fn record_skipped(
+        &mut self,
+        fun: &FunctionEnv<'_>,
+        mutant: &Mutant,
+        status: MutationStatus,
+        reason: &str,
+    ) -> Result<(), MutationError> {
+        let name = fun.get_full_name_str();
+        self.progress(format_args!(
+            "mutated function {} .. {}, skipped.\n",
+            name, reason
+        ))?;
+        self.push_record(MutationRecord::of_mutant(
+            mutant,
+            self.options.backend.hard_timeout_secs,
+            0,
+            status,
+        ))
+    }
+
+    /// Records the result of a mutation, writing it to the record stream if there is one.
+    fn push_record(&mut self, record: MutationRecord) -> Result<(), MutationError> {
+        if let Some(stream) = &self.mutation_options.record_stream {
+            stream.append(&record)?;
+        }
+        self.report.records.push(record);
+        Ok(())
+    }
+
+    /// Records the result of a spec mutation, writing it to the record stream if there is one.
+    fn push_spec_record(&mut self, record: SpecMutationRecord) -> Result<(), MutationError> {
+        if let Some(stream) = &self.mutation_options.record_stream {
+            stream.append_spec(&record)?;
+        }
+        self.report.spec_records.push(record);
+        Ok(())
+    }
+
+    /// Generates the Boogie program of the verified function without any mutation, restoring
+    /// the given state of the mutation manager afterwards.
+    fn unmutated_boogie(
+        &mut self,
+        env: &GlobalEnv,
+        manager_state: &Option<MutationManager>,
+    ) -> Result<String, MutationError> {
+        let custom_mutation = env.clear_extension::<CustomMutation>();
+        env.set_extension(MutationManager::default());
+        let result = self.translate(env);
+        if let Some(custom_mutation) = custom_mutation {
+            env.set_extension(CustomMutation::clone(&custom_mutation));
+        }
+        if let Some(manager_state) = manager_state {
+            env.set_extension(MutationManager { ..*manager_state });
+        }
+        let (_, code_writer) = result?;
+        Ok(code_writer.process_result(|s| s.to_string()))
+    }
+
+    /// Determines where the current operator has been applied by the last run, and the source
+    /// text of the expression before and after the mutation.
+    fn mutation_site(&self, env: &GlobalEnv, fun: &FunctionEnv<'_>) -> Option<MutationSite> {
+        let mutated = env.get_extension::<MutatedLocation>()?;
+        let data = StacklessBytecodeGenerator::new(fun).generate_function();
+        mutation_site(
+            env,
+            self.mutation_options.registry.get(&self.operator)?,
+            fun,
+            &data,
+            &mutated.loc,
+            mutated.replacement.as_ref(),
+        )
+    }
+
+    /// Verifies the mutation again until it has been verified as often as requested by the
+    /// `repeat` option, or a result differs from the first one, which is given. Each run starts
+    /// from the state the mutation manager had for the first verification. Returns the results
+    /// of all runs including the first one, or none if the mutation is verified only once.
+    fn repeat_verification(
+        &mut self,
+        env: &GlobalEnv,
+        manager_state: &Option<MutationManager>,
+        duration: Duration,
+        status: MutationStatus,
+    ) -> Result<Vec<VerificationRun>, MutationError> {
+        if self.mutation_options.repeat <= 1 {
+            return Ok(vec![]);
+        }
+        let mut runs = vec![VerificationRun {
+            status,
+            duration_ms: duration.as_millis() as u64,
+        }];
+        while runs.len() < self.mutation_options.repeat && !is_interrupted() {
+            let (duration, run_status, _) = self.run_in_scopes(env, manager_state)?;
+            let disagrees = run_status != status;
+            self.progress(format_args!(
+                "  run {}: {:.3}s {}.\n",
+                runs.len() + 1,
+                duration.as_secs_f64(),
+                run_status
+            ))?;
+            runs.push(VerificationRun {
+                status: run_status,
+                duration_ms: duration.as_millis() as u64,
+            });
+            if disagrees {
+                break;
+            }
+        }
+        // Only the program of the first verification is kept.
+        self.boogie_source = None;
+        Ok(runs)
+    }
+
+    /// Verifies the mutation, which timed out, once more with the timeout escalated as given by
+    /// the `timeout_retry` option, starting from the state the mutation manager had for the
+    /// first verification. Returns the result, or none if timeouts are not retried.
+    fn retry_timeout(
+        &mut self,
+        env: &GlobalEnv,
+        manager_state: &Option<MutationManager>,
+    ) -> Result<Option<TimeoutRetry>, MutationError> {
+        let escalation = match self.mutation_options.timeout_retry {
+            Some(escalation) => escalation,
+            None => return Ok(None),
+        };
+        let timeout_secs = self.options.backend.hard_timeout_secs;
+        let retry_timeout_secs = escalation.escalate(timeout_secs);
+        self.options.backend.hard_timeout_secs = retry_timeout_secs;
+        let result = self.run_in_scopes(env, manager_state);
+        self.options.backend.hard_timeout_secs = timeout_secs;
+        let (duration, status, _) = result?;
+        // Only the program of the first verification is kept.
+        self.boogie_source = None;
+        self.progress(format_args!(
+            "  retry with timeout {}s: {:.3}s {}.\n",
+            retry_timeout_secs,
+            duration.as_secs_f64(),
+            status
+        ))?;
+        Ok(Some(TimeoutRetry {
+            timeout_secs: retry_timeout_secs,
+            status,
+            duration_ms: duration.as_millis() as u64,
+        }))
+    }
+
+    /// Verifies the mutation once more with the given backend, starting from the state the
+    /// mutation manager had for the primary verification, and returns the status.
+    fn cross_check(
+        &mut self,
+        env: &GlobalEnv,
+        mut backend: BoogieOptions,
+        manager_state: Option<MutationManager>,
+    ) -> Result<MutationStatus, MutationError> {
+        backend.hard_timeout_secs = self.options.backend.hard_timeout_secs;
+        backend.derive_options();
+        let primary = std::mem::replace(&mut self.options.backend, backend);
+        let result = self.run_in_scopes(env, &manager_state);
+        self.options.backend = primary;
+        let (_, status, _) = result?;
+        Ok(status)
+    }
+
+    fn run_mutated_function(
+        &mut self,
+        env: &GlobalEnv,
+    ) -> Result<(Duration, MutationStatus, Vec<String>), MutationError> {
+        let (targets, code_writer) = self.translate(env)?;
+        self.verify(env, &targets, code_writer)
+    }
+
+    /// Verifies the mutation in each of the scopes of the current mutation in turn, starting
+    /// each from the given state of the mutation manager, and combines the results.
+    fn run_in_scopes(
+        &mut self,
+        env: &GlobalEnv,
+        manager_state: &Option<MutationManager>,
+    ) -> Result<(Duration, MutationStatus, Vec<String>), MutationError> {
+        self.enter_scope(env, 0, manager_state);
+        let result = self.run_mutated_function(env)?;
+        self.verify_remaining_scopes(env, manager_state, result)
+    }
+
+    /// Verifies the mutation, given its result in the first of the scopes of the current
+    /// mutation, in the remaining scopes, starting each from the given state of the mutation
+    /// manager. A mutation killed in any scope is killed, so verification stops at the first
+    /// scope with errors. Otherwise, a timeout or exceeded memory limit in any scope means the
+    /// mutation is not known to survive. A mutation only killed in a scope verifying a module
+    /// depending on the mutated one is `caught_downstream`.
+    fn verify_remaining_scopes(
+        &mut self,
+        env: &GlobalEnv,
+        manager_state: &Option<MutationManager>,
+        first: (Duration, MutationStatus, Vec<String>),
+    ) -> Result<(Duration, MutationStatus, Vec<String>), MutationError> {
+        let (mut duration, mut status, mut diagnostics) = first;
+        let mut peak_mem_mb = self.peak_mem_mb;
+        for i in 1..self.scopes.len() {
+            if matches!(
+                status,
+                MutationStatus::Errors | MutationStatus::CaughtDownstream
+            ) || is_interrupted()
+            {
+                break;
+            }
+            self.enter_scope(env, i, manager_state);
+            let (scope_duration, scope_status, scope_diagnostics) =
+                self.run_mutated_function(env)?;
+            duration += scope_duration;
+            diagnostics.extend(scope_diagnostics);
+            peak_mem_mb = peak_mem_mb.max(self.peak_mem_mb);
+            if scope_status == MutationStatus::Errors && i >= self.local_scopes {
+                status = MutationStatus::CaughtDownstream;
+            } else if scope_status == MutationStatus::Errors || status == MutationStatus::Ok {
+                status = scope_status;
+            }
+            // Only the program of the first scope is kept.
+            self.boogie_source = None;
+        }
+        self.peak_mem_mb = peak_mem_mb;
+        Ok((duration, status, diagnostics))
+    }
+
+    /// Sets up verification in the `i`-th scope of the current mutation, restoring the given
+    /// state of the mutation manager so the mutation is applied again.
+    fn enter_scope(&mut self, env: &GlobalEnv, i: usize, manager_state: &Option<MutationManager>) {
+        if let Some(manager_state) = manager_state {
+            env.set_extension(MutationManager { ..*manager_state });
+        }
+        self.options.prover.verify_scope = self.scopes[i].clone();
+        ProverOptions::set(env, self.options.prover.clone());
+    }
+
+    /// Translates the program with the mutation of the current mutation manager into Boogie.
+    fn translate(
+        &mut self,
+        env: &GlobalEnv,
+    ) -> Result<(FunctionTargetsHolder, CodeWriter), MutationError> {
+        // Create and process bytecode.
+        let targets = create_and_process_bytecode(&self.options, env);
+        self.log_diagnostics(env, "unexpected transformation errors")?;
+
+        // Generate boogie code.
+        let code_writer = generate_boogie(env, &self.options, &targets)
+            .map_err(MutationError::TranslationFailed)?;
+        self.log_diagnostics(env, "unexpected boogie generation errors")?;
+        if self.mutation_options.dump_boogie_dir.is_some()
+            || self.mutation_options.artifacts_dir.is_some()
+        {
+            self.boogie_source = Some(code_writer.process_result(|s| s.to_string()));
+        }
+        Ok((targets, code_writer))
+    }
+
+    /// Writes the diagnostics of the environment into the log of the current mutation, and fails
+    /// with the given message if there are errors. The diagnostics are printed to stderr as well
+    /// if verbose, or if translation fails, as the run is aborted then.
+    fn log_diagnostics(&mut self, env: &GlobalEnv, msg: &'static str) -> Result<(), MutationError> {
+        let mut buffer = Buffer::no_color();
+        let result = check_errors(env, &self.options, &mut buffer, msg);
+        let output = buffer.into_inner();
+        if self.mutation_options.verbose || result.is_err() {
+            std::io::stderr().write_all(&output)?;
+        }
+        self.log.extend(output);
+        result.map_err(MutationError::TranslationFailed)
+    }
+
+    /// Takes the log of the current mutation, truncated to the size limit of the mutation
+    /// options.
+    fn take_log(&mut self) -> String {
+        let log = String::from_utf8_lossy(&std::mem::take(&mut self.log)).into_owned();
+        match self.mutation_options.max_log_bytes {
+            Some(max_bytes) => truncate_log(log, max_bytes),
+            None => log,
+        }
+    }
+
+    /// Verifies the translated program, returning the duration, the status, and the
+    /// diagnostics of the verification.
+    fn verify(
+        &mut self,
+        env: &GlobalEnv,
+        targets: &FunctionTargetsHolder,
+        code_writer: CodeWriter,
+    ) -> Result<(Duration, MutationStatus, Vec<String>), MutationError> {
+        if self.mutation_options.stub_backend {
+            env.clear_diag();
+            return Ok((Duration::default(), stub_status(env), vec![]));
+        }
+        // Verify boogie, measuring duration and memory.
+        let now = Instant::now();
+        let deadline_secs = self.options.backend.hard_timeout_secs
+            + self
+                .mutation_options
+                .watchdog_grace_secs
+                .unwrap_or(DEFAULT_WATCHDOG_GRACE_SECS);
+        // A hard timeout of zero means verification may take arbitrarily long.
+        let deadline = if self.options.backend.hard_timeout_secs > 0 {
+            Some(Duration::from_secs(deadline_secs))
+        } else {
+            None
+        };
+        let monitor = MemoryMonitor::start(
+            self.mutation_options.mem_limit_mb,
+            deadline,
+            &self.options.output_path,
+        );
+        let result = verify_boogie(env, &self.options, targets, code_writer);
+        let memory = monitor.stop();
+        self.peak_mem_mb = memory.peak_mb;
+        if memory.exceeded {
+            // The prover has been killed, so its result is meaningless.
+            env.clear_diag();
+            let diagnostic = format!(
+                "verification exceeded the memory limit of {} MiB",
+                self.mutation_options.mem_limit_mb.unwrap_or_default()
+            );
+            return Ok((now.elapsed(), MutationStatus::Oom, vec![diagnostic]));
+        }
+        if memory.hung {
+            // The backend has been killed at the deadline, so its result is meaningless.
+            env.clear_diag();
+            let diagnostic = format!(
+                "verification did not finish within {}s and was killed",
+                deadline_secs
+            );
+            return Ok((now.elapsed(), MutationStatus::Hung, vec![diagnostic]));
+        }
+        result.map_err(MutationError::VerificationFailed)?;
+
+        // Determine result status.
+        let status = if env.error_count() > 0 {
+            if env.has_diag("timeout") {
+                MutationStatus::Timeout
+            } else {
+                MutationStatus::Errors
+            }
+        } else {
+            MutationStatus::Ok
+        };
+        let duration = now.elapsed();
+
+        // Keep the diagnostics of the prover with the result.
+        let mut buffer = Buffer::no_color();
+        env.report_diag(&mut buffer, Severity::Warning);
+        let diagnostics = String::from_utf8_lossy(&buffer.into_inner())
+            .split("\n\n")
+            .map(|d| d.trim().to_string())
+            .filter(|d| !d.is_empty())
+            .collect_vec();
+        env.clear_diag();
+
+        Ok((duration, status, diagnostics))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operators::MUTATION_OPERATORS;
+    use move_model::run_model_builder;
+
+    #[test]
+    fn run_mutation_on_fixture() {
+        let source = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/sources/arith.move");
+        let env = run_model_builder(&[source.to_string()], &[]).unwrap();
+        let mutation_options = MutationOptions {
+            stub_backend: true,
+            ..MutationOptions::default()
+        };
+        let report = run_mutation(&env, &Options::default(), &mutation_options).unwrap();
+        assert!(!report.records.is_empty());
+        assert_eq!(report.summary.total, report.records.len());
+        assert!(report
+            .records
+            .iter()
+            .all(|r| r.module.ends_with("Arith") && !r.test_code));
+        assert!(report
+            .records
+            .iter()
+            .any(|r| r.function.ends_with("::add") && r.status == MutationStatus::Errors));
+        assert!(report
+            .records
+            .iter()
+            .filter(|r| r.function.ends_with("::sub"))
+            .all(|r| r.status == MutationStatus::Ok));
+        // A dry run lists the same mutants as those verified by the run.
+        let listed = list_mutants(&env, &mutation_options)
+            .iter()
+            .map(Mutant::id)
+            .collect::<BTreeSet<_>>();
+        let verified = report
+            .records
+            .iter()
+            .map(|r| r.mutant_id.clone())
+            .collect::<BTreeSet<_>>();
+        assert_eq!(listed, verified);
+    }
+
+    #[test]
+    fn preflight_source_builds() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("MutationPreflight.move");
+        std::fs::write(&source, PREFLIGHT_SOURCE).unwrap();
+        let env = run_model_builder(&[source.to_string_lossy().to_string()], &[]).unwrap();
+        assert!(!env.has_errors());
+        assert_eq!(env.get_module_count(), 1);
+    }
+
+    #[test]
+    fn preflight_rejects_unresponsive_backend() {
+        let source = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/sources/arith.move");
+        let env = run_model_builder(&[source.to_string()], &[]).unwrap();
+        let mut options = Options::default();
+        options.backend.boogie_exe = "/nonexistent/boogie".to_string();
+        options.backend.z3_exe = "/nonexistent/z3".to_string();
+        match preflight(&env, &options) {
+            Err(MutationError::BackendMissing(message)) => {
+                assert!(message.contains("/nonexistent/boogie"), "{}", message)
+            }
+            result => panic!("expected the backend to be missing, found {:?}", result),
+        }
+    }
+
+    #[test]
+    fn named_addresses_are_resolved() {
+        let source = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/sources/named.move");
+        let named_addresses = parse_addresses_from_options(vec!["Test=0x42".to_string()]).unwrap();
+        let env = run_model_builder_with_options(
+            &[source.to_string()],
+            &[],
+            ModelBuilderOptions::default(),
+            named_addresses,
+        )
+        .unwrap();
+        assert!(!env.has_errors());
+        let counts = count_mutations(&env, &MutationOptions::default());
+        let (_, named) = counts
+            .iter()
+            .find(|(module, _)| module.ends_with("::Named"))
+            .unwrap();
+        assert_eq!(named["add-sub"], 1);
+    }
+
+    #[test]
+    fn mutations_are_counted() {
+        let source = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/sources/arith.move");
+        let env = run_model_builder(&[source.to_string()], &[]).unwrap();
+        let counts = count_mutations(&env, &MutationOptions::default());
+        let arith = &counts["0x42::Arith"];
+        assert_eq!(arith.len(), MUTATION_OPERATORS.len());
+        assert_eq!(arith["add-sub"], 1);
+        assert_eq!(arith["sub-add"], 1);
+        assert_eq!(arith["mul-div"], 0);
+
+        let only_add = MutationOptions {
+            functions: vec!["add".to_string()],
+            operators: vec!["sub-add".to_string()],
+            ..MutationOptions::default()
+        };
+        let counts = count_mutations(&env, &only_add);
+        assert_eq!(counts["0x42::Arith"].len(), 1);
+        assert_eq!(counts["0x42::Arith"]["sub-add"], 0);
+    }
+
+    #[test]
+    fn mutants_are_listed_with_sites() {
+        let source = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/sources/arith.move");
+        let env = run_model_builder(&[source.to_string()], &[]).unwrap();
+        let mutants = list_mutants(&env, &MutationOptions::default());
+        let add_sub = mutants
+            .iter()
+            .find(|m| m.operator == "add-sub")
+            .expect("add-sub mutant");
+        assert!(add_sub.id().starts_with("0x42::Arith::add::add-sub::1@"));
+        let site = add_sub.site.as_ref().expect("site of mutant");
+        assert!(site.file.ends_with("arith.move"));
+        assert!(site.before.contains('+'));
+        assert!(site.after.contains('-'));
+        assert!(mutant_line(add_sub, &OperatorRegistry::default()).contains("`+` replaced by `-`"));
+    }
+
+    #[test]
+    fn mutant_ids_are_stable_under_unrelated_changes() {
+        let mutants_of = |file: &str| {
+            let source = format!("{}/tests/sources/{}", env!("CARGO_MANIFEST_DIR"), file);
+            let env = run_model_builder(&[source], &[]).unwrap();
+            list_mutants(&env, &MutationOptions::default())
+                .iter()
+                .map(|m| (m.function.clone(), m.id()))
+                .collect::<Vec<_>>()
+        };
+        let ids_of = |mutants: &[(String, String)], function: &str| {
+            mutants
+                .iter()
+                .filter(|(f, _)| f.ends_with(function))
+                .map(|(_, id)| id.clone())
+                .collect::<BTreeSet<_>>()
+        };
+        let original = mutants_of("arith.move");
+        let reordered = mutants_of("reordered.move");
+        // The unchanged function keeps its ids although it moved and a function was added.
+        assert!(!ids_of(&original, "::sub").is_empty());
+        assert_eq!(ids_of(&original, "::sub"), ids_of(&reordered, "::sub"));
+        // The ids of the changed function differ, as its mutated expression changed.
+        assert!(ids_of(&original, "::add").is_disjoint(&ids_of(&reordered, "::add")));
+    }
+
+    #[test]
+    fn excluded_modules_are_not_mutated() {
+        let sources = ["arith.move", "relational.move"]
+            .iter()
+            .map(|s| format!("{}/tests/sources/{}", env!("CARGO_MANIFEST_DIR"), s))
+            .collect_vec();
+        let env = run_model_builder(&sources, &[]).unwrap();
+        let mutation_options = MutationOptions {
+            exclude_modules: vec![ModulePattern::exact("Relational")],
+            ..MutationOptions::default()
+        };
+        let counts = count_mutations(&env, &mutation_options);
+        assert!(counts.contains_key("0x42::Arith"));
+        assert!(!counts.contains_key("0x42::Relational"));
+        // Exact names are not globs.
+        let mutation_options = MutationOptions {
+            exclude_modules: vec![ModulePattern::exact("0x42::Rel*")],
+            ..MutationOptions::default()
+        };
+        assert!(count_mutations(&env, &mutation_options).contains_key("0x42::Relational"));
+    }
+
+    #[test]
+    fn callers_scope_covers_transitive_callers() {
+        let source = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/sources/callers.move");
+        let env = run_model_builder(&[source.to_string()], &[]).unwrap();
+        let module = env
+            .get_modules()
+            .find(|m| m.get_full_name_str() == "0x42::Counter")
+            .unwrap();
+        let fun = module.get_functions().next().unwrap();
+        let scopes = VerifyScope::Callers.verification_scopes(&fun);
+        assert_eq!(
+            scopes[0],
+            VerificationScope::OnlyModule("0x42::Counter".to_string())
+        );
+        let modules = scopes
+            .iter()
+            .map(|scope| match scope {
+                VerificationScope::OnlyModule(module) => module.as_str(),
+                scope => panic!("unexpected scope {:?}", scope),
+            })
+            .collect::<BTreeSet<_>>();
+        assert_eq!(
+            modules,
+            ["0x42::Bank", "0x42::Counter", "0x42::Wallet"]
+                .iter()
+                .copied()
+                .collect()
+        );
+        assert_eq!(
+            VerifyScope::Function.verification_scopes(&fun),
+            vec![VerificationScope::Only(fun.get_full_name_str())]
+        );
+
+        // The mutation of the function is applied even when only a caller is verified.
+        let registry = OperatorRegistry::default();
+        registry.get("add-sub").unwrap().select(&env, 1);
+        let manager = env.get_extension::<MutationManager>().unwrap();
+        env.set_extension(MutationManager {
+            function: Some(fun.get_qualified_id()),
+            ..*manager
+        });
+        let mut options = Options::default();
+        options.prover.mutation = true;
+        options.prover.verify_scope = scopes[1].clone();
+        ProverOptions::set(&env, options.prover.clone());
+        create_and_process_bytecode(&options, &env);
+        assert!(env
+            .get_extension::<MutationManager>()
+            .map_or(false, |m| m.mutated));
+    }
+
+    #[test]
+    fn dependents_are_the_importing_target_modules() {
+        let source = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/sources/callers.move");
+        let env = run_model_builder(&[source.to_string()], &[]).unwrap();
+        let dependents = |name: &str| {
+            let module = env
+                .get_modules()
+                .find(|m| m.get_full_name_str() == name)
+                .unwrap();
+            dependent_modules(&module)
+        };
+        // Only direct imports count, so `Bank` does not depend on `Counter`.
+        assert_eq!(dependents("0x42::Counter"), vec!["0x42::Wallet"]);
+        assert_eq!(dependents("0x42::Wallet"), vec!["0x42::Bank"]);
+        assert!(dependents("0x42::Bank").is_empty());
+        assert!(dependents("0x42::Unrelated").is_empty());
+    }
+
+    fn counted_modules(env: &GlobalEnv, include: &[&str], exclude: &[&str]) -> Vec<String> {
+        let patterns = |ps: &[&str]| {
+            ps.iter()
+                .map(|p| ModulePattern::new(p).unwrap())
+                .collect_vec()
+        };
+        let mutation_options = MutationOptions {
+            include_modules: patterns(include),
+            exclude_modules: patterns(exclude),
+            ..MutationOptions::default()
+        };
+        count_mutations(env, &mutation_options)
+            .into_iter()
+            .map(|(module, _)| module)
+            .collect()
+    }
+
+    #[test]
+    fn modules_are_filtered_by_pattern() {
+        let source = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/sources/two_modules.move"
+        );
+        let env = run_model_builder(&[source.to_string()], &[]).unwrap();
+        assert_eq!(
+            counted_modules(&env, &["0x42::Token"], &[]),
+            vec!["0x42::Token"]
+        );
+        assert_eq!(
+            counted_modules(&env, &["/Store$/"], &[]),
+            vec!["0x42::TokenStore"]
+        );
+        assert_eq!(counted_modules(&env, &[], &["*Store"]), vec!["0x42::Token"]);
+        // Exclusion takes precedence over inclusion.
+        assert_eq!(
+            counted_modules(&env, &["0x42::Token*"], &["0x42::Token?????"]),
+            vec!["0x42::Token"]
+        );
+    }
+
+    #[test]
+    fn unmatched_module_patterns_are_reported() {
+        let source = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/sources/two_modules.move"
+        );
+        let env = run_model_builder(&[source.to_string()], &[]).unwrap();
+        let mutation_options = MutationOptions {
+            include_modules: vec![ModulePattern::new("0x42::*").unwrap()],
+            exclude_modules: vec![ModulePattern::new("0x1::*").unwrap()],
+            ..MutationOptions::default()
+        };
+        assert_eq!(
+            unmatched_module_patterns(&env, &mutation_options),
+            vec!["0x1::*"]
+        );
+        assert!(ModulePattern::new("/(/").is_err());
+    }
+
+    #[test]
+    fn relational_mutations_are_counted() {
+        let source = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/sources/relational.move");
+        let env = run_model_builder(&[source.to_string()], &[]).unwrap();
+        let counts = count_mutations(&env, &MutationOptions::default());
+        let relational = &counts["0x42::Relational"];
+        assert_eq!(relational["lt-le"], 1);
+        assert_eq!(relational["ge-gt"], 1);
+        assert_eq!(relational["eq-neq"], 1);
+        assert_eq!(relational["le-lt"], 0);
+    }
+
+    #[test]
+    fn constant_mutations_are_capped() {
+        let source = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/sources/constant.move");
+        let env = run_model_builder(&[source.to_string()], &[]).unwrap();
+        let counts = count_mutations(&env, &MutationOptions::default());
+        // `10` is replaced by 0, 1, 11, and the maximal u64, `0` by 1 and the maximal u8.
+        assert_eq!(counts["0x42::Constant"]["constant"], 6);
+
+        let capped = MutationOptions {
+            max_mutants_per_function: Some(3),
+            ..MutationOptions::default()
+        };
+        let counts = count_mutations(&env, &capped);
+        assert_eq!(counts["0x42::Constant"]["add-sub"], 1);
+        assert_eq!(counts["0x42::Constant"]["constant"], 4);
+    }
+
+    #[test]
+    fn shards_partition_mutants() {
+        let sources = ["arith.move", "relational.move", "constant.move"]
+            .iter()
+            .map(|s| format!("{}/tests/sources/{}", env!("CARGO_MANIFEST_DIR"), s))
+            .collect_vec();
+        let env = run_model_builder(&sources, &[]).unwrap();
+        let all = list_mutants(&env, &MutationOptions::default());
+        let mut sharded = vec![];
+        for index in 0..3 {
+            let shard_options = MutationOptions {
+                shard: Some(Shard { index, count: 3 }),
+                ..MutationOptions::default()
+            };
+            let shard = list_mutants(&env, &shard_options);
+            // The assignment of mutants does not change between runs.
+            assert_eq!(shard, list_mutants(&env, &shard_options));
+            sharded.extend(shard);
+        }
+        assert_eq!(sharded.len(), all.len());
+        for mutant in &all {
+            assert!(sharded.contains(mutant));
+        }
+    }
+
+    #[test]
+    fn samples_are_reproducible() {
+        let sources = ["arith.move", "relational.move", "constant.move"]
+            .iter()
+            .map(|s| format!("{}/tests/sources/{}", env!("CARGO_MANIFEST_DIR"), s))
+            .collect_vec();
+        let env = run_model_builder(&sources, &[]).unwrap();
+        let all = list_mutants(&env, &MutationOptions::default());
+        let sample_options = |size| MutationOptions {
+            sample: Some(Sample { size, seed: 42 }),
+            ..MutationOptions::default()
+        };
+        let sample = list_mutants(&env, &sample_options(SampleSize::Count(3)));
+        assert_eq!(sample.len(), 3);
+        assert_eq!(
+            sample,
+            list_mutants(&env, &sample_options(SampleSize::Count(3)))
+        );
+        assert!(sample.iter().all(|m| all.contains(m)));
+        assert_eq!(
+            list_mutants(&env, &sample_options(SampleSize::Count(all.len() + 1))),
+            all
+        );
+        assert_eq!(
+            list_mutants(&env, &sample_options(SampleSize::Fraction(1.0))),
+            all
+        );
+
+        // Sampling is applied before sharding, so the shards of a sample partition it.
+        let mut sharded = vec![];
+        for index in 0..2 {
+            let shard_options = MutationOptions {
+                shard: Some(Shard { index, count: 2 }),
+                ..sample_options(SampleSize::Count(3))
+            };
+            sharded.extend(list_mutants(&env, &shard_options));
+        }
+        sharded.sort_by_key(Mutant::id);
+        let mut sorted = sample.clone();
+        sorted.sort_by_key(Mutant::id);
+        assert_eq!(sharded, sorted);
+    }
+
+    #[test]
+    fn sample_fraction_must_be_in_range() {
+        assert!((parse_fraction("sample", "0.5").unwrap() - 0.5).abs() < f64::EPSILON);
+        assert!(parse_fraction("sample", "1").is_ok());
+        assert!(parse_fraction("sample", "0").is_err());
+        assert!(parse_fraction("sample", "1.5").is_err());
+    }
+
+    #[test]
+    fn shard_must_be_below_count() {
+        assert_eq!(parse_shard("2", "3").unwrap(), Shard { index: 2, count: 3 });
+        assert!(matches!(
+            parse_shard("3", "3"),
+            Err(MutationError::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            parse_shard("0", "0"),
+            Err(MutationError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn html_paths_are_distinguished_by_config() {
+        assert_eq!(
+            html_path("out/report.html", "configs/cvc4.toml"),
+            "out/report.cvc4.html"
+        );
+        assert_eq!(html_path("report", "z3.toml"), "report.z3.html");
+    }
+
+    #[test]
+    fn dumped_boogie_files_are_distinguished_by_mutant() {
+        let mutant = |module: &str, index: usize| Mutant {
+            module: module.to_string(),
+            function: format!("{}::add", module),
+            operator: "add-sub".to_string(),
+            index,
+            site: None,
+        };
+        assert_eq!(
+            dump_boogie_file(&mutant("0x42::Arith", 1)),
+            "0x42_Arith_add_add-sub_1.bpl"
+        );
+        let files = [
+            mutant("0x42::Arith", 1),
+            mutant("0x42::Arith", 2),
+            mutant("0x43::Arith", 1),
+        ]
+        .iter()
+        .map(dump_boogie_file)
+        .collect::<BTreeSet<_>>();
+        assert_eq!(files.len(), 3);
+    }
+
+    #[test]
+    fn progress_line_estimates_remaining_time() {
+        let mutant = Mutant {
+            module: "0x42::Arith".to_string(),
+            function: "0x42::Arith::add".to_string(),
+            operator: "add-sub".to_string(),
+            index: 3,
+            site: None,
+        };
+        let mut campaign = CampaignProgress::new(412);
+        campaign.done = 37;
+        assert_eq!(
+            campaign.line(&mutant, Duration::from_secs(41)),
+            "[37/412] 0x42::Arith::add mutant add-sub #3 \u{2014} 00:41 elapsed, ~06:55 remaining"
+        );
+        campaign.done = 412;
+        assert!(campaign
+            .line(&mutant, Duration::from_secs(3700))
+            .ends_with("01:01:40 elapsed, ~00:00 remaining"));
+    }
+
+    #[test]
+    fn equivalent_programs_are_recognized() {
+        let source = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/sources/equivalent.move");
+        let env = run_model_builder(&[source.to_string()], &[]).unwrap();
+        let mutation_options = MutationOptions {
+            operators: vec!["swap-args".to_string()],
+            stub_backend: true,
+            ..MutationOptions::default()
+        };
+        let report = run_mutation(&env, &Options::default(), &mutation_options).unwrap();
+        assert_eq!(report.records.len(), 1);
+        assert_eq!(report.records[0].function, "Equivalent::double");
+        assert_eq!(report.records[0].status, MutationStatus::Equivalent);
+        assert_eq!((report.summary.total, report.summary.equivalent), (0, 1));
+
+        let summary = MutationSummary::from_records(
+            &[
+                MutationRecord::test_record(MutationStatus::Equivalent),
+                MutationRecord::test_record(MutationStatus::Errors),
+                MutationRecord::test_record(MutationStatus::Ok),
+            ],
+            FlakyPolicy::Exclude,
+        );
+        assert_eq!((summary.total, summary.equivalent), (2, 1));
+        assert!((summary.kill_rate - 50.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn survivors_are_rerun_or_stale() {
+        let source = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/sources/arith.move");
+        let env = run_model_builder(&[source.to_string()], &[]).unwrap();
+        let mutant = list_mutants(&env, &MutationOptions::default())
+            .into_iter()
+            .find(|m| m.operator == "sub-add")
+            .unwrap();
+        let survivor = |id: &str| MutationRecord {
+            function: "Arith::sub".to_string(),
+            mutant_id: id.to_string(),
+            operator: "sub-add".to_string(),
+            ..MutationRecord::test_record(MutationStatus::Ok)
+        };
+        let survivors = vec![
+            survivor(&mutant.id()),
+            survivor("0x42::Arith::gone::sub-add::1"),
+        ];
+        let mutation_options = MutationOptions {
+            mutant_ids: Some(survivors.iter().map(|r| r.mutant_id.clone()).collect()),
+            ..MutationOptions::default()
+        };
+        let listed = list_mutants(&env, &mutation_options);
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id(), mutant.id());
+        let stale = stale_records(&env, &mutation_options, &survivors);
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].mutant_id, "0x42::Arith::gone::sub-add::1");
+        assert_eq!(stale[0].status, MutationStatus::Stale);
+        let summary = MutationSummary::from_records(&stale, FlakyPolicy::Exclude);
+        assert_eq!((summary.total, summary.stale), (0, 1));
+    }
+
+    #[test]
+    fn artifacts_of_killed_mutants_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut record = MutationRecord::test_record(MutationStatus::Ok);
+        let kept = write_artifacts(dir.path(), &record, Some("procedure p();"), false)
+            .unwrap()
+            .unwrap();
+        let mutant_dir = dir
+            .path()
+            .join("0x42.Arith")
+            .join("0x42.Arith.add.add-sub.1");
+        assert_eq!(kept, mutant_dir.display().to_string());
+        assert!(mutant_dir.join("mutant.bpl").exists());
+        assert!(mutant_dir.join("prover.log").exists());
+        assert!(mutant_dir.join("mutant.json").exists());
+
+        record.status = MutationStatus::Errors;
+        assert_eq!(
+            write_artifacts(dir.path(), &record, None, false).unwrap(),
+            None
+        );
+        assert!(!mutant_dir.exists());
+        assert!(write_artifacts(dir.path(), &record, None, true)
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn streamed_records_are_replaced_by_concluded_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("mutation.data").display().to_string();
+        let record = |id: &str, status| MutationRecord {
+            mutant_id: id.to_string(),
+            ..MutationRecord::test_record(status)
+        };
+        let mut report = MutationReport {
+            config: "default".to_string(),
+            seed: 7,
+            ..MutationReport::default()
+        };
+        let stream = RecordStream::create(&out, &report).unwrap();
+        for &(id, status) in &[
+            ("0x42::Arith::add::add-sub::1", MutationStatus::Errors),
+            ("b", MutationStatus::Ok),
+        ] {
+            let record = record(id, status);
+            stream.clone().append(&record).unwrap();
+            report.records.push(record);
+        }
+        // A run which dies leaves the streamed records without a trailer.
+        let streamed = read_report(&out).unwrap();
+        assert_eq!(streamed.records.len(), 2);
+        assert_eq!(streamed.seed, 7);
+        assert!(streamed.is_truncated());
+
+        report.summarize();
+        report.conclude();
+        for format in &[OutputFormat::Text, OutputFormat::Json] {
+            write_report(&out, &report, *format).unwrap();
+            let written = read_report(&out).unwrap();
+            assert_eq!(written.records.len(), 2);
+            assert!(!written.is_truncated());
+            assert_eq!(written.trailer, report.trailer);
+        }
+    }
+
+    #[test]
+    fn gh_annotations_are_escaped_and_fall_back_to_files() {
+        assert_eq!(
+            gh_annotation(
+                Some("sources/arith.move"),
+                Some((3, 9)),
+                "mutation 0x42::Arith::add::add-sub::1 survived: `+` replaced by `-`"
+            ),
+            "::warning file=sources/arith.move,line=3,col=9::mutation \
+            0x42::Arith::add::add-sub::1 survived: `+` replaced by `-`"
+        );
+        assert_eq!(
+            gh_annotation(Some("a,b.move"), None, "100%\ncovered"),
+            "::warning file=a%2Cb.move::100%25%0Acovered"
+        );
+        assert_eq!(gh_annotation(None, Some((1, 1)), "x"), "::warning::x");
+    }
+
+    #[test]
+    fn files_are_relative_to_repo_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("sources").join("arith.move");
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(&file, "").unwrap();
+        assert_eq!(
+            relative_to(&file.display().to_string(), dir.path()),
+            Path::new("sources")
+                .join("arith.move")
+                .display()
+                .to_string()
+        );
+        assert_eq!(
+            relative_to("/elsewhere/x.move", dir.path()),
+            "/elsewhere/x.move"
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn hung_backend_is_killed_and_run_continues() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        // A stub backend which verifies the unmutated code, hangs on the first mutation as a
+        // wedged solver would, and verifies all further mutations.
+        let stub = dir.path().join("boogie");
+        std::fs::write(
+            &stub,
+            format!(
+                "#!/bin/sh\n\
+                count_file={}\n\
+                count=$(cat \"$count_file\" 2>/dev/null || echo 0)\n\
+                echo $((count + 1)) > \"$count_file\"\n\
+                if [ \"$count\" -eq 1 ]; then sleep 1000; fi\n",
+                dir.path().join("count").display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&stub, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let source = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/sources/arith.move");
+        let env = run_model_builder(&[source.to_string()], &[]).unwrap();
+        let mut options = Options::default();
+        options.backend.boogie_exe = stub.display().to_string();
+        options.backend.z3_exe = "z3".to_string();
+        options.output_path = dir.path().join("output.bpl").display().to_string();
+        options.backend.hard_timeout_secs = 1;
+        let mutation_options = MutationOptions {
+            watchdog_grace_secs: Some(1),
+            quiet: true,
+            ..MutationOptions::default()
+        };
+        let report = run_mutation(&env, &options, &mutation_options).unwrap();
+        let statuses = report.records.iter().map(|r| r.status).collect_vec();
+        let hung = statuses
+            .iter()
+            .position(|s| *s == MutationStatus::Hung)
+            .unwrap();
+        assert_eq!(report.summary.hung, 1);
+        assert!(statuses[hung + 1..].contains(&MutationStatus::Ok));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn mutants_of_vacuous_specs_are_skipped() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        // A stub backend which verifies everything, so even `ensures false` holds.
+        let stub = dir.path().join("boogie");
+        std::fs::write(&stub, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&stub, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let source = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/sources/arith.move");
+        let env = run_model_builder(&[source.to_string()], &[]).unwrap();
+        let mut options = Options::default();
+        options.backend.boogie_exe = stub.display().to_string();
+        options.backend.z3_exe = "z3".to_string();
+        options.output_path = dir.path().join("output.bpl").display().to_string();
+        let mutation_options = MutationOptions {
+            check_vacuity: true,
+            quiet: true,
+            ..MutationOptions::default()
+        };
+        let report = run_mutation(&env, &options, &mutation_options).unwrap();
+        // Only `add` has an `ensures` to check.
+        assert_eq!(report.vacuous_specs, vec!["Arith::add"]);
+        let status_of = |function: &str| {
+            report
+                .records
+                .iter()
+                .filter(|r| r.function == function)
+                .map(|r| r.status)
+                .unique()
+                .collect_vec()
+        };
+        assert_eq!(status_of("Arith::add"), vec![MutationStatus::VacuousSpec]);
+        assert_eq!(status_of("Arith::sub"), vec![MutationStatus::Ok]);
+        assert!(report.summary.vacuous_spec > 0);
+    }
+
+    #[test]
+    fn logs_are_truncated_at_char_boundaries() {
+        assert_eq!(truncate_log("warning".to_string(), 7), "warning");
+        assert_eq!(
+            truncate_log("warning: \u{e9}t\u{e9}".to_string(), 10),
+            "warning: \n[5 more bytes truncated]\n"
+        );
+    }
+
+    #[test]
+    fn numeric_flags_must_be_positive() {
+        assert_eq!(parse_positive("timeout-secs", "100").unwrap(), 100);
+        assert!(matches!(
+            parse_positive("timeout-secs", "0"),
+            Err(MutationError::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            parse_positive("jobs", "-5"),
+            Err(MutationError::InvalidConfig(_))
+        ));
+    }
+
+    /// Checks the mutants the given operators produce for the fixture against the expected ones,
+    /// each given as its id without content hash, its line, and its description.
+    fn check_fixture(file: &str, operators: &[&str], expected: &[&str]) {
+        let source = format!("{}/tests/sources/{}", env!("CARGO_MANIFEST_DIR"), file);
+        let env = run_model_builder(&[source], &[]).unwrap();
+        let mutation_options = MutationOptions {
+            operators: operators.iter().map(|o| o.to_string()).collect(),
+            ..MutationOptions::default()
+        };
+        let registry = OperatorRegistry::default();
+        let mutants = list_mutants(&env, &mutation_options)
+            .iter()
+            .map(|m| {
+                let site = m.site.as_ref().expect("site of mutant");
+                format!(
+                    "{} line {}: {}",
+                    m.id().split('@').next().unwrap(),
+                    site.line,
+                    mutation_description(&registry, &m.operator, Some(site))
+                )
+            })
+            .sorted()
+            .collect_vec();
+        assert_eq!(mutants, expected);
+    }
+
+    #[test]
+    fn argument_swap_on_fixture() {
+        // The calls with arguments of different types, or of which one is a reference, are not
+        // mutated.
+        check_fixture(
+            "swap.move",
+            &["swap-args"],
+            &[
+                "0x42::Swap::diff::swap-args::1 line 16: arguments 1 and 2 swapped at the call \
+                of `Swap::sub`",
+                "0x42::Swap::total::swap-args::1 line 24: arguments 1 and 2 swapped at the call \
+                of `Swap::add`",
+            ],
+        );
+    }
+
+    #[test]
+    fn return_value_mutation_on_fixture() {
+        // The boolean is negated, the integer replaced by zero and incremented, and the other
+        // functions are not mutated.
+        check_fixture(
+            "returns.move",
+            &["return-value"],
+            &[
+                "0x42::Returns::half::return-value::1 line 13: returned value replaced by zero",
+                "0x42::Returns::half::return-value::2 line 13: returned value incremented by one",
+                "0x42::Returns::is_zero::return-value::1 line 4: returned value negated",
+            ],
+        );
+    }
+
+    #[test]
+    fn branch_negation_on_fixture() {
+        check_fixture(
+            "branch.move",
+            &["negate-branch"],
+            &[
+                "0x42::Branch::choose::negate-branch::1 line 13: branch condition negated",
+                "0x42::Branch::max::negate-branch::1 line 5: branch condition negated",
+            ],
+        );
+    }
+
+    #[test]
+    fn spec_mutation_on_fixture() {
+        let source = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/sources/arith.move");
+        let env = run_model_builder(&[source.to_string()], &[]).unwrap();
+        let build_env = |file: &str, mutated_file: &str| {
+            assert_eq!(file, source);
+            run_model_builder(&[mutated_file.to_string()], &[])
+        };
+        let mutation_options = MutationOptions {
+            stub_backend: true,
+            ..MutationOptions::default()
+        };
+        let report =
+            run_spec_mutation(&env, build_env, &Options::default(), &mutation_options).unwrap();
+        // Each condition of the spec of `add` is dropped in turn, and `sub` has no spec.
+        let dropped = report
+            .spec_records
+            .iter()
+            .map(|r| (r.function.as_str(), r.kind.as_str(), r.site.line))
+            .collect_vec();
+        assert_eq!(
+            dropped,
+            vec![("Arith::add", "aborts_if", 7), ("Arith::add", "ensures", 8)]
+        );
+    }
+
+    #[test]
+    fn sources_are_read_from_stdin() {
+        let input = "b.move\n\n  c.move  \n".as_bytes();
+        assert_eq!(
+            expand_sources(vec!["a.move".to_string(), "-".to_string()], input).unwrap(),
+            vec!["a.move", "b.move", "c.move"]
+        );
+        assert_eq!(
+            expand_sources(vec!["a.move".to_string()], "b.move\n".as_bytes()).unwrap(),
+            vec!["a.move"]
+        );
+    }
+
+    #[test]
+    fn min_score_must_be_a_fraction() {
+        assert!((parse_min_score("0.8").unwrap() - 0.8).abs() < f64::EPSILON);
+        assert!(parse_min_score("80").is_err());
+        assert!(parse_min_score("-0.1").is_err());
+        assert!(parse_min_score("high").is_err());
+    }
+
+    #[test]
+    fn operator_timeout_is_parsed() {
+        let registry = OperatorRegistry::default();
+        assert_eq!(
+            parse_operator_timeout("mul-div=300", &registry).unwrap(),
+            ("mul-div".to_string(), 300)
+        );
+        assert!(parse_operator_timeout("mul-div", &registry).is_err());
+        assert!(parse_operator_timeout("mul-add=10", &registry).is_err());
+        assert!(parse_operator_timeout("mul-div=0", &registry).is_err());
+    }
+
+    #[test]
+    fn backend_opts_are_applied() {
+        let mut backend = BoogieOptions::default();
+        for spec in &["random_seed=7", "vc_timeout=20", "boogie_flag=/trace"] {
+            let (key, value) = parse_backend_opt(spec).unwrap();
+            apply_backend_opt(&mut backend, &key, &value).unwrap();
+        }
+        assert_eq!(backend.random_seed, 7);
+        assert_eq!(backend.vc_timeout, 20);
+        assert_eq!(
+            backend.boogie_flags.last().map(String::as_str),
+            Some("/trace")
+        );
+        assert!(parse_backend_opt("random_seed").is_err());
+        assert!(parse_backend_opt("random_seed=x").is_err());
+        assert!(parse_backend_opt("use_cvc4=yes").is_err());
+        let err = parse_backend_opt("random_sed=1").unwrap_err().to_string();
+        assert!(err.contains("random_seed, vc_timeout"));
+    }
+
+    #[test]
+    fn timeout_retry_is_parsed() {
+        let escalation = parse_timeout_retry("2.5").unwrap();
+        assert_eq!(escalation.max_secs, None);
+        assert_eq!(escalation.escalate(40), 100);
+        let escalation = parse_timeout_retry("3,90").unwrap();
+        assert_eq!(escalation.escalate(20), 60);
+        assert_eq!(escalation.escalate(40), 90);
+        assert!(parse_timeout_retry("1").is_err());
+        assert!(parse_timeout_retry("2,0").is_err());
+        assert!(parse_timeout_retry("x,10").is_err());
+    }
+
+    #[test]
+    fn required_versions_are_checked() {
+        let required = parse_required_versions("boogie=2.9.0,z3=4.8.9").unwrap();
+        assert_eq!(required["boogie"], "2.9.0");
+        assert!(parse_required_versions("boogie").is_err());
+        assert!(parse_required_versions("dafny=3.0").is_err());
+        let mut versions = required.clone();
+        assert!(check_required_versions(&versions, &required).is_ok());
+        versions.insert("z3".to_string(), "4.8.12".to_string());
+        match check_required_versions(&versions, &required) {
+            Err(MutationError::ToolVersionMismatch(tool, _, found)) => {
+                assert_eq!((tool.as_str(), found.as_str()), ("z3", "4.8.12"))
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+        versions.remove("z3");
+        assert!(check_required_versions(&versions, &required).is_err());
+    }
+
+    #[test]
+    fn qualified_function_names_are_resolved() {
+        let source = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/sources/arith.move");
+        let env = run_model_builder(&[source.to_string()], &[]).unwrap();
+        let module = env.get_modules().find(|m| m.is_target()).unwrap();
+        let fun = module
+            .get_functions()
+            .find(|f| f.matches_name("add"))
+            .unwrap();
+        assert!(function_matches(&fun, "add"));
+        assert!(function_matches(&fun, "Arith::add"));
+        assert!(function_matches(&fun, "0x42::Arith::add"));
+        assert!(!function_matches(&fun, "0x43::Arith::add"));
+        assert!(!function_matches(&fun, "Arith::sub"));
+    }
+
+    #[test]
+    fn unknown_function_is_rejected() {
+        let source = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/sources/arith.move");
+        let env = run_model_builder(&[source.to_string()], &[]).unwrap();
+        let mutation_options = MutationOptions {
+            functions: vec!["mul".to_string()],
+            ..MutationOptions::default()
+        };
+        let err = run_mutation(&env, &Options::default(), &mutation_options).unwrap_err();
+        match err.downcast_ref::<MutationError>() {
+            Some(MutationError::UnknownFunction(name, available)) => {
+                assert_eq!(name, "mul");
+                assert!(available.iter().any(|f| f == "Arith::add"));
+                assert!(available.iter().any(|f| f == "Arith::sub"));
+            }
+            _ => panic!("unexpected error: {}", err),
+        }
     }
 }
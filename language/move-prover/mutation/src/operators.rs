@@ -0,0 +1,702 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// Mutation operators, and the registry the operators of a run are selected from.
+
+use bytecode::{
+    function_target::FunctionData,
+    mutation_tester::{
+        constant_replacements, integer_constant, is_in_spec_block, return_mutations,
+        source_branches, swapped_arguments, CustomMutation, MutatedLocation, MutationManager,
+        ReturnMutation,
+    },
+    stackless_bytecode::{AttrId, Bytecode, Constant, Operation},
+};
+use itertools::Itertools;
+use move_model::model::{FunId, FunctionEnv, GlobalEnv, Loc, QualifiedId};
+use std::{collections::BTreeSet, fmt, rc::Rc, sync::Arc};
+
+/// The names of the built-in mutation operators, in the order they are applied.
+pub const MUTATION_OPERATORS: &[&str] = &[
+    "add-sub",
+    "sub-add",
+    "mul-div",
+    "div-mul",
+    "lt-le",
+    "le-lt",
+    "gt-ge",
+    "ge-gt",
+    "eq-neq",
+    "neq-eq",
+    "constant",
+    "negate-branch",
+    "swap-args",
+    "return-value",
+];
+
+/// Named groups of built-in mutation operators which can be selected as a whole.
+pub const MUTATION_OPERATOR_GROUPS: &[(&str, &[&str])] = &[
+    ("arithmetic", &["add-sub", "sub-add", "mul-div", "div-mul"]),
+    (
+        "relational",
+        &["lt-le", "le-lt", "gt-ge", "ge-gt", "eq-neq", "neq-eq"],
+    ),
+];
+
+/// A mutation operator. An operator applies to a number of sites in the code generated for a
+/// function, and produces one mutation for each site.
+///
+/// Operators of other crates mutate the code by installing a mutation with `install_mutation`
+/// when selected, and are made available to a run by registering them with an
+/// `OperatorRegistry` passed to `mutate_with_operators`.
+pub trait MutationOperator: Send + Sync {
+    /// Returns the name the operator is selected by, as in `add-sub`.
+    fn name(&self) -> &str;
+
+    /// Returns a human readable description of the change the operator applies.
+    fn description(&self) -> &str;
+
+    /// Returns the sites the operator applies to in the code generated for the function, in the
+    /// order they are numbered when selecting a mutation. Each is given by the location of the
+    /// mutated instruction and, if a constant is replaced, its replacement.
+    fn sites(&self, fun: &FunctionEnv<'_>, data: &FunctionData) -> Vec<(Loc, Option<Constant>)>;
+
+    /// Arranges for the `index`-th site of the verified function, starting at 1, to be mutated
+    /// the next time code is generated for the environment. Any previously selected mutation is
+    /// reset by the caller.
+    fn select(&self, env: &GlobalEnv, index: usize);
+
+    /// Returns the source text of the mutated expression, given its source text before the
+    /// mutation and the replacement constant, if any. Returns an empty string if unknown.
+    fn mutated_text(&self, _before: &str, replacement: Option<&Constant>) -> String {
+        replacement.map_or_else(String::new, |c| c.to_string())
+    }
+
+    /// Returns a description of the mutation at the site with the given location and
+    /// replacement constant, if any, in the code generated for the function, which is more
+    /// specific than the one of the operator, or an empty string if the operator does not
+    /// describe its sites.
+    fn site_description(
+        &self,
+        _fun: &FunctionEnv<'_>,
+        _data: &FunctionData,
+        _loc: &Loc,
+        _replacement: Option<&Constant>,
+    ) -> String {
+        String::new()
+    }
+}
+
+/// Installs a mutation the mutation tester applies to the code of verified functions after the
+/// built-in mutations. A mutation which changes an instruction reports so with `mark_mutated`.
+pub fn install_mutation(
+    env: &GlobalEnv,
+    mutation: impl Fn(&FunctionEnv<'_>, FunctionData) -> FunctionData + 'static,
+) {
+    env.set_extension(CustomMutation(Rc::new(mutation)));
+}
+
+/// Records that the instruction at the given location has been mutated, so the mutation is
+/// verified and its site reported.
+pub fn mark_mutated(env: &GlobalEnv, loc: Loc) {
+    let manager = env
+        .get_extension::<MutationManager>()
+        .map_or_else(MutationManager::default, |m| MutationManager { ..*m });
+    env.set_extension(MutationManager {
+        mutated: true,
+        ..manager
+    });
+    env.set_extension(MutatedLocation {
+        loc,
+        replacement: None,
+    });
+}
+
+/// Returns the location of the instruction with the given attribute id.
+fn instruction_loc(fun: &FunctionEnv<'_>, data: &FunctionData, attr_id: &AttrId) -> Loc {
+    data.locations
+        .get(attr_id)
+        .cloned()
+        .unwrap_or_else(|| fun.get_loc())
+}
+
+/// A built-in operator which replaces the operation of a binary operator by another one.
+struct OperationReplacement {
+    name: &'static str,
+    description: &'static str,
+    operation: Operation,
+    /// The symbol of the operator in the source, and the symbol it is replaced by.
+    symbols: (&'static str, &'static str),
+    /// The counter of the mutation manager which selects the site to mutate.
+    counter: fn(&mut MutationManager) -> &mut usize,
+}
+
+impl MutationOperator for OperationReplacement {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn description(&self) -> &str {
+        self.description
+    }
+
+    fn sites(&self, fun: &FunctionEnv<'_>, data: &FunctionData) -> Vec<(Loc, Option<Constant>)> {
+        data.code
+            .iter()
+            .filter_map(|bc| match bc {
+                Bytecode::Call(attr_id, _, op, _, _) if *op == self.operation => {
+                    Some((instruction_loc(fun, data, attr_id), None))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn select(&self, env: &GlobalEnv, index: usize) {
+        let mut manager = MutationManager::default();
+        *(self.counter)(&mut manager) = index;
+        env.set_extension(manager);
+    }
+
+    fn mutated_text(&self, before: &str, _replacement: Option<&Constant>) -> String {
+        let (from, to) = self.symbols;
+        replace_operator(before, from, to).unwrap_or_default()
+    }
+}
+
+/// The built-in operator which replaces integer constants by boundary values.
+struct ConstantReplacement;
+
+impl MutationOperator for ConstantReplacement {
+    fn name(&self) -> &str {
+        "constant"
+    }
+
+    fn description(&self) -> &str {
+        "integer constant replaced by a boundary value"
+    }
+
+    fn sites(&self, fun: &FunctionEnv<'_>, data: &FunctionData) -> Vec<(Loc, Option<Constant>)> {
+        data.code
+            .iter()
+            .flat_map(|bc| match bc {
+                Bytecode::Load(attr_id, _, constant) => {
+                    let loc = instruction_loc(fun, data, attr_id);
+                    if is_in_spec_block(fun, &loc) {
+                        vec![]
+                    } else {
+                        constant_replacements(constant)
+                            .into_iter()
+                            .map(|c| (loc.clone(), Some(c)))
+                            .collect_vec()
+                    }
+                }
+                _ => vec![],
+            })
+            .collect()
+    }
+
+    fn select(&self, env: &GlobalEnv, index: usize) {
+        env.set_extension(MutationManager {
+            constant: index,
+            ..MutationManager::default()
+        });
+    }
+}
+
+/// The built-in operator which negates the conditions of branches of the source code.
+struct BranchNegation;
+
+impl MutationOperator for BranchNegation {
+    fn name(&self) -> &str {
+        "negate-branch"
+    }
+
+    fn description(&self) -> &str {
+        "branch condition negated"
+    }
+
+    fn sites(&self, fun: &FunctionEnv<'_>, data: &FunctionData) -> Vec<(Loc, Option<Constant>)> {
+        let branches = source_branches(fun);
+        data.code
+            .iter()
+            .filter_map(|bc| match bc {
+                Bytecode::Branch(attr_id, ..) if branches.contains(attr_id) => {
+                    Some((instruction_loc(fun, data, attr_id), None))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn select(&self, env: &GlobalEnv, index: usize) {
+        env.set_extension(MutationManager {
+            negate_branch: index,
+            ..MutationManager::default()
+        });
+    }
+
+    fn mutated_text(&self, before: &str, _replacement: Option<&Constant>) -> String {
+        negate_condition(before)
+    }
+}
+
+/// The built-in operator which swaps two arguments of the same type of a function call, which
+/// survives specs treating the arguments symmetrically, e.g. those only constraining a total.
+struct ArgumentSwap;
+
+impl ArgumentSwap {
+    /// Returns the calls of the code whose arguments can be swapped, each with its location,
+    /// the callee, and the positions of the swapped arguments.
+    fn calls(
+        fun: &FunctionEnv<'_>,
+        data: &FunctionData,
+    ) -> Vec<(Loc, QualifiedId<FunId>, (usize, usize))> {
+        data.code
+            .iter()
+            .filter_map(|bc| match bc {
+                Bytecode::Call(attr_id, _, Operation::Function(module_id, fun_id, _), srcs, _) => {
+                    swapped_arguments(&data.local_types, srcs).map(|positions| {
+                        let callee = module_id.qualified(*fun_id);
+                        (instruction_loc(fun, data, attr_id), callee, positions)
+                    })
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl MutationOperator for ArgumentSwap {
+    fn name(&self) -> &str {
+        "swap-args"
+    }
+
+    fn description(&self) -> &str {
+        "arguments of the same type swapped at a call"
+    }
+
+    fn sites(&self, fun: &FunctionEnv<'_>, data: &FunctionData) -> Vec<(Loc, Option<Constant>)> {
+        Self::calls(fun, data)
+            .into_iter()
+            .map(|(loc, ..)| (loc, None))
+            .collect()
+    }
+
+    fn select(&self, env: &GlobalEnv, index: usize) {
+        env.set_extension(MutationManager {
+            swap_args: index,
+            ..MutationManager::default()
+        });
+    }
+
+    fn site_description(
+        &self,
+        fun: &FunctionEnv<'_>,
+        data: &FunctionData,
+        loc: &Loc,
+        _replacement: Option<&Constant>,
+    ) -> String {
+        let env = fun.module_env.env;
+        Self::calls(fun, data)
+            .into_iter()
+            .find(|(call_loc, ..)| call_loc == loc)
+            .map_or_else(String::new, |(_, callee, (i, j))| {
+                format!(
+                    "arguments {} and {} swapped at the call of `{}`",
+                    i + 1,
+                    j + 1,
+                    env.get_function(callee).get_full_name_str()
+                )
+            })
+    }
+}
+
+/// The built-in operator which transforms the value a function returns, which survives specs
+/// not constraining the result, or only loosely as in `ensures result >= x`. Functions of which
+/// not exactly one boolean or integer value is returned, and native functions, have no sites.
+struct ReturnValueMutation;
+
+impl ReturnValueMutation {
+    /// Returns the transformations of the returned values of the code, each with the location of
+    /// the return and the constant replacing the value, if any.
+    fn returns(
+        fun: &FunctionEnv<'_>,
+        data: &FunctionData,
+    ) -> Vec<(Loc, ReturnMutation, Option<Constant>)> {
+        data.code
+            .iter()
+            .flat_map(|bc| match bc {
+                Bytecode::Ret(attr_id, srcs) => {
+                    let loc = instruction_loc(fun, data, attr_id);
+                    return_mutations(&data.local_types, srcs)
+                        .into_iter()
+                        .map(|mutation| {
+                            let replacement = match mutation {
+                                ReturnMutation::Zero => {
+                                    integer_constant(&data.local_types[srcs[0]], 0)
+                                }
+                                _ => None,
+                            };
+                            (loc.clone(), mutation, replacement)
+                        })
+                        .collect_vec()
+                }
+                _ => vec![],
+            })
+            .collect()
+    }
+}
+
+impl MutationOperator for ReturnValueMutation {
+    fn name(&self) -> &str {
+        "return-value"
+    }
+
+    fn description(&self) -> &str {
+        "returned value negated, replaced by zero, or incremented"
+    }
+
+    fn sites(&self, fun: &FunctionEnv<'_>, data: &FunctionData) -> Vec<(Loc, Option<Constant>)> {
+        Self::returns(fun, data)
+            .into_iter()
+            .map(|(loc, _, replacement)| (loc, replacement))
+            .collect()
+    }
+
+    fn select(&self, env: &GlobalEnv, index: usize) {
+        env.set_extension(MutationManager {
+            return_value: index,
+            ..MutationManager::default()
+        });
+    }
+
+    fn site_description(
+        &self,
+        fun: &FunctionEnv<'_>,
+        data: &FunctionData,
+        loc: &Loc,
+        replacement: Option<&Constant>,
+    ) -> String {
+        Self::returns(fun, data)
+            .into_iter()
+            .find(|(ret_loc, _, ret_replacement)| {
+                ret_loc == loc && ret_replacement.as_ref() == replacement
+            })
+            .map_or_else(String::new, |(_, mutation, _)| {
+                match mutation {
+                    ReturnMutation::Negate => "returned value negated",
+                    ReturnMutation::Zero => "returned value replaced by zero",
+                    ReturnMutation::Increment => "returned value incremented by one",
+                }
+                .to_string()
+            })
+    }
+}
+
+/// Returns the built-in operators, in the order of `MUTATION_OPERATORS`.
+fn builtin_operators() -> Vec<Arc<dyn MutationOperator>> {
+    vec![
+        Arc::new(OperationReplacement {
+            name: "add-sub",
+            description: "`+` replaced by `-`",
+            operation: Operation::Add,
+            symbols: ("+", "-"),
+            counter: |m| &mut m.add_sub,
+        }),
+        Arc::new(OperationReplacement {
+            name: "sub-add",
+            description: "`-` replaced by `+`",
+            operation: Operation::Sub,
+            symbols: ("-", "+"),
+            counter: |m| &mut m.sub_add,
+        }),
+        Arc::new(OperationReplacement {
+            name: "mul-div",
+            description: "`*` replaced by `/`",
+            operation: Operation::Mul,
+            symbols: ("*", "/"),
+            counter: |m| &mut m.mul_div,
+        }),
+        Arc::new(OperationReplacement {
+            name: "div-mul",
+            description: "`/` replaced by `*`",
+            operation: Operation::Div,
+            symbols: ("/", "*"),
+            counter: |m| &mut m.div_mul,
+        }),
+        Arc::new(OperationReplacement {
+            name: "lt-le",
+            description: "`<` replaced by `<=`",
+            operation: Operation::Lt,
+            symbols: ("<", "<="),
+            counter: |m| &mut m.lt_le,
+        }),
+        Arc::new(OperationReplacement {
+            name: "le-lt",
+            description: "`<=` replaced by `<`",
+            operation: Operation::Le,
+            symbols: ("<=", "<"),
+            counter: |m| &mut m.le_lt,
+        }),
+        Arc::new(OperationReplacement {
+            name: "gt-ge",
+            description: "`>` replaced by `>=`",
+            operation: Operation::Gt,
+            symbols: (">", ">="),
+            counter: |m| &mut m.gt_ge,
+        }),
+        Arc::new(OperationReplacement {
+            name: "ge-gt",
+            description: "`>=` replaced by `>`",
+            operation: Operation::Ge,
+            symbols: (">=", ">"),
+            counter: |m| &mut m.ge_gt,
+        }),
+        Arc::new(OperationReplacement {
+            name: "eq-neq",
+            description: "`==` replaced by `!=`",
+            operation: Operation::Eq,
+            symbols: ("==", "!="),
+            counter: |m| &mut m.eq_neq,
+        }),
+        Arc::new(OperationReplacement {
+            name: "neq-eq",
+            description: "`!=` replaced by `==`",
+            operation: Operation::Neq,
+            symbols: ("!=", "=="),
+            counter: |m| &mut m.neq_eq,
+        }),
+        Arc::new(ConstantReplacement),
+        Arc::new(BranchNegation),
+        Arc::new(ArgumentSwap),
+        Arc::new(ReturnValueMutation),
+    ]
+}
+
+/// The mutation operators available to a run, in the order they are applied, together with
+/// named groups of them. The default registry contains the built-in operators and groups.
+#[derive(Clone)]
+pub struct OperatorRegistry {
+    operators: Vec<Arc<dyn MutationOperator>>,
+    groups: Vec<(String, Vec<String>)>,
+}
+
+impl Default for OperatorRegistry {
+    fn default() -> Self {
+        Self {
+            operators: builtin_operators(),
+            groups: MUTATION_OPERATOR_GROUPS
+                .iter()
+                .map(|(group, operators)| {
+                    (
+                        group.to_string(),
+                        operators.iter().map(|o| o.to_string()).collect(),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Debug for OperatorRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OperatorRegistry")
+            .field("operators", &self.names())
+            .field("groups", &self.groups)
+            .finish()
+    }
+}
+
+impl OperatorRegistry {
+    /// Registers the operator, to be applied after the operators registered before. An operator
+    /// of the same name is replaced, keeping its position.
+    pub fn register(&mut self, operator: impl MutationOperator + 'static) {
+        let operator: Arc<dyn MutationOperator> = Arc::new(operator);
+        match self
+            .operators
+            .iter_mut()
+            .find(|o| o.name() == operator.name())
+        {
+            Some(registered) => *registered = operator,
+            None => self.operators.push(operator),
+        }
+    }
+
+    /// Registers a group of operators which can be selected as a whole by the given name. A
+    /// group of the same name is replaced.
+    pub fn register_group(&mut self, name: &str, operators: &[&str]) {
+        let operators = operators.iter().map(|o| o.to_string()).collect();
+        match self.groups.iter_mut().find(|(g, _)| g == name) {
+            Some((_, registered)) => *registered = operators,
+            None => self.groups.push((name.to_string(), operators)),
+        }
+    }
+
+    /// Returns the operator of the given name, if registered.
+    pub fn get(&self, name: &str) -> Option<&dyn MutationOperator> {
+        self.operators
+            .iter()
+            .find(|o| o.name() == name)
+            .map(|o| o.as_ref())
+    }
+
+    /// Returns the registered operators, in the order they are applied.
+    pub fn operators(&self) -> impl Iterator<Item = &dyn MutationOperator> {
+        self.operators.iter().map(|o| o.as_ref())
+    }
+
+    /// Returns the names of the registered operators, in the order they are applied.
+    pub fn names(&self) -> Vec<&str> {
+        self.operators.iter().map(|o| o.name()).collect()
+    }
+
+    /// Returns the registered groups with the names of their operators.
+    pub fn groups(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.groups.iter().map(|(g, o)| (g.as_str(), o.as_slice()))
+    }
+
+    /// Returns a human readable description of the change the given operator applies.
+    pub fn description(&self, operator: &str) -> &str {
+        self.get(operator)
+            .map_or("unknown mutation", |o| o.description())
+    }
+
+    /// Resolves the given operator and operator group names into the list of selected operators,
+    /// in the order they are applied. Returns the first unknown name as error.
+    pub fn resolve(&self, names: &[String]) -> Result<Vec<String>, String> {
+        let mut selected = BTreeSet::new();
+        for name in names {
+            if let Some((_, operators)) = self.groups.iter().find(|(g, _)| g == name) {
+                selected.extend(operators.iter().map(|o| o.as_str()));
+            } else if self.get(name).is_some() {
+                selected.insert(name.as_str());
+            } else {
+                return Err(name.clone());
+            }
+        }
+        Ok(self
+            .names()
+            .into_iter()
+            .filter(|o| selected.contains(o))
+            .map(|o| o.to_string())
+            .collect())
+    }
+}
+
+/// Replaces the operator symbol `from` of the expression with the given source text by `to`.
+/// Binary operators of the same precedence associate to the left, so the operator of the
+/// expression is the last occurrence of the symbol outside of parentheses.
+fn replace_operator(source: &str, from: &str, to: &str) -> Option<String> {
+    let mut depth = 0;
+    for (i, c) in source.char_indices().rev() {
+        match c {
+            ')' => depth += 1,
+            '(' => depth -= 1,
+            _ if depth == 0 && source[i..].starts_with(from) => {
+                return Some(format!(
+                    "{}{}{}",
+                    &source[..i],
+                    to,
+                    &source[i + from.len()..]
+                ));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Negates the condition of the conditional with the given source text. The condition of an
+/// `if` or `while` is the first parenthesized expression; any other source text, e.g. of a
+/// short-circuiting boolean operator, is negated as a whole.
+fn negate_condition(source: &str) -> String {
+    if source.starts_with("if") || source.starts_with("while") {
+        if let Some(open) = source.find('(') {
+            let mut depth = 0;
+            for (i, c) in source[open..].char_indices() {
+                match c {
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    _ => {}
+                }
+                if depth == 0 {
+                    let close = open + i;
+                    return format!(
+                        "{}!({}){}",
+                        &source[..open + 1],
+                        &source[open + 1..close],
+                        &source[close..]
+                    );
+                }
+            }
+        }
+    }
+    format!("!({})", source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_operators_are_registered() {
+        let registry = OperatorRegistry::default();
+        assert_eq!(registry.names(), MUTATION_OPERATORS.to_vec());
+        assert_eq!(registry.description("lt-le"), "`<` replaced by `<=`");
+        assert_eq!(registry.description("boundary"), "unknown mutation");
+    }
+
+    #[test]
+    fn operator_selection_is_resolved() {
+        let registry = OperatorRegistry::default();
+        assert_eq!(
+            registry
+                .resolve(&["mul-div".to_string(), "add-sub".to_string()])
+                .unwrap(),
+            vec!["add-sub", "mul-div"]
+        );
+        assert_eq!(
+            registry.resolve(&["arithmetic".to_string()]).unwrap(),
+            MUTATION_OPERATORS[..4].to_vec()
+        );
+        assert_eq!(
+            registry
+                .resolve(&["eq-neq".to_string(), "relational".to_string()])
+                .unwrap(),
+            vec!["lt-le", "le-lt", "gt-ge", "ge-gt", "eq-neq", "neq-eq"]
+        );
+        assert!(registry.resolve(&[]).unwrap().is_empty());
+        assert_eq!(
+            registry.resolve(&["boundary".to_string()]).unwrap_err(),
+            "boundary"
+        );
+    }
+
+    #[test]
+    fn operator_is_replaced_in_source() {
+        assert_eq!(replace_operator("a + b", "+", "-").unwrap(), "a - b");
+        assert_eq!(
+            replace_operator("a + b + c", "+", "-").unwrap(),
+            "a + b - c"
+        );
+        assert_eq!(
+            replace_operator("x * (y * z)", "*", "/").unwrap(),
+            "x / (y * z)"
+        );
+        assert!(replace_operator("a - b", "+", "-").is_none());
+    }
+
+    #[test]
+    fn conditions_are_negated_in_source() {
+        assert_eq!(
+            negate_condition("if (x >= y) x else y"),
+            "if (!(x >= y)) x else y"
+        );
+        assert_eq!(
+            negate_condition("while ((i < n)) i = i + 1"),
+            "while (!((i < n))) i = i + 1"
+        );
+        assert_eq!(negate_condition("a && b"), "!(a && b)");
+    }
+}
@@ -0,0 +1,371 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// Options controlling which mutations are applied and how they are run.
+
+use crate::{
+    mutator::RecordStream,
+    operators::OperatorRegistry,
+    report::{FlakyPolicy, Mutant},
+};
+use boogie_backend::options::BoogieOptions;
+use move_model::model::{FunctionEnv, ModuleEnv, VerificationScope};
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    path::PathBuf,
+};
+
+/// A pattern matched against the full names of modules, as in `0x1::Diem`. A pattern enclosed in
+/// slashes, as in `/^0x1::(XUS|XDX)$/`, is a regular expression. Otherwise it is a glob, in
+/// which `*` matches any sequence of characters and `?` any single character.
+#[derive(Clone, Debug)]
+pub struct ModulePattern {
+    pattern: String,
+    regex: Regex,
+}
+
+impl ModulePattern {
+    /// Parses the given pattern, returning an error if it is not a valid regular expression.
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        let regex = match pattern.strip_prefix('/').and_then(|p| p.strip_suffix('/')) {
+            Some(regex) => Regex::new(regex)?,
+            None => {
+                let glob = regex::escape(pattern)
+                    .replace(r"\*", ".*")
+                    .replace(r"\?", ".");
+                Regex::new(&format!("^{}$", glob))?
+            }
+        };
+        Ok(Self {
+            pattern: pattern.to_string(),
+            regex,
+        })
+    }
+
+    /// Returns the pattern matching exactly the module of the given name, given either by simple
+    /// name or with its address as in `0x1::Diem`.
+    pub fn exact(name: &str) -> Self {
+        let regex = Regex::new(&format!("^(.*::)?{}$", regex::escape(name)))
+            .expect("escaped name is a valid regular expression");
+        Self {
+            pattern: name.to_string(),
+            regex,
+        }
+    }
+
+    /// Returns true if the pattern matches the full name of the given module.
+    pub fn matches(&self, module: &ModuleEnv<'_>) -> bool {
+        self.regex.is_match(&module.get_full_name_str())
+    }
+
+    /// Returns the pattern as given.
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+}
+
+/// One of a number of disjoint parts the mutants of a run are partitioned into, so they can be
+/// verified by separate runs. A mutant belongs to the shard given by the stable hash of its id
+/// modulo the number of shards.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shard {
+    /// The index of the shard, starting at 0.
+    pub index: usize,
+    /// The number of shards.
+    pub count: usize,
+}
+
+impl Shard {
+    /// Returns true if the given mutant belongs to this shard.
+    pub fn contains(&self, mutant: &Mutant) -> bool {
+        mutant.stable_hash() % self.count as u64 == self.index as u64
+    }
+}
+
+/// The number of mutants a sample selects.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SampleSize {
+    /// Selects each mutant with the given probability, between 0 and 1.
+    Fraction(f64),
+    /// Selects the given number of mutants, or all if there are fewer.
+    Count(usize),
+}
+
+/// A reproducible random sample of the mutants of a run. Each mutant is assigned a key derived
+/// from the seed and its id, and the mutants with the smallest keys are selected. For the same
+/// seed, sources, and operators, the same mutants are selected, no matter in which order they
+/// are enumerated nor which shard is verified.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sample {
+    pub size: SampleSize,
+    pub seed: u64,
+}
+
+impl Sample {
+    /// Returns the key of the mutant, which is uniformly distributed between 0 and 1.
+    pub fn key(&self, mutant: &Mutant) -> f64 {
+        let mut hasher = Sha256::new();
+        hasher.update(self.seed.to_be_bytes());
+        hasher.update(mutant.id().as_bytes());
+        let digest = hasher.finalize();
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        // Keep 53 bits, so the key is exactly representable.
+        (u64::from_be_bytes(bytes) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns the bound below which the key of a mutant among the given ones must be to be
+    /// selected.
+    pub fn bound<'a>(&self, mutants: impl Iterator<Item = &'a Mutant>) -> f64 {
+        match self.size {
+            SampleSize::Fraction(fraction) => fraction,
+            SampleSize::Count(count) => {
+                let mut keys = mutants.map(|m| self.key(m)).collect::<Vec<_>>();
+                keys.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                keys.get(count).copied().unwrap_or(1.0)
+            }
+        }
+    }
+}
+
+/// How the timeout is escalated for retrying mutations which time out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeoutEscalation {
+    /// The factor the timeout is multiplied by, greater than 1.
+    pub factor: f64,
+    /// The maximal escalated timeout in seconds, if any.
+    pub max_secs: Option<u64>,
+}
+
+impl TimeoutEscalation {
+    /// Returns the escalated timeout for the given one, in seconds.
+    pub fn escalate(&self, timeout_secs: u64) -> u64 {
+        let secs = (timeout_secs as f64 * self.factor).ceil() as u64;
+        self.max_secs.map_or(secs, |max| secs.min(max))
+    }
+}
+
+/// Determines for which units the time verification of the unmutated code takes is recorded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Granularity {
+    /// The time is recorded for each module.
+    Module,
+    /// The time is recorded for each module, and by verifying each selected function on its
+    /// own, for each function.
+    Function,
+}
+
+impl Default for Granularity {
+    fn default() -> Self {
+        Granularity::Module
+    }
+}
+
+/// Determines what is verified for each mutation. Kill rates of runs with different scopes are
+/// not comparable, as a larger scope can kill mutations which only violate specs of callers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerifyScope {
+    /// Only the mutated function is verified.
+    Function,
+    /// All functions of the module of the mutated function are verified.
+    Module,
+    /// All functions of the target modules are verified.
+    Package,
+    /// The module of the mutated function and each target module with a function calling it,
+    /// directly or transitively, are verified.
+    Callers,
+}
+
+impl VerifyScope {
+    /// Returns the scopes a mutation of the function is verified in, one after the other.
+    pub fn verification_scopes(&self, fun: &FunctionEnv<'_>) -> Vec<VerificationScope> {
+        match self {
+            VerifyScope::Function => vec![VerificationScope::Only(fun.get_full_name_str())],
+            VerifyScope::Module => vec![VerificationScope::OnlyModule(
+                fun.module_env.get_full_name_str(),
+            )],
+            VerifyScope::Package => vec![VerificationScope::All],
+            VerifyScope::Callers => {
+                let env = fun.module_env.env;
+                let mut callers = BTreeSet::new();
+                let mut todo = vec![fun.get_qualified_id()];
+                while let Some(id) = todo.pop() {
+                    for caller in env.get_function(id).get_calling_functions() {
+                        if callers.insert(caller) {
+                            todo.push(caller);
+                        }
+                    }
+                }
+                let mut modules = vec![fun.module_env.get_full_name_str()];
+                for caller in callers {
+                    let module = env.get_module(caller.module_id);
+                    let name = module.get_full_name_str();
+                    if module.is_target() && !modules.contains(&name) {
+                        modules.push(name);
+                    }
+                }
+                modules
+                    .into_iter()
+                    .map(VerificationScope::OnlyModule)
+                    .collect()
+            }
+        }
+    }
+}
+
+impl Default for VerifyScope {
+    fn default() -> Self {
+        VerifyScope::Function
+    }
+}
+
+impl fmt::Display for VerifyScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            VerifyScope::Function => "function",
+            VerifyScope::Module => "module",
+            VerifyScope::Package => "package",
+            VerifyScope::Callers => "callers",
+        })
+    }
+}
+
+/// Represents options of a mutation run which are not covered by the prover options.
+#[derive(Clone, Debug, Default)]
+pub struct MutationOptions {
+    /// The target modules to restrict mutation to, given by simple or full name. If empty,
+    /// all target modules are mutated.
+    pub modules: Vec<String>,
+    /// Patterns of which the full name of a target module must match one to be mutated. If
+    /// empty, all target modules may be mutated.
+    pub include_modules: Vec<ModulePattern>,
+    /// Patterns of target modules which are not mutated. Takes precedence over `modules` and
+    /// `include_modules`.
+    pub exclude_modules: Vec<ModulePattern>,
+    /// The functions to restrict mutation to, given by simple name, or qualified by a module
+    /// name as in `MODULE::FUN`, where the module may include its address. If empty, all
+    /// functions of the target modules are mutated.
+    pub functions: Vec<String>,
+    /// The operators available for mutation, which are applied in the order they are
+    /// registered.
+    pub registry: OperatorRegistry,
+    /// The mutation operators to apply, from those of the registry. If empty, all operators are
+    /// applied.
+    pub operators: Vec<String>,
+    /// Hard timeouts in seconds for individual operators, overriding the timeout of the
+    /// backend options for mutations produced by that operator.
+    pub operator_timeouts: BTreeMap<String, u64>,
+    /// The options of a second backend each mutation is verified with as well, to detect
+    /// mutations for which the result depends on the solver. The hard timeout of the primary
+    /// backend is used for both.
+    pub cross_check: Option<BoogieOptions>,
+    /// If set, modules for which verification of the unmutated code takes longer than the
+    /// given number of seconds are not mutated.
+    pub skip_slow_secs: Option<u64>,
+    /// If set, once the mutants of a module took longer than the given number of seconds in
+    /// total, its remaining mutants are recorded as `skipped_budget` without being verified.
+    pub module_budget_secs: Option<u64>,
+    /// Whether the baseline checks the specs of each selected function with an `ensures` for
+    /// vacuity, by verifying it with `ensures false` added. The mutants of functions whose specs
+    /// are vacuous are recorded as `vacuous_spec` without being verified.
+    pub check_vacuity: bool,
+    /// If set, at most this many mutations are generated for each function, over all operators
+    /// in the order they are applied.
+    pub max_mutants_per_function: Option<usize>,
+    /// If set, the Boogie program of each mutation which fails to verify or times out is
+    /// written to `<module>.bpl` in this directory.
+    pub dump_boogie_dir: Option<PathBuf>,
+    /// If set, the Boogie program, prover diagnostics, and record of each surviving mutation
+    /// are kept in a directory of their own below this one.
+    pub artifacts_dir: Option<PathBuf>,
+    /// Whether to keep the artifacts of all mutations, not only of the surviving ones.
+    pub keep_all_artifacts: bool,
+    /// If set, only the mutants of this sample are verified. The sample is selected before the
+    /// mutants are partitioned into shards.
+    pub sample: Option<Sample>,
+    /// If set, only the mutants of this shard are verified.
+    pub shard: Option<Shard>,
+    /// If set, only the mutants with these ids are verified.
+    pub mutant_ids: Option<BTreeSet<String>>,
+    /// The number of times each mutation is verified at most. A mutation for which the results
+    /// disagree is classified as flaky and not verified further.
+    pub repeat: usize,
+    /// How flaky mutations count towards the kill rate.
+    pub flaky_policy: FlakyPolicy,
+    /// If set, verification of a mutation is aborted once the processes of the prover backend
+    /// use more than this many MiB of resident memory.
+    pub mem_limit_mb: Option<u64>,
+    /// If set, the number of seconds verification may take beyond the hard timeout before the
+    /// processes of the prover backend are killed, overriding the default grace period. Without
+    /// a hard timeout, the processes are never killed.
+    pub watchdog_grace_secs: Option<u64>,
+    /// For which units the time verification of the unmutated code takes is recorded.
+    pub granularity: Granularity,
+    /// What is verified for each mutation.
+    pub verify_scope: VerifyScope,
+    /// Whether each mutation is also verified in each target module importing the module of
+    /// the mutated function, after its own scopes. A mutation which is only killed in one of
+    /// these modules has the status `caught_downstream`.
+    pub with_dependents: bool,
+    /// Whether to suppress the progress output while verifying, leaving only the summary.
+    pub quiet: bool,
+    /// Whether the output of translating each mutation is printed to stderr as well, rather
+    /// than only kept in its record.
+    pub verbose: bool,
+    /// The number of bytes the log kept in the record of a mutation is truncated to, if limited.
+    pub max_log_bytes: Option<usize>,
+    /// Whether verification is replaced by a stub which needs neither boogie nor a solver, so
+    /// runs can be tested anywhere. The stub verifies unmutated code, and kills each mutation
+    /// unless the source line of the mutated instruction contains the marker `stub: survives`.
+    pub stub_backend: bool,
+    /// If set, each mutation which times out is verified once more with the timeout escalated
+    /// as given.
+    pub timeout_retry: Option<TimeoutEscalation>,
+    /// If set, each record is written to this stream as soon as it is produced.
+    pub record_stream: Option<RecordStream>,
+}
+
+impl MutationOptions {
+    /// Returns true if the given module is selected for mutation.
+    pub fn is_module_selected(&self, module: &ModuleEnv<'_>) -> bool {
+        module.is_target()
+            && (self.modules.is_empty() || self.modules.iter().any(|n| module.matches_name(n)))
+            && (self.include_modules.is_empty()
+                || self.include_modules.iter().any(|p| p.matches(module)))
+            && !self.exclude_modules.iter().any(|p| p.matches(module))
+    }
+
+    /// Returns true if the given operator is selected.
+    pub fn is_operator_selected(&self, operator: &str) -> bool {
+        self.operators.is_empty() || self.operators.iter().any(|o| o == operator)
+    }
+
+    /// Returns true if the given mutant belongs to the selected shard and ids, if any.
+    pub fn is_mutant_selected(&self, mutant: &Mutant) -> bool {
+        self.shard.map_or(true, |shard| shard.contains(mutant))
+            && self
+                .mutant_ids
+                .as_ref()
+                .map_or(true, |ids| ids.contains(&mutant.id()))
+    }
+
+    /// Returns true if the given function is selected for mutation.
+    pub fn is_function_selected(&self, fun: &FunctionEnv<'_>) -> bool {
+        self.functions.is_empty() || self.functions.iter().any(|n| function_matches(fun, n))
+    }
+}
+
+/// Returns true if the given function is denoted by the name. The name is either the simple name
+/// of the function, or of the form `MODULE::FUN`, where `MODULE` is matched against the simple
+/// as well as the full name of the enclosing module.
+pub fn function_matches(fun: &FunctionEnv<'_>, name: &str) -> bool {
+    match name.rsplit_once("::") {
+        Some((module, simple_name)) => {
+            fun.module_env.matches_name(module) && fun.matches_name(simple_name)
+        }
+        None => fun.matches_name(name),
+    }
+}
@@ -0,0 +1,1446 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// Data structures representing the results of a mutation run.
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+/// Represents the results of a mutation run with a given configuration.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MutationReport {
+    /// The description of the configuration.
+    pub config: String,
+    /// A hash of the prover options the run was executed with. Allows to correlate reports
+    /// with the configuration which produced them.
+    pub options_hash: String,
+    /// The time the run was started.
+    pub time: String,
+    /// Whether the run was interrupted, in which case the records are incomplete.
+    #[serde(default)]
+    pub interrupted: bool,
+    /// The git revision of the source tree the mutated modules are taken from, if available.
+    #[serde(default)]
+    pub git_rev: Option<String>,
+    /// The git revision mutation was restricted to the functions changed since, if any.
+    #[serde(default)]
+    pub changed_since: Option<String>,
+    /// Whether no target function changed since `changed_since`, so there was nothing to
+    /// mutate. The records are empty in this case.
+    #[serde(default)]
+    pub nothing_to_mutate: bool,
+    /// The versions of the tools the run was executed with by name of the tool, i.e. of boogie,
+    /// the solver, and the mutation tool itself. Results are only comparable between runs with
+    /// the same versions.
+    #[serde(default)]
+    pub tool_versions: BTreeMap<String, String>,
+    /// The seed the run was started with. Rerunning with this seed reproduces the run.
+    #[serde(default)]
+    pub seed: u64,
+    /// Whether test code was included in the run.
+    pub test_code: bool,
+    /// How flaky mutations count towards the kill rate of the summary.
+    #[serde(default)]
+    pub flaky_policy: FlakyPolicy,
+    /// The results of the individual mutations.
+    pub records: Vec<MutationRecord>,
+    /// The results of dropping individual spec conditions, if specs have been mutated.
+    #[serde(default)]
+    pub spec_records: Vec<SpecMutationRecord>,
+    /// The functions whose specs are vacuous, i.e. which verify with `ensures false` added, if
+    /// specs have been checked for vacuity.
+    #[serde(default)]
+    pub vacuous_specs: Vec<String>,
+    /// The time verification of the unmutated code took, for each module and, if timed by
+    /// function, for each of its selected functions.
+    #[serde(default)]
+    pub timings: Vec<TimingRecord>,
+    /// Aggregate statistics over the records.
+    #[serde(default)]
+    pub summary: MutationSummary,
+    /// Aggregate statistics over the records of each mutation operator, by operator name.
+    #[serde(default)]
+    pub operators: BTreeMap<String, OperatorSummary>,
+    /// The trailer concluding the result file the report is written to, once the run
+    /// completed or was interrupted.
+    #[serde(default)]
+    pub trailer: Option<ReportTrailer>,
+}
+
+impl MutationReport {
+    /// Computes the summary and the summaries of each operator from the records.
+    pub fn summarize(&mut self) {
+        self.summary = MutationSummary::from_records(&self.records, self.flaky_policy);
+        self.operators = OperatorSummary::per_operator(&self.records, self.flaky_policy);
+    }
+
+    /// Adds the results of the given report of a part of the same run, e.g. of a worker of a
+    /// parallel run, to this report. The fields describing the run are kept, and the summaries
+    /// must be computed again with `summarize`.
+    pub fn merge(&mut self, other: MutationReport) {
+        // Destructured exhaustively, so a new field has to be merged or deliberately ignored.
+        let MutationReport {
+            config: _,
+            options_hash: _,
+            time: _,
+            interrupted,
+            git_rev: _,
+            changed_since: _,
+            nothing_to_mutate: _,
+            tool_versions: _,
+            seed: _,
+            test_code: _,
+            flaky_policy: _,
+            records,
+            spec_records,
+            vacuous_specs,
+            timings,
+            summary: _,
+            operators: _,
+            trailer: _,
+        } = other;
+        self.interrupted |= interrupted;
+        self.records.extend(records);
+        self.spec_records.extend(spec_records);
+        self.vacuous_specs.extend(vacuous_specs);
+        self.timings.extend(timings);
+    }
+
+    /// Concludes the report with a trailer matching its records and summary.
+    pub fn conclude(&mut self) {
+        self.trailer = Some(ReportTrailer {
+            complete: !self.interrupted,
+            records: self.records.len(),
+            spec_records: self.spec_records.len(),
+            killed: self.summary.errors,
+            survived: self.summary.survived(self.flaky_policy),
+            timeout: self.summary.timeout,
+        });
+    }
+
+    /// Returns true if the result file the report has been read from was truncated, i.e. it
+    /// lacks the trailer or contains other numbers of records than the trailer.
+    pub fn is_truncated(&self) -> bool {
+        match &self.trailer {
+            Some(trailer) => {
+                trailer.records != self.records.len()
+                    || trailer.spec_records != self.spec_records.len()
+            }
+            None => true,
+        }
+    }
+
+    /// Parses a report from the content of a result file written by the mutation tool, in
+    /// either the text or the JSON output format. The text format does not contain the
+    /// diagnostics and the source text of mutations, so these are empty in the result.
+    pub fn parse(content: &str) -> anyhow::Result<Self> {
+        if content.trim_start().starts_with('{') {
+            return Ok(serde_json::from_str(content)?);
+        }
+        let mut report = MutationReport::default();
+        for (i, line) in content.lines().enumerate() {
+            if let Some(header) = line.strip_prefix('#') {
+                let (key, value) = header.split_once(':').unwrap_or((header, ""));
+                let value = value.trim();
+                match key.trim() {
+                    "config" => report.config = value.to_string(),
+                    "options" => report.options_hash = value.to_string(),
+                    "time" => report.time = value.to_string(),
+                    "git rev" if value != "unknown" => report.git_rev = Some(value.to_string()),
+                    "versions" => {
+                        for version in value.split_whitespace() {
+                            let (tool, version) = version
+                                .split_once('=')
+                                .ok_or_else(|| anyhow!("invalid tool version `{}`", version))?;
+                            report
+                                .tool_versions
+                                .insert(tool.to_string(), version.to_string());
+                        }
+                    }
+                    "seed" => report.seed = value.parse()?,
+                    "flaky" => report.flaky_policy = value.parse()?,
+                    "changed" => report.changed_since = Some(value.to_string()),
+                    "status" => {
+                        report.interrupted = value == "interrupted";
+                        report.nothing_to_mutate = value == "nothing to mutate";
+                    }
+                    "test code" => report.test_code = value == "included",
+                    "vacuous" => report
+                        .vacuous_specs
+                        .extend(value.split_whitespace().map(str::to_string)),
+                    "timing" => report.timings.push(TimingRecord::parse(value)?),
+                    "trailer" => report.trailer = Some(ReportTrailer::parse(value)?),
+                    _ => {}
+                }
+                continue;
+            }
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let parse_ms = |s: &str| {
+                s.parse::<u64>()
+                    .map_err(|_| anyhow!("line {}: invalid duration `{}`", i + 1, s))
+            };
+            // Result files written before memory was recorded lack the last column.
+            let parse_mem = |rest: &[&str]| match rest {
+                [] => Ok(0),
+                [s] => s
+                    .parse::<u64>()
+                    .map_err(|_| anyhow!("line {}: invalid memory `{}`", i + 1, s)),
+                _ => bail!("line {}: unrecognized record `{}`", i + 1, line),
+            };
+            // The text format only records that the backends of an unstable mutation disagree,
+            // which is read back as a mutation killed by the primary backend.
+            let parse_status = |s: &str| match s {
+                "unstable" => Ok((MutationStatus::Errors, Some(MutationStatus::Ok))),
+                _ => s
+                    .parse()
+                    .map(|status| (status, None))
+                    .map_err(|_| anyhow!("line {}: invalid status `{}`", i + 1, s)),
+            };
+            match fields.as_slice() {
+                [] => {}
+                [function, kind @ "ensures", duration, status, location, ..]
+                | [function, kind @ "aborts_if", duration, status, location, ..] => {
+                    let mut site = MutationSite::from_location(location)
+                        .ok_or_else(|| anyhow!("line {}: invalid location", i + 1))?;
+                    // The source text of the condition is the remainder of the line.
+                    site.before = line[line.find(location).unwrap() + location.len()..]
+                        .trim()
+                        .to_string();
+                    report.spec_records.push(SpecMutationRecord {
+                        module: module_of_function(function),
+                        function: function.to_string(),
+                        kind: kind.to_string(),
+                        site,
+                        duration_ms: parse_ms(duration)?,
+                        status: status.to_string(),
+                        diagnostics: vec![],
+                    });
+                }
+                [function, operator, timeout, duration, status, code, location, id, rest @ ..]
+                    if operator.parse::<u64>().is_err() =>
+                {
+                    let module = match id.rsplitn(4, "::").nth(3) {
+                        Some(module) => module.to_string(),
+                        None => module_of_function(function),
+                    };
+                    let (status, cross_check_status) = parse_status(status)?;
+                    report.records.push(MutationRecord {
+                        function: function.to_string(),
+                        mutant_id: if *id == "-" {
+                            String::new()
+                        } else {
+                            id.to_string()
+                        },
+                        operator: operator.to_string(),
+                        site: MutationSite::from_location(location),
+                        peak_mem_mb: parse_mem(rest)?,
+                        cross_check_status,
+                        test_code: *code == "test",
+                        ..MutationRecord::new(module, timeout.parse()?, parse_ms(duration)?, status)
+                    });
+                }
+                // Records which concern a whole module have no function nor operator.
+                [module, timeout, duration, status, code, _, _, rest @ ..] => {
+                    report.records.push(MutationRecord {
+                        peak_mem_mb: parse_mem(rest)?,
+                        test_code: *code == "test",
+                        ..MutationRecord::new(
+                            module.to_string(),
+                            timeout.parse()?,
+                            parse_ms(duration)?,
+                            parse_status(status)?.0,
+                        )
+                    });
+                }
+                _ => bail!("line {}: unrecognized record `{}`", i + 1, line),
+            }
+        }
+        report.summarize();
+        Ok(report)
+    }
+}
+
+/// Returns the name of the module of the given function name of the form `MODULE::FUN`.
+fn module_of_function(function: &str) -> String {
+    function
+        .rsplit_once("::")
+        .map_or("", |(module, _)| module)
+        .to_string()
+}
+
+/// A data record of a mutation result.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MutationRecord {
+    /// The full name of the module containing the mutated function.
+    pub module: String,
+    /// The full name of the mutated function. Empty for records which concern the whole module.
+    pub function: String,
+    /// The id of the mutant as given by `Mutant::id`. Empty for records which concern the
+    /// whole module.
+    #[serde(default)]
+    pub mutant_id: String,
+    /// The mutation operator which produced this mutation.
+    #[serde(default)]
+    pub operator: String,
+    /// The hard timeout in seconds verification of this mutation was run with.
+    #[serde(default)]
+    pub timeout_secs: u64,
+    /// Where the mutation was applied, if known. Not present for records which concern the
+    /// whole module.
+    #[serde(default)]
+    pub site: Option<MutationSite>,
+    /// The time verification took, in milliseconds.
+    pub duration_ms: u64,
+    /// The peak resident memory of the processes of the prover backend during verification, in
+    /// MiB. Only measured on Linux.
+    #[serde(default)]
+    pub peak_mem_mb: u64,
+    /// The verification status.
+    pub status: MutationStatus,
+    /// The verification status reported by the second backend, if cross-checking was enabled.
+    #[serde(default)]
+    pub cross_check_status: Option<MutationStatus>,
+    /// Whether the mutated function is test code.
+    pub test_code: bool,
+    /// The diagnostics reported by the prover for this mutation.
+    pub diagnostics: Vec<String>,
+    /// The directory the artifacts of this mutation are kept in, if any.
+    #[serde(default)]
+    pub artifacts: Option<String>,
+    /// The results of the individual verifications if the mutation has been verified
+    /// repeatedly, in the order they were run.
+    #[serde(default)]
+    pub runs: Vec<VerificationRun>,
+    /// The result of the retry with an escalated timeout, if the mutation timed out and
+    /// timeouts are retried. The other fields describe the first attempt.
+    #[serde(default)]
+    pub timeout_retry: Option<TimeoutRetry>,
+    /// What was verified for the mutation, one of `function`, `module`, `package`, or
+    /// `callers`, if it was verified.
+    #[serde(default)]
+    pub verify_scope: Option<String>,
+    /// The output of translating the mutation, such as warnings of the prover, truncated to
+    /// the size limit of the mutation options.
+    #[serde(default)]
+    pub log: String,
+}
+
+impl MutationRecord {
+    /// Returns a record of the given status which concerns the whole module, e.g. because its
+    /// unmutated code does not verify. The details of the verification are left empty.
+    pub fn new(
+        module: String,
+        timeout_secs: u64,
+        duration_ms: u64,
+        status: MutationStatus,
+    ) -> Self {
+        MutationRecord {
+            module,
+            function: String::new(),
+            mutant_id: String::new(),
+            operator: String::new(),
+            timeout_secs,
+            site: None,
+            duration_ms,
+            peak_mem_mb: 0,
+            status,
+            cross_check_status: None,
+            test_code: false,
+            diagnostics: vec![],
+            artifacts: None,
+            runs: vec![],
+            timeout_retry: None,
+            verify_scope: None,
+            log: String::new(),
+        }
+    }
+
+    /// Returns a record of the given mutant with the given status, with the details of the
+    /// verification left empty.
+    pub fn of_mutant(
+        mutant: &Mutant,
+        timeout_secs: u64,
+        duration_ms: u64,
+        status: MutationStatus,
+    ) -> Self {
+        MutationRecord {
+            function: mutant.function.clone(),
+            mutant_id: mutant.id(),
+            operator: mutant.operator.clone(),
+            site: mutant.site.clone(),
+            ..Self::new(mutant.module.clone(), timeout_secs, duration_ms, status)
+        }
+    }
+
+    /// Returns the status of the first verification attempt, which for mutations retried
+    /// after a timeout is `timeout`.
+    pub fn first_attempt_status(&self) -> MutationStatus {
+        match self.status {
+            MutationStatus::TimeoutThenCaught
+            | MutationStatus::TimeoutThenSurvived
+            | MutationStatus::TimeoutFinal => MutationStatus::Timeout,
+            status => status,
+        }
+    }
+
+    /// Returns true if the mutation was cross-checked and the two backends disagree on whether
+    /// it is killed, which indicates solver instability rather than a property of the specs.
+    pub fn is_unstable(&self) -> bool {
+        match self.cross_check_status {
+            Some(cross_check_status) => {
+                (self.status == MutationStatus::Ok) != (cross_check_status == MutationStatus::Ok)
+            }
+            None => false,
+        }
+    }
+
+    /// Returns a record of the first `add-sub` mutant of `0x42::Arith::add` with the given
+    /// status, for tests to override the fields they depend on.
+    #[cfg(test)]
+    pub(crate) fn test_record(status: MutationStatus) -> Self {
+        MutationRecord {
+            function: "Arith::add".to_string(),
+            mutant_id: "0x42::Arith::add::add-sub::1".to_string(),
+            operator: "add-sub".to_string(),
+            ..Self::new("0x42::Arith".to_string(), 40, 10, status)
+        }
+    }
+}
+
+/// The verification status of a mutation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MutationStatus {
+    /// The mutation verified, i.e. survived.
+    Ok,
+    /// Verification reported errors.
+    Errors,
+    /// Verification timed out.
+    Timeout,
+    /// The prover exceeded the memory limit.
+    Oom,
+    /// Repeated verifications of the mutation disagree.
+    Flaky,
+    /// The backend was killed after exceeding the hard timeout by the grace period.
+    Hung,
+    /// The mutation timed out and was caught when retried with an escalated timeout.
+    TimeoutThenCaught,
+    /// The mutation timed out and survived when retried with an escalated timeout.
+    TimeoutThenSurvived,
+    /// The mutation timed out again when retried with an escalated timeout.
+    TimeoutFinal,
+    /// Verification only reported errors in a module importing the mutated one.
+    CaughtDownstream,
+    /// The module was not mutated since its unmutated code does not verify.
+    BaselineFailed,
+    /// The module was not mutated since its unmutated code verifies too slowly.
+    SkippedSlow,
+    /// The mutation was not verified since its module exceeded its time budget.
+    SkippedBudget,
+    /// The mutation was not verified since the specs of its function are vacuous.
+    VacuousSpec,
+    /// The mutation was not verified since it does not change the generated Boogie program.
+    Equivalent,
+    /// The mutation of a previous run could not be reconstructed anymore.
+    Stale,
+}
+
+impl MutationStatus {
+    /// All statuses, in the order they are declared.
+    pub const ALL: [MutationStatus; 16] = [
+        MutationStatus::Ok,
+        MutationStatus::Errors,
+        MutationStatus::Timeout,
+        MutationStatus::Oom,
+        MutationStatus::Flaky,
+        MutationStatus::Hung,
+        MutationStatus::TimeoutThenCaught,
+        MutationStatus::TimeoutThenSurvived,
+        MutationStatus::TimeoutFinal,
+        MutationStatus::CaughtDownstream,
+        MutationStatus::BaselineFailed,
+        MutationStatus::SkippedSlow,
+        MutationStatus::SkippedBudget,
+        MutationStatus::VacuousSpec,
+        MutationStatus::Equivalent,
+        MutationStatus::Stale,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MutationStatus::Ok => "ok",
+            MutationStatus::Errors => "errors",
+            MutationStatus::Timeout => "timeout",
+            MutationStatus::Oom => "oom",
+            MutationStatus::Flaky => "flaky",
+            MutationStatus::Hung => "hung",
+            MutationStatus::TimeoutThenCaught => "timeout_then_caught",
+            MutationStatus::TimeoutThenSurvived => "timeout_then_survived",
+            MutationStatus::TimeoutFinal => "timeout_final",
+            MutationStatus::CaughtDownstream => "caught_downstream",
+            MutationStatus::BaselineFailed => "baseline_failed",
+            MutationStatus::SkippedSlow => "skipped_slow",
+            MutationStatus::SkippedBudget => "skipped_budget",
+            MutationStatus::VacuousSpec => "vacuous_spec",
+            MutationStatus::Equivalent => "equivalent",
+            MutationStatus::Stale => "stale",
+        }
+    }
+}
+
+impl fmt::Display for MutationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl std::str::FromStr for MutationStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match MutationStatus::ALL
+            .iter()
+            .find(|status| status.as_str() == s)
+        {
+            Some(status) => Ok(*status),
+            None => bail!("unknown mutation status `{}`", s),
+        }
+    }
+}
+
+/// The result of one of the repeated verifications of a mutation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VerificationRun {
+    /// The verification status, one of `ok`, `errors`, `timeout`, or `oom`.
+    pub status: MutationStatus,
+    /// The time verification took, in milliseconds.
+    pub duration_ms: u64,
+}
+
+/// The result of retrying the verification of a mutation which timed out with an escalated
+/// timeout.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TimeoutRetry {
+    /// The escalated hard timeout in seconds.
+    pub timeout_secs: u64,
+    /// The verification status of the retry, one of `ok`, `errors`, `timeout`, or `oom`.
+    pub status: MutationStatus,
+    /// The time the retry took, in milliseconds.
+    pub duration_ms: u64,
+}
+
+/// Determines how flaky mutations, for which repeated verifications disagree, count towards
+/// the kill rate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FlakyPolicy {
+    /// Flaky mutations are left out of the kill rate.
+    Exclude,
+    /// Flaky mutations count as surviving, so the kill rate is a lower bound.
+    Survived,
+}
+
+impl Default for FlakyPolicy {
+    fn default() -> Self {
+        FlakyPolicy::Exclude
+    }
+}
+
+impl FlakyPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FlakyPolicy::Exclude => "exclude",
+            FlakyPolicy::Survived => "survived",
+        }
+    }
+}
+
+impl std::str::FromStr for FlakyPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "exclude" => Ok(FlakyPolicy::Exclude),
+            "survived" => Ok(FlakyPolicy::Survived),
+            _ => bail!("unknown flaky policy `{}`", s),
+        }
+    }
+}
+
+/// A data record of the time verification of the unmutated code of a module or function took.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimingRecord {
+    /// The full name of the module.
+    pub module: String,
+    /// The full name of the function, or empty if the whole module has been verified.
+    pub function: String,
+    /// The time verification took, in milliseconds.
+    pub duration_ms: u64,
+    /// The verification status, one of `ok`, `errors`, `timeout`, or `oom`.
+    pub status: MutationStatus,
+}
+
+impl TimingRecord {
+    /// Returns the line representing this record in the text output format, which is
+    /// `MODULE FUNCTION DURATION STATUS`, with `-` for the function of a module.
+    pub fn to_line(&self) -> String {
+        format!(
+            "{} {} {} {}",
+            self.module,
+            if self.function.is_empty() {
+                "-"
+            } else {
+                &self.function
+            },
+            self.duration_ms,
+            self.status
+        )
+    }
+
+    /// Parses a record from a line of the text output format as written by `to_line`.
+    pub fn parse(line: &str) -> anyhow::Result<Self> {
+        match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [module, function, duration, status] => Ok(Self {
+                module: module.to_string(),
+                function: if *function == "-" {
+                    String::new()
+                } else {
+                    function.to_string()
+                },
+                duration_ms: duration.parse()?,
+                status: status.parse()?,
+            }),
+            _ => bail!("unrecognized timing `{}`", line),
+        }
+    }
+}
+
+/// The record concluding a result file, written once the run completed or was interrupted. While
+/// a run is in progress, its records are written to the result file as they are produced, so a
+/// result file without a trailer is the truncated output of a run which died.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportTrailer {
+    /// Whether the run completed, as opposed to having been interrupted.
+    pub complete: bool,
+    /// The number of mutation records in the result file.
+    pub records: usize,
+    /// The number of spec mutation records in the result file.
+    pub spec_records: usize,
+    /// The number of killed mutations.
+    pub killed: usize,
+    /// The number of surviving mutations, according to the flaky policy of the run.
+    pub survived: usize,
+    /// The number of mutations which timed out.
+    pub timeout: usize,
+}
+
+impl ReportTrailer {
+    /// Returns the line representing the trailer in the text output format, which is
+    /// `complete` or `interrupted` followed by the totals as `KEY=VALUE` pairs.
+    pub fn to_line(&self) -> String {
+        format!(
+            "{} records={} spec_records={} killed={} survived={} timeout={}",
+            if self.complete {
+                "complete"
+            } else {
+                "interrupted"
+            },
+            self.records,
+            self.spec_records,
+            self.killed,
+            self.survived,
+            self.timeout
+        )
+    }
+
+    /// Parses a trailer from a line of the text output format as written by `to_line`.
+    pub fn parse(line: &str) -> anyhow::Result<Self> {
+        let mut fields = line.split_whitespace();
+        let mut trailer = ReportTrailer {
+            complete: match fields.next() {
+                Some("complete") => true,
+                Some("interrupted") => false,
+                _ => bail!("unrecognized trailer `{}`", line),
+            },
+            ..ReportTrailer::default()
+        };
+        for field in fields {
+            let (key, value) = field
+                .split_once('=')
+                .ok_or_else(|| anyhow!("unrecognized trailer field `{}`", field))?;
+            let value = value.parse()?;
+            match key {
+                "records" => trailer.records = value,
+                "spec_records" => trailer.spec_records = value,
+                "killed" => trailer.killed = value,
+                "survived" => trailer.survived = value,
+                "timeout" => trailer.timeout = value,
+                _ => bail!("unrecognized trailer field `{}`", field),
+            }
+        }
+        Ok(trailer)
+    }
+}
+
+/// A data record of the result of dropping a condition from the spec of a function.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SpecMutationRecord {
+    /// The full name of the module containing the function.
+    pub module: String,
+    /// The full name of the function whose spec contains the condition.
+    pub function: String,
+    /// The kind of the condition, either `ensures` or `aborts_if`.
+    pub kind: String,
+    /// Where the condition is located. `before` is the source text of the condition, `after`
+    /// the one it is replaced by to drop it.
+    pub site: MutationSite,
+    /// The time verification of the function and its callers took, in milliseconds.
+    pub duration_ms: u64,
+    /// `needed` if dropping the condition leads to verification errors, `redundant` if
+    /// everything still verifies, or `timeout`.
+    pub status: String,
+    /// The diagnostics reported by the prover with the condition dropped.
+    pub diagnostics: Vec<String>,
+}
+
+/// The source location of a mutated expression, together with its source text before and
+/// after the mutation.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MutationSite {
+    /// The source file containing the expression.
+    pub file: String,
+    /// The line of the start of the expression, starting at 1.
+    pub line: usize,
+    /// The column of the start of the expression, starting at 1.
+    pub column: usize,
+    /// The source text of the expression, e.g. `a + b`.
+    pub before: String,
+    /// The source text of the expression with the mutation applied, e.g. `a - b`. Empty if it
+    /// could not be determined.
+    pub after: String,
+    /// A description of the mutation at this site which is more specific than the one of its
+    /// operator, e.g. naming the mutated call. Empty if the operator has none.
+    #[serde(default)]
+    pub description: String,
+}
+
+impl MutationSite {
+    /// Returns the location in the form `file:line:column`.
+    pub fn location(&self) -> String {
+        format!("{}:{}:{}", self.file, self.line, self.column)
+    }
+
+    /// Parses a location of the form `file:line:column` into a site without source text.
+    pub fn from_location(location: &str) -> Option<Self> {
+        let mut parts = location.rsplitn(3, ':');
+        let column = parts.next()?.parse().ok()?;
+        let line = parts.next()?.parse().ok()?;
+        let file = parts.next()?.to_string();
+        Some(Self {
+            file,
+            line,
+            column,
+            before: String::new(),
+            after: String::new(),
+            description: String::new(),
+        })
+    }
+}
+
+/// A single mutation, given by the function it is applied to, the operator, and the index of
+/// the mutation among those the operator produces for the function, starting at 1.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mutant {
+    /// The full name of the module containing the mutated function.
+    pub module: String,
+    /// The full name of the mutated function.
+    pub function: String,
+    /// The mutation operator which produces this mutation.
+    pub operator: String,
+    /// The index of the mutation among those of the operator in the function.
+    pub index: usize,
+    /// Where the mutation is applied, if known.
+    #[serde(default)]
+    pub site: Option<MutationSite>,
+}
+
+impl Mutant {
+    /// Returns an identifier of the mutant of the form `MODULE::FUN::OPERATOR::INDEX@HASH`,
+    /// where `HASH` is the content hash of the mutated expression, if its site is known. The
+    /// identifier only depends on the source of the function, so it is stable under changes
+    /// elsewhere, while a change of the mutated expression also changes the identifier.
+    pub fn id(&self) -> String {
+        let simple_name = self.function.rsplit("::").next().unwrap_or_default();
+        let mut id = format!(
+            "{}::{}::{}::{}",
+            self.module, simple_name, self.operator, self.index
+        );
+        if let Some(hash) = self.content_hash() {
+            id.push('@');
+            id.push_str(&hash);
+        }
+        id
+    }
+
+    /// Returns a hash of the source text of the mutated expression, ignoring differences in
+    /// whitespace, or None if the text is not known.
+    pub fn content_hash(&self) -> Option<String> {
+        let before = &self.site.as_ref()?.before;
+        if before.trim().is_empty() {
+            return None;
+        }
+        let normalized = before.split_whitespace().collect::<Vec<_>>().join(" ");
+        let digest = Sha256::digest(normalized.as_bytes());
+        Some(hex::encode(&digest[..4]))
+    }
+
+    /// Returns a hash of the identifier of the mutant. Unlike the hasher of the standard
+    /// library, this is stable across runs and platforms, so it can be used to partition
+    /// mutants between separate runs.
+    pub fn stable_hash(&self) -> u64 {
+        let digest = Sha256::digest(self.id().as_bytes());
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        u64::from_be_bytes(bytes)
+    }
+}
+
+/// Aggregate statistics over the results of a mutation run.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MutationSummary {
+    /// The number of mutations which have been verified.
+    pub total: usize,
+    /// The number of modules skipped because their unmutated code does not verify.
+    #[serde(default)]
+    pub baseline_failed: usize,
+    /// The number of modules skipped because their unmutated code verifies too slowly.
+    #[serde(default)]
+    pub skipped_slow: usize,
+    /// The number of mutations which have not been verified because their module exceeded its
+    /// time budget.
+    #[serde(default)]
+    pub skipped_budget: usize,
+    /// The number of mutations which have not been verified because the specs of their
+    /// function are vacuous.
+    #[serde(default)]
+    pub vacuous_spec: usize,
+    /// The number of mutations of a previous run which could not be reconstructed anymore.
+    #[serde(default)]
+    pub stale: usize,
+    /// The number of mutations which have not been verified because they do not change the
+    /// generated Boogie program.
+    #[serde(default)]
+    pub equivalent: usize,
+    /// The number of mutations which verified, i.e. survived.
+    pub ok: usize,
+    /// The number of mutations for which verification reported errors.
+    pub errors: usize,
+    /// The number of mutations for which verification only reported errors in modules
+    /// importing the mutated one, which count as killed as well.
+    #[serde(default)]
+    pub caught_downstream: usize,
+    /// The number of mutations for which verification timed out, including those retried with
+    /// an escalated timeout, so the kill rate only depends on the first attempts.
+    pub timeout: usize,
+    /// The number of timed out mutations which were caught when retried with an escalated
+    /// timeout.
+    #[serde(default)]
+    pub timeout_then_caught: usize,
+    /// The number of timed out mutations which survived when retried with an escalated timeout.
+    #[serde(default)]
+    pub timeout_then_survived: usize,
+    /// The number of timed out mutations which timed out again when retried with an escalated
+    /// timeout.
+    #[serde(default)]
+    pub timeout_final: usize,
+    /// The number of mutations for which verification exceeded the memory limit.
+    #[serde(default)]
+    pub oom: usize,
+    /// The number of mutations for which the backend was killed since it did not terminate.
+    #[serde(default)]
+    pub hung: usize,
+    /// The number of mutations for which the cross-checking backend disagrees.
+    #[serde(default)]
+    pub unstable: usize,
+    /// The number of mutations for which repeated verifications disagree.
+    #[serde(default)]
+    pub flaky: usize,
+    /// The percentage of mutations which have been killed, i.e. did not verify.
+    pub kill_rate: f64,
+    /// The peak memory of the prover backend over all records, in MiB.
+    #[serde(default)]
+    pub peak_mem_mb: u64,
+}
+
+impl MutationSummary {
+    /// Compute the summary for the given records, counting flaky mutations according to the
+    /// given policy.
+    pub fn from_records(records: &[MutationRecord], flaky_policy: FlakyPolicy) -> Self {
+        let count = |status| records.iter().filter(|r| r.status == status).count();
+        let baseline_failed = count(MutationStatus::BaselineFailed);
+        let skipped_slow = count(MutationStatus::SkippedSlow);
+        let skipped_budget = count(MutationStatus::SkippedBudget);
+        let vacuous_spec = count(MutationStatus::VacuousSpec);
+        let stale = count(MutationStatus::Stale);
+        let equivalent = count(MutationStatus::Equivalent);
+        let total = records.len()
+            - baseline_failed
+            - skipped_slow
+            - skipped_budget
+            - vacuous_spec
+            - stale
+            - equivalent;
+        let ok = count(MutationStatus::Ok);
+        let flaky = count(MutationStatus::Flaky);
+        let timeout_then_caught = count(MutationStatus::TimeoutThenCaught);
+        let timeout_then_survived = count(MutationStatus::TimeoutThenSurvived);
+        let timeout_final = count(MutationStatus::TimeoutFinal);
+        let scored = match flaky_policy {
+            FlakyPolicy::Exclude => total - flaky,
+            FlakyPolicy::Survived => total,
+        };
+        let kill_rate = if scored > 0 {
+            (total - ok - flaky) as f64 * 100.0 / scored as f64
+        } else {
+            0.0
+        };
+        Self {
+            total,
+            baseline_failed,
+            skipped_slow,
+            skipped_budget,
+            vacuous_spec,
+            stale,
+            equivalent,
+            ok,
+            errors: count(MutationStatus::Errors),
+            caught_downstream: count(MutationStatus::CaughtDownstream),
+            timeout: count(MutationStatus::Timeout)
+                + timeout_then_caught
+                + timeout_then_survived
+                + timeout_final,
+            timeout_then_caught,
+            timeout_then_survived,
+            timeout_final,
+            oom: count(MutationStatus::Oom),
+            hung: count(MutationStatus::Hung),
+            unstable: records.iter().filter(|r| r.is_unstable()).count(),
+            flaky,
+            kill_rate,
+            peak_mem_mb: records.iter().map(|r| r.peak_mem_mb).max().unwrap_or(0),
+        }
+    }
+
+    /// Returns the number of mutations which count as surviving under the given policy.
+    pub fn survived(&self, flaky_policy: FlakyPolicy) -> usize {
+        match flaky_policy {
+            FlakyPolicy::Exclude => self.ok,
+            FlakyPolicy::Survived => self.ok + self.flaky,
+        }
+    }
+
+    /// Compute the summaries of the records of each module, by module name.
+    pub fn per_module(
+        records: &[MutationRecord],
+        flaky_policy: FlakyPolicy,
+    ) -> BTreeMap<String, Self> {
+        let mut by_module: BTreeMap<String, Vec<MutationRecord>> = BTreeMap::new();
+        for record in records {
+            by_module
+                .entry(record.module.clone())
+                .or_default()
+                .push(record.clone());
+        }
+        by_module
+            .into_iter()
+            .map(|(module, records)| (module, Self::from_records(&records, flaky_policy)))
+            .collect()
+    }
+}
+
+/// Aggregate statistics over the mutations produced by one mutation operator.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct OperatorSummary {
+    /// The statistics over the mutations of the operator in all modules.
+    pub summary: MutationSummary,
+    /// The statistics over the mutations of the operator in each module, by module name.
+    pub modules: BTreeMap<String, MutationSummary>,
+    /// The number of modules in which all surviving mutations are produced by this operator,
+    /// i.e. the holes in the specs of which no other operator finds.
+    pub sole_survivor_modules: usize,
+}
+
+impl OperatorSummary {
+    /// Computes the summaries of the records of each operator, by operator name, counting
+    /// flaky mutations according to the given policy. Records not of a mutation, e.g. of
+    /// modules skipped as a whole, are not attributed to any operator.
+    pub fn per_operator(
+        records: &[MutationRecord],
+        flaky_policy: FlakyPolicy,
+    ) -> BTreeMap<String, Self> {
+        let mut by_operator: BTreeMap<String, Vec<MutationRecord>> = BTreeMap::new();
+        for record in records.iter().filter(|r| !r.operator.is_empty()) {
+            by_operator
+                .entry(record.operator.clone())
+                .or_default()
+                .push(record.clone());
+        }
+        // The operators which produce surviving mutations in each module.
+        let mut surviving: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+        for record in records.iter().filter(|r| !r.operator.is_empty()) {
+            let survived = record.status == MutationStatus::Ok
+                || (record.status == MutationStatus::Flaky
+                    && flaky_policy == FlakyPolicy::Survived);
+            if survived {
+                surviving
+                    .entry(&record.module)
+                    .or_default()
+                    .insert(&record.operator);
+            }
+        }
+        by_operator
+            .into_iter()
+            .map(|(operator, records)| {
+                let sole_survivor_modules = surviving
+                    .values()
+                    .filter(|operators| {
+                        operators.len() == 1 && operators.contains(operator.as_str())
+                    })
+                    .count();
+                let summary = Self {
+                    summary: MutationSummary::from_records(&records, flaky_policy),
+                    modules: MutationSummary::per_module(&records, flaky_policy),
+                    sole_survivor_modules,
+                };
+                (operator, summary)
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for MutationSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} mutations: {} ok, {} errors, {} timeout, {} unstable, kill rate {:.2}%",
+            self.total, self.ok, self.errors, self.timeout, self.unstable, self.kill_rate
+        )?;
+        if self.caught_downstream > 0 {
+            write!(f, ", {} caught downstream", self.caught_downstream)?;
+        }
+        if self.oom > 0 {
+            write!(f, ", {} out of memory", self.oom)?;
+        }
+        if self.hung > 0 {
+            write!(f, ", {} hung", self.hung)?;
+        }
+        if self.flaky > 0 {
+            write!(f, ", {} flaky", self.flaky)?;
+        }
+        if self.baseline_failed > 0 {
+            write!(f, ", {} modules failed baseline", self.baseline_failed)?;
+        }
+        if self.skipped_slow > 0 {
+            write!(f, ", {} slow modules skipped", self.skipped_slow)?;
+        }
+        if self.skipped_budget > 0 {
+            write!(f, ", {} mutations skipped over budget", self.skipped_budget)?;
+        }
+        if self.vacuous_spec > 0 {
+            write!(
+                f,
+                ", {} mutations skipped for vacuous specs",
+                self.vacuous_spec
+            )?;
+        }
+        if self.stale > 0 {
+            write!(f, ", {} stale", self.stale)?;
+        }
+        if self.equivalent > 0 {
+            write!(f, ", {} equivalent mutations pruned", self.equivalent)?;
+        }
+        let retried = self.timeout_then_caught + self.timeout_then_survived + self.timeout_final;
+        if retried > 0 {
+            write!(
+                f,
+                ", {} timeouts retried: {} caught, {} survived, {} timed out again",
+                retried, self.timeout_then_caught, self.timeout_then_survived, self.timeout_final
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(
+        status: MutationStatus,
+        cross_check_status: Option<MutationStatus>,
+    ) -> MutationRecord {
+        MutationRecord {
+            timeout_secs: 100,
+            duration_ms: 0,
+            cross_check_status,
+            ..MutationRecord::test_record(status)
+        }
+    }
+
+    #[test]
+    fn merged_reports_keep_the_run_and_add_the_results() {
+        let mut report = MutationReport {
+            config: "default".to_string(),
+            seed: 7,
+            records: vec![record(MutationStatus::Errors, None)],
+            ..MutationReport::default()
+        };
+        let worker = MutationReport {
+            seed: 8,
+            interrupted: true,
+            records: vec![record(MutationStatus::Ok, None)],
+            vacuous_specs: vec!["Arith::sub".to_string()],
+            ..report.clone()
+        };
+        report.merge(worker);
+        report.summarize();
+        assert_eq!((report.config.as_str(), report.seed), ("default", 7));
+        assert!(report.interrupted);
+        assert_eq!(report.records.len(), 2);
+        assert_eq!(report.vacuous_specs, vec!["Arith::sub"]);
+        assert_eq!(report.summary.ok, 1);
+    }
+
+    #[test]
+    fn summary_counts_unstable_mutations() {
+        let records = vec![
+            record(MutationStatus::Errors, None),
+            record(MutationStatus::Errors, Some(MutationStatus::Timeout)),
+            record(MutationStatus::Ok, Some(MutationStatus::Errors)),
+            record(MutationStatus::Ok, Some(MutationStatus::Ok)),
+        ];
+        let summary = MutationSummary::from_records(&records, FlakyPolicy::Exclude);
+        assert_eq!(summary.total, 4);
+        assert_eq!(summary.ok, 2);
+        assert_eq!(summary.unstable, 1);
+        assert!((summary.kill_rate - 50.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn flaky_mutations_are_scored_by_policy() {
+        let records = vec![
+            record(MutationStatus::Errors, None),
+            record(MutationStatus::Ok, None),
+            record(MutationStatus::Flaky, None),
+            record(MutationStatus::Flaky, None),
+        ];
+        let excluded = MutationSummary::from_records(&records, FlakyPolicy::Exclude);
+        assert_eq!(excluded.flaky, 2);
+        assert!((excluded.kill_rate - 50.0).abs() < f64::EPSILON);
+        assert_eq!(excluded.survived(FlakyPolicy::Exclude), 1);
+        let pessimistic = MutationSummary::from_records(&records, FlakyPolicy::Survived);
+        assert!((pessimistic.kill_rate - 25.0).abs() < f64::EPSILON);
+        assert_eq!(pessimistic.survived(FlakyPolicy::Survived), 3);
+    }
+
+    #[test]
+    fn site_location_is_formatted() {
+        let site = MutationSite {
+            file: "sources/Arith.move".to_string(),
+            line: 4,
+            column: 9,
+            before: "x + y".to_string(),
+            after: "x - y".to_string(),
+            description: String::new(),
+        };
+        assert_eq!(site.location(), "sources/Arith.move:4:9");
+    }
+
+    #[test]
+    fn mutant_id_uses_simple_function_name() {
+        let mutant = Mutant {
+            module: "0x42::Arith".to_string(),
+            function: "Arith::add".to_string(),
+            operator: "add-sub".to_string(),
+            index: 2,
+            site: None,
+        };
+        assert_eq!(mutant.id(), "0x42::Arith::add::add-sub::2");
+        assert_eq!(mutant.stable_hash(), mutant.clone().stable_hash());
+    }
+
+    #[test]
+    fn mutant_id_includes_content_hash() {
+        let mutant = |before: &str| Mutant {
+            module: "0x42::Arith".to_string(),
+            function: "Arith::add".to_string(),
+            operator: "add-sub".to_string(),
+            index: 1,
+            site: Some(MutationSite {
+                file: "arith.move".to_string(),
+                line: 4,
+                column: 9,
+                before: before.to_string(),
+                after: String::new(),
+                description: String::new(),
+            }),
+        };
+        let id = mutant("x + y").id();
+        let (prefix, hash) = id.split_once('@').unwrap();
+        assert_eq!(prefix, "0x42::Arith::add::add-sub::1");
+        assert_eq!(hash.len(), 8);
+        assert_eq!(mutant("x  +\n y").id(), id);
+        assert_ne!(mutant("y + x").id(), id);
+        assert_eq!(mutant("").id(), prefix);
+    }
+
+    #[test]
+    fn text_report_is_parsed() {
+        let content = "\
+# config   : default
+# options  : 1234
+# time     : 2021-08-01 12:00:00 UTC
+# git rev  : unknown
+# versions : boogie=2.9.0 mutation=0.1.0 z3=4.8.9
+# seed     : 7
+# test code: excluded
+0x42::Slow                                      100        5000  skipped_slow   source  - - 310
+Arith::add                                add-sub      100          20       errors   source  a.move:4:9 0x42::Arith::add::add-sub::1 250
+Arith::sub                                sub-add      100          20           ok   source  a.move:5:9 0x42::Arith::sub::sub-add::1
+Arith::add                                ensures           30    redundant  a.move:8:9 ensures result == x + y
+# summary  : 1 mutations: 0 ok, 1 errors, 0 timeout, 0 unstable, kill rate 100.00%
+";
+        let report = MutationReport::parse(content).unwrap();
+        assert_eq!(report.seed, 7);
+        assert_eq!(report.tool_versions["boogie"], "2.9.0");
+        assert_eq!(report.tool_versions["z3"], "4.8.9");
+        assert_eq!(report.git_rev, None);
+        assert_eq!(report.time, "2021-08-01 12:00:00 UTC");
+        assert_eq!(report.records.len(), 3);
+        assert_eq!(report.records[0].module, "0x42::Slow");
+        assert_eq!(report.records[0].status, MutationStatus::SkippedSlow);
+        assert_eq!(report.records[0].peak_mem_mb, 310);
+        let record = &report.records[1];
+        assert_eq!(record.module, "0x42::Arith");
+        assert_eq!(record.peak_mem_mb, 250);
+        // Records written without the memory column are still accepted.
+        assert_eq!(report.records[2].peak_mem_mb, 0);
+        assert_eq!(record.mutant_id, "0x42::Arith::add::add-sub::1");
+        assert_eq!(record.site.as_ref().unwrap().location(), "a.move:4:9");
+        assert_eq!(report.spec_records.len(), 1);
+        assert_eq!(
+            report.spec_records[0].site.before,
+            "ensures result == x + y"
+        );
+        assert_eq!(report.summary.errors, 1);
+        assert!(MutationReport::parse("Arith::add garbage").is_err());
+    }
+
+    #[test]
+    fn nothing_to_mutate_is_parsed() {
+        let content = "\
+# config   : default
+# changed  : origin/main
+# status   : nothing to mutate
+# test code: excluded
+";
+        let report = MutationReport::parse(content).unwrap();
+        assert_eq!(report.changed_since.as_deref(), Some("origin/main"));
+        assert!(report.nothing_to_mutate);
+        assert!(!report.interrupted);
+        assert!(report.records.is_empty());
+    }
+
+    #[test]
+    fn truncated_files_are_recognized() {
+        let header = "\
+# config   : default
+# test code: excluded
+Arith::add                                add-sub      100          20       errors   source  a.move:4:9 0x42::Arith::add::add-sub::1 250
+Arith::sub                                sub-add      100          20           ok   source  a.move:5:9 0x42::Arith::sub::sub-add::1 250
+";
+        let report = MutationReport::parse(header).unwrap();
+        assert!(report.is_truncated());
+        let mut concluded = report.clone();
+        concluded.conclude();
+        let trailer = concluded.trailer.clone().unwrap();
+        assert!(trailer.complete);
+        assert_eq!(
+            (trailer.records, trailer.killed, trailer.survived),
+            (2, 1, 1)
+        );
+        let content = format!("{}# trailer  : {}\n", header, trailer.to_line());
+        let report = MutationReport::parse(&content).unwrap();
+        assert!(!report.is_truncated());
+        assert_eq!(report.trailer, Some(trailer));
+        // A file missing records listed by the trailer is truncated as well.
+        let content = format!(
+            "{}# trailer  : complete records=3 spec_records=0 killed=1 survived=1 timeout=0\n",
+            header
+        );
+        assert!(MutationReport::parse(&content).unwrap().is_truncated());
+        assert!(ReportTrailer::parse("done records=1").is_err());
+        assert!(ReportTrailer::parse("complete records=x").is_err());
+    }
+
+    #[test]
+    fn statuses_are_written_as_they_are_parsed() {
+        for status in MutationStatus::ALL.iter() {
+            assert_eq!(
+                status.to_string().parse::<MutationStatus>().unwrap(),
+                *status
+            );
+            assert_eq!(
+                serde_json::to_string(status).unwrap(),
+                format!("\"{}\"", status)
+            );
+        }
+        assert_eq!(format!("{:>8}", MutationStatus::Ok), "      ok");
+        assert!("survived".parse::<MutationStatus>().is_err());
+        // The status of a record whose backends disagree is written as `unstable`.
+        let record = MutationRecord {
+            cross_check_status: Some(MutationStatus::Ok),
+            ..record(MutationStatus::Errors, None)
+        };
+        let content = format!(
+            "Arith::add add-sub 100 20 unstable source a.move:4:9 {} 250\n",
+            record.mutant_id
+        );
+        let parsed = &MutationReport::parse(&content).unwrap().records[0];
+        assert!(parsed.is_unstable());
+        assert_eq!(parsed.status, record.status);
+        assert!(MutationReport::parse("Arith::add add-sub 100 20 killed source - x 250").is_err());
+    }
+
+    #[test]
+    fn timing_is_parsed_from_line() {
+        let module = TimingRecord {
+            module: "0x42::Arith".to_string(),
+            function: String::new(),
+            duration_ms: 1200,
+            status: MutationStatus::Ok,
+        };
+        let function = TimingRecord {
+            function: "Arith::add".to_string(),
+            duration_ms: 700,
+            ..module.clone()
+        };
+        assert_eq!(TimingRecord::parse(&module.to_line()).unwrap(), module);
+        assert_eq!(TimingRecord::parse(&function.to_line()).unwrap(), function);
+        assert!(TimingRecord::parse("0x42::Arith 1200").is_err());
+    }
+
+    #[test]
+    fn summary_excludes_skipped_modules() {
+        let mut skipped = record(MutationStatus::SkippedSlow, None);
+        skipped.function = String::new();
+        let mut failed = record(MutationStatus::BaselineFailed, None);
+        failed.function = String::new();
+        let records = vec![skipped, failed, record(MutationStatus::Errors, None)];
+        let summary = MutationSummary::from_records(&records, FlakyPolicy::Exclude);
+        assert_eq!(summary.total, 1);
+        assert_eq!(summary.baseline_failed, 1);
+        assert_eq!(summary.skipped_slow, 1);
+        assert!((summary.kill_rate - 100.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn summary_excludes_mutations_skipped_over_budget() {
+        let records = vec![
+            record(MutationStatus::Ok, None),
+            record(MutationStatus::SkippedBudget, None),
+            record(MutationStatus::SkippedBudget, None),
+        ];
+        let summary = MutationSummary::from_records(&records, FlakyPolicy::Exclude);
+        assert_eq!(summary.total, 1);
+        assert_eq!(summary.skipped_budget, 2);
+        assert!(summary
+            .to_string()
+            .ends_with(", 2 mutations skipped over budget"));
+    }
+
+    #[test]
+    fn vacuous_specs_are_parsed() {
+        let content = "\
+# config   : default
+# vacuous  : Arith::add Arith::sub
+Arith::add                                add-sub      100           0 vacuous_spec   source  a.move:4:9 0x42::Arith::add::add-sub::1
+";
+        let report = MutationReport::parse(content).unwrap();
+        assert_eq!(report.vacuous_specs, vec!["Arith::add", "Arith::sub"]);
+        assert_eq!(report.summary.total, 0);
+        assert_eq!(report.summary.vacuous_spec, 1);
+    }
+
+    #[test]
+    fn summary_is_aggregated_per_operator() {
+        let with = |module: &str, operator: &str, status: MutationStatus| MutationRecord {
+            module: module.to_string(),
+            operator: operator.to_string(),
+            ..record(status, None)
+        };
+        let mut skipped = record(MutationStatus::SkippedSlow, None);
+        skipped.operator = String::new();
+        let records = vec![
+            with("0x42::A", "add-sub", MutationStatus::Ok),
+            with("0x42::A", "sub-add", MutationStatus::Errors),
+            with("0x42::B", "add-sub", MutationStatus::Ok),
+            with("0x42::B", "sub-add", MutationStatus::Ok),
+            with("0x42::C", "sub-add", MutationStatus::Timeout),
+            skipped,
+        ];
+        let operators = OperatorSummary::per_operator(&records, FlakyPolicy::Exclude);
+        assert_eq!(operators.len(), 2);
+        let add_sub = &operators["add-sub"];
+        assert_eq!(add_sub.summary.total, 2);
+        assert_eq!(add_sub.summary.ok, 2);
+        assert_eq!(add_sub.modules.len(), 2);
+        // Only in module A do all survivors come from a single operator.
+        assert_eq!(add_sub.sole_survivor_modules, 1);
+        let sub_add = &operators["sub-add"];
+        assert_eq!(sub_add.summary.errors, 1);
+        assert_eq!(sub_add.summary.timeout, 1);
+        assert_eq!(sub_add.modules["0x42::C"].timeout, 1);
+        assert_eq!(sub_add.sole_survivor_modules, 0);
+    }
+
+    #[test]
+    fn summary_separates_escalated_timeouts() {
+        let records = vec![
+            record(MutationStatus::Ok, None),
+            record(MutationStatus::Timeout, None),
+            record(MutationStatus::TimeoutThenCaught, None),
+            record(MutationStatus::TimeoutThenSurvived, None),
+        ];
+        let summary = MutationSummary::from_records(&records, FlakyPolicy::Exclude);
+        assert_eq!(summary.timeout, 3);
+        assert_eq!(summary.timeout_then_caught, 1);
+        assert_eq!(summary.timeout_then_survived, 1);
+        // The kill rate is the one of the first attempts.
+        assert!((summary.kill_rate - 75.0).abs() < f64::EPSILON);
+        assert!(summary
+            .to_string()
+            .ends_with(", 2 timeouts retried: 1 caught, 1 survived, 0 timed out again"));
+        assert_eq!(records[3].first_attempt_status(), MutationStatus::Timeout);
+    }
+
+    #[test]
+    fn downstream_catches_are_killed_but_counted_apart() {
+        let records = vec![
+            record(MutationStatus::Ok, None),
+            record(MutationStatus::Errors, None),
+            record(MutationStatus::CaughtDownstream, None),
+            record(MutationStatus::CaughtDownstream, None),
+        ];
+        let summary = MutationSummary::from_records(&records, FlakyPolicy::Exclude);
+        assert_eq!(summary.errors, 1);
+        assert_eq!(summary.caught_downstream, 2);
+        assert!((summary.kill_rate - 75.0).abs() < f64::EPSILON);
+        assert!(summary.to_string().contains(", 2 caught downstream"));
+    }
+}
@@ -0,0 +1,125 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// Tests a mutation operator defined outside of the mutation crate.
+
+use bytecode::{
+    function_target::FunctionData,
+    mutation_tester::{MutatedLocation, MutationManager},
+    options::ProverOptions,
+    stackless_bytecode::{Bytecode, Constant, Operation},
+};
+use move_model::{
+    model::{FunctionEnv, GlobalEnv, Loc, VerificationScope},
+    run_model_builder,
+};
+use move_prover::{cli::Options, create_and_process_bytecode};
+use prover_mutation::{
+    mutator::list_mutants,
+    operators::{install_mutation, mark_mutated, MutationOperator, OperatorRegistry},
+    options::MutationOptions,
+};
+
+/// Swaps the operands of subtractions.
+struct SwapOperands;
+
+impl MutationOperator for SwapOperands {
+    fn name(&self) -> &str {
+        "swap-sub"
+    }
+
+    fn description(&self) -> &str {
+        "operands of `-` swapped"
+    }
+
+    fn sites(&self, fun: &FunctionEnv<'_>, data: &FunctionData) -> Vec<(Loc, Option<Constant>)> {
+        data.code
+            .iter()
+            .filter_map(|bc| match bc {
+                Bytecode::Call(attr_id, _, Operation::Sub, _, _) => Some((
+                    data.locations
+                        .get(attr_id)
+                        .cloned()
+                        .unwrap_or_else(|| fun.get_loc()),
+                    None,
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn select(&self, env: &GlobalEnv, index: usize) {
+        install_mutation(env, move |fun, mut data| {
+            let mut remaining = index;
+            for bc in &mut data.code {
+                if let Bytecode::Call(attr_id, _, Operation::Sub, srcs, _) = bc {
+                    remaining -= 1;
+                    if remaining == 0 {
+                        srcs.swap(0, 1);
+                        let loc = data
+                            .locations
+                            .get(&*attr_id)
+                            .cloned()
+                            .unwrap_or_else(|| fun.get_loc());
+                        mark_mutated(fun.module_env.env, loc);
+                        break;
+                    }
+                }
+            }
+            data
+        });
+    }
+
+    fn mutated_text(&self, before: &str, _replacement: Option<&Constant>) -> String {
+        before
+            .split_once(" - ")
+            .map_or_else(String::new, |(x, y)| format!("{} - {}", y, x))
+    }
+}
+
+#[test]
+fn custom_operator_is_listed_and_applied() {
+    let mut registry = OperatorRegistry::default();
+    registry.register(SwapOperands);
+    registry.register_group("swaps", &["swap-sub"]);
+    assert_eq!(
+        registry.resolve(&["swaps".to_string()]).unwrap(),
+        vec!["swap-sub"]
+    );
+    assert_eq!(registry.description("swap-sub"), "operands of `-` swapped");
+
+    let source = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/sources/arith.move");
+    let env = run_model_builder(&[source.to_string()], &[]).unwrap();
+    let mutation_options = MutationOptions {
+        registry,
+        operators: vec!["swap-sub".to_string()],
+        ..MutationOptions::default()
+    };
+    let mutants = list_mutants(&env, &mutation_options);
+    assert_eq!(mutants.len(), 1);
+    let mutant = &mutants[0];
+    assert!(mutant.id().starts_with("0x42::Arith::sub::swap-sub::1@"));
+    let site = mutant.site.as_ref().expect("site of mutant");
+    assert!(site.before.contains("x - y"));
+    assert!(site.after.contains("y - x"));
+
+    // Selecting the mutation applies it when the code of the function is generated.
+    env.set_extension(MutationManager::default());
+    mutation_options
+        .registry
+        .get("swap-sub")
+        .unwrap()
+        .select(&env, mutant.index);
+    let mut options = Options::default();
+    options.prover.verify_scope = VerificationScope::Only(mutant.function.clone());
+    ProverOptions::set(&env, options.prover.clone());
+    create_and_process_bytecode(&options, &env);
+    assert!(env
+        .get_extension::<MutationManager>()
+        .map_or(false, |m| m.mutated));
+    let mutated = env.get_extension::<MutatedLocation>().unwrap();
+    assert_eq!(
+        env.get_source(&mutated.loc).ok(),
+        Some(site.before.as_str())
+    );
+}
@@ -0,0 +1,79 @@
+// Copyright (c) The Diem Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// Tests the mutants of the fixtures and the results of mutating them against golden files.
+// Verification is done by the stub backend, so neither boogie nor a solver is needed. Run with
+// `UPBL=1` to update the golden files.
+
+use move_model::{model::GlobalEnv, run_model_builder};
+use move_prover::cli::Options;
+use move_prover_test_utils::baseline_test::verify_or_update_baseline;
+use prover_mutation::{
+    mutator::{list_mutants, run_mutation},
+    options::MutationOptions,
+    report::MutationSummary,
+};
+use std::path::{Path, PathBuf};
+
+/// The fixtures, one with specs which catch every mutation, and one with specs too weak to
+/// catch any.
+const FIXTURES: &[&str] = &["tight.move", "weak.move"];
+
+fn build_env() -> GlobalEnv {
+    let sources = FIXTURES
+        .iter()
+        .map(|file| format!("{}/tests/sources/{}", env!("CARGO_MANIFEST_DIR"), file))
+        .collect::<Vec<_>>();
+    run_model_builder(&sources, &[]).unwrap()
+}
+
+fn golden_file(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name)
+}
+
+/// Returns the id of a mutant without its content hash, which is covered by unit tests.
+fn base_id(id: &str) -> &str {
+    id.split('@').next().unwrap_or(id)
+}
+
+#[test]
+fn mutants_match_golden_file() {
+    let env = build_env();
+    let mut lines = list_mutants(&env, &MutationOptions::default())
+        .iter()
+        .map(|mutant| {
+            let line = mutant.site.as_ref().map_or(0, |site| site.line);
+            format!("{} line {}", base_id(&mutant.id()), line)
+        })
+        .collect::<Vec<_>>();
+    lines.sort();
+    verify_or_update_baseline(&golden_file("mutants.exp"), &lines.join("\n")).unwrap();
+}
+
+#[test]
+fn report_matches_golden_file() {
+    let env = build_env();
+    let dir = tempfile::tempdir().unwrap();
+    let mut options = Options::default();
+    options.output_path = dir.path().join("output.bpl").display().to_string();
+    let mutation_options = MutationOptions {
+        stub_backend: true,
+        quiet: true,
+        ..MutationOptions::default()
+    };
+    let report = run_mutation(&env, &options, &mutation_options).unwrap();
+
+    let mut lines = report
+        .records
+        .iter()
+        .map(|record| format!("{} {}", base_id(&record.mutant_id), record.status))
+        .collect::<Vec<_>>();
+    lines.sort();
+    for (module, summary) in MutationSummary::per_module(&report.records, report.flaky_policy) {
+        lines.push(format!("{}: {}", module, summary));
+    }
+    lines.push(format!("total: {}", report.summary));
+    verify_or_update_baseline(&golden_file("report.exp"), &lines.join("\n")).unwrap();
+}
@@ -0,0 +1,15 @@
+module 0x42::Arith {
+    // The mutation of this function is caught by its spec.
+    fun add(x: u64, y: u64): u64 {
+        x + y
+    }
+    spec add {
+        aborts_if x + y > MAX_U64;
+        ensures result == x + y; // stub: survives
+    }
+
+    // The mutation of this function survives, as it has no spec.
+    fun sub(x: u64, y: u64): u64 {
+        x - y // stub: survives
+    }
+}
@@ -0,0 +1,18 @@
+module 0x42::Branch {
+    // The negation of the branch is caught by the spec, which determines the result in both
+    // cases.
+    fun max(x: u64, y: u64): u64 {
+        if (x >= y) x else y
+    }
+    spec max {
+        ensures result >= x && result >= y;
+    }
+
+    // The negation of the branch survives, as the spec does not distinguish the cases.
+    fun choose(b: bool): u64 {
+        if (b) 1 else 2
+    }
+    spec choose {
+        ensures result > 0;
+    }
+}
@@ -0,0 +1,30 @@
+module 0x42::Counter {
+    public fun inc(x: u64): u64 {
+        x + 1
+    }
+}
+
+module 0x42::Wallet {
+    use 0x42::Counter;
+
+    public fun deposit(balance: u64): u64 {
+        Counter::inc(balance)
+    }
+    spec deposit {
+        ensures result == balance + 1;
+    }
+}
+
+module 0x42::Bank {
+    use 0x42::Wallet;
+
+    fun pay(balance: u64): u64 {
+        Wallet::deposit(balance)
+    }
+}
+
+module 0x42::Unrelated {
+    fun id(x: u64): u64 {
+        x
+    }
+}
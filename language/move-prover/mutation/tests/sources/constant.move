@@ -0,0 +1,19 @@
+module 0x42::Constant {
+    // The mutations of the constant survive, as the spec only relates the result to the
+    // argument symbolically.
+    fun add_fee(x: u64): u64 {
+        x + 10
+    }
+    spec add_fee {
+        aborts_if x + 10 > MAX_U64;
+        ensures result > x;
+    }
+
+    // The mutations of the constant are caught, as the spec pins down the concrete value.
+    fun initial(): u8 {
+        0
+    }
+    spec initial {
+        ensures result == 0;
+    }
+}
@@ -0,0 +1,10 @@
+module 0x42::Equivalent {
+    fun add(x: u64, y: u64): u64 {
+        x + y
+    }
+
+    // Both arguments are the same value, so swapping them yields the unmutated program.
+    fun double(x: u64): u64 {
+        add(x, x)
+    }
+}
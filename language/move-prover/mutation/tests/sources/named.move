@@ -0,0 +1,6 @@
+module Test::Named {
+    // The module is only found if the named address `Test` is assigned.
+    fun add(x: u64, y: u64): u64 {
+        x + y
+    }
+}
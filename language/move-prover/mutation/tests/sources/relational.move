@@ -0,0 +1,25 @@
+module 0x42::Relational {
+    // The mutation to `<=` is caught by the spec, as it changes the result for equal arguments.
+    fun below(x: u64, bound: u64): bool {
+        x < bound
+    }
+    spec below {
+        ensures result == (x < bound);
+    }
+
+    // The mutation to `>` survives, as the spec does not pin down the boundary.
+    fun at_least(x: u64, bound: u64): bool {
+        x >= bound
+    }
+    spec at_least {
+        ensures x > bound ==> result;
+    }
+
+    // The mutation to `!=` is caught by the spec.
+    fun same(x: u64, y: u64): bool {
+        x == y
+    }
+    spec same {
+        ensures result == (x == y);
+    }
+}
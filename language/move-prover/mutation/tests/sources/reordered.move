@@ -0,0 +1,21 @@
+// The module of `arith.move` with its functions reordered, a function added, and the body of
+// `add` changed.
+module 0x42::Arith {
+    fun mul(x: u64, y: u64): u64 {
+        x * y
+    }
+
+    // The mutation of this function survives, as it has no spec.
+    fun sub(x: u64, y: u64): u64 {
+        x - y
+    }
+
+    // The mutation of this function is caught by its spec.
+    fun add(x: u64, y: u64): u64 {
+        y + x
+    }
+    spec add {
+        aborts_if x + y > MAX_U64;
+        ensures result == x + y;
+    }
+}
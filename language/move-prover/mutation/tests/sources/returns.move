@@ -0,0 +1,30 @@
+module 0x42::Returns {
+    // Negating the result is caught by the spec.
+    fun is_zero(x: u64): bool {
+        x == 0
+    }
+    spec is_zero {
+        ensures result == (x == 0);
+    }
+
+    // Replacing the result by zero survives, as the spec only bounds it from above, while
+    // incrementing it is caught.
+    fun half(x: u64): u64 {
+        x / 2
+    }
+    spec half {
+        ensures result <= x;
+    }
+
+    // Functions returning several values, values of other types, or which are native, are not
+    // mutated.
+    fun pair(x: u64): (u64, u64) {
+        (x, x)
+    }
+
+    fun owner(a: address): address {
+        a
+    }
+
+    native fun hash(x: u64): u64;
+}
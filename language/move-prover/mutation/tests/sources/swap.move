@@ -0,0 +1,44 @@
+module 0x42::Swap {
+    fun sub(x: u64, y: u64): u64 {
+        x - y
+    }
+
+    fun add(x: u64, y: u64): u64 {
+        x + y
+    }
+
+    fun scale(x: u64, double: bool): u64 {
+        if (double) x * 2 else x
+    }
+
+    // Swapping the arguments is caught by the spec, which is not symmetric in them.
+    fun diff(a: u64, b: u64): u64 {
+        sub(a, b)
+    }
+    spec diff {
+        ensures result == a - b;
+    }
+
+    // Swapping the arguments survives, as the spec treats them symmetrically.
+    fun total(a: u64, b: u64): u64 {
+        add(a, b)
+    }
+    spec total {
+        ensures result == a + b;
+    }
+
+    // The arguments of the call have different types, so they are not swapped.
+    fun doubled(a: u64): u64 {
+        scale(a, true)
+    }
+
+    fun lower(x: &u64, y: u64): u64 {
+        if (*x < y) *x else y
+    }
+
+    // One argument is a reference and the other is not, so they are not swapped, even though
+    // the prover passes the reference by value.
+    fun smaller(a: u64, b: u64): u64 {
+        lower(&a, b)
+    }
+}
@@ -0,0 +1,17 @@
+module 0x42::Tight {
+    // Every mutation of this module is caught by its specs.
+    fun add(x: u64, y: u64): u64 {
+        x + y
+    }
+    spec add {
+        aborts_if x + y > MAX_U64;
+        ensures result == x + y;
+    }
+
+    fun lt(x: u64, y: u64): bool {
+        x < y
+    }
+    spec lt {
+        ensures result == (x < y);
+    }
+}
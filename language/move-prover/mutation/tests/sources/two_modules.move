@@ -0,0 +1,11 @@
+module 0x42::Token {
+    fun mint(supply: u64, amount: u64): u64 {
+        supply + amount
+    }
+}
+
+module 0x42::TokenStore {
+    fun withdraw(balance: u64, amount: u64): u64 {
+        balance - amount
+    }
+}
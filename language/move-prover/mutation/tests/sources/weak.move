@@ -0,0 +1,17 @@
+module 0x42::Weak {
+    // The mutations of this module survive, as its specs are too weak to catch them. The
+    // markers tell the stub backend so.
+    fun mul(x: u64, y: u64): u64 {
+        x * y // stub: survives
+    }
+    spec mul {
+        ensures result >= 0;
+    }
+
+    fun ge(x: u64, y: u64): bool {
+        x >= y // stub: survives
+    }
+    spec ge {
+        ensures result ==> x >= y;
+    }
+}
@@ -13,9 +13,12 @@ use channel::{self, message_queues::QueueStyle};
 use diem_config::{
     config::{
         DiscoveryMethod, NetworkConfig, Peer, PeerRole, PeerSet, RateLimitConfig, RoleType,
-        CONNECTION_BACKOFF_BASE, CONNECTIVITY_CHECK_INTERVAL_MS, MAX_CONCURRENT_NETWORK_REQS,
-        MAX_CONNECTION_DELAY_MS, MAX_FRAME_SIZE, MAX_FULLNODE_OUTBOUND_CONNECTIONS,
-        MAX_INBOUND_CONNECTIONS, NETWORK_CHANNEL_SIZE,
+        CONNECTION_BACKOFF_BASE, CONNECTIVITY_CHECK_INTERVAL_MS, DIAL_RETRY_BACKOFF_MS,
+        DIAL_RETRY_MAX_ATTEMPTS, DIAL_TIMEOUT_MS, INBOUND_UPGRADE_TIMEOUT_MS,
+        MAX_CONCURRENT_INBOUND_UPGRADES, MAX_CONCURRENT_NETWORK_REQS,
+        MAX_CONCURRENT_OUTBOUND_UPGRADES, MAX_CONNECTION_DELAY_MS, MAX_FRAME_SIZE,
+        MAX_FULLNODE_OUTBOUND_CONNECTIONS, MAX_INBOUND_CONNECTIONS, NETWORK_CHANNEL_SIZE,
+        TRANSPORT_NOTIFICATION_CHANNEL_SIZE, UPGRADE_LATENCY_LOG_INTERVAL_MS,
     },
     network_id::NetworkContext,
 };
@@ -90,6 +93,14 @@ impl NetworkBuilder {
         network_channel_size: usize,
         max_concurrent_network_reqs: usize,
         inbound_connection_limit: usize,
+        dial_timeout_ms: u64,
+        inbound_upgrade_timeout_ms: u64,
+        max_concurrent_inbound_upgrades: usize,
+        max_concurrent_outbound_upgrades: usize,
+        transport_notification_channel_size: usize,
+        upgrade_latency_log_interval_ms: u64,
+        dial_retry_max_attempts: usize,
+        dial_retry_backoff_ms: u64,
         inbound_rate_limit_config: Option<RateLimitConfig>,
         outbound_rate_limit_config: Option<RateLimitConfig>,
     ) -> Self {
@@ -107,6 +118,14 @@ impl NetworkBuilder {
             max_frame_size,
             enable_proxy_protocol,
             inbound_connection_limit,
+            dial_timeout_ms,
+            inbound_upgrade_timeout_ms,
+            max_concurrent_inbound_upgrades,
+            max_concurrent_outbound_upgrades,
+            transport_notification_channel_size,
+            upgrade_latency_log_interval_ms,
+            dial_retry_max_attempts,
+            dial_retry_backoff_ms,
             inbound_rate_limit_config,
             outbound_rate_limit_config,
         );
@@ -147,6 +166,14 @@ impl NetworkBuilder {
             NETWORK_CHANNEL_SIZE,
             MAX_CONCURRENT_NETWORK_REQS,
             MAX_INBOUND_CONNECTIONS,
+            DIAL_TIMEOUT_MS,
+            INBOUND_UPGRADE_TIMEOUT_MS,
+            MAX_CONCURRENT_INBOUND_UPGRADES,
+            MAX_CONCURRENT_OUTBOUND_UPGRADES,
+            TRANSPORT_NOTIFICATION_CHANNEL_SIZE,
+            UPGRADE_LATENCY_LOG_INTERVAL_MS,
+            DIAL_RETRY_MAX_ATTEMPTS,
+            DIAL_RETRY_BACKOFF_MS,
             None,
             None,
         );
@@ -202,6 +229,14 @@ impl NetworkBuilder {
             config.network_channel_size,
             config.max_concurrent_network_reqs,
             config.max_inbound_connections,
+            config.dial_timeout_ms,
+            config.inbound_upgrade_timeout_ms,
+            config.max_concurrent_inbound_upgrades,
+            config.max_concurrent_outbound_upgrades,
+            config.transport_notification_channel_size,
+            config.upgrade_latency_log_interval_ms,
+            config.dial_retry_max_attempts,
+            config.dial_retry_backoff_ms,
             config.inbound_rate_limit_config,
             config.outbound_rate_limit_config,
         );
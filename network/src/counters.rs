@@ -146,6 +146,119 @@ pub fn connection_upgrade_time(
     ])
 }
 
+pub static DIEM_NETWORK_TRANSPORT_NOTIFICATION_SEND_WAIT: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "diem_network_transport_notification_send_wait_seconds",
+        "Time the transport waited for room to notify PeerManager of a new connection",
+        &["role_type", "network_id", "peer_id", "direction"]
+    )
+    .unwrap()
+});
+
+pub fn transport_notification_send_wait(
+    network_context: &NetworkContext,
+    direction: ConnectionOrigin,
+) -> Histogram {
+    DIEM_NETWORK_TRANSPORT_NOTIFICATION_SEND_WAIT.with_label_values(&[
+        network_context.role().as_str(),
+        network_context.network_id().as_str(),
+        network_context.peer_id().short_str().as_str(),
+        direction.as_str(),
+    ])
+}
+
+pub static DIEM_NETWORK_INBOUND_UPGRADES_REJECTED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_network_inbound_upgrades_rejected",
+        "Number of inbound connections dropped because too many upgrades were already pending",
+        &["role_type", "network_id", "peer_id"]
+    )
+    .unwrap()
+});
+
+pub fn inbound_upgrades_rejected(network_context: &NetworkContext) -> IntCounter {
+    DIEM_NETWORK_INBOUND_UPGRADES_REJECTED.with_label_values(&[
+        network_context.role().as_str(),
+        network_context.network_id().as_str(),
+        network_context.peer_id().short_str().as_str(),
+    ])
+}
+
+pub static DIEM_NETWORK_LISTENER_ACCEPT_ERRORS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_network_listener_accept_errors",
+        "Number of errors accepting incoming connections, by error kind",
+        &["role_type", "network_id", "peer_id", "error_kind"]
+    )
+    .unwrap()
+});
+
+pub fn listener_accept_errors(network_context: &NetworkContext, error_kind: &str) -> IntCounter {
+    DIEM_NETWORK_LISTENER_ACCEPT_ERRORS.with_label_values(&[
+        network_context.role().as_str(),
+        network_context.network_id().as_str(),
+        network_context.peer_id().short_str().as_str(),
+        error_kind,
+    ])
+}
+
+pub static DIEM_NETWORK_DIAL_INITIATION_FAILURES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_network_dial_initiation_failures",
+        "Number of dials which failed before a connection upgrade started, by error kind",
+        &["role_type", "network_id", "peer_id", "error_kind"]
+    )
+    .unwrap()
+});
+
+pub fn dial_initiation_failures(network_context: &NetworkContext, error_kind: &str) -> IntCounter {
+    DIEM_NETWORK_DIAL_INITIATION_FAILURES.with_label_values(&[
+        network_context.role().as_str(),
+        network_context.network_id().as_str(),
+        network_context.peer_id().short_str().as_str(),
+        error_kind,
+    ])
+}
+
+pub static DIEM_NETWORK_DIAL_RETRIES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_network_dial_retries",
+        "Number of failed dials which were retried, by the kind of the transient error",
+        &["role_type", "network_id", "peer_id", "error_kind"]
+    )
+    .unwrap()
+});
+
+pub fn dial_retries(network_context: &NetworkContext, error_kind: &str) -> IntCounter {
+    DIEM_NETWORK_DIAL_RETRIES.with_label_values(&[
+        network_context.role().as_str(),
+        network_context.network_id().as_str(),
+        network_context.peer_id().short_str().as_str(),
+        error_kind,
+    ])
+}
+
+pub static DIEM_NETWORK_OUTBOUND_PEER_ID_MISMATCHES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "diem_network_outbound_peer_id_mismatches",
+        "Number of dialed connections for which the remote peer id differs from the expected one",
+        &["role_type", "network_id", "peer_id", "expected_peer_id"]
+    )
+    .unwrap()
+});
+
+pub fn outbound_peer_id_mismatches(
+    network_context: &NetworkContext,
+    expected_peer_id: &PeerId,
+) -> IntCounter {
+    DIEM_NETWORK_OUTBOUND_PEER_ID_MISMATCHES.with_label_values(&[
+        network_context.role().as_str(),
+        network_context.network_id().as_str(),
+        network_context.peer_id().short_str().as_str(),
+        expected_peer_id.short_str().as_str(),
+    ])
+}
+
 pub static DIEM_NETWORK_DISCOVERY_NOTES: Lazy<IntGaugeVec> = Lazy::new(|| {
     register_int_gauge_vec!(
         "diem_network_discovery_notes",
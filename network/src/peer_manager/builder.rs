@@ -6,8 +6,8 @@ use crate::{
     counters::NETWORK_RATE_LIMIT_METRICS,
     noise::{stream::NoiseStream, HandshakeAuthMode},
     peer_manager::{
-        conn_notifs_channel, ConnectionRequest, ConnectionRequestSender, PeerManager,
-        PeerManagerNotification, PeerManagerRequest, PeerManagerRequestSender,
+        conn_notifs_channel, ConnectionRequest, ConnectionRequestSender, DialRetryPolicy,
+        PeerManager, PeerManagerNotification, PeerManagerRequest, PeerManagerRequestSender,
     },
     protocols::wire::handshake::v1::SupportedProtocols,
     transport::{self, Connection, DiemNetTransport, DIEM_TCP_TRANSPORT},
@@ -31,7 +31,7 @@ use netcore::transport::{
     tcp::{TcpSocket, TcpTransport},
     Transport,
 };
-use std::{clone::Clone, collections::HashMap, fmt::Debug, net::IpAddr, sync::Arc};
+use std::{clone::Clone, collections::HashMap, fmt::Debug, net::IpAddr, sync::Arc, time::Duration};
 use tokio::runtime::Handle;
 
 /// Inbound and Outbound connections are always secured with NoiseIK.  The dialer
@@ -113,6 +113,13 @@ struct PeerManagerContext {
     channel_size: usize,
     max_frame_size: usize,
     inbound_connection_limit: usize,
+    dial_timeout: Duration,
+    inbound_upgrade_timeout: Duration,
+    max_concurrent_inbound_upgrades: usize,
+    max_concurrent_outbound_upgrades: usize,
+    transport_notification_channel_size: usize,
+    dial_retry_policy: Option<DialRetryPolicy>,
+    upgrade_latency_log_interval: Duration,
     inbound_rate_limit_config: Option<RateLimitConfig>,
     outbound_rate_limit_config: Option<RateLimitConfig>,
 }
@@ -135,6 +142,13 @@ impl PeerManagerContext {
         channel_size: usize,
         max_frame_size: usize,
         inbound_connection_limit: usize,
+        dial_timeout: Duration,
+        inbound_upgrade_timeout: Duration,
+        max_concurrent_inbound_upgrades: usize,
+        max_concurrent_outbound_upgrades: usize,
+        transport_notification_channel_size: usize,
+        dial_retry_policy: Option<DialRetryPolicy>,
+        upgrade_latency_log_interval: Duration,
         inbound_rate_limit_config: Option<RateLimitConfig>,
         outbound_rate_limit_config: Option<RateLimitConfig>,
     ) -> Self {
@@ -152,6 +166,13 @@ impl PeerManagerContext {
             channel_size,
             max_frame_size,
             inbound_connection_limit,
+            dial_timeout,
+            inbound_upgrade_timeout,
+            max_concurrent_inbound_upgrades,
+            max_concurrent_outbound_upgrades,
+            transport_notification_channel_size,
+            dial_retry_policy,
+            upgrade_latency_log_interval,
             inbound_rate_limit_config,
             outbound_rate_limit_config,
         }
@@ -209,6 +230,14 @@ impl PeerManagerBuilder {
         max_frame_size: usize,
         enable_proxy_protocol: bool,
         inbound_connection_limit: usize,
+        dial_timeout_ms: u64,
+        inbound_upgrade_timeout_ms: u64,
+        max_concurrent_inbound_upgrades: usize,
+        max_concurrent_outbound_upgrades: usize,
+        transport_notification_channel_size: usize,
+        upgrade_latency_log_interval_ms: u64,
+        dial_retry_max_attempts: usize,
+        dial_retry_backoff_ms: u64,
         inbound_rate_limit_config: Option<RateLimitConfig>,
         outbound_rate_limit_config: Option<RateLimitConfig>,
     ) -> Self {
@@ -221,6 +250,15 @@ impl PeerManagerBuilder {
         // Setup channel to send connection requests to peer manager.
         let (connection_reqs_tx, connection_reqs_rx) =
             diem_channel::new(QueueStyle::FIFO, channel_size, None);
+        // With a single attempt, retrying failed dials is left to PeerManager.
+        let dial_retry_policy = if dial_retry_max_attempts > 1 {
+            Some(DialRetryPolicy {
+                max_attempts: dial_retry_max_attempts,
+                backoff: Duration::from_millis(dial_retry_backoff_ms),
+            })
+        } else {
+            None
+        };
 
         Self {
             network_context,
@@ -245,6 +283,13 @@ impl PeerManagerBuilder {
                 channel_size,
                 max_frame_size,
                 inbound_connection_limit,
+                Duration::from_millis(dial_timeout_ms),
+                Duration::from_millis(inbound_upgrade_timeout_ms),
+                max_concurrent_inbound_upgrades,
+                max_concurrent_outbound_upgrades,
+                transport_notification_channel_size,
+                dial_retry_policy,
+                Duration::from_millis(upgrade_latency_log_interval_ms),
                 inbound_rate_limit_config,
                 outbound_rate_limit_config,
             )),
@@ -374,6 +419,13 @@ impl PeerManagerBuilder {
             pm_context.channel_size,
             pm_context.max_frame_size,
             pm_context.inbound_connection_limit,
+            pm_context.dial_timeout,
+            pm_context.inbound_upgrade_timeout,
+            pm_context.max_concurrent_inbound_upgrades,
+            pm_context.max_concurrent_outbound_upgrades,
+            pm_context.transport_notification_channel_size,
+            pm_context.dial_retry_policy,
+            pm_context.upgrade_latency_log_interval,
             inbound_rate_limiters,
             outbound_rate_limiters,
         );
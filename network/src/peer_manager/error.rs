@@ -28,6 +28,12 @@ pub enum PeerManagerError {
     #[error("Already connected at {0}")]
     AlreadyConnected(NetworkAddress),
 
+    #[error("Dial of Peer {0} cancelled")]
+    DialCancelled(PeerId),
+
+    #[error("Dial of Peer {0} already pending")]
+    DialPending(PeerId),
+
     #[error("Sending end of oneshot dropped")]
     OneshotSenderDropped,
 
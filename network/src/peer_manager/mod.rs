@@ -53,8 +53,10 @@ mod tests;
 mod transport;
 mod types;
 
-pub use self::error::PeerManagerError;
-use crate::peer_manager::transport::{TransportHandler, TransportRequest};
+pub use self::{error::PeerManagerError, transport::DialRetryPolicy};
+use crate::peer_manager::transport::{
+    transport_notification_channel, TransportHandler, TransportRequest,
+};
 use diem_config::config::{PeerRole, PeerSet};
 use diem_infallible::RwLock;
 pub use senders::*;
@@ -147,13 +149,18 @@ where
         max_concurrent_network_reqs: usize,
         max_frame_size: usize,
         inbound_connection_limit: usize,
+        dial_timeout: Duration,
+        inbound_upgrade_timeout: Duration,
+        max_concurrent_inbound_upgrades: usize,
+        max_concurrent_outbound_upgrades: usize,
+        transport_notification_channel_size: usize,
+        dial_retry_policy: Option<DialRetryPolicy>,
+        upgrade_latency_log_interval: Duration,
         inbound_rate_limiters: IpAddrTokenBucketLimiter,
         outbound_rate_limiters: IpAddrTokenBucketLimiter,
     ) -> Self {
-        let (transport_notifs_tx, transport_notifs_rx) = channel::new(
-            channel_size,
-            &counters::PENDING_CONNECTION_HANDLER_NOTIFICATIONS,
-        );
+        let (transport_notifs_tx, transport_notifs_rx) =
+            transport_notification_channel(transport_notification_channel_size);
         let (transport_reqs_tx, transport_reqs_rx) =
             channel::new(channel_size, &counters::PENDING_PEER_MANAGER_DIAL_REQUESTS);
         //TODO now that you can only listen on a socket inside of a tokio runtime we'll need to
@@ -167,6 +174,12 @@ where
             listen_addr,
             transport_reqs_rx,
             transport_notifs_tx_clone,
+            dial_timeout,
+            inbound_upgrade_timeout,
+            max_concurrent_inbound_upgrades,
+            max_concurrent_outbound_upgrades,
+            dial_retry_policy,
+            upgrade_latency_log_interval,
         );
 
         Self {
@@ -699,6 +712,24 @@ where
         // Save PeerRequest sender to `active_peers`.
         self.active_peers
             .insert(peer_id, (conn_meta.clone(), peer_reqs_tx));
+        // A pending dial of the peer only races the inbound connection, one of which tie breaking
+        // would drop, so it is cancelled. If the channel to the transport handler is full, the
+        // dial is left to complete and tie breaking settles the race.
+        if conn_meta.origin == ConnectionOrigin::Inbound {
+            if let Err(err) = self
+                .transport_reqs_tx
+                .try_send(TransportRequest::CancelDial(peer_id))
+            {
+                debug!(
+                    NetworkSchema::new(&self.network_context).remote_peer(&peer_id),
+                    error = %err,
+                    "{} Failed to cancel a pending dial of Peer {}: {}",
+                    self.network_context,
+                    peer_id.short_str(),
+                    err
+                );
+            }
+        }
         // Send NewPeer notification to connection event handlers.
         if send_new_peer_notification {
             let notif = ConnectionNotification::NewPeer(conn_meta, self.network_context.clone());
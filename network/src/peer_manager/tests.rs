@@ -5,8 +5,9 @@ use crate::{
     constants,
     peer::DisconnectReason,
     peer_manager::{
-        conn_notifs_channel, error::PeerManagerError, ConnectionNotification, ConnectionRequest,
-        PeerManager, PeerManagerNotification, PeerManagerRequest, TransportNotification,
+        conn_notifs_channel, error::PeerManagerError, transport::TransportRequest,
+        ConnectionNotification, ConnectionRequest, PeerManager, PeerManagerNotification,
+        PeerManagerRequest, TransportNotification,
     },
     protocols::wire::{
         handshake::v1::MessagingProtocolVersion,
@@ -18,21 +19,25 @@ use crate::{
 };
 use anyhow::anyhow;
 use bytes::Bytes;
-use channel::{diem_channel, message_queues::QueueStyle};
+use channel::{self, diem_channel, message_queues::QueueStyle};
 use diem_config::{
-    config::{PeerRole, MAX_INBOUND_CONNECTIONS},
+    config::{
+        PeerRole, DIAL_TIMEOUT_MS, INBOUND_UPGRADE_TIMEOUT_MS, MAX_CONCURRENT_INBOUND_UPGRADES,
+        MAX_CONCURRENT_OUTBOUND_UPGRADES, MAX_INBOUND_CONNECTIONS,
+        TRANSPORT_NOTIFICATION_CHANNEL_SIZE, UPGRADE_LATENCY_LOG_INTERVAL_MS,
+    },
     network_id::NetworkContext,
 };
 use diem_infallible::RwLock;
 use diem_rate_limiter::rate_limit::TokenBucketRateLimiter;
 use diem_time_service::TimeService;
 use diem_types::{network_address::NetworkAddress, PeerId};
-use futures::{channel::oneshot, io::AsyncWriteExt, stream::StreamExt};
+use futures::{channel::oneshot, future::FutureExt, io::AsyncWriteExt, stream::StreamExt};
 use memsocket::MemorySocket;
 use netcore::transport::{
     boxed::BoxedTransport, memory::MemoryTransport, ConnectionOrigin, TransportExt,
 };
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 use tokio::runtime::Handle;
 use tokio_util::compat::{
     FuturesAsyncReadCompatExt, TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt,
@@ -112,6 +117,13 @@ fn build_test_peer_manager(
         constants::MAX_CONCURRENT_NETWORK_REQS,
         constants::MAX_FRAME_SIZE,
         MAX_INBOUND_CONNECTIONS,
+        Duration::from_millis(DIAL_TIMEOUT_MS),
+        Duration::from_millis(INBOUND_UPGRADE_TIMEOUT_MS),
+        MAX_CONCURRENT_INBOUND_UPGRADES,
+        MAX_CONCURRENT_OUTBOUND_UPGRADES,
+        TRANSPORT_NOTIFICATION_CHANNEL_SIZE,
+        None,
+        Duration::from_millis(UPGRADE_LATENCY_LOG_INTERVAL_MS),
         TokenBucketRateLimiter::open("inbound"),
         TokenBucketRateLimiter::open("outbound"),
     );
@@ -576,6 +588,64 @@ fn peer_manager_simultaneous_dial_disconnect_event() {
     runtime.block_on(test);
 }
 
+#[test]
+fn inbound_connection_cancels_pending_dial() {
+    ::diem_logger::Logger::init_for_testing();
+    let runtime = ::tokio::runtime::Runtime::new().unwrap();
+
+    let ids = ordered_peer_ids(2);
+    let (mut peer_manager, _request_tx, _connection_reqs_tx, _hello_rx, _conn_status_rx) =
+        build_test_peer_manager(runtime.handle().clone(), ids[1]);
+    // Take the requests to the transport handler, which is never started.
+    let (transport_reqs_tx, mut transport_reqs_rx) = channel::new_test(2);
+    peer_manager.transport_reqs_tx = transport_reqs_tx;
+
+    let test = async move {
+        let (response_tx, _response_rx) = oneshot::channel();
+        peer_manager
+            .handle_connection_request(ConnectionRequest::DialPeer(
+                ids[0],
+                NetworkAddress::mock(),
+                response_tx,
+            ))
+            .await;
+        match transport_reqs_rx.select_next_some().await {
+            TransportRequest::DialPeer(peer_id, _, _) => assert_eq!(peer_id, ids[0]),
+            request => panic!("Expected a DialPeer request, received: {:?}", request),
+        }
+
+        // The peer dials in before the dial completes.
+        let (_outbound, inbound) = build_test_connection();
+        peer_manager.add_peer(create_connection(
+            inbound,
+            ids[0],
+            NetworkAddress::mock(),
+            ConnectionOrigin::Inbound,
+            ConnectionId::from(0),
+        ));
+        match transport_reqs_rx.select_next_some().await {
+            TransportRequest::CancelDial(peer_id) => assert_eq!(peer_id, ids[0]),
+            request => panic!("Expected a CancelDial request, received: {:?}", request),
+        }
+
+        // An outbound connection is the completed dial itself.
+        let (outbound, _inbound) = build_test_connection();
+        peer_manager.add_peer(create_connection(
+            outbound,
+            ids[0],
+            NetworkAddress::mock(),
+            ConnectionOrigin::Outbound,
+            ConnectionId::from(1),
+        ));
+        assert!(transport_reqs_rx
+            .select_next_some()
+            .now_or_never()
+            .is_none());
+    };
+
+    runtime.block_on(test);
+}
+
 #[test]
 fn test_dial_disconnect() {
     ::diem_logger::Logger::init_for_testing();
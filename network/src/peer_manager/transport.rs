@@ -10,26 +10,93 @@ use anyhow::format_err;
 use channel::{self};
 use diem_config::network_id::NetworkContext;
 use diem_logger::prelude::*;
-use diem_time_service::{TimeService, TimeServiceTrait};
+use diem_metrics::IntGauge;
+use diem_time_service::{timeout, TimeService, TimeServiceTrait};
 use diem_types::{network_address::NetworkAddress, PeerId};
 use futures::{
-    channel::oneshot,
-    future::{BoxFuture, FutureExt},
-    io::{AsyncRead, AsyncWrite},
+    channel::{mpsc, oneshot},
+    future::{self, abortable, AbortHandle, Aborted, BoxFuture, FutureExt, OptionFuture},
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
     sink::SinkExt,
-    stream::{Fuse, FuturesUnordered, StreamExt},
+    stream::{Fuse, FusedStream, FuturesUnordered, StreamExt},
 };
 use netcore::transport::{ConnectionOrigin, Transport};
 use short_hex_str::AsShortHexStr;
-use std::{sync::Arc, time::Instant};
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    io,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 #[derive(Debug)]
 pub enum TransportRequest {
+    /// Dials the peer at the address. Fails with `PeerManagerError::DialPending` if a dial to
+    /// the peer is already pending
     DialPeer(
         PeerId,
         NetworkAddress,
         oneshot::Sender<Result<(), PeerManagerError>>,
     ),
+    /// Cancels the pending dial to the peer, if any, which then fails with
+    /// `PeerManagerError::DialCancelled`. PeerManager sends it once an inbound connection from
+    /// the peer is added
+    CancelDial(PeerId),
+}
+
+/// The policy by which a [`TransportHandler`] retries dials which fail with a transient error,
+/// e.g. as the peer is restarting and refuses connections, before it reports the failure.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DialRetryPolicy {
+    /// The maximum number of attempts of a dial, including the first one
+    pub max_attempts: usize,
+    /// The delay before the first retry, which doubles with every further retry
+    pub backoff: Duration,
+}
+
+impl DialRetryPolicy {
+    /// Returns the delay before the next attempt of a dial which failed the given number of
+    /// times, or `None` if the dial used up its attempts.
+    pub fn backoff(&self, failed_attempts: usize) -> Option<Duration> {
+        if failed_attempts >= self.max_attempts {
+            return None;
+        }
+        let exponent = failed_attempts.saturating_sub(1).min(16) as u32;
+        Some(self.backoff.saturating_mul(1 << exponent))
+    }
+}
+
+/// A pending outbound connection upgrade, which resolves to the upgraded connection together
+/// with the dialed address and peer, the start time and number of attempts of the dial, and the
+/// channel on which its result is reported.
+type PendingDial<TSocket> = BoxFuture<
+    'static,
+    (
+        Result<Connection<TSocket>, PeerManagerError>,
+        NetworkAddress,
+        PeerId,
+        Instant,
+        usize,
+        oneshot::Sender<Result<(), PeerManagerError>>,
+    ),
+>;
+
+/// Counts an outbound connection upgrade in `pending_connection_upgrades` for as long as it is
+/// alive, so that the count drops as well when the dial is cancelled.
+struct PendingUpgrade(IntGauge);
+
+impl PendingUpgrade {
+    fn start(pending_upgrades: IntGauge) -> Self {
+        pending_upgrades.inc();
+        Self(pending_upgrades)
+    }
+}
+
+impl Drop for PendingUpgrade {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
 }
 
 /// Responsible for listening for new incoming connections
@@ -45,6 +112,105 @@ where
     listener: Fuse<TTransport::Listener>,
     transport_reqs_rx: channel::Receiver<TransportRequest>,
     transport_notifs_tx: channel::Sender<TransportNotification<TSocket>>,
+    /// The time an outbound connection has to complete its upgrade before the dial fails
+    dial_timeout: Duration,
+    /// The time an inbound connection has to complete its upgrade before it is dropped
+    inbound_upgrade_timeout: Duration,
+    /// The maximum number of inbound connections being upgraded at once, beyond which new
+    /// inbound connections are dropped
+    max_concurrent_inbound_upgrades: usize,
+    /// The maximum number of outbound connections being upgraded at once, beyond which dial
+    /// requests are left queued in `transport_reqs_rx` until pending dials complete. It is at
+    /// least 1
+    max_concurrent_outbound_upgrades: usize,
+    /// The policy for retrying dials which fail with a transient error. Without one, a failed
+    /// dial is reported right away and retrying it is left to PeerManager
+    dial_retry_policy: Option<DialRetryPolicy>,
+    /// The interval at which the percentiles of the latencies of completed upgrades are logged
+    upgrade_latency_log_interval: Duration,
+    /// The latencies of the upgrades completed since they were last logged
+    upgrade_latencies: UpgradeLatencies,
+    /// Handles to abort the pending outbound connection upgrades, by the dialed peer
+    pending_dials: HashMap<PeerId, AbortHandle>,
+}
+
+/// The latencies of the connection upgrades which completed successfully within an interval, in
+/// seconds, by direction.
+#[derive(Debug, Default)]
+struct UpgradeLatencies {
+    inbound: Vec<f64>,
+    outbound: Vec<f64>,
+}
+
+impl UpgradeLatencies {
+    fn record(&mut self, origin: ConnectionOrigin, secs: f64) {
+        match origin {
+            ConnectionOrigin::Inbound => self.inbound.push(secs),
+            ConnectionOrigin::Outbound => self.outbound.push(secs),
+        }
+    }
+
+    /// Summarizes the latencies of each direction, skipping directions without any upgrades.
+    fn summarize(self) -> Vec<UpgradeLatencySummary> {
+        vec![
+            (ConnectionOrigin::Inbound, self.inbound),
+            (ConnectionOrigin::Outbound, self.outbound),
+        ]
+        .into_iter()
+        .filter(|(_, samples)| !samples.is_empty())
+        .map(|(origin, mut samples)| {
+            let (p50, p95, p99) = percentiles(&mut samples);
+            UpgradeLatencySummary {
+                origin,
+                upgrades: samples.len(),
+                p50,
+                p95,
+                p99,
+            }
+        })
+        .collect()
+    }
+}
+
+/// The percentiles of the latencies of the upgrades in one direction within an interval, in
+/// seconds, as logged by a [`TransportHandler`].
+#[derive(Debug, PartialEq)]
+struct UpgradeLatencySummary {
+    origin: ConnectionOrigin,
+    upgrades: usize,
+    p50: f64,
+    p95: f64,
+    p99: f64,
+}
+
+/// Returns the p50, p95 and p99 of the non-empty samples by the nearest-rank method.
+fn percentiles(samples: &mut [f64]) -> (f64, f64, f64) {
+    samples.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let rank = |p: f64| {
+        let rank = (p * samples.len() as f64).ceil() as usize;
+        samples[rank.max(1) - 1]
+    };
+    (rank(0.5), rank(0.95), rank(0.99))
+}
+
+/// Creates the bounded channel on which a [`TransportHandler`] hands upgraded connections to
+/// PeerManager, holding at most `capacity` pending notifications.
+///
+/// The capacity trades head-of-line blocking against memory. Once the channel is full, the
+/// handler waits for PeerManager before it handles any further dial request or connection, so a
+/// slow PeerManager stalls connection setup in both directions. Every pending notification on
+/// the other hand holds an open, fully upgraded connection with its buffers. The time the
+/// handler waits is recorded by `counters::transport_notification_send_wait`.
+pub fn transport_notification_channel<TSocket>(
+    capacity: usize,
+) -> (
+    channel::Sender<TransportNotification<TSocket>>,
+    channel::Receiver<TransportNotification<TSocket>>,
+) {
+    channel::new(
+        capacity,
+        &counters::PENDING_CONNECTION_HANDLER_NOTIFICATIONS,
+    )
 }
 
 impl<TTransport, TSocket> TransportHandler<TTransport, TSocket>
@@ -62,6 +228,12 @@ where
         listen_addr: NetworkAddress,
         transport_reqs_rx: channel::Receiver<TransportRequest>,
         transport_notifs_tx: channel::Sender<TransportNotification<TSocket>>,
+        dial_timeout: Duration,
+        inbound_upgrade_timeout: Duration,
+        max_concurrent_inbound_upgrades: usize,
+        max_concurrent_outbound_upgrades: usize,
+        dial_retry_policy: Option<DialRetryPolicy>,
+        upgrade_latency_log_interval: Duration,
     ) -> (Self, NetworkAddress) {
         let (listener, listen_addr) = transport
             .listen_on(listen_addr)
@@ -73,6 +245,8 @@ where
             network_context,
             listen_addr
         );
+        // Without room for a single dial, requests would never be taken from the channel.
+        let max_concurrent_outbound_upgrades = max_concurrent_outbound_upgrades.max(1);
         (
             Self {
                 network_context,
@@ -81,6 +255,14 @@ where
                 listener: listener.fuse(),
                 transport_reqs_rx,
                 transport_notifs_tx,
+                dial_timeout,
+                inbound_upgrade_timeout,
+                max_concurrent_inbound_upgrades,
+                max_concurrent_outbound_upgrades,
+                dial_retry_policy,
+                upgrade_latency_log_interval,
+                upgrade_latencies: UpgradeLatencies::default(),
+                pending_dials: HashMap::new(),
             },
             listen_addr,
         )
@@ -89,6 +271,12 @@ where
     pub async fn listen(mut self) {
         let mut pending_inbound_connections = FuturesUnordered::new();
         let mut pending_outbound_connections = FuturesUnordered::new();
+        // The first summary covers a full interval as well.
+        let ticker = self.time_service.interval_at(
+            self.time_service.now() + self.upgrade_latency_log_interval,
+            self.upgrade_latency_log_interval,
+        );
+        tokio::pin!(ticker);
 
         debug!(
             NetworkSchema::new(&self.network_context),
@@ -96,14 +284,48 @@ where
         );
 
         loop {
+            // Once neither new requests nor connections can arrive, the summaries stop, so the
+            // handler ends when the pending upgrades complete.
+            let next_tick = OptionFuture::from(
+                if self.transport_reqs_rx.is_terminated() && self.listener.is_terminated() {
+                    None
+                } else {
+                    Some(ticker.select_next_some())
+                },
+            );
+            // At capacity, requests are not taken from the channel, which is bounded and so
+            // pushes back on the senders, until pending dials complete.
+            let next_request = OptionFuture::from(
+                if pending_outbound_connections.len() < self.max_concurrent_outbound_upgrades {
+                    Some(self.transport_reqs_rx.select_next_some())
+                } else {
+                    None
+                },
+            );
             futures::select! {
-                dial_request = self.transport_reqs_rx.select_next_some() => {
-                    if let Some(fut) = self.dial_peer(dial_request) {
+                transport_request = next_request => {
+                    if let Some(fut) = transport_request.and_then(|req| self.dial_peer(req)) {
                         pending_outbound_connections.push(fut);
                     }
                 },
                 incoming_connection = self.listener.select_next_some() => {
                     match incoming_connection {
+                        // Dropping the upgrade closes the connection, so a flood of connecting
+                        // peers cannot tie up resources in the expensive handshake.
+                        Ok((_upgrade, addr))
+                            if pending_inbound_connections.len()
+                                >= self.max_concurrent_inbound_upgrades =>
+                        {
+                            warn!(
+                                NetworkSchema::new(&self.network_context)
+                                    .network_address(&addr),
+                                "{} Dropping incoming connection from {}: {} upgrades already pending",
+                                self.network_context,
+                                addr,
+                                pending_inbound_connections.len()
+                            );
+                            counters::inbound_upgrades_rejected(&self.network_context).inc();
+                        }
                         Ok((upgrade, addr)) => {
                             debug!(
                                 NetworkSchema::new(&self.network_context)
@@ -120,6 +342,11 @@ where
                             .inc();
 
                             let start_time = self.time_service.now();
+                            // A remote which opens a connection but stalls the handshake must
+                            // not hold on to a pending upgrade forever.
+                            let upgrade = self
+                                .time_service
+                                .timeout(self.inbound_upgrade_timeout, upgrade);
                             pending_inbound_connections.push(upgrade.map(move |out| (out, addr, start_time)));
                         }
                         Err(e) => {
@@ -130,15 +357,35 @@ where
                                 self.network_context,
                                 e
                             );
+                            counters::listener_accept_errors(
+                                &self.network_context,
+                                &error_kind_label(&e),
+                            )
+                            .inc();
                         }
                     }
                 },
-                (upgrade, addr, peer_id, start_time, response_tx) = pending_outbound_connections.select_next_some() => {
-                    self.handle_completed_outbound_upgrade(upgrade, addr, peer_id, start_time, response_tx).await;
+                (upgrade, addr, peer_id, start_time, attempts, response_tx) = pending_outbound_connections.select_next_some() => {
+                    let retry = self
+                        .handle_completed_outbound_upgrade(
+                            upgrade,
+                            addr,
+                            peer_id,
+                            start_time,
+                            attempts,
+                            response_tx,
+                        )
+                        .await;
+                    if let Some(retry) = retry {
+                        pending_outbound_connections.push(retry);
+                    }
                 },
                 (upgrade, addr, start_time) = pending_inbound_connections.select_next_some() => {
                     self.handle_completed_inbound_upgrade(upgrade, addr, start_time).await;
                 },
+                _ = next_tick => {
+                    self.log_upgrade_latencies();
+                },
                 complete => break,
             }
         }
@@ -149,83 +396,204 @@ where
         );
     }
 
-    fn dial_peer(
-        &self,
-        dial_peer_request: TransportRequest,
-    ) -> Option<
-        BoxFuture<
-            'static,
-            (
-                Result<Connection<TSocket>, TTransport::Error>,
-                NetworkAddress,
-                PeerId,
-                Instant,
-                oneshot::Sender<Result<(), PeerManagerError>>,
-            ),
-        >,
-    > {
+    fn dial_peer(&mut self, dial_peer_request: TransportRequest) -> Option<PendingDial<TSocket>> {
         match dial_peer_request {
             TransportRequest::DialPeer(peer_id, addr, response_tx) => {
-                match self.transport.dial(peer_id, addr.clone()) {
-                    Ok(upgrade) => {
-                        counters::pending_connection_upgrades(
-                            &self.network_context,
-                            ConnectionOrigin::Outbound,
-                        )
-                        .inc();
+                // Only one dial to a peer is in flight at a time, so simultaneous requests do
+                // not race to set up duplicate connections.
+                if self.pending_dials.contains_key(&peer_id) {
+                    debug!(
+                        NetworkSchema::new(&self.network_context).remote_peer(&peer_id),
+                        "{} Dial to peer {} already pending",
+                        self.network_context,
+                        peer_id.short_str()
+                    );
+                    if let Err(send_err) =
+                        response_tx.send(Err(PeerManagerError::DialPending(peer_id)))
+                    {
+                        info!(
+                            NetworkSchema::new(&self.network_context).remote_peer(&peer_id),
+                            "{} Failed to notify clients of pending dial for Peer {}: {:?}",
+                            self.network_context,
+                            peer_id.short_str(),
+                            send_err
+                        );
+                    }
+                    return None;
+                }
+                let start_time = self.time_service.now();
+                self.start_dial(peer_id, addr, start_time, 0, None, response_tx)
+            }
+            TransportRequest::CancelDial(peer_id) => {
+                self.cancel_dial(peer_id);
+                None
+            }
+        }
+    }
 
-                        let start_time = self.time_service.now();
-                        Some(
-                            upgrade
-                                .map(move |out| (out, addr, peer_id, start_time, response_tx))
-                                .boxed(),
-                        )
+    /// Starts an attempt of a dial which failed the given number of times before, after the
+    /// backoff, if any. The dial stays pending, and so can be cancelled, during the backoff.
+    fn start_dial(
+        &mut self,
+        peer_id: PeerId,
+        addr: NetworkAddress,
+        start_time: Instant,
+        failed_attempts: usize,
+        backoff: Option<Duration>,
+        response_tx: oneshot::Sender<Result<(), PeerManagerError>>,
+    ) -> Option<PendingDial<TSocket>> {
+        match self.transport.dial(peer_id, addr.clone()) {
+            Ok(upgrade) => {
+                // A remote which accepts the connection but never completes the handshake must
+                // not keep the dial pending forever. The timeout only starts after the backoff,
+                // which does not count as a pending upgrade either.
+                let dial_timeout = self.dial_timeout;
+                let time_service = self.time_service.clone();
+                let backoff = backoff.map(|backoff| self.time_service.sleep(backoff));
+                let pending_upgrades = counters::pending_connection_upgrades(
+                    &self.network_context,
+                    ConnectionOrigin::Outbound,
+                );
+                let upgrade = async move {
+                    if let Some(backoff) = backoff {
+                        backoff.await;
                     }
-                    Err(error) => {
-                        if let Err(send_err) =
-                            response_tx.send(Err(PeerManagerError::from_transport_error(error)))
-                        {
-                            info!(
-                                NetworkSchema::new(&self.network_context).remote_peer(&peer_id),
-                                "{} Failed to notify clients of TransportError for Peer {}: {:?}",
-                                self.network_context,
-                                peer_id.short_str(),
-                                send_err
-                            );
+                    let _pending_upgrade = PendingUpgrade::start(pending_upgrades);
+                    match time_service.timeout(dial_timeout, upgrade).await {
+                        Ok(upgrade) => upgrade.map_err(PeerManagerError::from_transport_error),
+                        Err(timeout::Elapsed) => {
+                            Err(PeerManagerError::from_transport_error(io::Error::new(
+                                io::ErrorKind::TimedOut,
+                                format!(
+                                    "Outbound connection upgrade timed out after {:?}",
+                                    dial_timeout
+                                ),
+                            )))
                         }
-                        None
                     }
+                };
+                let (upgrade, abort_handle) = abortable(upgrade);
+                self.pending_dials.insert(peer_id, abort_handle);
+                let upgrade = upgrade.map(move |out| match out {
+                    Ok(upgrade) => upgrade,
+                    Err(Aborted) => Err(PeerManagerError::DialCancelled(peer_id)),
+                });
+                let attempts = failed_attempts + 1;
+                Some(
+                    upgrade
+                        .map(move |out| (out, addr, peer_id, start_time, attempts, response_tx))
+                        .boxed(),
+                )
+            }
+            Err(error) => {
+                // The dial failed before any connection was set up, e.g. as the address could
+                // not be resolved, which is counted apart from failed upgrades.
+                counters::dial_initiation_failures(
+                    &self.network_context,
+                    &error_kind_label(&error),
+                )
+                .inc();
+                if let Err(send_err) =
+                    response_tx.send(Err(PeerManagerError::from_transport_error(error)))
+                {
+                    info!(
+                        NetworkSchema::new(&self.network_context).remote_peer(&peer_id),
+                        "{} Failed to notify clients of TransportError for Peer {}: {:?}",
+                        self.network_context,
+                        peer_id.short_str(),
+                        send_err
+                    );
                 }
+                None
             }
         }
     }
 
+    fn cancel_dial(&mut self, peer_id: PeerId) {
+        // The dial stays pending until its aborted upgrade completes.
+        match self.pending_dials.get(&peer_id) {
+            Some(abort_handle) => {
+                debug!(
+                    NetworkSchema::new(&self.network_context).remote_peer(&peer_id),
+                    "{} Cancelling pending dial to peer {}",
+                    self.network_context,
+                    peer_id.short_str()
+                );
+                abort_handle.abort();
+            }
+            None => {
+                debug!(
+                    NetworkSchema::new(&self.network_context).remote_peer(&peer_id),
+                    "{} No pending dial to peer {} to cancel",
+                    self.network_context,
+                    peer_id.short_str()
+                );
+            }
+        }
+    }
+
+    /// Handles the completed attempt of a dial, and returns the next attempt if the dial is
+    /// retried.
     async fn handle_completed_outbound_upgrade(
         &mut self,
-        upgrade: Result<Connection<TSocket>, TTransport::Error>,
+        upgrade: Result<Connection<TSocket>, PeerManagerError>,
         addr: NetworkAddress,
         peer_id: PeerId,
         start_time: Instant,
+        attempts: usize,
         response_tx: oneshot::Sender<Result<(), PeerManagerError>>,
-    ) {
-        counters::pending_connection_upgrades(&self.network_context, ConnectionOrigin::Outbound)
-            .dec();
+    ) -> Option<PendingDial<TSocket>> {
+        self.pending_dials.remove(&peer_id);
+
+        // Only failed upgrades are retried, so a connection to a peer with another id than the
+        // expected one, which retrying cannot fix, never is.
+        if let Err(err) = &upgrade {
+            let retry = retryable_error_kind(err).and_then(|kind| {
+                let backoff = self.dial_retry_policy?.backoff(attempts)?;
+                Some((kind, backoff))
+            });
+            if let Some((kind, backoff)) = retry {
+                info!(
+                    NetworkSchema::new(&self.network_context)
+                        .remote_peer(&peer_id)
+                        .network_address(&addr),
+                    error = %err,
+                    "{} Retrying dial to peer {} at {} in {:?} after {} failed attempts: {}",
+                    self.network_context,
+                    peer_id.short_str(),
+                    addr,
+                    backoff,
+                    attempts,
+                    err
+                );
+                counters::dial_retries(&self.network_context, &format!("{:?}", kind)).inc();
+                return self.start_dial(
+                    peer_id,
+                    addr,
+                    start_time,
+                    attempts,
+                    Some(backoff),
+                    response_tx,
+                );
+            }
+        }
 
         let elapsed_time = (self.time_service.now() - start_time).as_secs_f64();
         let upgrade = match upgrade {
-            Ok(connection) => {
+            Ok(connection) if connection.metadata.remote_peer_id != peer_id => {
+                // The address usually maps to the wrong node, e.g. due to stale discovery
+                // information, so it is the actionable part of the error.
                 let dialed_peer_id = connection.metadata.remote_peer_id;
-                if dialed_peer_id == peer_id {
-                    Ok(connection)
-                } else {
-                    Err(PeerManagerError::from_transport_error(format_err!(
-                        "Dialed PeerId '{}' differs from expected PeerId '{}'",
-                        dialed_peer_id.short_str(),
-                        peer_id.short_str()
-                    )))
-                }
+                counters::outbound_peer_id_mismatches(&self.network_context, &peer_id).inc();
+                self.close_connection(connection).await;
+                Err(PeerManagerError::from_transport_error(format_err!(
+                    "Dialed PeerId '{}' at {} differs from expected PeerId '{}'",
+                    dialed_peer_id.short_str(),
+                    addr,
+                    peer_id.short_str()
+                )))
             }
-            Err(err) => Err(PeerManagerError::from_transport_error(err)),
+            upgrade => upgrade,
         };
 
         let response = match upgrade {
@@ -247,12 +615,54 @@ where
                     SUCCEEDED_LABEL,
                 )
                 .observe(elapsed_time);
+                self.upgrade_latencies
+                    .record(ConnectionOrigin::Outbound, elapsed_time);
 
-                // Send the new connection to PeerManager
-                let event = TransportNotification::NewConnection(connection);
-                self.transport_notifs_tx.send(event).await.unwrap();
+                // Send the new connection to PeerManager. If it has shut down in the meantime,
+                // which is a normal race during shutdown, close the connection instead.
+                match self.ready_to_notify(ConnectionOrigin::Outbound).await {
+                    Ok(()) => {
+                        let event = TransportNotification::NewConnection(connection);
+                        let result = self.transport_notifs_tx.start_send_unpin(event);
+                        if let Err(err) = &result {
+                            warn!(
+                                NetworkSchema::new(&self.network_context).remote_peer(&peer_id),
+                                error = %err,
+                                "{} Failed to notify PeerManager of connection to peer {}: {}",
+                                self.network_context,
+                                peer_id.short_str(),
+                                err
+                            );
+                        }
+                        result.map_err(PeerManagerError::from)
+                    }
+                    Err(err) => {
+                        warn!(
+                            NetworkSchema::new(&self.network_context).remote_peer(&peer_id),
+                            error = %err,
+                            "{} PeerManager is gone, closing new connection to peer {}: {}",
+                            self.network_context,
+                            peer_id.short_str(),
+                            err
+                        );
+                        self.close_connection(connection).await;
+                        Err(err.into())
+                    }
+                }
+            }
+            Err(err @ PeerManagerError::DialCancelled(_)) => {
+                debug!(
+                    NetworkSchema::new(&self.network_context)
+                        .remote_peer(&peer_id)
+                        .network_address(&addr),
+                    "{} Outbound connection to peer {} at {} cancelled after {:.3} secs",
+                    self.network_context,
+                    peer_id.short_str(),
+                    addr,
+                    elapsed_time
+                );
 
-                Ok(())
+                Err(err)
             }
             Err(err) => {
                 error!(
@@ -287,11 +697,67 @@ where
                 send_err
             );
         }
+        None
+    }
+
+    /// Logs the p50, p95 and p99 latencies of the upgrades completed in each direction since the
+    /// last summary, skipping directions without any, and starts a new interval.
+    fn log_upgrade_latencies(&mut self) {
+        let latencies = std::mem::take(&mut self.upgrade_latencies);
+        for summary in latencies.summarize() {
+            info!(
+                NetworkSchema::new(&self.network_context),
+                direction = summary.origin.as_str(),
+                upgrades = summary.upgrades,
+                p50_secs = summary.p50,
+                p95_secs = summary.p95,
+                p99_secs = summary.p99,
+                "{} {} upgrade latencies over the last {:?}: p50 {:.3}s, p95 {:.3}s, p99 {:.3}s \
+                 over {} upgrades",
+                self.network_context,
+                summary.origin,
+                self.upgrade_latency_log_interval,
+                summary.p50,
+                summary.p95,
+                summary.p99,
+                summary.upgrades
+            );
+        }
+    }
+
+    /// Waits until the notification channel to PeerManager has room for another notification,
+    /// recording how long the handler was blocked.
+    async fn ready_to_notify(
+        &mut self,
+        direction: ConnectionOrigin,
+    ) -> Result<(), mpsc::SendError> {
+        let start_time = self.time_service.now();
+        let ready = future::poll_fn(|cx| self.transport_notifs_tx.poll_ready_unpin(cx)).await;
+        counters::transport_notification_send_wait(&self.network_context, direction)
+            .observe((self.time_service.now() - start_time).as_secs_f64());
+        ready
+    }
+
+    /// Closes the socket of an upgraded connection which is not handed to PeerManager, so the
+    /// remote sees a clean shutdown rather than a reset.
+    async fn close_connection(&self, connection: Connection<TSocket>) {
+        let remote_peer_id = connection.metadata.remote_peer_id;
+        let mut socket = Box::pin(connection.socket);
+        if let Err(err) = socket.close().await {
+            debug!(
+                NetworkSchema::new(&self.network_context).remote_peer(&remote_peer_id),
+                error = %err,
+                "{} Failed to close connection to peer {}: {}",
+                self.network_context,
+                remote_peer_id.short_str(),
+                err
+            );
+        }
     }
 
     async fn handle_completed_inbound_upgrade(
         &mut self,
-        upgrade: Result<Connection<TSocket>, TTransport::Error>,
+        upgrade: Result<Result<Connection<TSocket>, TTransport::Error>, timeout::Elapsed>,
         addr: NetworkAddress,
         start_time: Instant,
     ) {
@@ -300,7 +766,7 @@ where
 
         let elapsed_time = (self.time_service.now() - start_time).as_secs_f64();
         match upgrade {
-            Ok(connection) => {
+            Ok(Ok(connection)) => {
                 debug!(
                     NetworkSchema::new(&self.network_context)
                         .connection_metadata_with_address(&connection.metadata),
@@ -317,12 +783,36 @@ where
                     SUCCEEDED_LABEL,
                 )
                 .observe(elapsed_time);
+                self.upgrade_latencies
+                    .record(ConnectionOrigin::Inbound, elapsed_time);
 
-                // Send the new connection to PeerManager
-                let event = TransportNotification::NewConnection(connection);
-                self.transport_notifs_tx.send(event).await.unwrap();
+                // Send the new connection to PeerManager. If it has shut down in the meantime,
+                // which is a normal race during shutdown, close the connection instead.
+                let remote_peer_id = connection.metadata.remote_peer_id;
+                let result = match self.ready_to_notify(ConnectionOrigin::Inbound).await {
+                    Ok(()) => {
+                        let event = TransportNotification::NewConnection(connection);
+                        self.transport_notifs_tx.start_send_unpin(event)
+                    }
+                    Err(err) => {
+                        self.close_connection(connection).await;
+                        Err(err)
+                    }
+                };
+                if let Err(err) = result {
+                    warn!(
+                        NetworkSchema::new(&self.network_context)
+                            .remote_peer(&remote_peer_id)
+                            .network_address(&addr),
+                        error = %err,
+                        "{} Failed to notify PeerManager of new connection from peer {}: {}",
+                        self.network_context,
+                        remote_peer_id.short_str(),
+                        err
+                    );
+                }
             }
-            Err(err) => {
+            Ok(Err(err)) => {
                 warn!(
                     NetworkSchema::new(&self.network_context)
                         .network_address(&addr),
@@ -341,6 +831,1017 @@ where
                 )
                 .observe(elapsed_time);
             }
+            Err(timeout::Elapsed) => {
+                warn!(
+                    NetworkSchema::new(&self.network_context).network_address(&addr),
+                    "{} Inbound connection from {} timed out upgrading after {:?}",
+                    self.network_context,
+                    addr,
+                    self.inbound_upgrade_timeout,
+                );
+
+                counters::connection_upgrade_time(
+                    &self.network_context,
+                    ConnectionOrigin::Inbound,
+                    FAILED_LABEL,
+                )
+                .observe(elapsed_time);
+            }
+        }
+    }
+}
+
+/// Returns the kind of the error to label metrics with, which is the kind of I/O errors and
+/// `other` for any other error.
+fn error_kind_label(err: &(dyn std::error::Error + 'static)) -> String {
+    err.downcast_ref::<io::Error>()
+        .map_or_else(|| "other".to_string(), |err| format!("{:?}", err.kind()))
+}
+
+/// Returns the kind of the socket error the dial failed with if it is likely transient, e.g. as
+/// the peer refuses connections while it restarts, and so worth retrying. Timeouts are not, as
+/// the dial already waited for the whole dial timeout.
+fn retryable_error_kind(err: &PeerManagerError) -> Option<io::ErrorKind> {
+    match err {
+        PeerManagerError::TransportError(err) => err
+            .downcast_ref::<io::Error>()
+            .map(io::Error::kind)
+            .filter(|kind| {
+                matches!(
+                    kind,
+                    io::ErrorKind::ConnectionRefused
+                        | io::ErrorKind::ConnectionReset
+                        | io::ErrorKind::ConnectionAborted
+                )
+            }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        protocols::wire::handshake::v1::MessagingProtocolVersion,
+        transport::{ConnectionId, ConnectionMetadata},
+        ProtocolId,
+    };
+    use diem_config::config::PeerRole;
+    use futures::{
+        future,
+        io::{AsyncReadExt, AsyncWriteExt},
+        stream,
+    };
+    use memsocket::MemorySocket;
+    use netcore::transport::{boxed::BoxedTransport, memory::MemoryTransport, TransportExt};
+    use std::{
+        io,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+    use tokio::runtime::Runtime;
+
+    const TEST_PROTOCOL: ProtocolId = ProtocolId::ConsensusRpc;
+    const TEST_DIAL_TIMEOUT: Duration = Duration::from_secs(30);
+    const TEST_INBOUND_UPGRADE_TIMEOUT: Duration = Duration::from_secs(30);
+    const TEST_MAX_CONCURRENT_INBOUND_UPGRADES: usize = 100;
+    const TEST_MAX_CONCURRENT_OUTBOUND_UPGRADES: usize = 100;
+    const TEST_UPGRADE_LATENCY_LOG_INTERVAL: Duration = Duration::from_secs(60);
+    /// The number of sleeps a running handler always has pending on its time service, which is
+    /// the one of the interval at which it logs its upgrade latencies.
+    const TICKER_WAITERS: usize = 1;
+
+    type TestTransport = BoxedTransport<Connection<MemorySocket>, io::Error>;
+    type TestTransportHandler = TransportHandler<TestTransport, MemorySocket>;
+
+    // Builds a memory transport which, in place of a real handshake, has both ends exchange
+    // their peer ids so that each side learns the actual remote peer id.
+    fn build_test_transport(peer_id: PeerId) -> TestTransport {
+        MemoryTransport::default()
+            .and_then(move |mut socket, addr, origin| async move {
+                socket.write_all(peer_id.as_ref()).await?;
+                let mut remote_peer_id = [0u8; PeerId::LENGTH];
+                socket.read_exact(&mut remote_peer_id).await?;
+                Ok(Connection {
+                    socket,
+                    metadata: ConnectionMetadata::new(
+                        PeerId::new(remote_peer_id),
+                        ConnectionId::default(),
+                        addr,
+                        origin,
+                        MessagingProtocolVersion::V1,
+                        [TEST_PROTOCOL].iter().into(),
+                        PeerRole::Unknown,
+                    ),
+                })
+            })
+            .boxed()
+    }
+
+    // Builds a memory transport whose upgrades fail, as if the handshake with the remote failed.
+    fn build_failing_transport() -> TestTransport {
+        MemoryTransport::default()
+            .and_then(|_socket, _addr, _origin| {
+                future::ready::<io::Result<Connection<MemorySocket>>>(Err(io::Error::new(
+                    io::ErrorKind::ConnectionReset,
+                    "handshake failed",
+                )))
+            })
+            .boxed()
+    }
+
+    // Builds a memory transport whose upgrades fail as if the remote reset the connection, and
+    // which counts the dials.
+    fn build_resetting_transport(dials: Arc<AtomicUsize>) -> TestTransport {
+        MemoryTransport::default()
+            .and_then(move |_socket, _addr, _origin| {
+                dials.fetch_add(1, Ordering::SeqCst);
+                future::ready::<io::Result<Connection<MemorySocket>>>(Err(io::Error::new(
+                    io::ErrorKind::ConnectionReset,
+                    "connection reset",
+                )))
+            })
+            .boxed()
+    }
+
+    // Builds a memory transport whose upgrades never complete, as if the remote accepted the
+    // connection but never finished the handshake.
+    fn build_stalled_transport() -> TestTransport {
+        MemoryTransport::default()
+            .and_then(|_socket, _addr, _origin| {
+                future::pending::<io::Result<Connection<MemorySocket>>>()
+            })
+            .boxed()
+    }
+
+    // A transport whose listener fails to accept a number of connections, and then ends.
+    struct FailingListenerTransport {
+        errors: usize,
+    }
+
+    impl Transport for FailingListenerTransport {
+        type Output = Connection<MemorySocket>;
+        type Error = io::Error;
+        type Listener =
+            stream::Iter<std::vec::IntoIter<io::Result<(Self::Inbound, NetworkAddress)>>>;
+        type Inbound = future::Pending<io::Result<Self::Output>>;
+        type Outbound = future::Pending<io::Result<Self::Output>>;
+
+        fn listen_on(&self, addr: NetworkAddress) -> io::Result<(Self::Listener, NetworkAddress)> {
+            let errors = (0..self.errors)
+                .map(|_| {
+                    Err(io::Error::new(
+                        io::ErrorKind::ConnectionAborted,
+                        "accept failed",
+                    ))
+                })
+                .collect::<Vec<_>>();
+            Ok((stream::iter(errors), addr))
+        }
+
+        fn dial(&self, _peer_id: PeerId, _addr: NetworkAddress) -> io::Result<Self::Outbound> {
+            Ok(future::pending())
         }
     }
+
+    fn build_test_transport_handler(
+        peer_id: PeerId,
+        transport: TestTransport,
+        time_service: TimeService,
+    ) -> (
+        TestTransportHandler,
+        NetworkAddress,
+        channel::Sender<TransportRequest>,
+        channel::Receiver<TransportNotification<MemorySocket>>,
+    ) {
+        let (transport_reqs_tx, transport_reqs_rx) = channel::new_test(1);
+        let (transport_notifs_tx, transport_notifs_rx) = channel::new_test(1);
+        let (transport_handler, listen_addr) = TransportHandler::new(
+            NetworkContext::mock_with_peer_id(peer_id),
+            time_service,
+            transport,
+            "/memory/0".parse().unwrap(),
+            transport_reqs_rx,
+            transport_notifs_tx,
+            TEST_DIAL_TIMEOUT,
+            TEST_INBOUND_UPGRADE_TIMEOUT,
+            TEST_MAX_CONCURRENT_INBOUND_UPGRADES,
+            TEST_MAX_CONCURRENT_OUTBOUND_UPGRADES,
+            None,
+            TEST_UPGRADE_LATENCY_LOG_INTERVAL,
+        );
+        (
+            transport_handler,
+            listen_addr,
+            transport_reqs_tx,
+            transport_notifs_rx,
+        )
+    }
+
+    // The ends of the two transport handlers spawned by `setup_two_handlers`, of which the tests
+    // have `a` dial `b`.
+    struct TwoHandlers {
+        peer_id_a: PeerId,
+        peer_id_b: PeerId,
+        listen_addr_b: NetworkAddress,
+        transport_reqs_tx_a: channel::Sender<TransportRequest>,
+        transport_notifs_rx_a: channel::Receiver<TransportNotification<MemorySocket>>,
+        transport_notifs_rx_b: channel::Receiver<TransportNotification<MemorySocket>>,
+    }
+
+    // Spawns the transport handlers `a` and `b` on the runtime, each with the transport built for
+    // its peer id and the time service given for it, once `configure` has adjusted them.
+    fn setup_two_handlers(
+        runtime: &Runtime,
+        build_transport_a: impl FnOnce(PeerId) -> TestTransport,
+        time_service_a: TimeService,
+        build_transport_b: impl FnOnce(PeerId) -> TestTransport,
+        time_service_b: TimeService,
+        configure: impl FnOnce(&mut TestTransportHandler, &mut TestTransportHandler),
+    ) -> TwoHandlers {
+        let peer_id_a = PeerId::random();
+        let peer_id_b = PeerId::random();
+        let (mut handler_a, _, transport_reqs_tx_a, transport_notifs_rx_a) =
+            build_test_transport_handler(peer_id_a, build_transport_a(peer_id_a), time_service_a);
+        // Without requests, `b` keeps serving its listener.
+        let (mut handler_b, listen_addr_b, _, transport_notifs_rx_b) =
+            build_test_transport_handler(peer_id_b, build_transport_b(peer_id_b), time_service_b);
+        configure(&mut handler_a, &mut handler_b);
+        runtime.spawn(handler_a.listen());
+        runtime.spawn(handler_b.listen());
+        TwoHandlers {
+            peer_id_a,
+            peer_id_b,
+            listen_addr_b,
+            transport_reqs_tx_a,
+            transport_notifs_rx_a,
+            transport_notifs_rx_b,
+        }
+    }
+
+    // Requests a dial of the peer at the address, returning the receiver of the result.
+    async fn dial(
+        transport_reqs_tx: &mut channel::Sender<TransportRequest>,
+        peer_id: PeerId,
+        addr: NetworkAddress,
+    ) -> oneshot::Receiver<Result<(), PeerManagerError>> {
+        let (response_tx, response_rx) = oneshot::channel();
+        transport_reqs_tx
+            .send(TransportRequest::DialPeer(peer_id, addr, response_tx))
+            .await
+            .unwrap();
+        response_rx
+    }
+
+    async fn expect_new_connection(
+        transport_notifs_rx: &mut channel::Receiver<TransportNotification<MemorySocket>>,
+    ) -> Connection<MemorySocket> {
+        match transport_notifs_rx.select_next_some().await {
+            TransportNotification::NewConnection(connection) => connection,
+            event => panic!("Expected a NewConnection event, received: {:?}", event),
+        }
+    }
+
+    #[test]
+    fn dial_establishes_connection_on_both_sides() {
+        let runtime = Runtime::new().unwrap();
+        let mut handlers = setup_two_handlers(
+            &runtime,
+            build_test_transport,
+            TimeService::mock(),
+            build_test_transport,
+            TimeService::mock(),
+            |_, _| {},
+        );
+        let (peer_id_a, peer_id_b) = (handlers.peer_id_a, handlers.peer_id_b);
+
+        let test = async move {
+            let response_rx = dial(
+                &mut handlers.transport_reqs_tx_a,
+                peer_id_b,
+                handlers.listen_addr_b.clone(),
+            )
+            .await;
+
+            let outbound = expect_new_connection(&mut handlers.transport_notifs_rx_a).await;
+            assert_eq!(outbound.metadata.remote_peer_id, peer_id_b);
+            assert_eq!(outbound.metadata.origin, ConnectionOrigin::Outbound);
+
+            let inbound = expect_new_connection(&mut handlers.transport_notifs_rx_b).await;
+            assert_eq!(inbound.metadata.remote_peer_id, peer_id_a);
+            assert_eq!(inbound.metadata.origin, ConnectionOrigin::Inbound);
+
+            response_rx.await.unwrap().unwrap();
+        };
+        runtime.block_on(test);
+
+        // Each side waited once for room to hand its connection to PeerManager.
+        let wait_samples = |peer_id, direction| {
+            counters::transport_notification_send_wait(
+                &NetworkContext::mock_with_peer_id(peer_id),
+                direction,
+            )
+            .get_sample_count()
+        };
+        assert_eq!(wait_samples(peer_id_a, ConnectionOrigin::Outbound), 1);
+        assert_eq!(wait_samples(peer_id_a, ConnectionOrigin::Inbound), 0);
+        assert_eq!(wait_samples(peer_id_b, ConnectionOrigin::Inbound), 1);
+    }
+
+    #[test]
+    fn upgrade_latency_percentiles_use_nearest_rank() {
+        let mut samples = (1..=100)
+            .rev()
+            .map(|i| i as f64 / 100.0)
+            .collect::<Vec<_>>();
+        assert_eq!(percentiles(&mut samples), (0.5, 0.95, 0.99));
+        assert_eq!(percentiles(&mut [0.2]), (0.2, 0.2, 0.2));
+        let mut samples = vec![0.4, 0.1, 0.3, 0.2];
+        assert_eq!(percentiles(&mut samples), (0.2, 0.4, 0.4));
+    }
+
+    #[test]
+    fn upgrade_latencies_are_summarized_per_direction() {
+        let mut latencies = UpgradeLatencies::default();
+        for i in (1..=100).rev() {
+            latencies.record(ConnectionOrigin::Outbound, i as f64 / 100.0);
+        }
+        latencies.record(ConnectionOrigin::Inbound, 0.2);
+        assert_eq!(
+            latencies.summarize(),
+            vec![
+                UpgradeLatencySummary {
+                    origin: ConnectionOrigin::Inbound,
+                    upgrades: 1,
+                    p50: 0.2,
+                    p95: 0.2,
+                    p99: 0.2,
+                },
+                UpgradeLatencySummary {
+                    origin: ConnectionOrigin::Outbound,
+                    upgrades: 100,
+                    p50: 0.5,
+                    p95: 0.95,
+                    p99: 0.99,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_upgrade_latency_window_is_not_summarized() {
+        assert!(UpgradeLatencies::default().summarize().is_empty());
+        let mut latencies = UpgradeLatencies::default();
+        latencies.record(ConnectionOrigin::Inbound, 0.1);
+        let summaries = latencies.summarize();
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].origin, ConnectionOrigin::Inbound);
+    }
+
+    #[test]
+    fn failed_upgrade_fails_dial() {
+        let runtime = Runtime::new().unwrap();
+        let mut handlers = setup_two_handlers(
+            &runtime,
+            |_| build_failing_transport(),
+            TimeService::mock(),
+            build_test_transport,
+            TimeService::mock(),
+            |_, _| {},
+        );
+        let peer_id_a = handlers.peer_id_a;
+
+        let test = async move {
+            let response_rx = dial(
+                &mut handlers.transport_reqs_tx_a,
+                handlers.peer_id_b,
+                handlers.listen_addr_b.clone(),
+            )
+            .await;
+            assert!(response_rx.await.unwrap().is_err());
+
+            // No connection is reported for a failed upgrade.
+            assert!(handlers
+                .transport_notifs_rx_a
+                .select_next_some()
+                .now_or_never()
+                .is_none());
+        };
+        runtime.block_on(test);
+
+        let network_context = NetworkContext::mock_with_peer_id(peer_id_a);
+        assert_eq!(
+            counters::connection_upgrade_time(
+                &network_context,
+                ConnectionOrigin::Outbound,
+                FAILED_LABEL
+            )
+            .get_sample_count(),
+            1
+        );
+    }
+
+    #[test]
+    fn inbound_upgrade_notifies_new_connection() {
+        let runtime = Runtime::new().unwrap();
+        let peer_id = PeerId::random();
+        let remote_peer_id = PeerId::random();
+
+        let (handler, listen_addr, _transport_reqs_tx, mut transport_notifs_rx) =
+            build_test_transport_handler(
+                peer_id,
+                build_test_transport(peer_id),
+                TimeService::mock(),
+            );
+        runtime.spawn(handler.listen());
+
+        let test = async move {
+            // Connect with a plain socket and perform the remote side of the handshake by hand.
+            let mut socket = MemoryTransport::default()
+                .dial(remote_peer_id, listen_addr)
+                .unwrap()
+                .await
+                .unwrap();
+            socket.write_all(remote_peer_id.as_ref()).await.unwrap();
+            let mut local_peer_id = [0u8; PeerId::LENGTH];
+            socket.read_exact(&mut local_peer_id).await.unwrap();
+            assert_eq!(PeerId::new(local_peer_id), peer_id);
+
+            let inbound = expect_new_connection(&mut transport_notifs_rx).await;
+            assert_eq!(inbound.metadata.remote_peer_id, remote_peer_id);
+            assert_eq!(inbound.metadata.origin, ConnectionOrigin::Inbound);
+        };
+        runtime.block_on(test);
+    }
+
+    #[test]
+    fn peer_id_mismatch_names_dialed_address() {
+        let runtime = Runtime::new().unwrap();
+        let mut handlers = setup_two_handlers(
+            &runtime,
+            build_test_transport,
+            TimeService::mock(),
+            build_test_transport,
+            TimeService::mock(),
+            |_, _| {},
+        );
+        let expected_peer_id = PeerId::random();
+
+        let test = async move {
+            let listen_addr_b = handlers.listen_addr_b.clone();
+            let response_rx = dial(
+                &mut handlers.transport_reqs_tx_a,
+                expected_peer_id,
+                listen_addr_b.clone(),
+            )
+            .await;
+
+            match response_rx.await.unwrap() {
+                Err(PeerManagerError::TransportError(err)) => {
+                    assert!(err.to_string().contains(&listen_addr_b.to_string()))
+                }
+                result => panic!("Expected a peer id mismatch, received: {:?}", result),
+            }
+            let mismatches = counters::outbound_peer_id_mismatches(
+                &NetworkContext::mock_with_peer_id(handlers.peer_id_a),
+                &expected_peer_id,
+            );
+            assert_eq!(mismatches.get(), 1);
+        };
+        runtime.block_on(test);
+    }
+
+    #[test]
+    fn dial_retry_backoff_doubles_until_attempts_are_used_up() {
+        let policy = DialRetryPolicy {
+            max_attempts: 4,
+            backoff: Duration::from_millis(100),
+        };
+        let backoffs = (1..=4)
+            .map(|failed| policy.backoff(failed))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            backoffs,
+            vec![
+                Some(Duration::from_millis(100)),
+                Some(Duration::from_millis(200)),
+                Some(Duration::from_millis(400)),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn transient_dial_failures_are_retried() {
+        let runtime = Runtime::new().unwrap();
+        let time_service = TimeService::mock();
+        let mock_time = time_service.clone().into_mock();
+        let dials = Arc::new(AtomicUsize::new(0));
+        let backoff = Duration::from_secs(1);
+        let mut handlers = setup_two_handlers(
+            &runtime,
+            {
+                let dials = dials.clone();
+                move |_| build_resetting_transport(dials)
+            },
+            time_service,
+            build_test_transport,
+            TimeService::mock(),
+            |handler_a, _| {
+                handler_a.dial_retry_policy = Some(DialRetryPolicy {
+                    max_attempts: 3,
+                    backoff,
+                })
+            },
+        );
+
+        let network_context = NetworkContext::mock_with_peer_id(handlers.peer_id_a);
+        let retries = counters::dial_retries(&network_context, "ConnectionReset");
+        let pending_upgrades =
+            counters::pending_connection_upgrades(&network_context, ConnectionOrigin::Outbound);
+        let test = async move {
+            let response_rx = dial(
+                &mut handlers.transport_reqs_tx_a,
+                handlers.peer_id_b,
+                handlers.listen_addr_b.clone(),
+            )
+            .await;
+
+            // Each retry waits for its backoff, which doubles, before dialing again.
+            for (retry, backoff) in vec![(1, backoff), (2, backoff * 2)] {
+                while retries.get() < retry || mock_time.num_waiters() < TICKER_WAITERS + 1 {
+                    ::tokio::task::yield_now().await;
+                }
+                assert_eq!(dials.load(Ordering::SeqCst), retry as usize);
+                // The retry is not upgrading anything during its backoff.
+                assert_eq!(pending_upgrades.get(), 0);
+                mock_time.advance_async(backoff).await;
+            }
+
+            match response_rx.await.unwrap() {
+                Err(PeerManagerError::TransportError(err)) => {
+                    assert!(err.to_string().contains("connection reset"), "{}", err)
+                }
+                result => panic!("Expected the dial to fail, received: {:?}", result),
+            }
+            assert_eq!(dials.load(Ordering::SeqCst), 3);
+            assert_eq!(retries.get(), 2);
+        };
+        runtime.block_on(test);
+    }
+
+    #[test]
+    fn peer_id_mismatch_is_never_retried() {
+        let runtime = Runtime::new().unwrap();
+        let mut handlers = setup_two_handlers(
+            &runtime,
+            build_test_transport,
+            TimeService::mock(),
+            build_test_transport,
+            TimeService::mock(),
+            |handler_a, _| {
+                handler_a.dial_retry_policy = Some(DialRetryPolicy {
+                    max_attempts: 3,
+                    backoff: Duration::from_secs(1),
+                })
+            },
+        );
+        let expected_peer_id = PeerId::random();
+
+        let test = async move {
+            let response_rx = dial(
+                &mut handlers.transport_reqs_tx_a,
+                expected_peer_id,
+                handlers.listen_addr_b.clone(),
+            )
+            .await;
+
+            // The mock time never advances, so a retry would wait for its backoff forever.
+            assert!(response_rx.await.unwrap().is_err());
+            let mismatches = counters::outbound_peer_id_mismatches(
+                &NetworkContext::mock_with_peer_id(handlers.peer_id_a),
+                &expected_peer_id,
+            );
+            assert_eq!(mismatches.get(), 1);
+        };
+        runtime.block_on(test);
+    }
+
+    #[test]
+    fn accept_errors_are_counted() {
+        let runtime = Runtime::new().unwrap();
+        let peer_id = PeerId::random();
+        let (handler, _, transport_reqs_tx, _transport_notifs_rx) = build_test_transport_handler(
+            peer_id,
+            FailingListenerTransport { errors: 3 }.boxed(),
+            TimeService::mock(),
+        );
+        // Without requests, the handler ends once its listener ends.
+        drop(transport_reqs_tx);
+        runtime.block_on(handler.listen());
+
+        let network_context = NetworkContext::mock_with_peer_id(peer_id);
+        assert_eq!(
+            counters::listener_accept_errors(&network_context, "ConnectionAborted").get(),
+            3
+        );
+        assert_eq!(
+            error_kind_label(&PeerManagerError::DialPending(peer_id)),
+            "other"
+        );
+    }
+
+    #[test]
+    fn dial_initiation_failures_are_counted() {
+        let runtime = Runtime::new().unwrap();
+        let peer_id = PeerId::random();
+        let (handler, _, mut transport_reqs_tx, _transport_notifs_rx) =
+            build_test_transport_handler(
+                peer_id,
+                build_test_transport(peer_id),
+                TimeService::mock(),
+            );
+        runtime.spawn(handler.listen());
+
+        let test = async move {
+            // The memory transport cannot dial tcp addresses.
+            let response_rx = dial(
+                &mut transport_reqs_tx,
+                PeerId::random(),
+                "/ip4/127.0.0.1/tcp/6180".parse().unwrap(),
+            )
+            .await;
+            assert!(response_rx.await.unwrap().is_err());
+        };
+        runtime.block_on(test);
+
+        let network_context = NetworkContext::mock_with_peer_id(peer_id);
+        assert_eq!(
+            counters::dial_initiation_failures(&network_context, "InvalidInput").get(),
+            1
+        );
+        assert_eq!(
+            counters::connection_upgrade_time(
+                &network_context,
+                ConnectionOrigin::Outbound,
+                FAILED_LABEL
+            )
+            .get_sample_count(),
+            0
+        );
+    }
+
+    #[test]
+    fn mismatched_outbound_connection_is_closed() {
+        let runtime = Runtime::new().unwrap();
+        let mut handlers = setup_two_handlers(
+            &runtime,
+            build_test_transport,
+            TimeService::mock(),
+            build_test_transport,
+            TimeService::mock(),
+            |_, _| {},
+        );
+
+        let test = async move {
+            let response_rx = dial(
+                &mut handlers.transport_reqs_tx_a,
+                PeerId::random(),
+                handlers.listen_addr_b.clone(),
+            )
+            .await;
+            assert!(response_rx.await.unwrap().is_err());
+
+            // The remote sees the connection closed.
+            let mut inbound = expect_new_connection(&mut handlers.transport_notifs_rx_b).await;
+            let mut buf = [0u8; 1];
+            assert_eq!(inbound.socket.read(&mut buf).await.unwrap(), 0);
+        };
+        runtime.block_on(test);
+    }
+
+    #[test]
+    fn outbound_connection_is_closed_if_peer_manager_is_gone() {
+        let runtime = Runtime::new().unwrap();
+        let TwoHandlers {
+            peer_id_b,
+            listen_addr_b,
+            mut transport_reqs_tx_a,
+            transport_notifs_rx_a,
+            mut transport_notifs_rx_b,
+            ..
+        } = setup_two_handlers(
+            &runtime,
+            build_test_transport,
+            TimeService::mock(),
+            build_test_transport,
+            TimeService::mock(),
+            |_, _| {},
+        );
+        // PeerManager of the dialer has shut down.
+        drop(transport_notifs_rx_a);
+
+        let test = async move {
+            let response_rx =
+                dial(&mut transport_reqs_tx_a, peer_id_b, listen_addr_b.clone()).await;
+
+            match response_rx.await.unwrap() {
+                Err(PeerManagerError::MpscSendError(_)) => {}
+                result => panic!("Expected the dial to fail, received: {:?}", result),
+            }
+
+            // The remote sees the connection closed.
+            let mut inbound = expect_new_connection(&mut transport_notifs_rx_b).await;
+            let mut buf = [0u8; 1];
+            assert_eq!(inbound.socket.read(&mut buf).await.unwrap(), 0);
+
+            // The listener of the dialer keeps serving requests.
+            let response_rx = dial(&mut transport_reqs_tx_a, peer_id_b, listen_addr_b).await;
+            assert!(response_rx.await.unwrap().is_err());
+        };
+        runtime.block_on(test);
+    }
+
+    #[test]
+    fn dial_times_out_if_upgrade_never_completes() {
+        let runtime = Runtime::new().unwrap();
+        let time_service = TimeService::mock();
+        let mock_time = time_service.clone().into_mock();
+        let mut handlers = setup_two_handlers(
+            &runtime,
+            |_| build_stalled_transport(),
+            time_service,
+            |_| build_stalled_transport(),
+            TimeService::mock(),
+            |_, _| {},
+        );
+
+        let test = async move {
+            let response_rx = dial(
+                &mut handlers.transport_reqs_tx_a,
+                handlers.peer_id_b,
+                handlers.listen_addr_b.clone(),
+            )
+            .await;
+
+            // Wait for the dial to start its timeout before letting the timeout elapse.
+            while mock_time.num_waiters() < TICKER_WAITERS + 1 {
+                ::tokio::task::yield_now().await;
+            }
+            mock_time.advance_async(TEST_DIAL_TIMEOUT).await;
+
+            match response_rx.await.unwrap() {
+                Err(PeerManagerError::TransportError(err)) => {
+                    assert!(err.to_string().contains("timed out"), "{}", err)
+                }
+                result => panic!("Expected the dial to time out, received: {:?}", result),
+            }
+        };
+        runtime.block_on(test);
+    }
+
+    #[test]
+    fn inbound_upgrade_times_out_if_it_never_completes() {
+        let runtime = Runtime::new().unwrap();
+        let time_service = TimeService::mock();
+        let mock_time = time_service.clone().into_mock();
+        let mut handlers = setup_two_handlers(
+            &runtime,
+            |_| build_stalled_transport(),
+            TimeService::mock(),
+            |_| build_stalled_transport(),
+            time_service,
+            |_, _| {},
+        );
+
+        let network_context = NetworkContext::mock_with_peer_id(handlers.peer_id_b);
+        let pending_upgrades =
+            counters::pending_connection_upgrades(&network_context, ConnectionOrigin::Inbound);
+        let failed_upgrades = counters::connection_upgrade_time(
+            &network_context,
+            ConnectionOrigin::Inbound,
+            FAILED_LABEL,
+        );
+
+        let test = async move {
+            let _response_rx = dial(
+                &mut handlers.transport_reqs_tx_a,
+                handlers.peer_id_b,
+                handlers.listen_addr_b.clone(),
+            )
+            .await;
+
+            // Wait for the inbound upgrade to start its timeout before letting it elapse.
+            while mock_time.num_waiters() < TICKER_WAITERS + 1 {
+                ::tokio::task::yield_now().await;
+            }
+            assert_eq!(pending_upgrades.get(), 1);
+            mock_time.advance_async(TEST_INBOUND_UPGRADE_TIMEOUT).await;
+
+            while pending_upgrades.get() != 0 {
+                ::tokio::task::yield_now().await;
+            }
+            assert_eq!(failed_upgrades.get_sample_count(), 1);
+        };
+        runtime.block_on(test);
+    }
+
+    #[test]
+    fn inbound_connections_beyond_limit_are_dropped() {
+        let runtime = Runtime::new().unwrap();
+        let mut handlers = setup_two_handlers(
+            &runtime,
+            |_| build_stalled_transport(),
+            TimeService::mock(),
+            |_| build_stalled_transport(),
+            TimeService::mock(),
+            |_, handler_b| handler_b.max_concurrent_inbound_upgrades = 1,
+        );
+
+        let network_context = NetworkContext::mock_with_peer_id(handlers.peer_id_b);
+        let pending_upgrades =
+            counters::pending_connection_upgrades(&network_context, ConnectionOrigin::Inbound);
+        let rejected_upgrades = counters::inbound_upgrades_rejected(&network_context);
+
+        let test = async move {
+            let mut response_rxs = vec![];
+            // The stalled transport does not check the peer id, so each dial expects another
+            // one, as simultaneous dials of the same peer are rejected.
+            for _ in 0..2 {
+                response_rxs.push(
+                    dial(
+                        &mut handlers.transport_reqs_tx_a,
+                        PeerId::random(),
+                        handlers.listen_addr_b.clone(),
+                    )
+                    .await,
+                );
+            }
+
+            // The first connection is being upgraded, so the second one is dropped.
+            while rejected_upgrades.get() == 0 {
+                ::tokio::task::yield_now().await;
+            }
+            assert_eq!(rejected_upgrades.get(), 1);
+            assert_eq!(pending_upgrades.get(), 1);
+        };
+        runtime.block_on(test);
+    }
+
+    #[test]
+    fn dials_beyond_limit_wait_for_pending_dials() {
+        let runtime = Runtime::new().unwrap();
+        let time_service = TimeService::mock();
+        let mock_time = time_service.clone().into_mock();
+        let mut handlers = setup_two_handlers(
+            &runtime,
+            |_| build_stalled_transport(),
+            time_service,
+            |_| build_stalled_transport(),
+            TimeService::mock(),
+            |handler_a, _| handler_a.max_concurrent_outbound_upgrades = 1,
+        );
+
+        let pending_upgrades = counters::pending_connection_upgrades(
+            &NetworkContext::mock_with_peer_id(handlers.peer_id_a),
+            ConnectionOrigin::Outbound,
+        );
+
+        let test = async move {
+            let mut response_rxs = vec![];
+            for _ in 0..2 {
+                response_rxs.push(
+                    dial(
+                        &mut handlers.transport_reqs_tx_a,
+                        PeerId::random(),
+                        handlers.listen_addr_b.clone(),
+                    )
+                    .await,
+                );
+            }
+            let second_rx = response_rxs.pop().unwrap();
+            let first_rx = response_rxs.pop().unwrap();
+
+            // Only the first dial is upgraded, the second one waits in the channel.
+            while mock_time.num_waiters() < TICKER_WAITERS + 1 {
+                ::tokio::task::yield_now().await;
+            }
+            for _ in 0..10 {
+                ::tokio::task::yield_now().await;
+            }
+            assert_eq!(pending_upgrades.get(), 1);
+            assert_eq!(mock_time.num_waiters(), TICKER_WAITERS + 1);
+
+            // Once the first dial times out, the second one is upgraded.
+            mock_time.advance_async(TEST_DIAL_TIMEOUT).await;
+            assert!(first_rx.await.unwrap().is_err());
+            while mock_time.num_waiters() < TICKER_WAITERS + 1 {
+                ::tokio::task::yield_now().await;
+            }
+            assert_eq!(pending_upgrades.get(), 1);
+            mock_time.advance_async(TEST_DIAL_TIMEOUT).await;
+            assert!(second_rx.await.unwrap().is_err());
+        };
+        runtime.block_on(test);
+    }
+
+    #[test]
+    fn pending_dial_is_cancelled() {
+        let runtime = Runtime::new().unwrap();
+        let time_service = TimeService::mock();
+        let mock_time = time_service.clone().into_mock();
+        let mut handlers = setup_two_handlers(
+            &runtime,
+            |_| build_stalled_transport(),
+            time_service,
+            |_| build_stalled_transport(),
+            TimeService::mock(),
+            |_, _| {},
+        );
+        let peer_id_b = handlers.peer_id_b;
+
+        let test = async move {
+            let response_rx = dial(
+                &mut handlers.transport_reqs_tx_a,
+                peer_id_b,
+                handlers.listen_addr_b.clone(),
+            )
+            .await;
+
+            // Wait for the dial to be pending before cancelling it.
+            while mock_time.num_waiters() < TICKER_WAITERS + 1 {
+                ::tokio::task::yield_now().await;
+            }
+            handlers
+                .transport_reqs_tx_a
+                .send(TransportRequest::CancelDial(peer_id_b))
+                .await
+                .unwrap();
+
+            match response_rx.await.unwrap() {
+                Err(PeerManagerError::DialCancelled(peer_id)) => assert_eq!(peer_id, peer_id_b),
+                result => panic!("Expected the dial to be cancelled, received: {:?}", result),
+            }
+        };
+        runtime.block_on(test);
+    }
+
+    #[test]
+    fn simultaneous_dials_are_deduplicated() {
+        let runtime = Runtime::new().unwrap();
+        // Counts the outbound upgrades started by the dialer, none of which ever complete.
+        let outbound_upgrades = Arc::new(AtomicUsize::new(0));
+        let transport_a = {
+            let outbound_upgrades = outbound_upgrades.clone();
+            MemoryTransport::default()
+                .and_then(move |_socket, _addr, origin| {
+                    if origin == ConnectionOrigin::Outbound {
+                        outbound_upgrades.fetch_add(1, Ordering::SeqCst);
+                    }
+                    future::pending::<io::Result<Connection<MemorySocket>>>()
+                })
+                .boxed()
+        };
+        let mut handlers = setup_two_handlers(
+            &runtime,
+            |_| transport_a,
+            TimeService::mock(),
+            |_| build_stalled_transport(),
+            TimeService::mock(),
+            |_, _| {},
+        );
+        let peer_id_b = handlers.peer_id_b;
+
+        let test = async move {
+            let _first_rx = dial(
+                &mut handlers.transport_reqs_tx_a,
+                peer_id_b,
+                handlers.listen_addr_b.clone(),
+            )
+            .await;
+            let second_rx = dial(
+                &mut handlers.transport_reqs_tx_a,
+                peer_id_b,
+                handlers.listen_addr_b.clone(),
+            )
+            .await;
+
+            match second_rx.await.unwrap() {
+                Err(PeerManagerError::DialPending(peer_id)) => assert_eq!(peer_id, peer_id_b),
+                result => panic!("Expected the dial to be rejected, received: {:?}", result),
+            }
+
+            // Only the first dial starts an upgrade.
+            while outbound_upgrades.load(Ordering::SeqCst) == 0 {
+                ::tokio::task::yield_now().await;
+            }
+            for _ in 0..10 {
+                ::tokio::task::yield_now().await;
+            }
+            assert_eq!(outbound_upgrades.load(Ordering::SeqCst), 1);
+        };
+        runtime.block_on(test);
+    }
 }
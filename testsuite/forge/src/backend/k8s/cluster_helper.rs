@@ -1,7 +1,7 @@
 // Copyright (c) The Diem Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{get_validators, k8s_retry_strategy, nodes_healthcheck, Result, Validator};
+use crate::{get_validators, k8s_retry_strategy, nodes_healthcheck, K8sRetryStrategy, Result};
 use anyhow::{bail, format_err};
 use diem_logger::*;
 use hyper::{Client, Uri};
@@ -11,7 +11,6 @@ use k8s_openapi::api::batch::v1::Job;
 use kube::{api::Api, client::Client as K8sClient, Config};
 use rand::Rng;
 use rayon::prelude::*;
-use regex::Regex;
 use rusoto_core::Region;
 use rusoto_credential::EnvironmentProvider;
 use rusoto_eks::{
@@ -21,12 +20,16 @@ use rusoto_sts::WebIdentityProvider;
 use serde_json::Value;
 use std::{
     cmp,
+    collections::BTreeSet,
     convert::TryFrom,
     env,
     fs::File,
-    io::Write,
-    process::{Command, Stdio},
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    process::{Child, Command, Output, Stdio},
     str,
+    sync::Arc,
+    thread,
 };
 use tempfile::TempDir;
 use tokio::runtime::Runtime;
@@ -47,7 +50,7 @@ async fn wait_genesis_job(kube_client: &K8sClient, era: &str) -> Result<()> {
             let status = genesis_job.status.unwrap();
             match status.succeeded {
                 Some(1) => {
-                    println!("Genesis job completed");
+                    info!("Genesis job completed");
                     Ok(())
                 }
                 _ => bail!("Genesis job not completed"),
@@ -57,6 +60,39 @@ async fn wait_genesis_job(kube_client: &K8sClient, era: &str) -> Result<()> {
     .await
 }
 
+/// Returns the index of the validator the helm release belongs to, if it is of the form `valN`.
+fn validator_index(release_name: &str) -> Option<usize> {
+    release_name.strip_prefix("val")?.parse().ok()
+}
+
+/// Runs a helm or kubectl command concerning the given release and captures its output, which is
+/// logged line by line with the index of the validator of the release, so the output of commands
+/// run in parallel can be told apart. The output on stdout is only logged if `log_stdout` is set,
+/// the one on stderr always at `warn` level, or at `error` level if the command failed.
+fn run_command(
+    bin: &str,
+    args: &[&str],
+    release_name: &str,
+    log_stdout: bool,
+) -> std::io::Result<Output> {
+    let validator_index = validator_index(release_name);
+    debug!(validator_index = validator_index, "{} {:?}", bin, args);
+    let output = Command::new(bin).args(args).output()?;
+    if log_stdout {
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            info!(validator_index = validator_index, "{}", line);
+        }
+    }
+    for line in String::from_utf8_lossy(&output.stderr).lines() {
+        if output.status.success() {
+            warn!(validator_index = validator_index, "{}", line);
+        } else {
+            error!(validator_index = validator_index, "{}", line);
+        }
+    }
+    Ok(output)
+}
+
 pub fn set_validator_image_tag(
     validator_name: &str,
     image_tag: &str,
@@ -74,21 +110,19 @@ pub fn set_validator_image_tag(
 
 pub(crate) fn remove_helm_release(release_name: &str) -> Result<()> {
     let release_uninstall_args = ["uninstall", "--keep-history", release_name];
-    println!("{:?}", release_uninstall_args);
-    let release_uninstall_output = Command::new(HELM_BIN)
-        .stdout(Stdio::inherit())
-        .args(&release_uninstall_args)
-        .output()
-        .expect("failed to helm uninstall valNN");
-
-    let uninstalled_re = Regex::new(r"already deleted").unwrap();
-    let uninstall_stderr = String::from_utf8(release_uninstall_output.stderr).unwrap();
-    let already_uninstalled = uninstalled_re.is_match(&uninstall_stderr);
-    assert!(
-        release_uninstall_output.status.success() || already_uninstalled,
-        "{}",
-        uninstall_stderr
-    );
+    let release_uninstall_output =
+        run_command(HELM_BIN, &release_uninstall_args, release_name, true)
+            .map_err(|e| format_err!("Failed to helm uninstall {}: {}", release_name, e))?;
+
+    let uninstall_stderr = String::from_utf8_lossy(&release_uninstall_output.stderr);
+    let already_uninstalled = uninstall_stderr.contains("already deleted");
+    if !release_uninstall_output.status.success() && !already_uninstalled {
+        bail!(
+            "Failed to helm uninstall {}: {}",
+            release_name,
+            uninstall_stderr
+        );
+    }
     Ok(())
 }
 
@@ -103,11 +137,7 @@ fn helm_release_patch(release_name: &str, version: usize) -> Result<()> {
         "-p",
         "{\"metadata\":{\"labels\":{\"status\":\"deployed\"}}}",
     ];
-    println!("{:?}", helm_patch_args);
-    let helm_patch_output = Command::new(KUBECTL_BIN)
-        .stdout(Stdio::inherit())
-        .args(&helm_patch_args)
-        .output()
+    let helm_patch_output = run_command(KUBECTL_BIN, &helm_patch_args, release_name, true)
         .expect("failed to kubectl patch secret valNN");
     assert!(
         helm_patch_output.status.success(),
@@ -118,15 +148,39 @@ fn helm_release_patch(release_name: &str, version: usize) -> Result<()> {
     Ok(())
 }
 
+/// Returns the logs of the pods of the given helm release, as printed by `kubectl logs`. Unless
+/// `tail_lines` is given, the complete logs are returned, and unless `container` is given, the
+/// logs of all containers, each line prefixed with the pod and container it stems from.
+pub fn get_release_logs(
+    release_name: &str,
+    tail_lines: Option<usize>,
+    container: Option<&str>,
+) -> Result<String> {
+    let selector = format!("app.kubernetes.io/instance={}", release_name);
+    // With a selector, kubectl only prints the last 10 lines by default.
+    let tail = tail_lines.map_or_else(|| "-1".to_string(), |n| n.to_string());
+    let mut logs_args = vec!["logs", "--selector", &selector, "--tail", &tail, "--prefix"];
+    match container {
+        Some(container) => logs_args.extend(&["--container", container]),
+        None => logs_args.push("--all-containers"),
+    }
+    debug!("{:?}", logs_args);
+    let logs_output = Command::new(KUBECTL_BIN).args(&logs_args).output()?;
+    if !logs_output.status.success() {
+        bail!(
+            "Failed to get the logs of {}: {}",
+            release_name,
+            String::from_utf8_lossy(&logs_output.stderr)
+        );
+    }
+    Ok(String::from_utf8_lossy(&logs_output.stdout).to_string())
+}
+
 fn upgrade_helm_release(release_name: &str, helm_chart: &str, options: &[&str]) -> Result<()> {
     let upgrade_base_args = ["upgrade", release_name, helm_chart];
     let upgrade_args = [&upgrade_base_args, options].concat();
-    println!("{:?}", upgrade_args);
-    let upgrade_output = Command::new(HELM_BIN)
-        .stdout(Stdio::inherit())
-        .args(&upgrade_args)
-        .output()
-        .unwrap_or_else(|_| {
+    let upgrade_output =
+        run_command(HELM_BIN, &upgrade_args, release_name, true).unwrap_or_else(|_| {
             panic!(
                 "failed to helm upgrade release {} with chart {}",
                 release_name, helm_chart
@@ -150,16 +204,45 @@ fn upgrade_validator(validator_name: &str, helm_repo: &str, options: &[&str]) ->
     )
 }
 
+/// Installs a public full node as the helm release of the given name. The node joins the chain
+/// of the current era of the testnet, and discovers its peers from the on-chain validator set
+/// only, as the chart is configured with the seeds of the public Diem testnet by default.
+pub(crate) fn install_public_fullnode(
+    release_name: &str,
+    image_tag: &str,
+    helm_repo: &str,
+) -> Result<()> {
+    let v: Value = get_helm_values("diem")?;
+    let era = era_to_string(&v["genesis"]["era"])?;
+    let fullnode_install_options = [
+        "--install",
+        "--history-max",
+        "2",
+        "--set",
+        &format!("chain.era={}", &era),
+        "--set",
+        &format!("chain.genesisConfigmap=diem-testnet-genesis-e{}", &era),
+        "--set",
+        &format!("image.tag={}", image_tag),
+        // The default seeds are the full nodes of another chain, which the node cannot join.
+        "--set",
+        "diem_chains.testnet.seeds=null",
+    ];
+    upgrade_helm_release(
+        release_name,
+        &format!("{}/diem-fullnode", helm_repo),
+        &fullnode_install_options,
+    )
+}
+
 fn upgrade_testnet(helm_repo: &str, options: &[&str]) -> Result<()> {
     upgrade_helm_release("diem", &format!("{}/testnet", helm_repo), options)
 }
 
 fn get_helm_status(helm_release_name: &str) -> Result<Value> {
     let status_args = ["status", helm_release_name, "-o", "json"];
-    println!("{:?}", status_args);
-    let raw_helm_values = Command::new(HELM_BIN)
-        .args(&status_args)
-        .output()
+    // The status is parsed rather than logged.
+    let raw_helm_values = run_command(HELM_BIN, &status_args, helm_release_name, false)
         .unwrap_or_else(|_| panic!("failed to helm status {}", helm_release_name));
 
     let helm_values = String::from_utf8(raw_helm_values.stdout).unwrap();
@@ -173,12 +256,34 @@ fn get_helm_values(helm_release_name: &str) -> Result<Value> {
     Ok(v["config"].take())
 }
 
+/// Returns the indices of the validators with a helm release of the form `valN`, including the
+/// releases which have been uninstalled while keeping their history.
+fn get_validator_releases() -> Result<BTreeSet<usize>> {
+    let list_args = ["list", "--all", "-o", "json"];
+    // The list is parsed rather than logged.
+    let list_output = run_command(HELM_BIN, &list_args, "", false)?;
+    if !list_output.status.success() {
+        bail!(
+            "Failed to list helm releases: {}",
+            String::from_utf8_lossy(&list_output.stderr)
+        );
+    }
+    let releases: Value = serde_json::from_slice(&list_output.stdout)
+        .map_err(|e| format_err!("failed to deserialize helm releases: {}", e))?;
+    Ok(releases
+        .as_array()
+        .ok_or_else(|| format_err!("helm releases are not a list: {}", releases))?
+        .iter()
+        .filter_map(|release| validator_index(release["name"].as_str()?))
+        .collect())
+}
+
 pub fn uninstall_from_k8s_cluster() -> Result<()> {
     // helm uninstall validators while keeping history for later
-    (0..MAX_NUM_VALIDATORS).into_par_iter().for_each(|i| {
-        remove_helm_release(&format!("val{}", i)).unwrap();
-    });
-    println!("All validators removed");
+    (0..MAX_NUM_VALIDATORS)
+        .into_par_iter()
+        .try_for_each(|i| remove_helm_release(&format!("val{}", i)))?;
+    info!("All validators removed");
 
     // NOTE: for now, do not remove testnet helm chart since it is more expensive
     // remove_helm_release("diem").unwrap();
@@ -195,6 +300,26 @@ pub fn clean_k8s_cluster(
 ) -> Result<()> {
     assert!(base_num_validators <= MAX_NUM_VALIDATORS);
 
+    // the releases of the validators are reused, so they must all exist
+    let validator_releases = get_validator_releases()?;
+    let missing = (0..base_num_validators)
+        .filter(|i| !validator_releases.contains(i))
+        .map(|i| format!("val{}", i))
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        bail!(
+            "Requested {} validators, but the cluster only has the {} validator releases {:?}, \
+            missing {}",
+            base_num_validators,
+            validator_releases.len(),
+            validator_releases
+                .iter()
+                .map(|i| format!("val{}", i))
+                .collect::<Vec<_>>(),
+            missing.join(", ")
+        );
+    }
+
     let new_era = get_new_era().unwrap();
 
     let tmp_dir = TempDir::new().expect("Could not create temp dir");
@@ -215,14 +340,17 @@ pub fn clean_k8s_cluster(
 
         // store the helm values for later use
         let file_path = tmp_dir.path().join(format!("val{}_status.json", i));
-        println!("Wrote helm values to: {:?}", &file_path);
+        debug!(
+            validator_index = i,
+            "Wrote helm values to: {:?}", &file_path
+        );
         let mut file = File::create(file_path).expect("Could not create file in temp dir");
         file.write_all(&config.to_string().into_bytes())
             .expect("Could not write to file");
 
         helm_release_patch(&format!("val{}", i), version).unwrap();
     });
-    println!("All validators prepare for upgrade");
+    info!("All validators prepare for upgrade");
 
     // upgrade validators in parallel
     (0..base_num_validators).into_par_iter().for_each(|i| {
@@ -246,7 +374,7 @@ pub fn clean_k8s_cluster(
         ];
         upgrade_validator(&format!("val{}", i), &helm_repo, &validator_upgrade_options).unwrap();
     });
-    println!("All validators upgraded");
+    info!("All validators upgraded");
 
     // get testnet values
     let v: Value = get_helm_status("diem").unwrap();
@@ -258,7 +386,7 @@ pub fn clean_k8s_cluster(
 
     // store the helm values for later use
     let file_path = tmp_dir.path().join("diem_status.json");
-    println!("Wrote helm values to: {:?}", &file_path);
+    debug!("Wrote helm values to: {:?}", &file_path);
     let mut file = File::create(file_path).expect("Could not create file in temp dir");
     file.write_all(&config.to_string().into_bytes())
         .expect("Could not write to file");
@@ -287,33 +415,40 @@ pub fn clean_k8s_cluster(
     upgrade_testnet(&helm_repo, &testnet_upgrade_options)?;
 
     // wait for genesis to run again, and get the updated validators
-    let rt = Runtime::new().unwrap();
+    let rt = Arc::new(Runtime::new().unwrap());
+    let retry_strategy = K8sRetryStrategy::default();
+    let mut kubectl_proxy = None;
     let mut validators = rt.block_on(async {
-        let kube_client = create_k8s_client().await;
+        let kube_client = create_k8s_client(&mut kubectl_proxy).await;
         wait_genesis_job(&kube_client, &new_era).await.unwrap();
-        let vals = get_validators(kube_client.clone(), &base_validator_image_tag)
-            .await
-            .unwrap();
+        let vals = get_validators(
+            kube_client.clone(),
+            &base_validator_image_tag,
+            rt.clone(),
+            retry_strategy,
+        )
+        .await
+        .unwrap();
         vals
     });
-    let all_nodes = Box::new(validators.values_mut().map(|v| v as &mut dyn Validator));
+    stop_kubectl_proxy(&mut kubectl_proxy);
 
     // healthcheck on each of the validators wait until they all healthy
     if require_validator_healthcheck {
-        return nodes_healthcheck(all_nodes);
+        return nodes_healthcheck(validators.values_mut().collect(), retry_strategy);
     }
     Ok(())
 }
 
 fn get_new_era() -> Result<String> {
     let v: Value = get_helm_values("diem")?;
-    println!("{}", v["genesis"]["era"]);
+    debug!("{}", v["genesis"]["era"]);
     let chain_era: &str = &era_to_string(&v["genesis"]["era"]).unwrap();
 
     // get the new era
     let mut rng = rand::thread_rng();
     let new_era: &str = &format!("fg{}", rng.gen::<u32>());
-    println!("genesis.era: {} --> {}", chain_era, new_era);
+    info!("genesis.era: {} --> {}", chain_era, new_era);
     Ok(new_era.to_string())
 }
 
@@ -326,8 +461,118 @@ fn era_to_string(era_value: &Value) -> Result<String> {
     }
 }
 
-pub async fn create_k8s_client() -> K8sClient {
-    let _ = Command::new(KUBECTL_BIN).arg("proxy").spawn();
+/// Makes sure a `kubectl proxy` to the cluster is running, reusing the given process if it is
+/// still alive and spawning a new one only if it has died.
+pub fn ensure_kubectl_proxy(proxy: &mut Option<Child>) -> Result<()> {
+    if let Some(child) = proxy {
+        if child.try_wait()?.is_none() {
+            return Ok(());
+        }
+        debug!("kubectl proxy exited, respawning it");
+    }
+    *proxy = Some(Command::new(KUBECTL_BIN).arg("proxy").spawn()?);
+    Ok(())
+}
+
+/// Kills the given `kubectl proxy`, if any.
+pub fn stop_kubectl_proxy(proxy: &mut Option<Child>) {
+    if let Some(mut child) = proxy.take() {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+/// A `kubectl port-forward` from a local port to a port of a pod, which is stopped when dropped.
+pub struct PortForward {
+    child: Child,
+    local_port: u16,
+}
+
+impl PortForward {
+    /// Returns the local port connections to which are forwarded to the pod.
+    pub fn local_port(&self) -> u16 {
+        self.local_port
+    }
+}
+
+impl Drop for PortForward {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Returns the names of the pods of the given helm release.
+pub fn get_release_pods(release_name: &str) -> Result<Vec<String>> {
+    let selector = format!("app.kubernetes.io/instance={}", release_name);
+    let get_pods_args = [
+        "get",
+        "pods",
+        "--selector",
+        &selector,
+        "-o",
+        "jsonpath={.items[*].metadata.name}",
+    ];
+    // The names are parsed rather than logged.
+    let get_pods_output = run_command(KUBECTL_BIN, &get_pods_args, release_name, false)?;
+    if !get_pods_output.status.success() {
+        bail!(
+            "Failed to get the pods of {}: {}",
+            release_name,
+            String::from_utf8_lossy(&get_pods_output.stderr)
+        );
+    }
+    Ok(String::from_utf8_lossy(&get_pods_output.stdout)
+        .split_whitespace()
+        .map(str::to_string)
+        .collect())
+}
+
+/// Forwards the given local port, or a free one if none is given, to the remote port of the pod,
+/// and returns once the forward is established.
+pub fn port_forward(pod: &str, local_port: Option<u16>, remote_port: u16) -> Result<PortForward> {
+    let local_port = match local_port {
+        Some(port) => port,
+        // The port is released again for kubectl to bind, so another process may take it in
+        // between, in which case kubectl fails and so does the forward.
+        None => TcpListener::bind(("127.0.0.1", 0))?.local_addr()?.port(),
+    };
+    let ports = format!("{}:{}", local_port, remote_port);
+    debug!("{} port-forward {} {}", KUBECTL_BIN, pod, ports);
+    let mut child = Command::new(KUBECTL_BIN)
+        .args(&["port-forward", pod, &ports])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // kubectl prints a line for each address it forwards from once it listens on it.
+    let mut line = String::new();
+    let mut stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+    let mut stderr = BufReader::new(child.stderr.take().expect("stderr is piped"));
+    stdout.read_line(&mut line)?;
+    if line.is_empty() {
+        let mut error = String::new();
+        let _ = stderr.read_to_string(&mut error);
+        let _ = child.wait();
+        bail!(
+            "Failed to forward port {} of {}: {}",
+            ports,
+            pod,
+            error.trim()
+        );
+    }
+    debug!("{}", line.trim());
+    // The output is drained for as long as kubectl runs, as it blocks once the pipes are full.
+    thread::spawn(move || stdout.lines().flatten().for_each(|line| debug!("{}", line)));
+    thread::spawn(move || stderr.lines().flatten().for_each(|line| warn!("{}", line)));
+    Ok(PortForward { child, local_port })
+}
+
+/// Creates a client for the cluster which connects through a `kubectl proxy`, spawning the proxy
+/// into `proxy` unless it is already running.
+pub async fn create_k8s_client(proxy: &mut Option<Child>) -> K8sClient {
+    if let Err(e) = ensure_kubectl_proxy(proxy) {
+        warn!("Failed to spawn kubectl proxy: {}", e);
+    }
     let _ = diem_retrier::retry_async(k8s_retry_strategy(), || {
         Box::pin(async move {
             debug!("Running local kube pod healthcheck on {}", HEALTH_CHECK_URL);
@@ -395,10 +640,12 @@ async fn submit_update_nodegroup_config_request(
         nodegroup_name,
         nodegroup_scaling_config,
     );
-    let update_response = eks_client
+    let update_id = eks_client
         .update_nodegroup_config(update_nodegroup_request)
-        .await;
-    let update_id = update_response.unwrap().update.unwrap().id.unwrap();
+        .await?
+        .update
+        .and_then(|update| update.id)
+        .ok_or_else(|| format_err!("No update id for the {} nodegroup", nodegroup_name))?;
     println!(
         "Created {} nodegroup update request with ID: {}",
         nodegroup_name, update_id
@@ -468,8 +715,8 @@ pub fn set_eks_nodegroup_size(
     ];
     updates
         .into_par_iter()
-        .for_each(|(nodegroup_name, update_id)| {
-            let rt = Runtime::new().unwrap();
+        .try_for_each(|(nodegroup_name, update_id)| {
+            let rt = Runtime::new()?;
             rt.block_on(async {
                 diem_retrier::retry_async(k8s_retry_strategy(), || {
                     let client = eks_client.clone();
@@ -482,10 +729,9 @@ pub fn set_eks_nodegroup_size(
                     Box::pin(async move {
                         let describe_update = client
                             .describe_update(describe_update_request)
-                            .await
-                            .unwrap()
+                            .await?
                             .update
-                            .unwrap();
+                            .ok_or_else(|| format_err!("Failed to describe nodegroup update"))?;
                         if let Some(s) = describe_update.status {
                             match s.as_str() {
                                 "Failed" => bail!("Nodegroup update failed"),
@@ -511,8 +757,5 @@ pub fn set_eks_nodegroup_size(
                 })
                 .await
             })
-            .unwrap();
-        });
-
-    Ok(())
+        })
 }
@@ -4,7 +4,7 @@
 use crate::{Factory, Result, Swarm, Version};
 use anyhow::format_err;
 use rand::rngs::StdRng;
-use std::{env, fs::File, io::Read, num::NonZeroUsize, path::PathBuf};
+use std::{env, fs::File, io::Read, num::NonZeroUsize, path::PathBuf, sync::Arc};
 use tokio::runtime::Runtime;
 
 mod cluster_helper;
@@ -19,6 +19,8 @@ use diem_sdk::crypto::ed25519::ED25519_PRIVATE_KEY_LENGTH;
 use diem_secure_storage::{CryptoStorage, KVStorage, VaultStorage};
 
 const DEFAULT_TESTNET_IMAGE_TAG: &str = "devnet";
+/// The namespace the testnet is deployed in, unless configured otherwise.
+const DEFAULT_NAMESPACE: &str = "default";
 
 pub struct K8sFactory {
     root_key: [u8; ED25519_PRIVATE_KEY_LENGTH],
@@ -27,6 +29,8 @@ pub struct K8sFactory {
     helm_repo: String,
     image_tag: String,
     base_image_tag: String,
+    namespace: String,
+    retry_strategy: K8sRetryStrategy,
 }
 
 impl K8sFactory {
@@ -78,8 +82,23 @@ impl K8sFactory {
             helm_repo,
             image_tag,
             base_image_tag,
+            namespace: DEFAULT_NAMESPACE.to_string(),
+            retry_strategy: K8sRetryStrategy::default(),
         })
     }
+
+    /// Sets the strategy the swarms launched by this factory retry with while waiting for the
+    /// cluster.
+    pub fn with_retry_strategy(mut self, retry_strategy: K8sRetryStrategy) -> Self {
+        self.retry_strategy = retry_strategy;
+        self
+    }
+
+    /// Sets the namespace the nodes of the swarms launched by this factory are looked up in.
+    pub fn with_namespace(mut self, namespace: String) -> Self {
+        self.namespace = namespace;
+        self
+    }
 }
 
 impl Factory for K8sFactory {
@@ -106,7 +125,8 @@ impl Factory for K8sFactory {
             DEFAULT_TESTNET_IMAGE_TAG.to_string(),
             true,
         )?;
-        let rt = Runtime::new().unwrap();
+        // The runtime is shared with the nodes of the swarm, which use it for their requests.
+        let rt = Arc::new(Runtime::new()?);
         let swarm = rt
             .block_on(K8sSwarm::new(
                 &self.root_key,
@@ -115,6 +135,9 @@ impl Factory for K8sFactory {
                 &self.helm_repo,
                 &self.image_tag,
                 &self.base_image_tag,
+                &self.namespace,
+                rt.clone(),
+                self.retry_strategy,
             ))
             .unwrap();
         Ok(Box::new(swarm))
@@ -9,6 +9,7 @@ use reqwest::Url;
 use std::{
     fmt::{Debug, Formatter},
     str::FromStr,
+    sync::Arc,
 };
 use tokio::runtime::Runtime;
 
@@ -19,7 +20,8 @@ pub struct K8sNode {
     pub(crate) dns: String,
     pub(crate) ip: String,
     pub(crate) port: u32,
-    pub(crate) runtime: Runtime,
+    /// The runtime requests to the node are executed on, which is shared by all nodes of a swarm.
+    pub(crate) runtime: Arc<Runtime>,
     pub version: Version,
 }
 
@@ -2,30 +2,59 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    backend::k8s::node::K8sNode, create_k8s_client, query_sequence_numbers, remove_helm_release,
-    set_eks_nodegroup_size, set_validator_image_tag, uninstall_from_k8s_cluster, ChainInfo,
-    FullNode, Node, Result, Swarm, Validator, Version,
+    backend::k8s::node::K8sNode, create_k8s_client, ensure_kubectl_proxy, get_release_logs,
+    get_release_pods, install_public_fullnode, port_forward, query_sequence_numbers,
+    remove_helm_release, set_eks_nodegroup_size, set_validator_image_tag, stop_kubectl_proxy,
+    uninstall_from_k8s_cluster, ChainInfo, FullNode, Node, PortForward, Result, Swarm, Validator,
+    Version,
 };
 use anyhow::{anyhow, bail, format_err};
 use diem_config::config::NodeConfig;
 use diem_logger::*;
 use diem_sdk::{
+    client::Client as JsonRpcClient,
     crypto::ed25519::Ed25519PrivateKey,
     types::{
-        chain_id::{ChainId, NamedChain},
-        AccountKey, LocalAccount, PeerId,
+        account_config::validator_set_address, account_state::AccountState,
+        account_state_blob::AccountStateBlob, chain_id::ChainId,
+        validator_config::ValidatorConfigResource, AccountKey, LocalAccount, PeerId,
     },
 };
-use k8s_openapi::api::core::v1::Service;
+use k8s_openapi::api::core::v1::{Pod, Service, ServicePort, ServiceStatus};
 use kube::{
     api::{Api, ListParams},
     client::Client as K8sClient,
 };
-use std::{collections::HashMap, convert::TryFrom, env, process::Command, str, sync::Arc};
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
+use regex::Regex;
+use std::{
+    collections::{BTreeSet, HashMap},
+    convert::TryFrom,
+    env,
+    process::{Child, Command},
+    str,
+    sync::Arc,
+};
 use tokio::{runtime::Runtime, time::Duration};
 
+/// The port JSON-RPC is assumed to be exposed on by services which do not declare it.
 const JSON_RPC_PORT: u32 = 80;
+/// The name of the port of a load balancer service JSON-RPC is exposed on.
+const JSON_RPC_PORT_NAME: &str = "json-rpc";
 const VALIDATOR_LB: &str = "validator-fullnode-lb";
+const FULLNODE_LB: &str = "-diem-fullnode-lb";
+const FULLNODE_PREFIX: &str = "pfn";
+/// The message a node logs its config with each time it starts.
+const LOADED_CONFIG_MESSAGE: &str = "Loaded DiemNode config";
+
+/// Matches the peer id of a network identity given in the config, in the config logged by a
+/// node. Identities kept in secure storage are logged without their peer id.
+static FROM_CONFIG_PEER_ID: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#""identity":\{"type":"from_config",[^}]*"peer_id":"([0-9a-fA-F]{32})""#).unwrap()
+});
+/// Matches the name of the service of a validator and captures its index.
+static VALIDATOR_SERVICE_NAME: Lazy<Regex> = Lazy::new(|| Regex::new(r"^val(\d+)(-|$)").unwrap());
 
 pub struct K8sSwarm {
     validators: HashMap<PeerId, K8sNode>,
@@ -34,9 +63,15 @@ pub struct K8sSwarm {
     treasury_compliance_account: LocalAccount,
     designated_dealer_account: LocalAccount,
     kube_client: K8sClient,
+    /// The `kubectl proxy` the kube client connects through.
+    kubectl_proxy: Option<Child>,
     cluster_name: String,
     helm_repo: String,
     versions: Arc<HashMap<Version, String>>,
+    /// The runtime the nodes of the swarm execute their requests on.
+    runtime: Arc<Runtime>,
+    /// How often and how long to retry while waiting for the cluster.
+    retry_strategy: K8sRetryStrategy,
     pub chain_id: ChainId,
 }
 
@@ -48,10 +83,26 @@ impl K8sSwarm {
         helm_repo: &str,
         image_tag: &str,
         base_image_tag: &str,
+        namespace: &str,
+        runtime: Arc<Runtime>,
+        retry_strategy: K8sRetryStrategy,
     ) -> Result<Self> {
-        let kube_client = create_k8s_client().await;
-        let fullnodes = HashMap::new();
-        let validators = get_validators(kube_client.clone(), image_tag).await?;
+        let mut kubectl_proxy = None;
+        let kube_client = create_k8s_client(&mut kubectl_proxy).await;
+        let validators = get_validators(
+            kube_client.clone(),
+            image_tag,
+            runtime.clone(),
+            retry_strategy,
+        )
+        .await?;
+        let fullnodes = get_fullnodes(
+            kube_client.clone(),
+            image_tag,
+            runtime.clone(),
+            retry_strategy,
+        )
+        .await?;
 
         let client = validators.values().next().unwrap().json_rpc_client();
         let key = load_root_key(root_key);
@@ -96,11 +147,21 @@ impl K8sSwarm {
             })?[0];
         let designated_dealer_account = LocalAccount::new(address, account_key, sequence_number);
 
+        let chain_id = get_chain_id(&client, retry_strategy).await?;
+
         let mut versions = HashMap::new();
         let base_version = Version::new(0, base_image_tag.to_string());
         let cur_version = Version::new(1, image_tag.to_string());
         versions.insert(cur_version, image_tag.to_string());
         versions.insert(base_version, base_image_tag.to_string());
+        // Also offer the versions the validators are actually running, e.g. after an upgrade.
+        let deployed_tags =
+            get_validator_image_tags(kube_client.clone(), namespace, &validators).await?;
+        for tag in deployed_tags {
+            if !versions.values().any(|t| *t == tag) {
+                versions.insert(Version::new(versions.len(), tag.clone()), tag);
+            }
+        }
 
         Ok(Self {
             validators,
@@ -109,10 +170,13 @@ impl K8sSwarm {
             treasury_compliance_account,
             designated_dealer_account,
             kube_client,
-            chain_id: ChainId::new(NamedChain::DEVNET.id()),
+            kubectl_proxy,
+            chain_id,
             cluster_name: cluster_name.to_string(),
             helm_repo: helm_repo.to_string(),
             versions: Arc::new(versions),
+            runtime,
+            retry_strategy,
         })
     }
 
@@ -125,6 +189,91 @@ impl K8sSwarm {
             .to_string()
     }
 
+    /// Waits until every validator reports at least `min_peers` connected validator peers via
+    /// its metrics, retrying with the retry strategy of the swarm. Fails with the list of
+    /// validators which are still under-connected once the retries are exhausted.
+    pub fn wait_for_validator_connectivity(&self, min_peers: usize) -> Result<()> {
+        diem_retrier::retry(self.retry_strategy.delays(), || {
+            // Validators whose connectivity cannot be queried are listed with the error, so a
+            // failing API is reported rather than taken for missing peers.
+            let mut under_connected = self
+                .validators
+                .values()
+                .filter_map(|v| match Validator::check_connectivity(v, min_peers) {
+                    Ok(true) => None,
+                    Ok(false) => Some(v.name().to_string()),
+                    Err(e) => Some(format!("{} ({})", v.name(), e)),
+                })
+                .collect::<Vec<_>>();
+            if under_connected.is_empty() {
+                Ok(())
+            } else {
+                under_connected.sort();
+                info!(
+                    "Waiting for validators to connect to {} peers: {:?}",
+                    min_peers, under_connected
+                );
+                Err(under_connected)
+            }
+        })
+        .map_err(|under_connected| {
+            format_err!(
+                "Validators connected to fewer than {} peers: {:?}",
+                min_peers,
+                under_connected
+            )
+        })
+    }
+
+    /// Returns the logs of the pods of the validator or full node with the given id, optionally
+    /// only the last `tail_lines` lines, and only those of the given container.
+    pub fn logs(
+        &self,
+        id: PeerId,
+        tail_lines: Option<usize>,
+        container: Option<&str>,
+    ) -> Result<String> {
+        let node = self
+            .validators
+            .get(&id)
+            .or_else(|| self.fullnodes.get(&id))
+            .ok_or_else(|| anyhow!("Invalid id: {}", id))?;
+        // The nodes are named after the helm releases they are installed by.
+        get_release_logs(node.name(), tail_lines, container)
+    }
+
+    /// Forwards a local port to the remote port of a pod of the validator or full node with the
+    /// given id, for reaching ports which are not exposed through its load balancer. If given,
+    /// the forward is to the pod whose name contains `component`, e.g. `validator`, else to the
+    /// first pod of the node. A free local port is chosen unless `local_port` is given. The
+    /// forward lasts until the returned guard is dropped.
+    pub fn port_forward(
+        &self,
+        id: PeerId,
+        component: Option<&str>,
+        remote_port: u16,
+        local_port: Option<u16>,
+    ) -> Result<PortForward> {
+        let node = self
+            .validators
+            .get(&id)
+            .or_else(|| self.fullnodes.get(&id))
+            .ok_or_else(|| anyhow!("Invalid id: {}", id))?;
+        let pods = get_release_pods(node.name())?;
+        let pod = pods
+            .iter()
+            .find(|pod| component.map_or(true, |c| pod.contains(c)))
+            .ok_or_else(|| {
+                anyhow!(
+                    "No pod of {} matches {:?}, the pods are {:?}",
+                    node.name(),
+                    component,
+                    pods
+                )
+            })?;
+        port_forward(pod, local_port, remote_port)
+    }
+
     #[allow(dead_code)]
     fn get_kube_client(&self) -> K8sClient {
         self.kube_client.clone()
@@ -132,20 +281,24 @@ impl K8sSwarm {
 }
 
 impl Drop for K8sSwarm {
-    // When the K8sSwarm struct goes out of scope we need to wipe the chain state and scale down
+    // When the K8sSwarm struct goes out of scope we need to wipe the chain state and scale down.
+    // This is best-effort: a failure is only logged, as panicking while the swarm is dropped
+    // because of another panic would abort the process and hide the original failure.
     fn drop(&mut self) {
-        uninstall_from_k8s_cluster().unwrap();
-        set_eks_nodegroup_size(self.cluster_name.clone(), 0, true).unwrap();
+        if let Err(e) = uninstall_from_k8s_cluster() {
+            error!("Failed to uninstall the testnet from the cluster: {}", e);
+        }
+        if let Err(e) = set_eks_nodegroup_size(self.cluster_name.clone(), 0, true) {
+            error!("Failed to scale down the nodegroups of the cluster: {}", e);
+        }
+        stop_kubectl_proxy(&mut self.kubectl_proxy);
     }
 }
 
 impl Swarm for K8sSwarm {
     fn health_check(&mut self) -> Result<()> {
-        nodes_healthcheck(Box::new(
-            self.validators
-                .values_mut()
-                .map(|v| v as &mut dyn Validator),
-        ))
+        ensure_kubectl_proxy(&mut self.kubectl_proxy)?;
+        nodes_healthcheck(self.validators.values_mut().collect(), self.retry_strategy)
     }
 
     fn validators<'a>(&'a self) -> Box<dyn Iterator<Item = &'a dyn Validator> + 'a> {
@@ -211,12 +364,55 @@ impl Swarm for K8sSwarm {
         remove_helm_release(self.validator(id).unwrap().name())
     }
 
-    fn add_full_node(&mut self, _version: &Version, _template: NodeConfig) -> Result<PeerId> {
-        todo!()
+    // The config of the full node is generated by its helm chart, so the template is not used
+    fn add_full_node(&mut self, version: &Version, _template: NodeConfig) -> Result<PeerId> {
+        let image_tag = self
+            .versions
+            .get(version)
+            .cloned()
+            .ok_or_else(|| anyhow!("Invalid version: {:?}", version))?;
+        let node_id = self
+            .fullnodes
+            .values()
+            .map(|n| n.node_id + 1)
+            .max()
+            .unwrap_or(0);
+        let name = format!("{}{}", FULLNODE_PREFIX, node_id);
+        install_public_fullnode(&name, &image_tag, &self.helm_repo)?;
+
+        let service_name = format!("{}{}", name, FULLNODE_LB);
+        let kube_client = self.kube_client.clone();
+        let service = self.runtime.block_on(diem_retrier::retry_async(
+            self.retry_strategy.delays(),
+            || {
+                let kube_client = kube_client.clone();
+                let service_name = service_name.clone();
+                Box::pin(async move {
+                    list_services(kube_client)
+                        .await?
+                        .into_iter()
+                        .find(|s| s.name == service_name)
+                        .ok_or_else(|| format_err!("service {} not found", service_name))
+                })
+            },
+        ))?;
+        let node = self.runtime.block_on(fullnode_from_service(
+            service,
+            version.clone(),
+            self.runtime.clone(),
+            self.retry_strategy,
+        ))?;
+        let peer_id = node.peer_id();
+        self.fullnodes.insert(peer_id, node);
+        Ok(peer_id)
     }
 
-    fn remove_full_node(&mut self, _id: PeerId) -> Result<()> {
-        todo!()
+    fn remove_full_node(&mut self, id: PeerId) -> Result<()> {
+        let fullnode = self
+            .fullnodes
+            .remove(&id)
+            .ok_or_else(|| anyhow!("Invalid id: {}", id))?;
+        remove_helm_release(fullnode.name())
     }
 
     fn versions<'a>(&'a self) -> Box<dyn Iterator<Item = Version> + 'a> {
@@ -252,14 +448,72 @@ impl Swarm for K8sSwarm {
     }
 }
 
+/// The exponential backoff used while waiting for the resources of a k8s cluster. Slow cloud
+/// clusters may need a higher number of attempts, while local clusters can fail faster.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct K8sRetryStrategy {
+    /// The delay before the first retry, in milliseconds.
+    pub base_delay_ms: u64,
+    /// The limit of the exponentially growing delay between retries, in milliseconds.
+    pub max_delay_ms: u64,
+    /// The maximum number of attempts.
+    pub max_attempts: usize,
+}
+
+impl Default for K8sRetryStrategy {
+    fn default() -> Self {
+        Self {
+            base_delay_ms: 1000,
+            max_delay_ms: 5000,
+            max_attempts: 30,
+        }
+    }
+}
+
+impl K8sRetryStrategy {
+    /// Returns the delays between the attempts.
+    pub fn delays(&self) -> impl Iterator<Item = Duration> {
+        diem_retrier::exp_retry_strategy(self.base_delay_ms, self.max_delay_ms, self.max_attempts)
+    }
+}
+
 pub(crate) fn k8s_retry_strategy() -> impl Iterator<Item = Duration> {
-    diem_retrier::exp_retry_strategy(1000, 5000, 30)
+    K8sRetryStrategy::default().delays()
+}
+
+/// Which of the addresses of a service its host ip is, determining from where it can be reached.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ServiceAddressKind {
+    /// The external ip or hostname of the load balancer of the service, which can be reached
+    /// from outside the cluster.
+    LoadBalancer,
+    /// The cluster ip of the service, which can only be reached from within the cluster.
+    ClusterIp,
 }
 
 #[derive(Clone, Debug)]
 pub struct KubeService {
     pub name: String,
+    /// The ip or hostname the service is reached at.
     pub host_ip: String,
+    /// Which address of the service `host_ip` is.
+    pub address_kind: ServiceAddressKind,
+    /// The port JSON-RPC is exposed on, if the service declares it.
+    pub json_rpc_port: Option<u32>,
+}
+
+impl KubeService {
+    /// Returns the port JSON-RPC is exposed on, falling back to `JSON_RPC_PORT` if the service
+    /// does not declare it.
+    fn json_rpc_port(&self) -> u32 {
+        self.json_rpc_port.unwrap_or_else(|| {
+            warn!(
+                "No JSON-RPC port found for service {}, assuming port {}",
+                self.name, JSON_RPC_PORT
+            );
+            JSON_RPC_PORT
+        })
+    }
 }
 
 impl TryFrom<Service> for KubeService {
@@ -273,11 +527,53 @@ impl TryFrom<Service> for KubeService {
         let spec = service
             .spec
             .ok_or_else(|| format_err!("spec not found for node"))?;
-        let host_ip = spec.cluster_ip.unwrap_or_default();
-        Ok(Self { name, host_ip })
+        let json_rpc_port = find_json_rpc_port(spec.ports.as_deref().unwrap_or_default());
+        let ingress = if spec.type_.as_deref() == Some("LoadBalancer") {
+            load_balancer_ingress(service.status.as_ref())
+        } else {
+            None
+        };
+        let (host_ip, address_kind) = match ingress {
+            Some(ingress) => (ingress, ServiceAddressKind::LoadBalancer),
+            None => (
+                spec.cluster_ip.unwrap_or_default(),
+                ServiceAddressKind::ClusterIp,
+            ),
+        };
+        Ok(Self {
+            name,
+            host_ip,
+            address_kind,
+            json_rpc_port,
+        })
     }
 }
 
+/// Returns the external ip of the load balancer of a service with the given status, or its
+/// hostname if it has no ip, as assigned once the load balancer has been provisioned.
+fn load_balancer_ingress(status: Option<&ServiceStatus>) -> Option<String> {
+    status?
+        .load_balancer
+        .as_ref()?
+        .ingress
+        .as_ref()?
+        .iter()
+        .find_map(|ingress| ingress.ip.clone().or_else(|| ingress.hostname.clone()))
+        .filter(|address| !address.is_empty())
+}
+
+/// Returns the port JSON-RPC is exposed on among the given ports of a service: the port named
+/// `json-rpc`, or the only port of a service which has a single one.
+fn find_json_rpc_port(ports: &[ServicePort]) -> Option<u32> {
+    let port = match ports {
+        [port] => port,
+        ports => ports
+            .iter()
+            .find(|p| p.name.as_deref() == Some(JSON_RPC_PORT_NAME))?,
+    };
+    u32::try_from(port.port).ok()
+}
+
 async fn list_services(client: K8sClient) -> Result<Vec<KubeService>> {
     let node_api: Api<Service> = Api::all(client);
     let lp = ListParams::default();
@@ -285,39 +581,244 @@ async fn list_services(client: K8sClient) -> Result<Vec<KubeService>> {
     services.into_iter().map(KubeService::try_from).collect()
 }
 
+/// Returns the validators of the cluster, identified by their load balancer services. The nodes
+/// execute their requests on the given runtime.
 pub(crate) async fn get_validators(
     client: K8sClient,
     image_tag: &str,
+    runtime: Arc<Runtime>,
+    retry_strategy: K8sRetryStrategy,
 ) -> Result<HashMap<PeerId, K8sNode>> {
-    let services = list_services(client).await?;
-    services
+    let services = list_services(client)
+        .await?
         .into_iter()
         .filter(|s| s.name.contains(VALIDATOR_LB))
-        .map(|s| {
-            let node_id = parse_node_id(&s.name).expect("error to parse node id");
+        .filter_map(|s| match parse_node_id(&s.name) {
+            Ok(node_id) => Some((node_id, s)),
+            Err(e) => {
+                warn!("Skipping validator service: {}", e);
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    let (_, service) = services
+        .first()
+        .ok_or_else(|| format_err!("no validator services found"))?;
+    let json_rpc_client = JsonRpcClient::new(format!(
+        "http://{}:{}/v1",
+        service.host_ip,
+        service.json_rpc_port()
+    ));
+    let peer_ids = diem_retrier::retry_async(retry_strategy.delays(), || {
+        Box::pin(get_validator_peer_ids(&json_rpc_client))
+    })
+    .await?;
+    // A service left over from a validator which is not in the validator set, e.g. one removed
+    // from it, does not prevent the others from being found.
+    let validators = services
+        .into_iter()
+        .filter_map(|(node_id, s)| {
+            let name = format!("val{}", node_id);
+            let peer_id = match peer_ids.get(&name) {
+                Some(peer_id) => *peer_id,
+                None => {
+                    warn!(
+                        "Skipping validator service {}: {} not found in validator set",
+                        s.name, name
+                    );
+                    return None;
+                }
+            };
             let node = K8sNode {
-                name: format!("val{}", node_id),
-                // TODO: fetch this from running node
-                peer_id: PeerId::random(),
+                name,
+                peer_id,
                 node_id,
                 ip: s.host_ip.clone(),
-                port: JSON_RPC_PORT,
+                port: s.json_rpc_port(),
                 dns: s.name,
                 version: Version::new(0, image_tag.to_string()),
-                runtime: Runtime::new().unwrap(),
+                runtime: runtime.clone(),
             };
-            Ok((node.peer_id(), node))
+            Some((node.peer_id(), node))
         })
-        .collect::<Result<HashMap<_, _>>>()
+        .collect::<HashMap<_, _>>();
+    if validators.is_empty() {
+        bail!("no validator services found for the validators in the validator set");
+    }
+    Ok(validators)
 }
 
-fn parse_node_id(s: &str) -> Result<usize> {
-    let v = s.split('-').collect::<Vec<&str>>();
-    if v.len() < 5 {
-        return Err(format_err!("Failed to parse {:?} node id format", s));
+/// Returns the distinct image tags of the validator containers of the pods of the given
+/// validators in the given namespace, as read from their pod specs. The pods are named after
+/// the helm releases of the validators, e.g. `val3-diem-validator-validator-0`.
+async fn get_validator_image_tags(
+    client: K8sClient,
+    namespace: &str,
+    validators: &HashMap<PeerId, K8sNode>,
+) -> Result<BTreeSet<String>> {
+    let pod_api: Api<Pod> = Api::namespaced(client, namespace);
+    let pods = pod_api.list(&ListParams::default()).await?.items;
+    let prefixes = validators
+        .values()
+        .map(|v| format!("{}-", v.name()))
+        .collect::<Vec<_>>();
+    Ok(pods
+        .into_iter()
+        .filter(|pod| {
+            pod.metadata
+                .name
+                .as_ref()
+                .map_or(false, |name| prefixes.iter().any(|p| name.starts_with(p)))
+        })
+        .flat_map(|pod| pod.spec.into_iter().flat_map(|spec| spec.containers))
+        .filter_map(|container| container.image)
+        .filter_map(|image| parse_validator_image_tag(&image))
+        .collect())
+}
+
+/// Parses the tag of a validator image, e.g. `devnet` of `diem/validator:devnet`. Returns none
+/// for images of other repositories, such as those of sidecars, or images without a tag.
+fn parse_validator_image_tag(image: &str) -> Option<String> {
+    let (repo, tag) = image.rsplit_once(':')?;
+    // A colon before the last slash separates the port of the registry, not the tag.
+    if tag.contains('/') || tag.is_empty() {
+        return None;
+    }
+    let name = repo.rsplit('/').next()?;
+    if name == "validator" {
+        Some(tag.to_string())
+    } else {
+        None
     }
-    let idx: usize = v[0][3..].parse().unwrap();
-    Ok(idx)
+}
+
+/// Returns the id of the chain the validator of the given client belongs to, retrying with the
+/// given strategy while it cannot be queried.
+async fn get_chain_id(client: &JsonRpcClient, retry_strategy: K8sRetryStrategy) -> Result<ChainId> {
+    let metadata =
+        diem_retrier::retry_async(retry_strategy.delays(), || Box::pin(client.get_metadata()))
+            .await
+            .map_err(|e| format_err!("Failed to query the chain id: {}", e))?;
+    Ok(ChainId::new(metadata.into_inner().chain_id))
+}
+
+/// Returns the public full nodes installed by `add_full_node`, identified by their load balancer
+/// services.
+pub(crate) async fn get_fullnodes(
+    client: K8sClient,
+    image_tag: &str,
+    runtime: Arc<Runtime>,
+    retry_strategy: K8sRetryStrategy,
+) -> Result<HashMap<PeerId, K8sNode>> {
+    let services = list_services(client)
+        .await?
+        .into_iter()
+        .filter(|s| s.name.starts_with(FULLNODE_PREFIX) && s.name.ends_with(FULLNODE_LB));
+    let mut fullnodes = HashMap::new();
+    for service in services {
+        let version = Version::new(0, image_tag.to_string());
+        let node = fullnode_from_service(service, version, runtime.clone(), retry_strategy).await?;
+        fullnodes.insert(node.peer_id(), node);
+    }
+    Ok(fullnodes)
+}
+
+/// Constructs the node of a public full node from its load balancer service, which is named
+/// after the helm release of the node, e.g. `pfn0-diem-fullnode-lb`. The peer id is read from
+/// the logs of the node, retrying with the given strategy until the node has started.
+async fn fullnode_from_service(
+    service: KubeService,
+    version: Version,
+    runtime: Arc<Runtime>,
+    retry_strategy: K8sRetryStrategy,
+) -> Result<K8sNode> {
+    let name = service
+        .name
+        .strip_suffix(FULLNODE_LB)
+        .ok_or_else(|| format_err!("Failed to parse {:?} full node name", service.name))?
+        .to_string();
+    let node_id = name[FULLNODE_PREFIX.len()..]
+        .parse()
+        .map_err(|_| format_err!("Failed to parse {:?} node id format", service.name))?;
+    // The config is only logged once the node has started.
+    let logs = diem_retrier::retry_async(retry_strategy.delays(), || {
+        let name = name.clone();
+        Box::pin(async move {
+            let logs = get_release_logs(&name, None, Some("fullnode"))?;
+            if !logs.contains(LOADED_CONFIG_MESSAGE) {
+                bail!("full node {} has not logged its config yet", name);
+            }
+            Ok(logs)
+        })
+    })
+    .await?;
+    let peer_id = parse_logged_peer_id(&logs).ok_or_else(|| {
+        format_err!(
+            "full node {} logged no peer id, which is only logged for an identity given in the \
+            config",
+            name
+        )
+    })?;
+    Ok(K8sNode {
+        name,
+        peer_id,
+        node_id,
+        port: service.json_rpc_port(),
+        ip: service.host_ip,
+        dns: service.name,
+        version,
+        runtime,
+    })
+}
+
+/// Returns the peer ids of the validators in the on-chain validator set, by the human readable
+/// name of their validator config, which is the name of the node.
+async fn get_validator_peer_ids(client: &JsonRpcClient) -> Result<HashMap<String, PeerId>> {
+    let account_state = client
+        .get_account_state_with_proof(validator_set_address(), None, None)
+        .await?
+        .into_inner();
+    let blob = account_state
+        .blob
+        .ok_or_else(|| format_err!("validator set account not found"))?;
+    let account_state_blob: AccountStateBlob = bcs::from_bytes(blob.inner())?;
+    let validator_set = AccountState::try_from(&account_state_blob)?
+        .get_validator_set()?
+        .ok_or_else(|| format_err!("validator set not found"))?;
+    let mut peer_ids = HashMap::new();
+    for validator_info in validator_set.payload() {
+        let peer_id = *validator_info.account_address();
+        let config = client
+            .get_deserialized_resource::<ValidatorConfigResource>(peer_id)
+            .await?
+            .into_inner()
+            .ok_or_else(|| format_err!("validator config of {} not found", peer_id))?;
+        let name = String::from_utf8(config.human_name)?;
+        peer_ids.insert(name, peer_id);
+    }
+    Ok(peer_ids)
+}
+
+/// Parses the peer id of a public full node from its logs, which contain the config of the node
+/// each time it starts, including the network identity generated for that start. Returns the
+/// peer id of the latest start, or none if the node has not started yet or its identity is not
+/// given in the config.
+fn parse_logged_peer_id(logs: &str) -> Option<PeerId> {
+    let config = logs
+        .lines()
+        .filter(|line| line.contains(LOADED_CONFIG_MESSAGE))
+        .last()?;
+    let captures = FROM_CONFIG_PEER_ID.captures(config)?;
+    PeerId::from_hex(&captures[1]).ok()
+}
+
+/// Parses the index of a validator from the name of its service, which starts with the name of
+/// the validator, e.g. `val3-diem-validator-fullnode-lb`.
+fn parse_node_id(s: &str) -> Result<usize> {
+    VALIDATOR_SERVICE_NAME
+        .captures(s)
+        .and_then(|captures| captures[1].parse().ok())
+        .ok_or_else(|| format_err!("Failed to parse {:?} node id format", s))
 }
 
 fn load_root_key(root_key_bytes: &[u8]) -> Ed25519PrivateKey {
@@ -328,24 +829,26 @@ fn load_tc_key(tc_key_bytes: &[u8]) -> Ed25519PrivateKey {
     Ed25519PrivateKey::try_from(tc_key_bytes).unwrap()
 }
 
-pub fn nodes_healthcheck<'a>(
-    nodes: Box<dyn Iterator<Item = &'a mut dyn Validator> + 'a>,
-) -> Result<()> {
+/// Health checks the given nodes concurrently, retrying each one with the given strategy, and
+/// fails if any of them stays unhealthy.
+pub fn nodes_healthcheck(nodes: Vec<&mut K8sNode>, retry_strategy: K8sRetryStrategy) -> Result<()> {
     let unhealthy_nodes = nodes
+        .into_par_iter()
         .filter_map(|node| {
             let node_name = node.name().to_string();
             println!("Attempting health check: {}", node_name);
             // perform healthcheck with retry, returning unhealthy
-            let check = diem_retrier::retry(k8s_retry_strategy(), || match node.health_check() {
-                Ok(_) => {
-                    println!("Node {} healthy", node_name);
-                    Ok(())
-                }
-                Err(ref x) => {
-                    debug!("Node {} unhealthy: {}", node_name, x);
-                    Err(())
-                }
-            });
+            let check =
+                diem_retrier::retry(retry_strategy.delays(), || match node.health_check() {
+                    Ok(_) => {
+                        println!("Node {} healthy", node_name);
+                        Ok(())
+                    }
+                    Err(ref x) => {
+                        debug!("Node {} unhealthy: {}", node_name, x);
+                        Err(())
+                    }
+                });
             if check.is_err() {
                 return Some(node_name);
             }
@@ -358,3 +861,139 @@ pub fn nodes_healthcheck<'a>(
     println!("All validators healthy after cleanup!");
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use k8s_openapi::api::core::v1::{LoadBalancerIngress, LoadBalancerStatus};
+
+    #[test]
+    pub fn test_parse_node_id() {
+        assert_eq!(parse_node_id("val0-diem-validator-fullnode-lb").unwrap(), 0);
+        assert_eq!(parse_node_id("val10-validator-fullnode-lb").unwrap(), 10);
+        assert_eq!(
+            parse_node_id("val7-diem-validator-fullnode-lb-extra-segments").unwrap(),
+            7
+        );
+        assert_eq!(parse_node_id("val3").unwrap(), 3);
+    }
+
+    #[test]
+    pub fn test_parse_logged_peer_id() {
+        let line = |peer_id: &str| {
+            format!(
+                "[pod/pfn0-diem-fullnode-0/fullnode] 2021-07-01T00:00:00Z [main] INFO \
+                 diem-node/src/lib.rs:76 Loaded DiemNode config {{\"config\":{{\
+                 \"full_node_networks\":[{{\"identity\":{{\"type\":\"from_config\",\
+                 \"key\":\"0\",\"peer_id\":\"{}\"}}}}]}}}}",
+                peer_id
+            )
+        };
+        let first = "1f".repeat(PeerId::LENGTH);
+        let restarted = "2e".repeat(PeerId::LENGTH);
+        let logs = [line(&first), "other output".to_string(), line(&restarted)].join("\n");
+        assert_eq!(
+            parse_logged_peer_id(&logs),
+            Some(PeerId::from_hex(&restarted).unwrap())
+        );
+        assert_eq!(parse_logged_peer_id("other output"), None);
+        // A restart with an identity from secure storage does not log the peer id.
+        let from_storage = "[pod/pfn0-diem-fullnode-0/fullnode] Loaded DiemNode config \
+            {\"identity\":{\"type\":\"from_storage\",\"key_name\":\"fullnode\"}}";
+        let logs = [line(&first), from_storage.to_string()].join("\n");
+        assert_eq!(parse_logged_peer_id(&logs), None);
+    }
+
+    #[test]
+    pub fn test_parse_validator_image_tag() {
+        assert_eq!(
+            parse_validator_image_tag("diem/validator:devnet"),
+            Some("devnet".to_string())
+        );
+        assert_eq!(
+            parse_validator_image_tag(
+                "853397791086.dkr.ecr.us-west-2.amazonaws.com/diem/validator:abc123"
+            ),
+            Some("abc123".to_string())
+        );
+        assert_eq!(
+            parse_validator_image_tag("registry:5000/diem/validator:v1"),
+            Some("v1".to_string())
+        );
+        assert_eq!(
+            parse_validator_image_tag("registry:5000/diem/validator"),
+            None
+        );
+        assert_eq!(parse_validator_image_tag("diem/validator_tcb:devnet"), None);
+        assert_eq!(parse_validator_image_tag("logstash:7.10"), None);
+    }
+
+    #[test]
+    pub fn test_retry_strategy() {
+        assert_eq!(K8sRetryStrategy::default().delays().count(), 30);
+        let strategy = K8sRetryStrategy {
+            base_delay_ms: 10,
+            max_delay_ms: 50,
+            max_attempts: 5,
+        };
+        let delays = strategy.delays().collect::<Vec<_>>();
+        assert_eq!(delays.len(), 5);
+        assert!(delays.iter().all(|d| *d <= Duration::from_millis(50)));
+    }
+
+    #[test]
+    pub fn test_find_json_rpc_port() {
+        let port = |name: &str, port| ServicePort {
+            name: Some(name.to_string()),
+            port,
+            ..ServicePort::default()
+        };
+        assert_eq!(find_json_rpc_port(&[port("http", 8080)]), Some(8080));
+        assert_eq!(
+            find_json_rpc_port(&[port("metrics", 9101), port("json-rpc", 8080)]),
+            Some(8080)
+        );
+        assert_eq!(
+            find_json_rpc_port(&[port("metrics", 9101), port("backup", 6186)]),
+            None
+        );
+        assert_eq!(find_json_rpc_port(&[]), None);
+    }
+
+    #[test]
+    pub fn test_load_balancer_ingress() {
+        let status = |ingress| ServiceStatus {
+            load_balancer: Some(LoadBalancerStatus {
+                ingress: Some(ingress),
+            }),
+        };
+        assert_eq!(
+            load_balancer_ingress(Some(&status(vec![LoadBalancerIngress {
+                hostname: Some("lb.elb.amazonaws.com".to_string()),
+                ip: None,
+            }]))),
+            Some("lb.elb.amazonaws.com".to_string())
+        );
+        assert_eq!(
+            load_balancer_ingress(Some(&status(vec![LoadBalancerIngress {
+                hostname: Some("lb.example.com".to_string()),
+                ip: Some("203.0.113.7".to_string()),
+            }]))),
+            Some("203.0.113.7".to_string())
+        );
+        // The load balancer has not been provisioned yet.
+        assert_eq!(load_balancer_ingress(Some(&status(vec![]))), None);
+        assert_eq!(load_balancer_ingress(Some(&ServiceStatus::default())), None);
+        assert_eq!(load_balancer_ingress(None), None);
+    }
+
+    #[test]
+    pub fn test_parse_malformed_node_id() {
+        assert!(parse_node_id("va").is_err());
+        assert!(parse_node_id("valx-diem-validator-fullnode-lb").is_err());
+        assert!(parse_node_id("val-diem-validator-fullnode-lb").is_err());
+        assert!(parse_node_id("val1x-diem-validator-fullnode-lb").is_err());
+        assert!(parse_node_id("diem-val1-validator-fullnode-lb").is_err());
+        assert!(parse_node_id("val99999999999999999999999-validator-fullnode-lb").is_err());
+    }
+}
@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{Coffer, PublicInfo, Result};
+use anyhow::format_err;
 use diem_sdk::{
     client::BlockingClient,
     transaction_builder::{Currency, TransactionFactory},
@@ -11,11 +12,20 @@ use diem_sdk::{
     },
 };
 
+/// Information about a running chain and the privileged accounts which can be used to act on it.
+///
+/// Each of the accounts is optional so that a `ChainInfo` can be handed to tests which only need
+/// some of them. Operations which need an account that was not initialized return an error:
+/// * the root account is needed for any transaction sent as Diem Root, e.g. reconfigurations.
+/// * the treasury compliance account is needed by `create_parent_vasp_account` and
+///   `create_designated_dealer_account`.
+/// * the designated dealer account is needed by `fund`.
+/// * `into_public_info` needs both the treasury compliance and designated dealer accounts.
 #[derive(Debug)]
 pub struct ChainInfo<'t> {
-    pub root_account: &'t mut LocalAccount,
-    pub treasury_compliance_account: &'t mut LocalAccount,
-    pub designated_dealer_account: &'t mut LocalAccount,
+    pub root_account: Option<&'t mut LocalAccount>,
+    pub treasury_compliance_account: Option<&'t mut LocalAccount>,
+    pub designated_dealer_account: Option<&'t mut LocalAccount>,
     pub json_rpc_url: String,
     pub chain_id: ChainId,
 }
@@ -27,6 +37,23 @@ impl<'t> ChainInfo<'t> {
         designated_dealer_account: &'t mut LocalAccount,
         json_rpc_url: String,
         chain_id: ChainId,
+    ) -> Self {
+        Self::with_accounts(
+            Some(root_account),
+            Some(treasury_compliance_account),
+            Some(designated_dealer_account),
+            json_rpc_url,
+            chain_id,
+        )
+    }
+
+    /// Creates a `ChainInfo` where only some of the accounts may be initialized.
+    pub fn with_accounts(
+        root_account: Option<&'t mut LocalAccount>,
+        treasury_compliance_account: Option<&'t mut LocalAccount>,
+        designated_dealer_account: Option<&'t mut LocalAccount>,
+        json_rpc_url: String,
+        chain_id: ChainId,
     ) -> Self {
         Self {
             root_account,
@@ -37,16 +64,22 @@ impl<'t> ChainInfo<'t> {
         }
     }
 
-    pub fn designated_dealer_account(&mut self) -> &mut LocalAccount {
+    pub fn designated_dealer_account(&mut self) -> Result<&mut LocalAccount> {
         self.designated_dealer_account
+            .as_deref_mut()
+            .ok_or_else(|| format_err!("designated dealer account is not initialized"))
     }
 
-    pub fn root_account(&mut self) -> &mut LocalAccount {
+    pub fn root_account(&mut self) -> Result<&mut LocalAccount> {
         self.root_account
+            .as_deref_mut()
+            .ok_or_else(|| format_err!("root account is not initialized"))
     }
 
-    pub fn treasury_compliance_account(&mut self) -> &mut LocalAccount {
+    pub fn treasury_compliance_account(&mut self) -> Result<&mut LocalAccount> {
         self.treasury_compliance_account
+            .as_deref_mut()
+            .ok_or_else(|| format_err!("treasury compliance account is not initialized"))
     }
 
     pub fn json_rpc(&self) -> &str {
@@ -72,7 +105,7 @@ impl<'t> ChainInfo<'t> {
     ) -> Result<()> {
         let factory = self.transaction_factory();
         let client = self.json_rpc_client();
-        let treasury_compliance_account = self.treasury_compliance_account();
+        let treasury_compliance_account = self.treasury_compliance_account()?;
 
         let create_account_txn = treasury_compliance_account.sign_with_transaction_builder(
             factory.create_parent_vasp_account(
@@ -95,7 +128,7 @@ impl<'t> ChainInfo<'t> {
     ) -> Result<()> {
         let factory = self.transaction_factory();
         let client = self.json_rpc_client();
-        let treasury_compliance_account = self.treasury_compliance_account();
+        let treasury_compliance_account = self.treasury_compliance_account()?;
 
         let create_account_txn = treasury_compliance_account.sign_with_transaction_builder(
             factory.create_designated_dealer(
@@ -114,7 +147,7 @@ impl<'t> ChainInfo<'t> {
     pub fn fund(&mut self, currency: Currency, address: AccountAddress, amount: u64) -> Result<()> {
         let factory = self.transaction_factory();
         let client = self.json_rpc_client();
-        let designated_dealer_account = self.designated_dealer_account();
+        let designated_dealer_account = self.designated_dealer_account()?;
         let fund_account_txn = designated_dealer_account
             .sign_with_transaction_builder(factory.peer_to_peer(currency, address, amount));
         client.submit(&fund_account_txn)?;
@@ -122,16 +155,22 @@ impl<'t> ChainInfo<'t> {
         Ok(())
     }
 
-    pub fn into_public_info(self) -> PublicInfo<'t> {
-        PublicInfo::new(
+    pub fn into_public_info(self) -> Result<PublicInfo<'t>> {
+        let treasury_compliance_account = self
+            .treasury_compliance_account
+            .ok_or_else(|| format_err!("treasury compliance account is not initialized"))?;
+        let designated_dealer_account = self
+            .designated_dealer_account
+            .ok_or_else(|| format_err!("designated dealer account is not initialized"))?;
+        Ok(PublicInfo::new(
             self.json_rpc_url.clone(),
             self.chain_id,
             Coffer::TreasuryCompliance {
                 transaction_factory: TransactionFactory::new(self.chain_id),
                 json_rpc_client: BlockingClient::new(self.json_rpc_url),
-                treasury_compliance_account: self.treasury_compliance_account,
-                designated_dealer_account: self.designated_dealer_account,
+                treasury_compliance_account,
+                designated_dealer_account,
             },
-        )
+        ))
     }
 }
@@ -232,7 +232,7 @@ impl<'cfg, F: Factory> Forge<'cfg, F> {
             for test in self.filter_tests(self.tests.public_usage_tests.iter()) {
                 let mut public_ctx = PublicUsageContext::new(
                     CoreContext::from_rng(&mut rng),
-                    swarm.chain_info().into_public_info(),
+                    swarm.chain_info().into_public_info()?,
                     &mut report,
                 );
                 let result = run_test(|| test.run(&mut public_ctx));
@@ -245,7 +245,7 @@ impl<'t> TxnEmitter<'t> {
     pub async fn get_money_source(&mut self, coins_total: u64) -> Result<&mut LocalAccount> {
         let client = self.client.clone();
         println!("Creating and minting faucet account");
-        let mut faucet_account = &mut self.chain_info.designated_dealer_account;
+        let mut faucet_account = self.chain_info.designated_dealer_account()?;
         let mint_txn = gen_transfer_txn_request(
             faucet_account,
             &faucet_account.address(),
@@ -280,7 +280,7 @@ impl<'t> TxnEmitter<'t> {
             let client = self.pick_mint_client(json_rpc_clients).clone();
             let batch_size = min(MAX_TXN_BATCH_SIZE, seed_account_num - i);
             let mut batch = gen_random_accounts(batch_size, self.rng());
-            let creation_account = &mut self.chain_info.treasury_compliance_account;
+            let creation_account = self.chain_info.treasury_compliance_account()?;
             let txn_factory = &self.txn_factory;
             let create_requests = batch
                 .iter()
@@ -209,7 +209,8 @@ fn test_genesis_transaction_flow() {
     // Create a new epoch to make things more complicated
     let txn = swarm
         .chain_info()
-        .root_account
+        .root_account()
+        .unwrap()
         .sign_with_transaction_builder(transaction_factory.update_diem_version(0, 12345));
     client_0.submit(&txn).unwrap();
     client_0
@@ -51,7 +51,8 @@ fn test_move_release_flow() {
     // Commit the release
     let txn = swarm
         .chain_info()
-        .root_account
+        .root_account()
+        .unwrap()
         .sign_with_transaction_builder(
             transaction_factory.payload(TransactionPayload::WriteSet(payload_1.clone())),
         );
@@ -117,7 +118,8 @@ fn test_move_release_flow() {
     // Commit the release
     let txn = swarm
         .chain_info()
-        .root_account
+        .root_account()
+        .unwrap()
         .sign_with_transaction_builder(
             transaction_factory.payload(TransactionPayload::WriteSet(payload_2)),
         );
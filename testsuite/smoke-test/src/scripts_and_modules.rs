@@ -38,7 +38,7 @@ impl AdminTest for MalformedScript {
         enable_custom_script(
             &client,
             &transaction_factory,
-            &mut ctx.chain_info().root_account,
+            ctx.chain_info().root_account()?,
         )?;
         let mut account = ctx.random_account();
         ctx.chain_info()
@@ -90,7 +90,7 @@ impl AdminTest for ExecuteCustomModuleAndScript {
     fn run<'t>(&self, ctx: &mut AdminContext<'t>) -> Result<()> {
         let client = ctx.client();
         let factory = ctx.chain_info().transaction_factory();
-        enable_custom_script(&client, &factory, &mut ctx.chain_info().root_account)?;
+        enable_custom_script(&client, &factory, ctx.chain_info().root_account()?)?;
 
         let mut account1 = ctx.random_account();
         ctx.chain_info()
@@ -283,7 +283,7 @@ fn test_state_sync_multichunk_epoch() {
     enable_custom_script(
         &client_0,
         &transaction_factory,
-        swarm.chain_info().root_account,
+        swarm.chain_info().root_account().unwrap(),
     )
     .unwrap();
 
@@ -339,7 +339,7 @@ fn test_state_sync_multichunk_epoch() {
         enable_custom_script(
             &client_0,
             &transaction_factory,
-            swarm.chain_info().root_account,
+            swarm.chain_info().root_account().unwrap(),
         )
         .unwrap();
 
@@ -59,7 +59,7 @@ fn test_db_restore() {
     transfer_and_reconfig(
         &client_1,
         &transaction_factory,
-        swarm.chain_info().root_account,
+        swarm.chain_info().root_account().unwrap(),
         &mut account_0,
         &account_1,
         20,
@@ -102,7 +102,7 @@ fn test_db_restore() {
         transfer_and_reconfig(
             &client_1,
             &transaction_factory,
-            swarm.chain_info().root_account,
+            swarm.chain_info().root_account().unwrap(),
             &mut account_0,
             &account_1,
             20,
@@ -151,12 +151,19 @@ pub mod diem_swarm_utils {
     }
 
     pub fn create_root_storage(swarm: &mut LocalSwarm) -> SecureBackend {
-        let chain_info = swarm.chain_info();
-        let root_key =
-            bcs::from_bytes(&bcs::to_bytes(chain_info.root_account.private_key()).unwrap())
-                .unwrap();
+        let mut chain_info = swarm.chain_info();
+        let root_key = bcs::from_bytes(
+            &bcs::to_bytes(chain_info.root_account().unwrap().private_key()).unwrap(),
+        )
+        .unwrap();
         let treasury_compliance_key = bcs::from_bytes(
-            &bcs::to_bytes(chain_info.treasury_compliance_account.private_key()).unwrap(),
+            &bcs::to_bytes(
+                chain_info
+                    .treasury_compliance_account()
+                    .unwrap()
+                    .private_key(),
+            )
+            .unwrap(),
         )
         .unwrap();
 